@@ -0,0 +1,273 @@
+// Aegis Authenticator のバックアップ（取り込みと書き出し）
+//
+// 暗号化されたバックアップでは、ランダムなマスター鍵で db を AES-256-GCM 暗号化し、
+// マスター鍵をスロットごとに包む。パスワードのスロットは scrypt で鍵を導出する。
+// 書き出しでは TOTP を持つエントリだけを対象にし、エントリIDを uuid に使う
+// （同じエントリを書き出し直しても Aegis 側で別物として増えないように）。
+// 取り込みでは uuid を使わない（書き出したものを戻すとIDが重複するため）。
+use crate::error::err;
+use crate::totp::{self, Algorithm, Otp};
+use crate::{scrypt, Entry, Vault};
+use crate::prompt;
+use aes_gcm::{aead::{Aead, KeyInit}, Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::{fs, path::Path};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+const SLOT_PASSWORD: u8 = 1;
+const DB_VERSION: u32 = 3;
+// Aegis アプリが新規作成時に使う値
+const SCRYPT_N: u64 = 1 << 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+#[derive(Deserialize)]
+struct File {
+    header: Header,
+    db: Value,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    slots: Option<Vec<Slot>>,
+    params: Option<Params>,
+}
+
+#[derive(Deserialize)]
+struct Params {
+    nonce: String,
+    tag: String,
+}
+
+#[derive(Deserialize)]
+struct Slot {
+    #[serde(rename = "type")]
+    kind: u8,
+    key: String,
+    key_params: Params,
+    #[serde(default)]
+    n: u64,
+    #[serde(default)]
+    r: u32,
+    #[serde(default)]
+    p: u32,
+    #[serde(default)]
+    salt: String,
+}
+
+#[derive(Deserialize)]
+struct Db {
+    entries: Vec<DbEntry>,
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Group {
+    uuid: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct DbEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    note: String,
+    /// v2 までのグループ名
+    #[serde(default)]
+    group: Option<String>,
+    /// v3 からのグループ（uuid）
+    #[serde(default)]
+    groups: Vec<String>,
+    info: Info,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    secret: String,
+    #[serde(default)]
+    algo: String,
+    #[serde(default)]
+    digits: Option<u32>,
+    #[serde(default)]
+    period: Option<u64>,
+}
+
+fn hex(b: &[u8]) -> String {
+    b.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Result<Vec<u8>> {
+    if !s.len().is_multiple_of(2) { return Err(err!(InvalidInput, "bad hex in Aegis backup")); }
+    (0..s.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| err!(InvalidInput, "bad hex in Aegis backup")))
+        .collect()
+}
+
+// 暗号文とタグが別々に保存されている
+fn open(key: &[u8], data: &[u8], params: &Params) -> Option<Zeroizing<Vec<u8>>> {
+    let nonce = unhex(&params.nonce).ok().filter(|n| n.len() == 12)?;
+    let mut buf = data.to_vec();
+    buf.extend(unhex(&params.tag).ok()?);
+    Aes256Gcm::new_from_slice(key).ok()?.decrypt(Nonce::from_slice(&nonce), buf.as_slice()).ok().map(Zeroizing::new)
+}
+
+fn seal(key: &[u8], data: &[u8]) -> Result<(Vec<u8>, Value)> {
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let mut out = Aes256Gcm::new_from_slice(key)?
+        .encrypt(Nonce::from_slice(&nonce), data)
+        .map_err(|_| anyhow!("encryption failed"))?;
+    let tag = out.split_off(out.len() - 16);
+    Ok((out, json!({ "nonce": hex(&nonce), "tag": hex(&tag) })))
+}
+
+fn master_key(slots: &[Slot]) -> Result<Zeroizing<Vec<u8>>> {
+    let slots: Vec<&Slot> = slots.iter().filter(|s| s.kind == SLOT_PASSWORD).collect();
+    if slots.is_empty() {
+        return Err(err!(InvalidInput, "Aegis backup has no password slot (biometric-only backups cannot be imported)"));
+    }
+    for _ in 0..3 {
+        let password = Zeroizing::new(prompt::password("Aegis backup password: ")?);
+        for s in &slots {
+            let key = scrypt::derive(password.as_bytes(), &unhex(&s.salt)?, s.n, s.r, s.p, 32)?;
+            if let Some(master) = open(&key, &unhex(&s.key)?, &s.key_params) { return Ok(master); }
+        }
+        eprintln!("Wrong password.");
+    }
+    Err(err!(WrongPassword, "wrong Aegis backup password"))
+}
+
+fn algorithm(s: &str) -> Result<Algorithm> {
+    match s.to_uppercase().as_str() {
+        "" | "SHA1" => Ok(Algorithm::Sha1),
+        "SHA256" => Ok(Algorithm::Sha256),
+        "SHA512" => Ok(Algorithm::Sha512),
+        other => Err(err!(InvalidInput, "unsupported TOTP algorithm {other}")),
+    }
+}
+
+// TOTP だけのエントリ（発行者を名前、アカウント名をユーザー名にする）
+pub(crate) fn otp_entry(issuer: &str, account: &str, otp: Otp) -> Entry {
+    let name = if issuer.is_empty() { account } else { issuer };
+    let mut e = Entry::new(name.trim().to_string());
+    e.username = account.trim().to_string();
+    e.otp = Some(otp);
+    e
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let file: File = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| err!(InvalidInput, "not an Aegis backup: {e}"))?;
+    let db: Db = match (file.header.slots, file.header.params, &file.db) {
+        (Some(slots), Some(params), Value::String(data)) => {
+            let data = B64.decode(data.trim()).map_err(|_| err!(InvalidInput, "Aegis db is not valid base64"))?;
+            let key = master_key(&slots)?;
+            let plain = open(&key, &data, &params).ok_or_else(|| err!(CorruptVault, "Aegis db failed to decrypt"))?;
+            serde_json::from_slice(&plain)?
+        }
+        (_, _, Value::Object(_)) => serde_json::from_value(file.db)?,
+        _ => return Err(err!(InvalidInput, "not an Aegis backup: unexpected header")),
+    };
+    let groups: BTreeMap<&str, &str> = db.groups.iter().map(|g| (g.uuid.as_str(), g.name.as_str())).collect();
+    let mut out = Vec::new();
+    let mut skipped = 0;
+    for d in &db.entries {
+        // HOTP・Steam などは未対応
+        if d.kind != "totp" { skipped += 1; continue; }
+        let secret: String = d.info.secret.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+        if totp::base32_decode(&secret).is_none() {
+            return Err(err!(InvalidInput, "{:?}: TOTP secret is not valid base32", d.name));
+        }
+        // otpauth:// URI と同じ範囲だけを受け付ける（周期 0 や桁数が大きすぎるとコードを計算できない）
+        let digits = d.info.digits.unwrap_or(6);
+        if !(6..=8).contains(&digits) {
+            return Err(err!(InvalidInput, "{:?}: TOTP digits must be 6-8, got {digits}", d.name));
+        }
+        let period = d.info.period.unwrap_or(30);
+        if period == 0 {
+            return Err(err!(InvalidInput, "{:?}: TOTP period must be greater than 0", d.name));
+        }
+        let otp = Otp {
+            secret,
+            algorithm: algorithm(&d.info.algo)?,
+            digits,
+            period,
+            issuer: Some(d.issuer.clone()).filter(|s| !s.is_empty()),
+            account: Some(d.name.clone()).filter(|s| !s.is_empty()),
+        };
+        let mut e = otp_entry(&d.issuer, &d.name, otp);
+        e.notes = Some(d.note.clone()).filter(|s| !s.is_empty());
+        e.tags = d.group.iter().map(String::as_str)
+            .chain(d.groups.iter().filter_map(|g| groups.get(g.as_str()).copied()))
+            .map(str::to_string)
+            .collect();
+        out.push(e);
+    }
+    if skipped > 0 { eprintln!("warning: skipped {skipped} non-TOTP entries (HOTP / Steam are not supported)"); }
+    Ok(out)
+}
+
+pub fn render(vault: &Vault, passphrase: &str) -> Result<String> {
+    let mut groups: BTreeMap<&str, String> = BTreeMap::new();
+    let mut entries = Vec::new();
+    for e in &vault.entries {
+        let Some(o) = &e.otp else { continue };
+        let ids: Vec<String> = e.tags.iter()
+            .map(|t| groups.entry(t.as_str()).or_insert_with(|| Uuid::new_v4().to_string()).clone())
+            .collect();
+        let account = o.account.clone().unwrap_or_else(|| if e.username.is_empty() { e.name.clone() } else { e.username.clone() });
+        entries.push(json!({
+            "type": "totp",
+            "uuid": e.id,
+            "name": account,
+            "issuer": o.issuer.as_deref().unwrap_or(&e.name),
+            "note": e.notes.as_deref().unwrap_or_default(),
+            "favorite": false,
+            "icon": null,
+            "groups": ids,
+            "info": { "secret": o.secret, "algo": o.algorithm, "digits": o.digits, "period": o.period },
+        }));
+    }
+    let groups: Vec<Group> = groups.into_iter().map(|(name, uuid)| Group { uuid, name: name.to_string() }).collect();
+    let db = Zeroizing::new(serde_json::to_vec(&json!({ "version": DB_VERSION, "entries": entries, "groups": groups }))?);
+
+    let mut master = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(master.as_mut());
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    let slot_key = scrypt::derive(passphrase.as_bytes(), &salt, SCRYPT_N, SCRYPT_R, SCRYPT_P, 32)?;
+    let (wrapped, key_params) = seal(&slot_key, master.as_ref())?;
+    let (data, params) = seal(master.as_ref(), &db)?;
+    let file = json!({
+        "version": 1,
+        "header": {
+            "slots": [{
+                "type": SLOT_PASSWORD,
+                "uuid": Uuid::new_v4().to_string(),
+                "key": hex(&wrapped),
+                "key_params": key_params,
+                "n": SCRYPT_N,
+                "r": SCRYPT_R,
+                "p": SCRYPT_P,
+                "salt": hex(&salt),
+                "repaired": true,
+                "is_backup": false,
+            }],
+            "params": params,
+        },
+        "db": B64.encode(data),
+    });
+    Ok(serde_json::to_string_pretty(&file)?)
+}
@@ -0,0 +1,86 @@
+//! age（age-encryption.org/v1）の X25519 鍵
+//!
+//! 鍵と暗号は age クレートのもの（[`Recipient`] と [`Identity`] はその `x25519` の型）。ここでは受信者（`age1...`）と
+//! 秘密鍵（`AGE-SECRET-KEY-1...`）を読み、金庫のデータ鍵を受信者あての age ファイルに包む。
+//! age や rage の age-keygen で作った鍵がそのまま使える。
+use crate::error::err;
+use ::age::secrecy::ExposeSecret;
+use ::age::{Decryptor, Encryptor};
+use anyhow::Result;
+use std::io::{Read, Write};
+use std::iter;
+use zeroize::Zeroizing;
+
+pub use ::age::x25519::{Identity, Recipient};
+
+/// `age1...` を読む
+pub fn parse_recipient(s: &str) -> Result<Recipient> {
+    s.trim().parse().map_err(|_| err!(InvalidInput, "not an age recipient (age1...): {s:?}"))
+}
+
+/// `AGE-SECRET-KEY-1...` を読む
+pub fn parse_identity(s: &str) -> Result<Identity> {
+    s.trim().parse().map_err(|_| err!(InvalidInput, "not an age identity (AGE-SECRET-KEY-1...)"))
+}
+
+/// `AGE-SECRET-KEY-1...`（大文字）
+pub fn secret_string(id: &Identity) -> Zeroizing<String> {
+    Zeroizing::new(id.to_string().expose_secret().to_string())
+}
+
+/// 秘密鍵ファイルの中身を読む（`#` で始まる行と空行は飛ばす）
+pub fn parse_identities(text: &str) -> Result<Vec<Identity>> {
+    let ids = text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(parse_identity)
+        .collect::<Result<Vec<_>>>()?;
+    if ids.is_empty() { return Err(err!(InvalidInput, "no age identity (AGE-SECRET-KEY-1...) found")); }
+    Ok(ids)
+}
+
+/// key を受信者あての age ファイル（armor なし）に暗号化する
+pub fn wrap(to: &Recipient, key: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut w = Encryptor::with_recipients(iter::once(to as &dyn ::age::Recipient))?.wrap_output(&mut out)?;
+    w.write_all(key)?;
+    w.finish()?;
+    Ok(out)
+}
+
+/// [`wrap`] で包んだ鍵を開く（この秘密鍵あてでないか、壊れていれば None）
+pub fn unwrap(id: &Identity, data: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+    let mut r = Decryptor::new_buffered(data).ok()?.decrypt(iter::once(id as &dyn ::age::Identity)).ok()?;
+    let mut key = Zeroizing::new(Vec::new());
+    r.read_to_end(&mut key).ok()?;
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // age のテストキット（C2SP/CCTV の age/testdata/x25519）の秘密鍵と受信者
+    const IDENTITY: &str = "AGE-SECRET-KEY-1XMWWC06LY3EE5RYTXM9MFLAZ2U56JJJ36S0MYPDRWSVLUL66MV4QX3S7F6";
+    const RECIPIENT: &str = "age1w3tyke4gev25vaxxsvcgqu4484rf6ejpmavs57p6yz6lhy2sfs5swrvwyn";
+
+    #[test]
+    fn identity_and_recipient_strings() {
+        let id = parse_identity(IDENTITY).unwrap();
+        assert_eq!(id.to_public().to_string(), RECIPIENT);
+        assert_eq!(secret_string(&id).as_str(), IDENTITY);
+        assert!(parse_recipient(RECIPIENT).unwrap() == id.to_public());
+        // 秘密鍵を受信者として、受信者を秘密鍵として読まない
+        assert!(parse_recipient(IDENTITY).is_err());
+        assert!(parse_identity(RECIPIENT).is_err());
+    }
+
+    #[test]
+    fn wrap_round_trip() {
+        let id = parse_identity(IDENTITY).unwrap();
+        let data = wrap(&id.to_public(), b"YELLOW SUBMARINE").unwrap();
+        assert!(data.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+        assert_eq!(unwrap(&id, &data).unwrap().as_slice(), b"YELLOW SUBMARINE");
+        assert!(unwrap(&Identity::generate(), &data).is_none());
+    }
+}
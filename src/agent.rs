@@ -0,0 +1,501 @@
+// ロック解除した金庫のデータ鍵を保持するエージェント（Unix はUnixドメインソケット、Windows は名前付きパイプ）
+//
+// 接続してきたプロセスは同じユーザーか確認し（Unix は SO_PEERCRED / getpeereid の UID、Windows は相手のプロセスの
+// トークンの SID）、設定があれば実行ファイルのパスでも絞り込む。許可・拒否にかかわらず全ての要求を agent.log に記録する。
+use crate::format::DataKey;
+use crate::{backup, now_iso, vaults};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+/// 最後の利用からこの秒数でロック（エージェント終了）
+pub const DEFAULT_TIMEOUT: u64 = 900;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    Key,
+    Status,
+    Stop,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Response {
+    ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    locks_in: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub struct Status {
+    pub pid: u32,
+    pub locks_in: u64,
+}
+
+// 既定の金庫は agent、名前付きの金庫などはパスごとに別のエージェントにする
+fn endpoint_name() -> Result<String> {
+    if vaults::is_default()? { return Ok("agent".into()); }
+    let hash = backup::sha256_hex(vaults::path()?.as_os_str().as_encoded_bytes());
+    Ok(format!("agent-{}", &hash[..12]))
+}
+
+pub fn log_path() -> Result<PathBuf> {
+    let base = dirs::data_local_dir().ok_or(anyhow!("data dir not found"))?;
+    Ok(base.join("rustpass").join("agent.log"))
+}
+
+fn request(req: &Request) -> Result<Response> {
+    let mut s = sys::connect()?;
+    writeln!(s, "{}", serde_json::to_string(req)?)?;
+    let mut line = Zeroizing::new(String::new());
+    BufReader::new(&mut s).read_line(&mut line)?;
+    let resp: Response = serde_json::from_str(&line)?;
+    if !resp.ok {
+        return Err(anyhow!("agent refused: {}", resp.error.unwrap_or_default()));
+    }
+    Ok(resp)
+}
+
+pub fn key() -> Option<DataKey> {
+    let resp = match request(&Request::Key) {
+        Ok(r) => r,
+        // ソケット（パイプ）が無い＝エージェントが動いていない
+        Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == ErrorKind::NotFound) => return None,
+        Err(e) => {
+            eprintln!("note: agent not used ({e})");
+            return None;
+        }
+    };
+    let bytes = Zeroizing::new(STANDARD.decode(resp.key?).ok()?);
+    DataKey::from_slice(&bytes)
+}
+
+pub fn status() -> Option<Status> {
+    let resp = request(&Request::Status).ok()?;
+    Some(Status { pid: resp.pid?, locks_in: resp.locks_in? })
+}
+
+pub fn stop() -> Result<bool> {
+    if status().is_none() { return Ok(false); }
+    request(&Request::Stop)?;
+    Ok(true)
+}
+
+// 鍵は標準入力で子プロセスに渡す（引数や環境変数には載せない）
+pub fn start(key: &DataKey, timeout: u64) -> Result<u32> {
+    let mut cmd = Command::new(std::env::current_exe()?);
+    cmd.arg("--vault").arg(vaults::path()?)
+        .args(["agent-serve", "--timeout", &timeout.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    sys::detach(&mut cmd);
+    let mut child = cmd.spawn()?;
+    let encoded = Zeroizing::new(STANDARD.encode(key.as_ref()));
+    child.stdin.take().ok_or(anyhow!("agent stdin unavailable"))?.write_all(encoded.as_bytes())?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if let Some(s) = status() { return Ok(s.pid); }
+        if let Some(code) = child.try_wait()? {
+            return Err(anyhow!("agent exited during startup ({code}); see {}", log_path()?.display()));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    Err(anyhow!("agent did not start"))
+}
+
+struct Peer {
+    /// ログに出す「uid=1000」「sid=S-1-5-21-...」
+    user: String,
+    same_user: bool,
+    pid: Option<u32>,
+    exe: Option<PathBuf>,
+}
+
+struct Log(fs::File);
+
+impl Log {
+    fn open() -> Result<Self> {
+        let path = log_path()?;
+        if let Some(d) = path.parent() { fs::create_dir_all(d)?; }
+        let mut opts = OpenOptions::new();
+        opts.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        Ok(Log(opts.open(path)?))
+    }
+
+    fn write(&mut self, peer: Option<&Peer>, what: &str) {
+        let who = match peer {
+            Some(p) => format!(
+                "pid={} {} exe={}",
+                p.pid.map_or("?".into(), |x| x.to_string()), p.user,
+                p.exe.as_deref().map_or("?".into(), |x| x.display().to_string()),
+            ),
+            None => "agent".into(),
+        };
+        let _ = writeln!(self.0, "{} {who} {what}", now_iso());
+    }
+}
+
+// 同じユーザーで、許可リストがあればそこに含まれる実行ファイルのみ。rustpass 自身は常に許可
+fn authorize(p: &Peer, allowed: &[PathBuf], own_exe: Option<&Path>) -> std::result::Result<(), String> {
+    if !p.same_user { return Err(format!("{} is not the agent owner", p.user)); }
+    if allowed.is_empty() { return Ok(()); }
+    let Some(exe) = &p.exe else { return Err("cannot determine the client executable".into()) };
+    let ok = own_exe == Some(exe.as_path())
+        || allowed.iter().any(|a| a == exe || fs::canonicalize(a).is_ok_and(|c| &c == exe));
+    if ok { Ok(()) } else { Err(format!("{} is not in agent.allowed_executables", exe.display())) }
+}
+
+// 鍵をスワップに書き出させず、コアダンプや ptrace からも読めないようにする（失敗してもログに残して続ける）
+fn protect(key: &DataKey, log: &mut Log) {
+    if let Err(e) = rustpass_core::secure::no_core_dumps() {
+        log.write(None, &format!("warning: cannot disable core dumps ({e})"));
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    // SAFETY: PR_SET_DUMPABLE は引数1つの単純な設定
+    if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0) } != 0 {
+        log.write(None, &format!("warning: cannot mark the agent non-dumpable ({})", io::Error::last_os_error()));
+    }
+    if !key.is_locked() {
+        log.write(None, "warning: mlock failed; the key may be swapped out");
+    }
+}
+
+fn reply(s: &mut impl Write, resp: &Response) {
+    if let Ok(text) = serde_json::to_string(resp).map(Zeroizing::new) {
+        let _ = writeln!(s, "{}", text.as_str());
+    }
+}
+
+fn refuse(s: &mut impl Write, msg: String) {
+    reply(s, &Response { error: Some(msg), ..Default::default() });
+}
+
+pub fn serve(timeout: u64, allowed: &[PathBuf]) -> Result<()> {
+    let mut encoded = Zeroizing::new(String::new());
+    io::stdin().read_to_string(&mut encoded)?;
+    let bytes = Zeroizing::new(STANDARD.decode(encoded.trim())?);
+    drop(encoded);
+    let key = DataKey::from_slice(&bytes).ok_or_else(|| anyhow!("agent key has wrong length"))?;
+    drop(bytes);
+    let mut log = Log::open()?;
+    protect(&key, &mut log);
+
+    let listener = sys::Listener::bind()?;
+    log.write(None, &format!("started (pid={}, timeout {timeout}s)", std::process::id()));
+
+    let own_exe = std::env::current_exe().ok();
+    let idle = Duration::from_secs(timeout);
+    let mut deadline = Instant::now() + idle;
+    let stopped = loop {
+        if Instant::now() >= deadline { break "locked after idle timeout"; }
+        let Some(mut s) = listener.accept()? else {
+            std::thread::sleep(Duration::from_millis(100));
+            continue;
+        };
+        let p = match sys::peer(&s) {
+            Ok(p) => p,
+            Err(e) => {
+                log.write(None, &format!("denied: peer credentials unavailable ({e})"));
+                continue;
+            }
+        };
+        if let Err(why) = authorize(&p, allowed, own_exe.as_deref()) {
+            log.write(Some(&p), &format!("denied: {why}"));
+            refuse(&mut s, why);
+            continue;
+        }
+        let mut line = String::new();
+        let req = BufReader::new(&mut s).read_line(&mut line).ok().and_then(|_| serde_json::from_str::<Request>(&line).ok());
+        let Some(req) = req else {
+            log.write(Some(&p), "denied: malformed request");
+            refuse(&mut s, "malformed request".into());
+            continue;
+        };
+        log.write(Some(&p), &format!("{req:?}").to_lowercase());
+        match req {
+            Request::Key => {
+                deadline = Instant::now() + idle;
+                let resp = Response { ok: true, key: Some(STANDARD.encode(key.as_slice())), ..Default::default() };
+                reply(&mut s, &resp);
+                // 応答に使った鍵の文字列も消しておく
+                drop(resp.key.map(Zeroizing::new));
+            }
+            Request::Status => {
+                let left = deadline.saturating_duration_since(Instant::now()).as_secs();
+                reply(&mut s, &Response { ok: true, pid: Some(std::process::id()), locks_in: Some(left), ..Default::default() });
+            }
+            Request::Stop => {
+                reply(&mut s, &Response { ok: true, ..Default::default() });
+                break "stopped by client";
+            }
+        }
+    };
+    drop(listener);
+    log.write(None, stopped);
+    Ok(())
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::*;
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::os::unix::process::CommandExt;
+
+    fn dir() -> Result<PathBuf> {
+        let base = dirs::runtime_dir()
+            .or_else(dirs::data_local_dir)
+            .ok_or(anyhow!("runtime dir not found"))?;
+        Ok(base.join("rustpass"))
+    }
+
+    fn socket_path() -> Result<PathBuf> {
+        Ok(dir()?.join(format!("{}.sock", endpoint_name()?)))
+    }
+
+    pub fn connect() -> Result<UnixStream> {
+        let s = UnixStream::connect(socket_path()?)?;
+        s.set_read_timeout(Some(Duration::from_secs(5)))?;
+        Ok(s)
+    }
+
+    // 端末を閉じても一緒に終わらないように、別のプロセスグループで動かす
+    pub fn detach(cmd: &mut Command) {
+        cmd.process_group(0);
+    }
+
+    pub struct Listener {
+        inner: UnixListener,
+        path: PathBuf,
+    }
+
+    impl Listener {
+        pub fn bind() -> Result<Self> {
+            fs::DirBuilder::new().recursive(true).mode(0o700).create(dir()?)?;
+            let path = socket_path()?;
+            // 応答しない古いソケットは削除して作り直す
+            if path.exists() && UnixStream::connect(&path).is_err() { fs::remove_file(&path)?; }
+            let inner = UnixListener::bind(&path)?;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+            inner.set_nonblocking(true)?;
+            Ok(Listener { inner, path })
+        }
+
+        /// 待っている接続が無ければ None
+        pub fn accept(&self) -> Result<Option<UnixStream>> {
+            let s = match self.inner.accept() {
+                Ok((s, _)) => s,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            s.set_nonblocking(false)?;
+            s.set_read_timeout(Some(Duration::from_secs(2)))?;
+            Ok(Some(s))
+        }
+    }
+
+    impl Drop for Listener {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn peer(s: &UnixStream) -> io::Result<Peer> {
+        use std::os::fd::AsRawFd;
+        let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        // SAFETY: cred と len は getsockopt(SO_PEERCRED) が要求するサイズのバッファ
+        let r = unsafe {
+            libc::getsockopt(
+                s.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PEERCRED,
+                &mut cred as *mut libc::ucred as *mut libc::c_void, &mut len,
+            )
+        };
+        if r != 0 { return Err(io::Error::last_os_error()); }
+        let exe = fs::read_link(format!("/proc/{}/exe", cred.pid)).ok();
+        Ok(Peer { user: format!("uid={}", cred.uid), same_user: cred.uid == uid(), pid: Some(cred.pid as u32), exe })
+    }
+
+    // macOS / BSD は getpeereid で UID のみ（実行ファイルは特定できない）
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn peer(s: &UnixStream) -> io::Result<Peer> {
+        use std::os::fd::AsRawFd;
+        let (mut uid, mut gid) = (0, 0);
+        // SAFETY: uid / gid は有効な書き込み先
+        if unsafe { libc::getpeereid(s.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Peer { user: format!("uid={uid}"), same_user: uid == self::uid(), pid: None, exe: None })
+    }
+
+    fn uid() -> u32 {
+        // SAFETY: getuid は常に成功する
+        unsafe { libc::getuid() }
+    }
+}
+
+// 名前付きパイプは \\.\pipe\rustpass-<SID>-agent（パイプの名前はユーザーをまたいで共有なので SID で分ける）。
+// パイプの DACL は自分の SID だけに絞り、接続の両側で相手のプロセスが同じユーザーか確かめる
+// （他のユーザーが先に同じ名前のパイプを作っていても、そこへは要求を送らない）
+#[cfg(windows)]
+mod sys {
+    use super::*;
+    use interprocess::os::windows::named_pipe::{pipe_mode, DuplexPipeStream, PipeListener, PipeListenerOptions};
+    use interprocess::os::windows::security_descriptor::SecurityDescriptor;
+    use std::os::windows::ffi::OsStringExt;
+    use std::os::windows::process::CommandExt;
+    use widestring::U16CString;
+    use windows_sys::Win32::Foundation::{CloseHandle, LocalFree, HANDLE};
+    use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows_sys::Win32::Security::{GetTokenInformation, TokenUser, TOKEN_QUERY, TOKEN_USER};
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, OpenProcessToken, QueryFullProcessImageNameW, CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS,
+        PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    type Stream = DuplexPipeStream<pipe_mode::Bytes>;
+
+    struct Handle(HANDLE);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            // SAFETY: self.0 は開いたハンドルで、ここでだけ閉じる
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    // プロセスのユーザーの SID（S-1-5-21-...）と実行ファイルのパス
+    fn process_info(pid: u32) -> io::Result<(String, Option<PathBuf>)> {
+        // SAFETY: 戻り値は検査し、得たハンドルは Handle が閉じる
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        if process.is_null() { return Err(io::Error::last_os_error()); }
+        let process = Handle(process);
+        let mut token = std::ptr::null_mut();
+        // SAFETY: token は有効な書き込み先
+        if unsafe { OpenProcessToken(process.0, TOKEN_QUERY, &mut token) } == 0 { return Err(io::Error::last_os_error()); }
+        let token = Handle(token);
+        // TOKEN_USER はポインタを含むので、u64 の配列で境界をそろえる
+        let mut buf = [0u64; 64];
+        let mut len = 0;
+        // SAFETY: buf は size_of_val(&buf) バイトの書き込み先
+        if unsafe { GetTokenInformation(token.0, TokenUser, buf.as_mut_ptr().cast(), size_of_val(&buf) as u32, &mut len) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: GetTokenInformation(TokenUser) が buf の先頭に TOKEN_USER を書いた
+        let user = unsafe { &*buf.as_ptr().cast::<TOKEN_USER>() };
+        let mut text = std::ptr::null_mut();
+        // SAFETY: Sid は上の TOKEN_USER が指す有効な SID、text は有効な書き込み先
+        if unsafe { ConvertSidToStringSidW(user.User.Sid, &mut text) } == 0 { return Err(io::Error::last_os_error()); }
+        // SAFETY: text は ConvertSidToStringSidW が確保した NUL 終端の文字列で、読んだ後に LocalFree で解放する
+        let sid = unsafe {
+            let sid = U16CString::from_ptr_str(text).to_string_lossy();
+            LocalFree(text.cast());
+            sid
+        };
+        let mut path = [0u16; 1024];
+        let mut size = path.len() as u32;
+        // SAFETY: path は size 文字の書き込み先
+        let exe = (unsafe { QueryFullProcessImageNameW(process.0, PROCESS_NAME_WIN32, path.as_mut_ptr(), &mut size) } != 0)
+            .then(|| PathBuf::from(std::ffi::OsString::from_wide(&path[..size as usize])));
+        Ok((sid, exe))
+    }
+
+    fn own_sid() -> io::Result<String> {
+        Ok(process_info(std::process::id())?.0)
+    }
+
+    fn pipe_path() -> Result<String> {
+        Ok(format!(r"\\.\pipe\rustpass-{}-{}", own_sid()?, endpoint_name()?))
+    }
+
+    pub fn connect() -> Result<Stream> {
+        let s = Stream::connect_by_path(pipe_path()?)?;
+        let (sid, _) = process_info(s.server_process_id()?)?;
+        if sid != own_sid()? { return Err(anyhow!("the agent pipe is owned by another user ({sid})")); }
+        Ok(s)
+    }
+
+    // コンソールを閉じても一緒に終わらないように、コンソールから切り離して動かす
+    pub fn detach(cmd: &mut Command) {
+        cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+
+    pub struct Listener(PipeListener<pipe_mode::Bytes, pipe_mode::Bytes>);
+
+    impl Listener {
+        pub fn bind() -> Result<Self> {
+            // 保護された DACL に自分の SID の GENERIC_ALL だけを入れる
+            let sddl = U16CString::from_str(format!("D:P(A;;GA;;;{})", own_sid()?))?;
+            let inner = PipeListenerOptions::new()
+                .path(pipe_path()?)
+                .security_descriptor(Some(SecurityDescriptor::deserialize(&sddl)?))
+                .nonblocking(true)
+                .create_duplex::<pipe_mode::Bytes>()?;
+            Ok(Listener(inner))
+        }
+
+        /// 待っている接続が無ければ None
+        // 名前付きパイプには読み込みのタイムアウトが無いので、同じユーザーのプロセスが何も送らないと応答が止まる
+        pub fn accept(&self) -> Result<Option<Stream>> {
+            let s = match self.0.accept() {
+                Ok(s) => s,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            s.set_nonblocking(false)?;
+            Ok(Some(s))
+        }
+    }
+
+    pub fn peer(s: &Stream) -> io::Result<Peer> {
+        let pid = s.client_process_id()?;
+        let (sid, exe) = process_info(pid)?;
+        Ok(Peer { same_user: sid == own_sid()?, user: format!("sid={sid}"), pid: Some(pid), exe })
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use super::*;
+    use crate::error::err;
+
+    pub fn connect() -> Result<io::Empty> {
+        Err(io::Error::from(ErrorKind::NotFound).into())
+    }
+
+    pub fn detach(_cmd: &mut Command) {}
+
+    pub struct Listener;
+
+    impl Listener {
+        pub fn bind() -> Result<Self> {
+            Err(err!(InvalidInput, "the agent needs a Unix system or Windows"))
+        }
+
+        pub fn accept(&self) -> Result<Option<io::Empty>> {
+            Ok(None)
+        }
+    }
+
+    pub fn peer(_s: &io::Empty) -> io::Result<Peer> {
+        Err(ErrorKind::Unsupported.into())
+    }
+}
@@ -0,0 +1,49 @@
+// エントリの添付ファイル（リカバリーコードのPDF・2段階認証のバックアップQRなど）
+//
+// 中身は金庫の平文に base64 で埋め込み、金庫と一緒に暗号化する。金庫は保存のたびに全体を
+// 書き直すので、1ファイルあたりと金庫全体の大きさに上限を設ける。
+use crate::error::err;
+use crate::Vault;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// 1ファイルの上限
+pub const MAX_FILE: usize = 5 * 1024 * 1024;
+/// 金庫全体の上限
+pub const MAX_TOTAL: usize = 25 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    /// ファイル名（エントリ内で一意）
+    pub name: String,
+    #[serde(with = "crate::format::b64")]
+    pub data: Vec<u8>,
+    /// 添付した日時（RFC 3339）
+    pub added: String,
+}
+
+/// 金庫の添付ファイルの合計
+pub fn total(v: &Vault) -> usize {
+    v.entries.iter().flat_map(|e| &e.attachments).map(|a| a.data.len()).sum()
+}
+
+/// size バイトのファイルを追加できるか（replacing は置き換えられる既存のファイルの大きさ）
+pub fn check(v: &Vault, size: usize, replacing: usize) -> Result<()> {
+    if size > MAX_FILE {
+        return Err(err!(InvalidInput, "attachment is {}; the limit is {} per file", human(size), human(MAX_FILE)));
+    }
+    let after = total(v) - replacing + size;
+    if after > MAX_TOTAL {
+        return Err(err!(InvalidInput, "attachments would take {} of the vault; the limit is {}", human(after), human(MAX_TOTAL)));
+    }
+    Ok(())
+}
+
+/// 「12.3 KiB」のような大きさ
+pub fn human(n: usize) -> String {
+    match n {
+        n if n < 1024 => format!("{n} B"),
+        n if n < 1024 * 1024 => format!("{:.1} KiB", n as f64 / 1024.0),
+        n => format!("{:.1} MiB", n as f64 / (1024.0 * 1024.0)),
+    }
+}
@@ -0,0 +1,320 @@
+// 金庫の監査（ポリシー違反の検出）
+use crate::{cert, passkey, strength};
+use crate::config::{self, Config};
+use crate::error::err;
+use crate::{Entry, Vault};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use time::{format_description::well_known::Rfc3339, macros::format_description, Date, Duration, OffsetDateTime};
+
+pub struct AgeViolation<'a> {
+    pub entry: &'a Entry,
+    pub age_days: i64,
+    pub max_days: i64,
+    /// 上限を決めた設定のパターン（エントリ自身の max_age_days なら None）
+    pub pattern: Option<String>,
+}
+
+fn days_since(ts: &str) -> Option<i64> {
+    let t = OffsetDateTime::parse(ts, &Rfc3339).ok()?;
+    Some((OffsetDateTime::now_utc() - t).whole_days())
+}
+
+pub fn age_days(e: &Entry) -> Option<i64> {
+    days_since(e.password_changed_at.as_deref().unwrap_or(&e.updated_at))
+}
+
+// 作成から間もないエントリは「未使用」として報告しない
+const NEVER_USED_GRACE_DAYS: i64 = 30;
+
+// エントリ自身の上限があればそれを、無ければエントリ名・タグにマッチする中で最も厳しい上限を採用
+fn age_limit(e: &Entry, cfg: &Config) -> Result<Option<(i64, Option<String>)>> {
+    if let Some(d) = e.max_age_days { return Ok(Some((i64::from(d), None))); }
+    let mut best: Option<(i64, Option<String>)> = None;
+    for (pattern, age) in &cfg.max_password_age {
+        let hit = config::glob_match(pattern, &e.name)
+            || e.tags.iter().any(|t| config::glob_match(pattern, t));
+        if !hit { continue; }
+        let days = config::parse_days(age)?;
+        if best.as_ref().is_none_or(|(d, _)| days < *d) {
+            best = Some((days, Some(pattern.clone())));
+        }
+    }
+    Ok(best)
+}
+
+pub fn age_violation<'a>(e: &'a Entry, cfg: &Config) -> Result<Option<AgeViolation<'a>>> {
+    let Some((max_days, pattern)) = age_limit(e, cfg)? else { return Ok(None) };
+    let Some(age) = age_days(e) else { return Ok(None) };
+    Ok((age > max_days).then_some(AgeViolation { entry: e, age_days: age, max_days, pattern }))
+}
+
+/// 有効期限の指定（YYYY-MM-DD か、今日からの期間 "90d" など）を YYYY-MM-DD にする
+pub fn parse_expiry(s: &str) -> Result<String> {
+    let s = s.trim();
+    if let Ok(d) = Date::parse(s, format_description!("[year]-[month]-[day]")) { return Ok(d.format(format_description!("[year]-[month]-[day]"))?); }
+    let days = config::parse_days(s).map_err(|_| err!(InvalidInput, "expiry must be a date (YYYY-MM-DD) or a duration like 90d"))?;
+    Ok((OffsetDateTime::now_utc().date() + Duration::days(days)).format(format_description!("[year]-[month]-[day]"))?)
+}
+
+/// 有効期限までの日数（過ぎていれば負。期限が無いか読めなければ None）
+pub fn expires_in(e: &Entry) -> Option<i64> {
+    let d = Date::parse(e.expires_at.as_deref()?, format_description!("[year]-[month]-[day]")).ok()?;
+    Some((d - OffsetDateTime::now_utc().date()).whole_days())
+}
+
+// 期限切れの警告（設定の warn_expired）。金庫を開いたときに数え、コマンドの最後に1行だけ表示する
+static WARN_EXPIRED: AtomicBool = AtomicBool::new(false);
+static EXPIRED: AtomicUsize = AtomicUsize::new(0);
+
+/// 以降に開いた金庫の期限切れのエントリを warn_expired で知らせる
+pub fn enable_expired_warning() {
+    WARN_EXPIRED.store(true, Ordering::Relaxed);
+}
+
+/// 開いた（保存する）金庫の期限切れのエントリを数えておく
+pub fn note_expired(vault: &Vault) {
+    if !WARN_EXPIRED.load(Ordering::Relaxed) { return; }
+    EXPIRED.store(vault.entries.iter().filter(|e| is_expired(e)).count(), Ordering::Relaxed);
+}
+
+/// 期限切れのエントリがあれば1行の警告を表示する
+pub fn warn_expired() {
+    match EXPIRED.load(Ordering::Relaxed) {
+        0 => {}
+        1 => eprintln!("warning: 1 entry has an expired password (see `rustpass audit age`)"),
+        n => eprintln!("warning: {n} entries have expired passwords (see `rustpass audit age`)"),
+    }
+}
+
+/// エントリ自身に設定した期限（expires_at・max_age_days）を過ぎているか
+pub fn is_expired(e: &Entry) -> bool {
+    expires_in(e).is_some_and(|d| d < 0)
+        || e.max_age_days.zip(age_days(e)).is_some_and(|(max, age)| age > i64::from(max))
+}
+
+/// 報告する問題（--json では kind で種類を判別できる）
+#[derive(Serialize)]
+struct Issue {
+    name: String,
+    kind: &'static str,
+    message: String,
+}
+
+pub fn run(vault: &Vault, cfg: &Config, policy: bool, passkeys: bool, json: bool) -> Result<()> {
+    let mut issues = Vec::new();
+    let mut issue = |e: &Entry, kind, message| issues.push(Issue { name: e.name.clone(), kind, message });
+    if policy {
+        match &vault.policy {
+            Some(p) => for e in &vault.entries {
+                if e.password.is_empty() { continue; }
+                let v = p.violations(&e.password, vault, &e.name);
+                if v.is_empty() { continue; }
+                issue(e, "policy", format!("violates policy: {}", v.join("; ")));
+            },
+            None if json => eprintln!("note: no vault policy set (see `rustpass policy set`)"),
+            None => println!("No vault policy set (see `rustpass policy set`)."),
+        }
+    }
+    // --accessible では "d" を略さず読み上げる
+    let days = |n: i64| if cfg.accessible { format!("{n} days") } else { format!("{n}d") };
+    for e in &vault.entries {
+        if let Some(v) = age_violation(e, cfg)? {
+            let limit = match &v.pattern {
+                Some(p) => format!("policy {p:?}"),
+                None => "the entry".to_string(),
+            };
+            issue(v.entry, "password_age", format!(
+                "password is {} old, {limit} allows {}",
+                days(v.age_days), days(v.max_days)
+            ));
+        }
+        if let Some(left) = expires_in(e).filter(|d| *d < 0) {
+            issue(e, "password_expired", format!("password expired {} ago", days(-left)));
+        }
+    }
+    // 参照記録が有効な場合のみ、長期間使われていない/一度も使われていないエントリを報告
+    if cfg.track_access {
+        let stale_days = cfg.stale_after_days()?;
+        for e in &vault.entries {
+            match &e.last_accessed {
+                Some(a) => match days_since(a) {
+                    Some(d) if d > stale_days => issue(e, "stale", format!("not used for {} (stale)", days(d))),
+                    _ => continue,
+                },
+                None => {
+                    let created = e.created_at.as_deref().unwrap_or(&e.updated_at);
+                    match days_since(created) {
+                        Some(d) if d > NEVER_USED_GRACE_DAYS => issue(e, "never_used", format!("never used ({} since created)", days(d))),
+                        _ => continue,
+                    }
+                }
+            }
+        }
+    }
+    let warn_days = cfg.cert_expiry_warning_days()?;
+    for e in &vault.entries {
+        let Some(left) = e.cert.as_ref().and_then(cert::days_left) else { continue };
+        if left < 0 {
+            issue(e, "certificate_expired", format!("certificate expired {} ago", days(-left)));
+        } else if left <= warn_days {
+            issue(e, "certificate_expiring", format!("certificate expires in {}", days(left)));
+        }
+    }
+    if passkeys {
+        for e in passkey::missing(vault) {
+            issue(e, "missing_passkey", format!("no passkey recorded for {}", e.url.as_deref().unwrap_or_default()));
+        }
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&issues)?);
+        return Ok(());
+    }
+    if issues.is_empty() {
+        println!("No issues found.");
+        return Ok(());
+    }
+    // スクリーンリーダーでは件数を先に伝える
+    if cfg.accessible { println!("{} issue(s) found:", issues.len()); }
+    for i in &issues { println!("{}: {}", i.name, i.message); }
+    if !cfg.accessible { println!("{} issue(s) found.", issues.len()); }
+    Ok(())
+}
+
+/// 全エントリのパスワード強度（弱い順）
+pub fn strength(vault: &Vault, cfg: &Config, weak_only: bool, json: bool) -> Result<()> {
+    let mut rated: Vec<(&Entry, strength::Estimate)> = vault.entries.iter()
+        .filter(|e| !e.password.is_empty())
+        .map(|e| (e, strength::estimate(&e.password, &[&e.name, &e.username, e.url.as_deref().unwrap_or_default()])))
+        .collect();
+    rated.sort_by(|a, b| a.1.log10_guesses.total_cmp(&b.1.log10_guesses).then_with(|| a.0.name.cmp(&b.0.name)));
+    let total = rated.len();
+    let weak = rated.iter().filter(|(_, s)| s.score < 3).count();
+    if weak_only { rated.retain(|(_, s)| s.score < 3); }
+    if json {
+        let out: Vec<serde_json::Value> = rated.iter().map(|(e, s)| serde_json::json!({
+            "name": e.name,
+            "score": s.score,
+            "rating": s.label(),
+            "bits": (s.bits() * 10.0).round() / 10.0,
+            "crack_time": s.crack_time(),
+            "warnings": s.warnings,
+        })).collect();
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+    if cfg.accessible { println!("{weak} of {total} passwords are weak."); }
+    for (e, s) in &rated {
+        if cfg.accessible {
+            let mut parts = vec![e.name.clone(), format!("{}, score {} of 4", s.label(), s.score), format!("crack time {}", s.crack_time())];
+            parts.extend(s.warnings.iter().map(|w| w.to_string()));
+            println!("{}.", parts.join(", "));
+            continue;
+        }
+        let why = if s.warnings.is_empty() { String::new() } else { format!("  ({})", s.warnings.join("; ")) };
+        println!("{}  {} {}/4  crack time {}{}", e.name, s.label(), s.score, s.crack_time(), why);
+    }
+    if !cfg.accessible { println!("{weak} of {total} passwords are weak."); }
+    Ok(())
+}
+
+/// 同じパスワードを使っているエントリのまとまり（多い順）
+pub fn reused(vault: &Vault) -> Vec<Vec<&Entry>> {
+    let mut groups: HashMap<&str, Vec<&Entry>> = HashMap::new();
+    for e in vault.entries.iter().filter(|e| !e.password.is_empty()) {
+        groups.entry(e.password.as_str()).or_default().push(e);
+    }
+    let mut groups: Vec<Vec<&Entry>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    for g in &mut groups { g.sort_by(|a, b| a.name.cmp(&b.name)); }
+    groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].name.cmp(&b[0].name)));
+    groups
+}
+
+/// パスワードがユーザー名かエントリ名と同じなら、その項目名（大文字小文字・前後の空白は無視）
+pub fn same_as_name(e: &Entry) -> Option<&'static str> {
+    let p = e.password.trim();
+    if p.is_empty() { return None; }
+    if !e.username.is_empty() && p.eq_ignore_ascii_case(e.username.trim()) { return Some("username"); }
+    if p.eq_ignore_ascii_case(e.name.trim()) { return Some("name"); }
+    None
+}
+
+/// パスワードの使い回しと、ユーザー名・エントリ名と同じパスワードの報告
+pub fn reuse(vault: &Vault, cfg: &Config, json: bool) -> Result<()> {
+    let groups = reused(vault);
+    let same: Vec<(&Entry, &str)> = vault.entries.iter().filter_map(|e| same_as_name(e).map(|f| (e, f))).collect();
+    if json {
+        let out = serde_json::json!({
+            "reused": groups.iter().map(|g| g.iter().map(|e| &e.name).collect::<Vec<_>>()).collect::<Vec<_>>(),
+            "same_as_name": same.iter().map(|(e, f)| serde_json::json!({ "name": e.name, "matches": f })).collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+    let entries: usize = groups.iter().map(Vec::len).sum();
+    let summary = format!(
+        "{} reused across {entries} entries; {} equal to the username or name.",
+        if groups.len() == 1 { "1 password".to_string() } else { format!("{} passwords", groups.len()) },
+        same.len(),
+    );
+    if groups.is_empty() && same.is_empty() {
+        println!("No reused passwords found.");
+        return Ok(());
+    }
+    // スクリーンリーダーでは件数を先に伝える
+    if cfg.accessible { println!("{summary}"); }
+    for g in &groups {
+        let names: Vec<&str> = g.iter().map(|e| e.name.as_str()).collect();
+        println!("same password ({} entries): {}", g.len(), names.join(", "));
+    }
+    for (e, f) in &same { println!("{}: password is the same as the {f}", e.name); }
+    if !cfg.accessible { println!("{summary}"); }
+    Ok(())
+}
+
+/// 古いパスワードと期限切れのパスワード（古い順）。older_than を指定すればその日数より古いものも含める
+pub fn age(vault: &Vault, cfg: &Config, older_than: Option<i64>, json: bool) -> Result<()> {
+    let days = |n: i64| if cfg.accessible { format!("{n} days") } else { format!("{n}d") };
+    let mut found: Vec<(&Entry, Option<i64>, Vec<String>)> = Vec::new();
+    let total = vault.entries.iter().filter(|e| !e.password.is_empty()).count();
+    for e in vault.entries.iter().filter(|e| !e.password.is_empty()) {
+        let age = age_days(e);
+        let mut reasons = Vec::new();
+        if let Some(left) = expires_in(e).filter(|d| *d < 0) {
+            reasons.push(format!("expired {} ago", days(-left)));
+        }
+        if let Some(v) = age_violation(e, cfg)? {
+            match &v.pattern {
+                Some(p) => reasons.push(format!("older than {} (policy {p:?})", days(v.max_days))),
+                None => reasons.push(format!("older than {} (entry max age)", days(v.max_days))),
+            }
+        }
+        if let Some(limit) = older_than.filter(|l| reasons.is_empty() && age.is_some_and(|a| a > *l)) {
+            reasons.push(format!("older than {}", days(limit)));
+        }
+        if !reasons.is_empty() { found.push((e, age, reasons)); }
+    }
+    found.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+    if json {
+        let out: Vec<serde_json::Value> = found.iter().map(|(e, age, reasons)| serde_json::json!({
+            "name": e.name,
+            "age_days": age,
+            "expires_at": e.expires_at,
+            "max_age_days": e.max_age_days,
+            "reasons": reasons,
+        })).collect();
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+    let summary = format!("{} of {total} passwords need changing.", found.len());
+    if cfg.accessible { println!("{summary}"); }
+    for (e, age, reasons) in &found {
+        let age = age.map_or("unknown age".to_string(), |a| format!("{} old", days(a)));
+        if cfg.accessible { println!("{}, password {age}, {}.", e.name, reasons.join(", ")); }
+        else { println!("{}  password {age}  ({})", e.name, reasons.join("; ")); }
+    }
+    if !cfg.accessible { println!("{summary}"); }
+    Ok(())
+}
@@ -0,0 +1,268 @@
+// 自動入力（フォーカス中のウィンドウへキー入力を送る）
+//
+// シーケンスは KeePass 互換の書式で、エントリごとに上書きできる。
+// 入力の送信は X11 では xdotool、Wayland では wtype、macOS では osascript（System Events）、
+// Windows では PowerShell（SendKeys）に任せ、秘密は標準入力で渡す。
+use crate::error::err;
+use crate::Entry;
+use anyhow::{anyhow, Result};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+pub const DEFAULT_SEQUENCE: &str = "{USERNAME}{TAB}{PASSWORD}{ENTER}";
+
+// キー名と xkb の keysym 名（xdotool・wtype 共通。macOS・Windows では下の表で読み替える）
+const KEYS: [(&str, &str); 17] = [
+    ("TAB", "Tab"), ("ENTER", "Return"), ("SPACE", "space"), ("BACKSPACE", "BackSpace"), ("BS", "BackSpace"),
+    ("ESC", "Escape"), ("DELETE", "Delete"), ("DEL", "Delete"), ("INSERT", "Insert"),
+    ("UP", "Up"), ("DOWN", "Down"), ("LEFT", "Left"), ("RIGHT", "Right"),
+    ("HOME", "Home"), ("END", "End"), ("PGUP", "Page_Up"), ("PGDN", "Page_Down"),
+];
+
+enum Value {
+    Username,
+    Password,
+    Url,
+    Notes,
+    Totp,
+    Field(String),
+}
+
+enum Token {
+    Text(String),
+    Value(Value),
+    Key(&'static str, usize),
+    /// {DELAY n}：n ミリ秒待つ
+    Pause(u64),
+    /// {DELAY=n}：以降のキー入力の間隔
+    KeyDelay(u64),
+}
+
+fn placeholder(body: &str) -> Result<Token> {
+    if let Some(name) = body.strip_prefix("S:") {
+        return Ok(Token::Value(Value::Field(name.to_string())));
+    }
+    let upper = body.to_uppercase();
+    let ms = |s: &str| s.trim().parse::<u64>().map_err(|_| err!(InvalidInput, "bad delay in {{{body}}}"));
+    if let Some(n) = upper.strip_prefix("DELAY=") { return Ok(Token::KeyDelay(ms(n)?)); }
+    if let Some(n) = upper.strip_prefix("DELAY ") { return Ok(Token::Pause(ms(n)?)); }
+    let value = match upper.as_str() {
+        "USERNAME" => Some(Value::Username),
+        "PASSWORD" => Some(Value::Password),
+        "URL" => Some(Value::Url),
+        "NOTES" => Some(Value::Notes),
+        "TOTP" => Some(Value::Totp),
+        _ => None,
+    };
+    if let Some(v) = value { return Ok(Token::Value(v)); }
+    // {TAB 3} のような繰り返し
+    let (name, count) = match upper.split_once(' ') {
+        Some((n, c)) => (n, c.trim().parse().map_err(|_| err!(InvalidInput, "bad repeat count in {{{body}}}"))?),
+        None => (upper.as_str(), 1),
+    };
+    KEYS.iter().find(|(k, _)| *k == name)
+        .map(|&(_, sym)| Token::Key(sym, count))
+        .ok_or_else(|| err!(InvalidInput, "unknown auto-type placeholder {{{body}}}"))
+}
+
+fn parse(seq: &str) -> Result<Vec<Token>> {
+    let mut out = Vec::new();
+    let mut text = String::new();
+    let mut rest = seq;
+    while let Some(c) = rest.chars().next() {
+        if c != '{' {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        // {{} と {}} は波括弧そのもの
+        if let Some(r) = rest.strip_prefix("{{}").or(rest.strip_prefix("{}}")) {
+            text.push(rest.as_bytes()[1] as char);
+            rest = r;
+            continue;
+        }
+        let end = rest.find('}').ok_or_else(|| err!(InvalidInput, "unclosed {{ in auto-type sequence"))?;
+        if !text.is_empty() { out.push(Token::Text(std::mem::take(&mut text))); }
+        out.push(placeholder(&rest[1..end])?);
+        rest = &rest[end + 1..];
+    }
+    if !text.is_empty() { out.push(Token::Text(text)); }
+    Ok(out)
+}
+
+/// 保存前の書式チェック
+pub fn validate(seq: &str) -> Result<()> {
+    parse(seq).map(|_| ())
+}
+
+fn resolve(v: &Value, e: &Entry) -> Result<Zeroizing<String>> {
+    let s = match v {
+        Value::Username => e.username.clone(),
+        Value::Password => e.password.clone(),
+        Value::Url => e.url.clone().unwrap_or_default(),
+        Value::Notes => e.notes.clone().unwrap_or_default(),
+        Value::Totp => {
+            let otp = e.otp.as_ref().ok_or_else(|| err!(NotFound, "entry {:?} has no TOTP", e.name))?;
+            otp.now()?.0
+        }
+        Value::Field(n) => e.fields.iter().find(|f| &f.name == n)
+            .map(|f| f.value.clone())
+            .ok_or_else(|| err!(NotFound, "field {n:?} not found in {:?}", e.name))?,
+    };
+    Ok(Zeroizing::new(s))
+}
+
+// keysym 名と macOS の仮想キーコード・SendKeys のキー名（Insert は Mac に無いので Help キー）
+const OTHER_KEYS: [(&str, u8, &str); 15] = [
+    ("Tab", 48, "{TAB}"), ("Return", 36, "{ENTER}"), ("space", 49, " "), ("BackSpace", 51, "{BACKSPACE}"),
+    ("Escape", 53, "{ESC}"), ("Delete", 117, "{DELETE}"), ("Insert", 114, "{INSERT}"),
+    ("Up", 126, "{UP}"), ("Down", 125, "{DOWN}"), ("Left", 123, "{LEFT}"), ("Right", 124, "{RIGHT}"),
+    ("Home", 115, "{HOME}"), ("End", 119, "{END}"), ("Page_Up", 116, "{PGUP}"), ("Page_Down", 121, "{PGDN}"),
+];
+
+fn other_key(sym: &str) -> (u8, &'static str) {
+    OTHER_KEYS.iter().find(|(s, _, _)| *s == sym).map(|&(_, code, name)| (code, name)).unwrap_or((0, ""))
+}
+
+// AppleScript の文字列リテラル
+fn applescript_str(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// PowerShell の単一引用符の文字列（‘ ’ なども引用符として扱われるので重ねる）
+fn powershell_str(s: &str) -> String {
+    let mut out = String::from("'");
+    for c in s.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') { out.push(c); }
+        out.push(c);
+    }
+    out.push('\'');
+    out
+}
+
+#[derive(Clone, Copy)]
+enum Backend {
+    Xdotool,
+    Wtype,
+    Osascript,
+    Powershell,
+}
+
+impl Backend {
+    fn detect() -> Result<Self> {
+        if cfg!(target_os = "macos") { return Ok(Backend::Osascript); }
+        if cfg!(windows) { return Ok(Backend::Powershell); }
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() { return Ok(Backend::Wtype); }
+        if std::env::var_os("DISPLAY").is_some() { return Ok(Backend::Xdotool); }
+        Err(err!(InvalidInput, "auto-type needs a graphical session (X11, Wayland, macOS or Windows)"))
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Backend::Xdotool => "xdotool",
+            Backend::Wtype => "wtype",
+            Backend::Osascript => "osascript",
+            Backend::Powershell => "powershell",
+        }
+    }
+
+    // osascript・PowerShell はスクリプトを標準入力から読ませる
+    fn script(self, script: &str) -> Result<()> {
+        let args: Vec<String> = match self {
+            Backend::Powershell => ["-NoProfile", "-NonInteractive", "-Command", "-"].map(String::from).into(),
+            _ => vec!["-".into()],
+        };
+        self.run(&args, Some(script))
+    }
+
+    fn run(self, args: &[String], stdin: Option<&str>) -> Result<()> {
+        let prog = self.program();
+        let mut child = Command::new(prog)
+            .args(args)
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => err!(NotFound, "{prog} not found in PATH (needed for auto-type)"),
+                _ => e.into(),
+            })?;
+        if let Some(s) = stdin {
+            child.stdin.take().ok_or(anyhow!("no stdin for {prog}"))?.write_all(s.as_bytes())?;
+        }
+        let out = child.wait_with_output()?;
+        if !out.status.success() {
+            return Err(anyhow!("{prog} failed: {}", String::from_utf8_lossy(&out.stderr).trim()));
+        }
+        Ok(())
+    }
+
+    fn text(self, s: &str, delay: u64) -> Result<()> {
+        let args = match self {
+            Backend::Xdotool => vec!["type".into(), "--delay".into(), delay.to_string(), "--file".into(), "-".into()],
+            Backend::Wtype => vec!["-d".into(), delay.to_string(), "-".into()],
+            Backend::Osascript => return self.script(&format!(
+                "set t to {}\ntell application \"System Events\"\nrepeat with c in (characters of t)\nkeystroke c\ndelay {}\nend repeat\nend tell\n",
+                applescript_str(s), delay as f64 / 1000.0,
+            )),
+            // SendKeys で特別な意味を持つ文字は {} で囲む
+            Backend::Powershell => return self.script(&format!(
+                "Add-Type -AssemblyName System.Windows.Forms\n\
+                 foreach ($c in {}.ToCharArray()) {{ $k = if ('+^%~(){{}}[]'.Contains($c)) {{ '{{' + $c + '}}' }} else {{ [string]$c }}; \
+                 [System.Windows.Forms.SendKeys]::SendWait($k); Start-Sleep -Milliseconds {delay} }}\n",
+                powershell_str(s),
+            )),
+        };
+        self.run(&args, Some(s))
+    }
+
+    fn key(self, sym: &str, count: usize, delay: u64) -> Result<()> {
+        let (code, name) = other_key(sym);
+        let mut args: Vec<String> = match self {
+            Backend::Xdotool => vec!["key".into(), "--delay".into(), delay.to_string()],
+            Backend::Wtype => vec!["-d".into(), delay.to_string()],
+            Backend::Osascript => return self.script(&format!(
+                "tell application \"System Events\"\nrepeat {count} times\nkey code {code}\ndelay {}\nend repeat\nend tell\n",
+                delay as f64 / 1000.0,
+            )),
+            Backend::Powershell => return self.script(&format!(
+                "Add-Type -AssemblyName System.Windows.Forms\n\
+                 for ($i = 0; $i -lt {count}; $i++) {{ [System.Windows.Forms.SendKeys]::SendWait('{name}'); Start-Sleep -Milliseconds {delay} }}\n",
+            )),
+        };
+        for _ in 0..count {
+            if let Backend::Wtype = self { args.push("-k".into()); }
+            args.push(sym.into());
+        }
+        self.run(&args, None)
+    }
+}
+
+/// エントリのシーケンス（未設定なら既定）を入力する
+pub fn run(e: &Entry, wait: u64) -> Result<()> {
+    let seq = e.autotype.as_deref().unwrap_or(DEFAULT_SEQUENCE);
+    let tokens = parse(seq).map_err(|err| err!(InvalidInput, "auto-type sequence of {:?}: {err}", e.name))?;
+    let backend = Backend::detect()?;
+    // 先に値を揃えておき、途中でエラーになって半端に入力しないようにする
+    let values = tokens.iter()
+        .map(|t| match t { Token::Value(v) => resolve(v, e).map(Some), _ => Ok(None) })
+        .collect::<Result<Vec<_>>>()?;
+    // 入力先のウィンドウにフォーカスを移す時間
+    sleep(Duration::from_secs(wait));
+    let mut delay = 12;
+    for (t, value) in tokens.iter().zip(values) {
+        match t {
+            Token::Text(s) => backend.text(s, delay)?,
+            Token::Value(_) => {
+                let s = value.unwrap_or_default();
+                if !s.is_empty() { backend.text(&s, delay)?; }
+            }
+            Token::Key(sym, count) => backend.key(sym, *count, delay)?,
+            Token::Pause(ms) => sleep(Duration::from_millis(*ms)),
+            Token::KeyDelay(ms) => delay = *ms,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+// 金庫のスナップショット作成と検証
+use crate::error::err;
+use crate::format::{self, Credentials};
+use crate::{perms, vault_path, VaultFile};
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+use std::{fs, io, path::{Path, PathBuf}, process::Command};
+use time::{macros::format_description, OffsetDateTime};
+
+pub fn backup_dir() -> Result<PathBuf> {
+    let path = vault_path()?;
+    let dir = path.parent().ok_or(anyhow!("vault path has no parent"))?.join("backups");
+    perms::private_dir(&dir)?;
+    Ok(dir)
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn checksum_path(file: &Path) -> PathBuf {
+    let mut s = file.as_os_str().to_owned();
+    s.push(".sha256");
+    PathBuf::from(s)
+}
+
+/// 保存のたびに回す直前の版（vault.bin.bak.1 が最新）
+pub fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(format!(".bak.{n}"));
+    PathBuf::from(s)
+}
+
+/// .bak.1 … .bak.N を1つずつずらし、現在の金庫を .bak.1 にコピーする（keep = 0 なら残さない）
+pub fn rotate(path: &Path, keep: usize) -> Result<()> {
+    // 設定で数を減らした場合の余りを消す
+    let mut n = keep.max(1);
+    while rotated(path, n).exists() {
+        if n >= keep { fs::remove_file(rotated(path, n))?; }
+        n += 1;
+    }
+    if keep == 0 { return Ok(()); }
+    for i in (1..keep).rev() {
+        if rotated(path, i).exists() { fs::rename(rotated(path, i), rotated(path, i + 1))?; }
+    }
+    fs::copy(path, rotated(path, 1))?;
+    Ok(())
+}
+
+// sha256sum 互換のサイドカーファイル（"<hex>  <ファイル名>"）を併せて書く
+pub fn create() -> Result<PathBuf> {
+    let src = vault_path()?;
+    if !src.exists() { return Err(err!(NoVault, "vault not found")); }
+    let data = VaultFile::current()?.read()?;
+    let stamp = OffsetDateTime::now_utc()
+        .format(format_description!("[year][month][day]T[hour][minute][second]Z"))?;
+    let dest = backup_dir()?.join(format!("vault-{stamp}.bin"));
+    fs::write(&dest, &data)?;
+    perms::private_file(&dest)?;
+    let hash = sha256_hex(&data);
+    // 読み戻して照合
+    if sha256_hex(&fs::read(&dest)?) != hash {
+        return Err(err!(CorruptVault, "backup verification after write failed: {}", dest.display()));
+    }
+    let fname = dest.file_name().unwrap().to_string_lossy();
+    fs::write(checksum_path(&dest), format!("{}  {}\n", hash, fname))?;
+    Ok(dest)
+}
+
+fn rclone(args: &[&str]) -> Result<String> {
+    let out = Command::new("rclone").args(args).output().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => err!(NotFound, "rclone not found in PATH (https://rclone.org/install/)"),
+        _ => e.into(),
+    })?;
+    if !out.status.success() {
+        return Err(anyhow!("rclone {} failed: {}", args[0], String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+// リモートのSHA-256（バックエンドが未対応ならダウンロードして計算）
+fn remote_sha256(object: &str) -> Result<String> {
+    let parse = |s: String| s.split_whitespace().next().map(str::to_lowercase).filter(|h| h.len() == 64);
+    if let Some(h) = parse(rclone(&["hashsum", "sha256", object])?) {
+        return Ok(h);
+    }
+    parse(rclone(&["hashsum", "sha256", "--download", object])?)
+        .ok_or_else(|| anyhow!("rclone returned no checksum for {object}"))
+}
+
+/// バックアップ（と .sha256）を rclone でリモートへ送り、アップロード後のチェックサムを照合
+pub fn push_rclone(file: &Path, remote: &str) -> Result<String> {
+    let local = sha256_hex(&fs::read(file)?);
+    let name = file.file_name().unwrap().to_string_lossy();
+    let base = remote.trim_end_matches('/');
+    let object = format!("{base}/{name}");
+    let local_path = file.to_string_lossy();
+    rclone(&["copyto", &local_path, &object])?;
+    rclone(&["copyto", &checksum_path(file).to_string_lossy(), &format!("{object}.sha256")])?;
+    let uploaded = remote_sha256(&object)?;
+    if uploaded != local {
+        return Err(err!(CorruptVault, "uploaded backup checksum mismatch ({uploaded} != {local})"));
+    }
+    Ok(object)
+}
+
+// 新しい順
+pub fn restore_points() -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(backup_dir()?)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|x| x == "bin"))
+        .collect();
+    files.sort();
+    files.reverse();
+    Ok(files)
+}
+
+/// 新しいものから keep 個だけ残し、古いスナップショット（と .sha256）を消す
+pub fn prune(keep: usize) -> Result<Vec<PathBuf>> {
+    let old: Vec<PathBuf> = restore_points()?.into_iter().skip(keep).collect();
+    for file in &old {
+        fs::remove_file(file)?;
+        let sum = checksum_path(file);
+        if sum.exists() { fs::remove_file(sum)?; }
+    }
+    Ok(old)
+}
+
+enum Check { Ok, Missing, Failed(String) }
+
+fn verify_one(file: &Path, creds: &mut dyn Credentials) -> Result<[Check; 3]> {
+    let data = fs::read(file)?;
+    let checksum = match fs::read_to_string(checksum_path(file)) {
+        Ok(s) => {
+            let expected = s.split_whitespace().next().unwrap_or("");
+            if expected.eq_ignore_ascii_case(&sha256_hex(&data)) { Check::Ok }
+            else { Check::Failed("mismatch".into()) }
+        }
+        Err(_) => Check::Missing,
+    };
+    let header = match format::inspect(&data) {
+        Ok(_) => Check::Ok,
+        Err(e) => Check::Failed(e.to_string()),
+    };
+    let auth = match (&header, format::unlock(&data, creds)) {
+        (Check::Ok, Ok(_)) => Check::Ok,
+        (Check::Ok, Err(e)) => Check::Failed(e.to_string()),
+        _ => Check::Missing,
+    };
+    Ok([checksum, header, auth])
+}
+
+pub fn verify(creds: &mut dyn Credentials, all: bool) -> Result<()> {
+    let points = restore_points()?;
+    if points.is_empty() { return Err(err!(NotFound, "no backups found")); }
+    let take = if all { points.len() } else { 1 };
+
+    let mut usable = 0;
+    for file in points.iter().take(take) {
+        let [checksum, header, auth] = verify_one(file, creds)?;
+        let ok = !matches!(checksum, Check::Failed(_))
+            && matches!(header, Check::Ok)
+            && matches!(auth, Check::Ok);
+        if ok { usable += 1; }
+        let show = |label: &str, c: &Check| match c {
+            Check::Ok => format!("{label} ok"),
+            Check::Missing => format!("{label} -"),
+            Check::Failed(e) => format!("{label} FAILED ({e})"),
+        };
+        println!(
+            "{}  {}  {}  {}  => {}",
+            file.file_name().unwrap().to_string_lossy(),
+            show("checksum", &checksum),
+            show("header", &header),
+            show("decrypt", &auth),
+            if ok { "usable" } else { "NOT usable" },
+        );
+    }
+    println!("{usable} of {take} restore point(s) usable.");
+    if usable < take {
+        return Err(err!(CorruptVault, "some backups failed verification"));
+    }
+    Ok(())
+}
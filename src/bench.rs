@@ -0,0 +1,25 @@
+// bench-kdf: このマシンで目標の時間になる Argon2id のパラメータを探す
+//
+// 先にメモリを増やし（GPU・ASICでの並列攻撃にはメモリのほうが効く）、1回の導出が目標の1/3を
+// 超えるか上限に達したら、残りを反復回数で合わせる。
+use anyhow::Result;
+use rustpass_core::format::KdfParams;
+use std::time::Duration;
+
+/// メモリの探索を始める値（KiB。既定のパラメータと同じ 64 MiB）
+const START_MEMORY: u32 = 64 * 1024;
+
+/// 提案するパラメータと、それで実際にかかった時間
+pub fn suggest(target: Duration, max_memory: u32, parallelism: u32) -> Result<(KdfParams, Duration)> {
+    let max_memory = max_memory.max(8 * parallelism);
+    let mut kdf = KdfParams { m: START_MEMORY.min(max_memory), t: 1, p: parallelism };
+    let mut took = kdf.measure()?;
+    while took * 3 < target && kdf.m < max_memory {
+        kdf.m = kdf.m.saturating_mul(2).min(max_memory);
+        took = kdf.measure()?;
+    }
+    // 反復回数にほぼ比例して時間が伸びる
+    kdf.t = (target.as_secs_f64() / took.as_secs_f64()).floor().max(1.0) as u32;
+    let took = if kdf.t == 1 { took } else { kdf.measure()? };
+    Ok((kdf, took))
+}
@@ -0,0 +1,373 @@
+// ブラウザ拡張のネイティブメッセージングのホスト（browser-host）
+//
+// Chrome・Firefox が拡張の connectNative / sendNativeMessage でこのコマンドを起動し、標準入出力で
+// 「4バイトの長さ（ネイティブのバイト順）+ UTF-8 の JSON」をやり取りする。標準出力には
+// メッセージ以外を書かない（診断は標準エラー出力に書き、ブラウザのログに出る）。
+// 金庫はエージェントの鍵で開く。ロックされていれば unlock の要求でマスターパスワードを
+// ダイアログで尋ね、エージェントを起動する（ホストの終了後も他のコマンドから使える）。
+// ログイン情報はサイトのオリジンごとに利用者が許可した場合だけ返す。設定の
+// [browser] allowed_origins と「常に許可」を選んだオリジンは尋ねない。
+use crate::config::Config;
+use crate::error::{err, kind_of, Kind};
+use crate::format::{self, KdfParams};
+use crate::{agent, domain, prompt, record_access, save, vaults, Entry, VaultFile};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use zeroize::Zeroizing;
+
+/// 拡張から受け取るメッセージの上限（要求は小さい）
+const MAX_REQUEST: usize = 64 * 1024;
+/// ブラウザが受け付けるホストからのメッセージの上限
+const MAX_RESPONSE: usize = 1024 * 1024;
+
+/// スキーム・ホスト・ポート（既定のポートは省く）
+#[derive(Clone, PartialEq)]
+struct Origin {
+    scheme: String,
+    host: String,
+    port: Option<u16>,
+}
+
+impl Origin {
+    fn parse(url: &str) -> Option<Origin> {
+        let (scheme, rest) = url.trim().split_once("://")?;
+        let scheme = scheme.to_ascii_lowercase();
+        if scheme != "https" && scheme != "http" { return None; }
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let authority = authority.rsplit('@').next()?;
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(v6) => {
+                let (h, p) = v6.split_once(']')?;
+                (format!("[{h}]"), p.strip_prefix(':'))
+            }
+            None => match authority.split_once(':') {
+                Some((h, p)) => (h.to_string(), Some(p)),
+                None => (authority.to_string(), None),
+            },
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if host.is_empty() { return None; }
+        let port = match port.filter(|p| !p.is_empty()) {
+            Some(p) => Some(p.parse::<u16>().ok()?),
+            None => None,
+        };
+        let port = port.filter(|&p| !matches!((scheme.as_str(), p), ("https", 443) | ("http", 80)));
+        Some(Origin { scheme, host, port })
+    }
+
+    // エントリの URL がこのサイトのものか（domain::closeness。https のエントリは http のページに渡さない）
+    fn closeness(&self, e: &Entry) -> u8 {
+        let Some(url) = e.url.as_deref().filter(|u| !u.trim().is_empty()) else { return 0 };
+        let url = if url.contains("://") { url.to_string() } else { format!("https://{url}") };
+        let Some(o) = Origin::parse(&url) else { return 0 };
+        if o.scheme == "https" && self.scheme != "https" { return 0; }
+        if o.port.is_some() && o.port != self.port { return 0; }
+        domain::closeness(&self.host, &o.host)
+    }
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}://{}", self.scheme, self.host)?;
+        if let Some(p) = self.port { write!(f, ":{p}")?; }
+        Ok(())
+    }
+}
+
+// ---- ダイアログ（ホストは端末を持たないので、デスクトップのダイアログで尋ねる）----
+
+enum Answer {
+    Always,
+    Once,
+    Deny,
+}
+
+#[derive(Clone, Copy)]
+enum Dialog {
+    Zenity,
+    Kdialog,
+    Osascript,
+}
+
+impl Dialog {
+    fn detect() -> Dialog {
+        if cfg!(target_os = "macos") { return Dialog::Osascript; }
+        let kde = std::env::var("XDG_CURRENT_DESKTOP").is_ok_and(|d| d.to_uppercase().contains("KDE"));
+        if kde { Dialog::Kdialog } else { Dialog::Zenity }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Dialog::Zenity => "zenity",
+            Dialog::Kdialog => "kdialog",
+            Dialog::Osascript => "osascript",
+        }
+    }
+
+    // 終了コードと標準出力（1行目）
+    fn run(self, args: &[&str]) -> Result<(Option<i32>, Zeroizing<String>)> {
+        let prog = self.program();
+        let out = Command::new(prog)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => err!(NotFound, "{prog} not found in PATH (needed for browser approval prompts; or list sites in [browser] allowed_origins)"),
+                _ => e.into(),
+            })?;
+        let text = Zeroizing::new(String::from_utf8_lossy(&out.stdout).into_owned());
+        let line = Zeroizing::new(text.lines().next().unwrap_or_default().to_string());
+        Ok((out.status.code(), line))
+    }
+
+    // AppleScript には文言を引数で渡す（文字列に埋め込まない）
+    fn applescript(body: &str, text: &str) -> Vec<String> {
+        ["on run argv", body, "end run"].iter().flat_map(|l| ["-e".to_string(), l.to_string()])
+            .chain(["--".to_string(), text.to_string()])
+            .collect()
+    }
+
+    fn approve(self, origin: &Origin) -> Result<Answer> {
+        let text = format!("Allow the rustpass browser extension to fill logins on {origin}?");
+        Ok(match self {
+            Dialog::Zenity => match self.run(&["--question", "--title", "rustpass", "--text", &text, "--ok-label", "Always allow", "--cancel-label", "Deny", "--extra-button", "Allow once"])? {
+                (Some(0), _) => Answer::Always,
+                (_, out) if out.as_str() == "Allow once" => Answer::Once,
+                _ => Answer::Deny,
+            },
+            Dialog::Kdialog => match self.run(&["--title", "rustpass", "--yesnocancel", &text, "--yes-label", "Always allow", "--no-label", "Allow once", "--cancel-label", "Deny"])?.0 {
+                Some(0) => Answer::Always,
+                Some(1) => Answer::Once,
+                _ => Answer::Deny,
+            },
+            Dialog::Osascript => {
+                let script = r#"display dialog (item 1 of argv) with title "rustpass" buttons {"Deny", "Allow once", "Always allow"} default button "Deny" cancel button "Deny""#;
+                let args = Dialog::applescript(&format!("button returned of ({script})"), &text);
+                match self.run(&args.iter().map(String::as_str).collect::<Vec<_>>())?.1.as_str() {
+                    "Always allow" => Answer::Always,
+                    "Allow once" => Answer::Once,
+                    _ => Answer::Deny,
+                }
+            }
+        })
+    }
+
+    // 入力された文字列（キャンセルなら None）
+    fn ask(self, text: &str, hidden: bool) -> Result<Option<Zeroizing<String>>> {
+        let (code, out) = match self {
+            Dialog::Zenity if hidden => self.run(&["--password", "--title", text])?,
+            Dialog::Zenity => self.run(&["--entry", "--title", "rustpass", "--text", text])?,
+            Dialog::Kdialog => self.run(&["--title", "rustpass", if hidden { "--password" } else { "--inputbox" }, text])?,
+            Dialog::Osascript => {
+                let hide = if hidden { " with hidden answer" } else { "" };
+                let body = format!(r#"text returned of (display dialog (item 1 of argv) with title "rustpass" default answer ""{hide})"#);
+                let args = Dialog::applescript(&body, text);
+                self.run(&args.iter().map(String::as_str).collect::<Vec<_>>())?
+            }
+        };
+        Ok(if code == Some(0) { Some(out) } else { None })
+    }
+}
+
+// ダイアログでマスターパスワードと TOTP のコードを尋ねる
+struct DialogCredentials {
+    dialog: Dialog,
+    min_kdf: Option<KdfParams>,
+}
+
+impl format::Credentials for DialogCredentials {
+    fn password(&mut self) -> Result<Zeroizing<String>> {
+        self.dialog.ask("rustpass master password", true)?
+            .ok_or_else(|| err!(InvalidInput, "unlock cancelled"))
+    }
+
+    fn totp_code(&mut self) -> Result<String> {
+        Ok(self.dialog.ask("rustpass TOTP code", false)?
+            .ok_or_else(|| err!(InvalidInput, "unlock cancelled"))?
+            .trim()
+            .to_string())
+    }
+
+    fn min_kdf(&self) -> Option<KdfParams> {
+        self.min_kdf
+    }
+}
+
+// 拡張が分岐に使うエラー（locked: unlock を送ってから再試行する / denied: 利用者が拒否した）
+#[derive(Debug)]
+struct Refused {
+    code: &'static str,
+    message: String,
+}
+
+impl std::fmt::Display for Refused {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Refused {}
+
+fn refused(code: &'static str, message: String) -> anyhow::Error {
+    Refused { code, message }.into()
+}
+
+// ---- 許可したオリジン ----
+
+// 「常に許可」を選んだオリジン（1行に1つ）
+fn approvals_path() -> Result<PathBuf> {
+    Ok(vaults::data_dir()?.join("browser-origins"))
+}
+
+fn remembered() -> Vec<String> {
+    approvals_path().and_then(|p| Ok(fs::read_to_string(p)?)).unwrap_or_default()
+        .lines().map(str::trim).filter(|l| !l.is_empty()).map(String::from).collect()
+}
+
+fn remember(origin: &str) -> Result<()> {
+    let path = approvals_path()?;
+    let mut f = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(f, "{origin}")?;
+    Ok(())
+}
+
+struct Host<'a> {
+    cfg: &'a Config,
+    min_kdf: Option<KdfParams>,
+    dialog: Dialog,
+    /// このホストが動いている間の答え（「今回だけ許可」と「拒否」）
+    decided: HashMap<String, bool>,
+}
+
+impl Host<'_> {
+    fn allowed(&mut self, origin: &Origin) -> Result<bool> {
+        let key = origin.to_string();
+        if self.cfg.browser.allowed_origins.iter().any(|o| o.trim_end_matches('/') == key) || remembered().contains(&key) {
+            return Ok(true);
+        }
+        if let Some(&answer) = self.decided.get(&key) { return Ok(answer); }
+        let answer = match self.dialog.approve(origin)? {
+            Answer::Always => {
+                remember(&key)?;
+                true
+            }
+            Answer::Once => true,
+            Answer::Deny => false,
+        };
+        self.decided.insert(key, answer);
+        Ok(answer)
+    }
+
+    fn unlock(&mut self) -> Result<Value> {
+        if agent::key().is_some() { return Ok(json!({ "locked": false })); }
+        let data = VaultFile::current()?.read()?;
+        let mut creds = DialogCredentials { dialog: self.dialog, min_kdf: self.min_kdf };
+        let (v, u) = format::unlock(&data, &mut creds)?;
+        // エージェントは最新形式の金庫しか開けないので先に移行しておく
+        if u.version < format::VERSION { save(&v, &u)?; }
+        let timeout = self.cfg.agent.timeout.unwrap_or(agent::DEFAULT_TIMEOUT);
+        agent::start(u.data_key(), timeout)?;
+        Ok(json!({ "locked": false }))
+    }
+
+    fn logins(&mut self, url: &str) -> Result<Value> {
+        let origin = Origin::parse(url).ok_or_else(|| err!(InvalidInput, "not an http(s) URL: {url:?}"))?;
+        let key = agent::key().ok_or_else(|| refused("locked", "vault is locked".into()))?;
+        if !self.allowed(&origin)? {
+            return Err(refused("denied", format!("access to logins for {origin} was denied")));
+        }
+        let (mut v, u) = format::unlock_with_key(&VaultFile::current()?.read()?, key)?;
+        // 同じホストのエントリを先に
+        let mut found: Vec<(u8, &Entry)> = v.entries.iter().map(|e| (origin.closeness(e), e)).filter(|(c, _)| *c > 0).collect();
+        found.sort_by_key(|(c, _)| std::cmp::Reverse(*c));
+        let mut names = Vec::new();
+        let mut logins = Vec::new();
+        for (_, e) in found {
+            let totp = e.otp.as_ref().map(|o| o.now()).transpose()?.map(|(code, _)| code);
+            logins.push(json!({ "name": e.name, "username": e.username, "password": e.password, "totp": totp }));
+            names.push(e.name.clone());
+        }
+        for name in &names { record_access(&mut v, &u, self.cfg, name, "browser")?; }
+        Ok(json!({ "origin": origin.to_string(), "logins": logins }))
+    }
+
+    fn handle(&mut self, req: &Value) -> Result<Value> {
+        match req["action"].as_str().unwrap_or_default() {
+            "status" => Ok(json!({ "locked": agent::key().is_none(), "version": env!("CARGO_PKG_VERSION") })),
+            "unlock" => self.unlock(),
+            "lock" => {
+                agent::stop()?;
+                Ok(json!({ "locked": true }))
+            }
+            "logins" => {
+                let url = req["url"].as_str().ok_or_else(|| err!(InvalidInput, "logins needs \"url\""))?;
+                self.logins(url)
+            }
+            a => Err(err!(InvalidInput, "unknown action {a:?} (expected status, unlock, lock or logins)")),
+        }
+    }
+}
+
+fn read_message(r: &mut impl Read) -> Result<Option<Value>> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        r => r?,
+    }
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_REQUEST { return Err(anyhow!("message too large ({len} bytes)")); }
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf)?;
+    Ok(Some(serde_json::from_slice(&buf)?))
+}
+
+fn write_message(w: &mut impl Write, msg: &Value) -> Result<()> {
+    let mut data = Zeroizing::new(serde_json::to_vec(msg)?);
+    if data.len() > MAX_RESPONSE {
+        let error = json!({ "id": msg["id"], "ok": false, "code": "too-large", "error": "response too large for the browser" });
+        *data = serde_json::to_vec(&error)?;
+    }
+    w.write_all(&(data.len() as u32).to_ne_bytes())?;
+    w.write_all(&data)?;
+    w.flush()?;
+    Ok(())
+}
+
+// エラーの種類の短い名前
+fn code(e: &anyhow::Error) -> &'static str {
+    if let Some(r) = e.downcast_ref::<Refused>() { return r.code; }
+    match kind_of(e) {
+        Kind::InvalidInput => "invalid",
+        Kind::NoVault => "no-vault",
+        Kind::WrongPassword => "wrong-password",
+        _ => "error",
+    }
+}
+
+/// 標準入力が閉じられるまで要求に答える
+pub fn run(cfg: &Config, min_kdf: Option<KdfParams>, caller: &[String]) -> Result<()> {
+    // 標準入出力はブラウザとのやり取りに使うので、端末からの入力は求めない
+    prompt::disable();
+    if let Some(c) = caller.first() { eprintln!("rustpass browser-host: started by {c}"); }
+    let mut host = Host { cfg, min_kdf, dialog: Dialog::detect(), decided: HashMap::new() };
+    let (mut input, mut output) = (io::stdin().lock(), io::stdout().lock());
+    while let Some(req) = read_message(&mut input)? {
+        let reply = match host.handle(&req) {
+            Ok(Value::Object(mut body)) => {
+                body.insert("ok".into(), json!(true));
+                body.insert("id".into(), req["id"].clone());
+                Value::Object(body)
+            }
+            Ok(_) => unreachable!("responses are objects"),
+            Err(e) => json!({ "id": req["id"], "ok": false, "code": code(&e), "error": format!("{e:#}") }),
+        };
+        write_message(&mut output, &reply)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// カナリア（おとり）エントリ
+//
+// 普段使わないエントリに印を付けておき、参照されたら金庫内の記録（暗号化される）に残して
+// 設定のフックを実行する。エージェントやスクリプトの悪用を早めに知るためのもので、
+// 参照した側には何も表示しない。
+use crate::{now_iso, Vault};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// 記録が際限なく増えないように古いものから捨てる
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Event {
+    pub at: String,
+    pub entry: String,
+    /// 参照したコマンド（get / autotype など）
+    pub action: String,
+    pub pid: u32,
+    /// 親プロセスの実行ファイル（分かる場合のみ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+fn parent_exe() -> Option<String> {
+    let ppid = std::os::unix::process::parent_id();
+    std::fs::read_link(format!("/proc/{ppid}/exe")).ok().map(|p| p.display().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn parent_exe() -> Option<String> {
+    None
+}
+
+// 終了を待たずに実行する（参照した側に遅延や失敗を見せない）
+fn run_hook(hook: &str, ev: &Event) {
+    #[cfg(unix)]
+    let mut cmd = Command::new("sh");
+    // 端末が閉じても道連れにならないよう別のプロセスグループにする
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.args(["-c", hook]).process_group(0);
+    }
+    #[cfg(not(unix))]
+    let mut cmd = Command::new("cmd");
+    #[cfg(not(unix))]
+    cmd.args(["/C", hook]);
+    let child = cmd
+        .env("RUSTPASS_CANARY_ENTRY", &ev.entry)
+        .env("RUSTPASS_CANARY_ACTION", &ev.action)
+        .env("RUSTPASS_CANARY_TIME", &ev.at)
+        .env("RUSTPASS_CANARY_PID", ev.pid.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Ok(mut child) = child {
+        if let (Some(mut stdin), Ok(json)) = (child.stdin.take(), serde_json::to_string(ev)) {
+            let _ = stdin.write_all(json.as_bytes());
+        }
+    }
+}
+
+/// 参照を記録してフックを実行する（呼び出し側で金庫を保存する）
+pub fn trip(vault: &mut Vault, hook: Option<&str>, entry: &str, action: &str) {
+    let ev = Event {
+        at: now_iso(),
+        entry: entry.to_string(),
+        action: action.to_string(),
+        pid: std::process::id(),
+        parent: parent_exe(),
+    };
+    if let Some(hook) = hook.filter(|h| !h.trim().is_empty()) {
+        run_hook(hook, &ev);
+    }
+    vault.canary_events.push(ev);
+    let excess = vault.canary_events.len().saturating_sub(MAX_EVENTS);
+    vault.canary_events.drain(..excess);
+}
@@ -0,0 +1,100 @@
+// クレジットカード
+//
+// 番号・セキュリティコード・暗証番号は金庫ごと暗号化される。番号は数字だけにして保存し、
+// 普段の表示は「Visa •••• 1234」のように末尾4桁だけにする。
+use crate::error::err;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Card {
+    /// 名義
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holder: Option<String>,
+    /// カード番号（数字のみ）
+    pub number: String,
+    /// 有効期限（MM/YY）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    /// セキュリティコード（CVV / CVC）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cvv: Option<String>,
+    /// 暗証番号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin: Option<String>,
+}
+
+/// 空白とハイフンを除いた番号（12〜19桁）
+pub fn number(s: &str) -> Result<String> {
+    let digits: String = s.chars().filter(|c| !matches!(c, ' ' | '-')).collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) || !(12..=19).contains(&digits.len()) {
+        return Err(err!(InvalidInput, "card number must be 12-19 digits"));
+    }
+    Ok(digits)
+}
+
+/// Luhn のチェックディジットが合うか（打ち間違いの検出用。合わないカードもある）
+pub fn luhn(digits: &str) -> bool {
+    let sum: u32 = digits.bytes().rev().enumerate().map(|(i, b)| {
+        let d = u32::from(b - b'0');
+        if i % 2 == 1 { if d > 4 { d * 2 - 9 } else { d * 2 } } else { d }
+    }).sum();
+    sum.is_multiple_of(10)
+}
+
+/// 有効期限を MM/YY にそろえる（MM/YY・MM/YYYY・YYYY-MM を受け付ける）
+pub fn expiry(s: &str) -> Result<String> {
+    let s = s.trim();
+    let bad = || err!(InvalidInput, "expiry must be MM/YY, MM/YYYY or YYYY-MM");
+    let (month, year) = match (s.split_once('/'), s.split_once('-')) {
+        (Some((m, y)), _) => (m, y),
+        (None, Some((y, m))) if y.len() == 4 => (m, y),
+        _ => return Err(bad()),
+    };
+    let month: u8 = month.trim().parse().map_err(|_| bad())?;
+    let year = year.trim();
+    let year = match year.len() {
+        2 => year,
+        4 if year.starts_with("20") => &year[2..],
+        _ => return Err(bad()),
+    };
+    if !(1..=12).contains(&month) || !year.chars().all(|c| c.is_ascii_digit()) { return Err(bad()); }
+    Ok(format!("{month:02}/{year}"))
+}
+
+/// 番号の先頭から判断したブランド
+pub fn brand(number: &str) -> &'static str {
+    let prefix = |n: usize| number.get(..n).and_then(|p| p.parse::<u32>().ok()).unwrap_or(0);
+    match (prefix(1), prefix(2), prefix(3), prefix(4)) {
+        (4, ..) => "Visa",
+        (_, 34 | 37, ..) => "American Express",
+        (_, 51..=55, ..) | (.., 2221..=2720) => "Mastercard",
+        (.., 3528..=3589) => "JCB",
+        (_, 36 | 38, ..) | (_, _, 300..=305, _) => "Diners Club",
+        (_, 65, ..) | (_, _, 644..=649, _) | (.., 6011) => "Discover",
+        (_, 62, ..) => "UnionPay",
+        _ => "Card",
+    }
+}
+
+impl Card {
+    /// 「Visa •••• 1234」
+    pub fn masked(&self) -> String {
+        let last = &self.number[self.number.len().saturating_sub(4)..];
+        format!("{} •••• {last}", brand(&self.number))
+    }
+
+    /// 4桁ごとに区切った番号
+    pub fn grouped(&self) -> String {
+        self.number.as_bytes().chunks(4).map(|c| String::from_utf8_lossy(c)).collect::<Vec<_>>().join(" ")
+    }
+
+    /// 有効期限の月が過ぎているか（期限が無ければ false）
+    pub fn is_expired(&self) -> bool {
+        let Some((m, y)) = self.expires.as_deref().and_then(|e| e.split_once('/')) else { return false };
+        let (Ok(m), Ok(y)) = (m.parse::<u8>(), y.parse::<i32>()) else { return false };
+        let today = OffsetDateTime::now_utc().date();
+        (2000 + y, m) < (today.year(), u8::from(today.month()))
+    }
+}
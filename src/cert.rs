@@ -0,0 +1,157 @@
+// TLS証明書/秘密鍵エントリ
+use crate::error::err;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+use time::{format_description::well_known::Rfc3339, macros::format_description, Date, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Certificate {
+    pub cert_pem: String,
+    pub key_pem: String,
+    #[serde(default)]
+    pub chain_pem: Option<String>,
+    /// 有効期限（RFC3339）
+    pub not_after: String,
+}
+
+// 最初の CERTIFICATE ブロックをDERで取り出す
+fn first_cert_der(pem: &str) -> Result<Vec<u8>> {
+    let begin = "-----BEGIN CERTIFICATE-----";
+    let start = pem.find(begin).ok_or(anyhow!("no CERTIFICATE block in PEM"))? + begin.len();
+    let end = pem[start..].find("-----END CERTIFICATE-----").ok_or(anyhow!("unterminated PEM block"))? + start;
+    let b64: String = pem[start..end].chars().filter(|c| !c.is_whitespace()).collect();
+    Ok(STANDARD.decode(b64)?)
+}
+
+// DER の TLV を1つ読む → (tag, 中身の開始, 中身の終了)
+fn tlv(der: &[u8], pos: usize) -> Result<(u8, usize, usize)> {
+    let bad = || anyhow!("malformed certificate DER");
+    let tag = *der.get(pos).ok_or_else(bad)?;
+    let first = *der.get(pos + 1).ok_or_else(bad)?;
+    let (len, hdr) = if first & 0x80 == 0 {
+        (first as usize, 2)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 { return Err(bad()); }
+        let bytes = der.get(pos + 2..pos + 2 + n).ok_or_else(bad)?;
+        (bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize), 2 + n)
+    };
+    let start = pos + hdr;
+    let end = start.checked_add(len).filter(|&e| e <= der.len()).ok_or_else(bad)?;
+    Ok((tag, start, end))
+}
+
+fn parse_time(tag: u8, s: &str) -> Result<OffsetDateTime> {
+    let full = match tag {
+        // UTCTime: YYMMDDHHMMSSZ（RFC 5280: 50未満は20xx）
+        0x17 => {
+            let yy: u32 = s.get(..2).ok_or(anyhow!("bad UTCTime"))?.parse()?;
+            format!("{}{}", if yy < 50 { "20" } else { "19" }, s)
+        }
+        0x18 => s.to_string(),
+        _ => return Err(anyhow!("unexpected time type in certificate")),
+    };
+    let fmt = format_description!("[year][month][day][hour][minute][second]Z");
+    Ok(PrimitiveDateTime::parse(&full, &fmt)?.assume_offset(UtcOffset::UTC))
+}
+
+// Certificate → tbsCertificate → validity → notAfter
+pub fn not_after(cert_pem: &str) -> Result<OffsetDateTime> {
+    let der = first_cert_der(cert_pem)?;
+    let (_, cert, _) = tlv(&der, 0)?;
+    let (_, tbs, _) = tlv(&der, cert)?;
+    let mut pos = tbs;
+    let (tag, _, end) = tlv(&der, pos)?;
+    if tag == 0xa0 { pos = end; } // version
+    for _ in 0..3 { pos = tlv(&der, pos)?.2; } // serial, signature, issuer
+    let (_, validity, _) = tlv(&der, pos)?;
+    let after = tlv(&der, validity)?.2;
+    let (tag, s, e) = tlv(&der, after)?;
+    parse_time(tag, std::str::from_utf8(&der[s..e])?)
+}
+
+pub fn load(cert: &Path, key: &Path, chain: Option<&Path>, expires: Option<&str>) -> Result<Certificate> {
+    let cert_pem = fs::read_to_string(cert)?;
+    let key_pem = fs::read_to_string(key)?;
+    if !key_pem.contains("PRIVATE KEY-----") {
+        return Err(anyhow!("{} does not look like a PEM private key", key.display()));
+    }
+    let chain_pem = chain.map(fs::read_to_string).transpose()?;
+    let not_after = match expires {
+        Some(d) => Date::parse(d, format_description!("[year]-[month]-[day]"))
+            .map_err(|_| err!(InvalidInput, "--expires must be YYYY-MM-DD"))?
+            .midnight()
+            .assume_utc(),
+        None => not_after(&cert_pem)?,
+    };
+    Ok(Certificate { cert_pem, key_pem, chain_pem, not_after: not_after.format(&Rfc3339)? })
+}
+
+pub fn days_left(c: &Certificate) -> Option<i64> {
+    let t = OffsetDateTime::parse(&c.not_after, &Rfc3339).ok()?;
+    Some((t - OffsetDateTime::now_utc()).whole_days())
+}
+
+// --force では同じディレクトリの一時ファイルに書いてから置き換える（既存ファイルの権限を引き継がない）
+fn write_file(path: &Path, data: &str, mode: u32, force: bool) -> Result<()> {
+    if !force {
+        return create(path, data, mode).map_err(|e| anyhow!("cannot write {}: {e}", path.display()));
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let _ = fs::remove_file(&tmp);
+    let written = create(&tmp, data, mode)
+        .and_then(|()| fs::rename(&tmp, path))
+        .map_err(|e| anyhow!("cannot write {}: {e}", path.display()));
+    if written.is_err() { let _ = fs::remove_file(&tmp); }
+    written
+}
+
+// 新しいファイルとして作る（Unixでは書く前に mode にする）
+fn create(path: &Path, data: &str, mode: u32) -> std::io::Result<()> {
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        opts.mode(mode);
+        let f = opts.open(path)?;
+        // umask で mode より狭くなっていても指定どおりにする
+        f.set_permissions(fs::Permissions::from_mode(mode))?;
+        write_all(f, data)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = mode;
+        write_all(opts.open(path)?, data)
+    }
+}
+
+fn write_all(mut f: fs::File, data: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    f.write_all(data.as_bytes())?;
+    f.sync_all()
+}
+
+// cert.pem / chain.pem / fullchain.pem は 0644、key.pem は 0600
+pub fn export(c: &Certificate, dir: &Path, force: bool) -> Result<Vec<String>> {
+    fs::create_dir_all(dir)?;
+    let mut written = vec![];
+    let mut put = |name: &str, data: &str, mode: u32| -> Result<()> {
+        write_file(&dir.join(name), data, mode, force)?;
+        written.push(name.to_string());
+        Ok(())
+    };
+    put("cert.pem", &c.cert_pem, 0o644)?;
+    put("key.pem", &c.key_pem, 0o600)?;
+    if let Some(chain) = &c.chain_pem {
+        put("chain.pem", chain, 0o644)?;
+        let mut full = c.cert_pem.clone();
+        if !full.ends_with('\n') { full.push('\n'); }
+        full.push_str(chain);
+        put("fullchain.pem", &full, 0o644)?;
+    }
+    Ok(written)
+}
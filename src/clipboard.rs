@@ -0,0 +1,79 @@
+// クリップボードへのコピーと自動消去
+//
+// X11/Waylandではコピー元のプロセスが終了すると内容が消えるため、
+// 自分自身を隠しサブコマンドで起動して一定時間だけ保持させる。
+// 期限が来たら、コピー前に入っていたテキストを書き戻す（なければ消去）。
+use anyhow::{anyhow, Result};
+use arboard::Clipboard;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+pub const DEFAULT_TIMEOUT: u64 = 45;
+
+/// バックグラウンドでクリップボードに保持し、timeout 秒後に消去する
+pub fn copy(secret: &str, timeout: u64) -> Result<()> {
+    // 先に開けるか確認して、失敗したらその場でエラーにする
+    let mut cb = Clipboard::new().map_err(|e| anyhow!("clipboard unavailable: {e}"))?;
+    let previous = Zeroizing::new(cb.get_text().unwrap_or_default());
+    let mut child = Command::new(std::env::current_exe()?)
+        .args(["clipboard-hold", "--timeout", &timeout.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // 秘密はコマンドライン引数に載せず標準入力で渡す（NUL の後ろは元の内容）
+    let mut stdin = child.stdin.take().ok_or(anyhow!("no stdin for clipboard helper"))?;
+    stdin.write_all(secret.as_bytes())?;
+    stdin.write_all(b"\0")?;
+    stdin.write_all(previous.as_bytes())?;
+    Ok(())
+}
+
+/// 隠しサブコマンド本体
+pub fn hold(timeout: u64) -> Result<()> {
+    let mut input = Zeroizing::new(String::new());
+    io::stdin().read_to_string(&mut input)?;
+    let (secret, previous) = input.split_once('\0').unwrap_or((&input, ""));
+    let mut cb = Clipboard::new()?;
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    set_until(&mut cb, secret, deadline)?;
+    // 他のアプリが上書きしていれば何もしない
+    if !cb.get_text().map(|t| t == secret).unwrap_or(false) { return Ok(()); }
+    if previous.is_empty() {
+        cb.clear()?;
+    } else {
+        restore(&mut cb, previous)?;
+    }
+    Ok(())
+}
+
+// 元の内容も所有者がいないと消えるため、他のアプリがコピーするまで保持し続ける
+#[cfg(target_os = "linux")]
+fn restore(cb: &mut Clipboard, previous: &str) -> Result<()> {
+    use arboard::SetExtLinux;
+    cb.set().wait().text(previous)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restore(cb: &mut Clipboard, previous: &str) -> Result<()> {
+    cb.set_text(previous)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_until(cb: &mut Clipboard, secret: &str, deadline: Instant) -> Result<()> {
+    use arboard::SetExtLinux;
+    // 期限まで、または他のアプリが所有権を取るまでブロック
+    cb.set().wait_until(deadline).text(secret)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_until(cb: &mut Clipboard, secret: &str, deadline: Instant) -> Result<()> {
+    cb.set_text(secret)?;
+    std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+    Ok(())
+}
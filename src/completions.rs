@@ -0,0 +1,112 @@
+// シェル補完スクリプトの生成（rustpass completions bash|zsh|fish|elvish|powershell）
+//
+// サブコマンドとオプションの補完は clap_complete が clap の定義から作る。ここで足すのは
+// get / edit / rm のエントリ名の補完（bash・zsh・fish）だけで、補完のたびに隠しコマンド __complete-names を呼んで得る。
+// __complete-names はエージェントが動いているときだけ名前を返し、パスワードを尋ねることも出すこともない。
+use clap::Command;
+use std::io::{self, Write};
+
+pub use clap_complete::Shell;
+
+const BIN: &str = "rustpass";
+
+/// エントリ名を補完するコマンド（最上位のもの。別名 show も含む）
+const NAMED: [&str; 3] = ["get", "edit", "rm"];
+
+/// 補完スクリプトを標準出力に書く
+pub fn generate(shell: Shell, cmd: &mut Command) -> io::Result<()> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, cmd, BIN, &mut script);
+    let script = String::from_utf8(script).expect("completion scripts are UTF-8");
+    let hook = Hook::new(cmd);
+    let script = match shell {
+        Shell::Bash => script + &hook.bash(),
+        Shell::Zsh => hook.zsh(script),
+        Shell::Fish => script + &hook.fish(),
+        _ => script,
+    };
+    io::stdout().lock().write_all(script.as_bytes())
+}
+
+struct Hook {
+    /// get / show / edit / rm
+    names: Vec<String>,
+    /// サブコマンドより前に置ける、値を取るオプション（--vault 以外）
+    global_values: Vec<String>,
+    /// get / edit / rm の値を取るオプション（直後は値なので名前を補完しない）
+    values: Vec<String>,
+}
+
+// 値を取るオプションの綴り
+fn value_options(cmd: &Command) -> impl Iterator<Item = String> + '_ {
+    cmd.get_arguments()
+        .filter(|a| !a.is_positional() && a.get_action().takes_values())
+        .flat_map(|a| a.get_long().map(|l| format!("--{l}")).into_iter().chain(a.get_short().map(|s| format!("-{s}"))))
+}
+
+impl Hook {
+    // cmd は clap_complete::generate で build 済み（グローバルなオプションが各サブコマンドに伝わっている）
+    fn new(cmd: &Command) -> Hook {
+        let named: Vec<&Command> = cmd.get_subcommands().filter(|c| NAMED.contains(&c.get_name())).collect();
+        let names = named.iter().flat_map(|c| std::iter::once(c.get_name()).chain(c.get_visible_aliases())).map(str::to_string).collect();
+        let global_values = value_options(cmd).filter(|o| o != "--vault").collect();
+        let mut values: Vec<String> = named.iter().flat_map(|c| value_options(c)).collect();
+        values.sort();
+        values.dedup();
+        Hook { names, global_values, values }
+    }
+
+    // 「--vault の値を覚え、最初のオプションでない語をサブコマンドとする」走査（bash と zsh で共通）
+    fn scan(&self, word: &str, vault: &str) -> String {
+        format!(
+            "        case {word} in\n            --vault) vault=(--vault {vault}); ((i++)) ;;\n            {}) ((i++)) ;;\n            -*) ;;\n            *) sub={word}; break ;;\n        esac\n",
+            self.global_values.join("|"),
+        )
+    }
+
+    fn bash(&self) -> String {
+        let mut s = String::from("\n# エントリ名（エージェントが動いているときだけ）\n_rustpass_names() {\n");
+        s += "    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\" i w sub=\n    local -a vault=()\n";
+        s += "    for ((i = 1; i < COMP_CWORD; i++)); do\n        w=\"${COMP_WORDS[i]}\"\n";
+        s += &self.scan("$w", "\"${COMP_WORDS[i+1]}\"");
+        s += "    done\n";
+        s += &format!("    case $sub in {}) ;; *) return 1 ;; esac\n", self.names.join("|"));
+        s += &format!("    case $prev in {}) return 1 ;; esac\n", self.values.join("|"));
+        s += "    [[ $cur == -* ]] && return 1\n    local IFS=$'\\n'\n";
+        s += &format!("    COMPREPLY=($(compgen -W \"$({BIN} \"${{vault[@]}}\" __complete-names 2>/dev/null)\" -- \"$cur\"))\n");
+        s += "    (( ${#COMPREPLY[@]} ))\n}\n\n";
+        s += "_rustpass_with_names() {\n    _rustpass_names || _rustpass \"$@\"\n}\n\n";
+        s += &format!("complete -F _rustpass_with_names -o bashdefault -o default {BIN}\n");
+        s
+    }
+
+    // 生成された _rustpass を _rustpass_clap にして、名前を先に試す _rustpass で包む
+    fn zsh(&self, script: String) -> String {
+        const DEF: &str = "\n_rustpass() {\n";
+        const TRAILER: &str = "if [ \"$funcstack[1]\" = \"_rustpass\" ]; then";
+        let (Some(def), Some(trailer)) = (script.find(DEF), script.rfind(TRAILER)) else {
+            debug_assert!(false, "unexpected zsh script from clap_complete");
+            return script;
+        };
+        let mut s = String::from("# エントリ名（エージェントが動いているときだけ）\n_rustpass_names() {\n");
+        s += "    local i w sub\n    local -a vault names\n";
+        s += "    for ((i = 2; i < CURRENT; i++)); do\n        w=${words[i]}\n";
+        s += &self.scan("$w", "${words[i+1]}");
+        s += "    done\n";
+        s += &format!("    [[ $sub == ({}) && ${{words[CURRENT]}} != -* ]] || return 1\n", self.names.join("|"));
+        s += &format!("    case ${{words[CURRENT-1]}} in {}) return 1 ;; esac\n", self.values.join("|"));
+        s += &format!("    names=(${{(f)\"$({BIN} $vault __complete-names 2>/dev/null)\"}})\n");
+        s += "    (( $#names )) || return 1\n    compadd -- $names\n}\n\n";
+        s += "_rustpass() {\n    _rustpass_names || _rustpass_clap \"$@\"\n}\n\n";
+        format!("{}\n_rustpass_clap() {{\n{}{s}{}", &script[..def], &script[def + DEF.len()..trailer], &script[trailer..])
+    }
+
+    fn fish(&self) -> String {
+        let mut s = String::from("\n# エントリ名（エージェントが動いているときだけ）\n");
+        s += "function __rustpass_names\n    set -l tokens (commandline -opc)\n    set -l vault\n";
+        s += "    if set -l i (contains -i -- --vault $tokens)\n        set vault --vault $tokens[(math $i + 1)]\n    end\n";
+        s += &format!("    {BIN} $vault __complete-names 2>/dev/null\nend\n\n");
+        s += &format!("complete -c {BIN} -n \"__fish_rustpass_using_subcommand {}\" -f -a '(__rustpass_names)'\n", self.names.join(" "));
+        s
+    }
+}
@@ -0,0 +1,256 @@
+// ~/.config/rustpass/config.toml の読み込み
+use crate::clipboard;
+use crate::error::err;
+use crate::format::KdfParams;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::{fs, path::PathBuf};
+
+/// 生成するパスワードの長さの既定値
+pub const DEFAULT_LENGTH: usize = 20;
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// パスワードの最大年齢。キーはエントリ名またはタグのパターン（`*` 可）
+    /// 例: `"work/*" = "180d"`
+    pub max_password_age: BTreeMap<String, String>,
+    /// 証明書の期限切れを警告する残り期間（デフォルト "30d"）
+    pub cert_expiry_warning: Option<String>,
+    /// 許容するKDFパラメータの下限。これより弱いヘッダの金庫はロック解除を拒否する
+    pub min_kdf: Option<KdfParams>,
+    /// 新しく作る金庫のKDFパラメータ（min_kdf を下回る値はそこまで上げる）
+    pub kdf: Option<KdfParams>,
+    /// --vault / RUSTPASS_VAULT が無いときに使う金庫（名前またはパス）
+    pub vault: Option<String>,
+    /// 受信者あてに暗号化した金庫を開く age の秘密鍵ファイル（--identity / RUSTPASS_IDENTITY が優先）
+    pub identity: Option<PathBuf>,
+    /// 参照時に last_accessed を記録する（参照のたびに保存が発生する）
+    pub track_access: bool,
+    /// この期間参照されていないエントリを audit で報告（デフォルト "365d"）
+    pub stale_after: Option<String>,
+    /// 期限（エントリの expires_at・max_age_days）を過ぎたパスワードがあれば、コマンドの最後に警告する
+    pub warn_expired: bool,
+    /// 削除・上書きしたエントリをゴミ箱に残す期間（デフォルト "30d"、"0d" でゴミ箱を使わない）
+    pub trash_retention: Option<String>,
+    /// 保存のたびに残す直前の版の数（vault.bin.bak.1〜N。デフォルト3、0で無効）
+    pub vault_backups: Option<usize>,
+    /// backup create で残すスナップショットの数（古いものから消す。デフォルトは全部残す）
+    pub backup_keep: Option<usize>,
+    /// クリップボードにコピーした値を消去するまでの秒数（デフォルト45）
+    pub clipboard_timeout: Option<u64>,
+    /// 金庫を書き換えるコマンドをすべてエラーにする（--read-only と同じ）
+    pub read_only: bool,
+    /// スクリーンリーダー向けの出力（--accessible と同じ）
+    pub accessible: bool,
+    pub generator: GeneratorConfig,
+    pub agent: AgentConfig,
+    pub canary: CanaryConfig,
+    pub browser: BrowserConfig,
+    /// 金庫ファイルの保存先。キーは金庫の名前（既定の金庫は "default"）
+    /// 例: `[storage.default]` に `backend = "webdav"` と `url = "https://..."`
+    pub storage: BTreeMap<String, StorageConfig>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratorConfig {
+    /// add --gen / edit --gen / gen の長さ（デフォルト20）
+    pub length: Option<usize>,
+    /// 記号を含める（--no-symbols で打ち消せる）
+    pub symbols: bool,
+}
+
+impl GeneratorConfig {
+    pub fn length(&self, cli: Option<usize>) -> usize {
+        cli.or(self.length).unwrap_or(DEFAULT_LENGTH)
+    }
+
+    pub fn symbols(&self, on: bool, off: bool) -> bool {
+        on || (self.symbols && !off)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// エージェントに接続できる実行ファイル（空なら同じユーザーの全プロセス。rustpass 自身は常に許可）
+    pub allowed_executables: Vec<PathBuf>,
+    /// 最後に使われてからロックするまでの秒数（agent start --timeout の既定値）
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CanaryConfig {
+    /// カナリアエントリが参照されたときに実行するコマンド（sh -c。内容は環境変数と標準入力のJSONで渡す）
+    pub hook: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowserConfig {
+    /// ブラウザ拡張に尋ねずにログイン情報を渡すサイトのオリジン（例: "https://github.com"）
+    pub allowed_origins: Vec<String>,
+}
+
+/// 金庫ファイルの保存先
+#[derive(Deserialize, Clone)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// 手元のファイルだけ（既定）
+    Local,
+    /// WebDAV（Nextcloud など）
+    Webdav(WebDavConfig),
+    /// S3 互換のオブジェクトストレージ
+    S3(S3Config),
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebDavConfig {
+    /// 金庫ファイルのURL（例: https://cloud.example.com/remote.php/dav/files/me/rustpass/vault.bin）
+    pub url: String,
+    pub username: Option<String>,
+    /// パスワード（アプリパスワード）を出力するコマンド（sh -c。環境変数 RUSTPASS_WEBDAV_PASSWORD が優先）
+    pub password_command: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    /// 例: https://s3.eu-central-1.amazonaws.com、https://minio.example.com:9000
+    pub endpoint: String,
+    pub bucket: String,
+    /// オブジェクトのキー（例: rustpass/vault.bin）
+    pub key: String,
+    /// リージョン（デフォルト us-east-1）
+    pub region: Option<String>,
+    /// 仮想ホスト形式（https://<bucket>.<endpoint>/）のURLを使う（デフォルトはパス形式）
+    #[serde(default)]
+    pub virtual_host: bool,
+    /// アクセスキーID（環境変数 AWS_ACCESS_KEY_ID が優先）
+    pub access_key_id: Option<String>,
+    /// シークレットアクセスキーを出力するコマンド（sh -c。環境変数 AWS_SECRET_ACCESS_KEY が優先）
+    pub secret_access_key_command: Option<String>,
+}
+
+impl StorageConfig {
+    fn check(&self) -> Result<()> {
+        let url = match self {
+            StorageConfig::Local => return Ok(()),
+            StorageConfig::Webdav(w) => &w.url,
+            StorageConfig::S3(s) => &s.endpoint,
+        };
+        // 認証情報を平文で送らないよう、http は手元のサーバーだけ
+        let local = ["http://localhost", "http://127.0.0.1", "http://[::1]"]
+            .iter()
+            .any(|p| url.strip_prefix(p).is_some_and(|rest| rest.is_empty() || rest.starts_with([':', '/'])));
+        if !url.starts_with("https://") && !local {
+            return Err(anyhow!("storage URL must use https:// (got {url:?})"));
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn cert_expiry_warning_days(&self) -> Result<i64> {
+        parse_days(self.cert_expiry_warning.as_deref().unwrap_or("30d"))
+    }
+
+    pub fn vault_backups(&self) -> usize {
+        self.vault_backups.unwrap_or(3)
+    }
+
+    pub fn clipboard_timeout(&self) -> u64 {
+        self.clipboard_timeout.unwrap_or(clipboard::DEFAULT_TIMEOUT)
+    }
+
+    pub fn stale_after_days(&self) -> Result<i64> {
+        parse_days(self.stale_after.as_deref().unwrap_or("365d"))
+    }
+
+    pub fn trash_retention_days(&self) -> Result<i64> {
+        parse_days(self.trash_retention.as_deref().unwrap_or("30d"))
+    }
+}
+
+pub fn config_path() -> Result<PathBuf> {
+    let base = dirs::config_dir().ok_or(anyhow!("config dir not found"))?;
+    Ok(base.join("rustpass").join("config.toml"))
+}
+
+// ファイルが無ければデフォルト設定
+pub fn load() -> Result<Config> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let text = fs::read_to_string(&path)?;
+    let cfg: Config = toml::from_str(&text).map_err(|e| err!(Config, "invalid config {}: {e}", path.display()))?;
+    for age in cfg.max_password_age.values() {
+        parse_days(age).map_err(|e| err!(Config, "invalid config {}: {e}", path.display()))?;
+    }
+    if let Some(kdf) = &cfg.kdf {
+        kdf.check().map_err(|e| err!(Config, "invalid config {}: kdf: {e}", path.display()))?;
+    }
+    if cfg.generator.length == Some(0) {
+        return Err(err!(Config, "invalid config {}: generator.length must be at least 1", path.display()));
+    }
+    if cfg.backup_keep == Some(0) {
+        return Err(err!(Config, "invalid config {}: backup_keep must be at least 1", path.display()));
+    }
+    if cfg.clipboard_timeout == Some(0) {
+        return Err(err!(Config, "invalid config {}: clipboard_timeout must be at least 1 second", path.display()));
+    }
+    for (name, s) in &cfg.storage {
+        s.check().map_err(|e| err!(Config, "invalid config {}: storage.{name}: {e}", path.display()))?;
+    }
+    cfg.stale_after_days().map_err(|e| err!(Config, "invalid config {}: {e}", path.display()))?;
+    cfg.cert_expiry_warning_days().map_err(|e| err!(Config, "invalid config {}: {e}", path.display()))?;
+    Ok(cfg)
+}
+
+// 期間の上限（日数）。これより長いと日付の計算が範囲外になる
+const MAX_DAYS: i64 = 1_000_000;
+
+// "180d" / "26w" / "6m" / "1y" → 日数
+pub fn parse_days(s: &str) -> Result<i64> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: i64 = num.parse().map_err(|_| anyhow!("invalid duration: {s:?}"))?;
+    let mul = match unit {
+        "" | "d" => 1,
+        "w" => 7,
+        "m" => 30,
+        "y" => 365,
+        _ => return Err(anyhow!("invalid duration unit in {s:?} (use d/w/m/y)")),
+    };
+    n.checked_mul(mul).filter(|d| *d <= MAX_DAYS).ok_or_else(|| anyhow!("duration too long: {s:?}"))
+}
+
+// `*` のみ対応する簡易グロブ
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let (mut star, mut mark) = (None, 0);
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == '*' {
+            star = Some(pi);
+            mark = ti;
+            pi += 1;
+        } else if pi < p.len() && p[pi] == t[ti] {
+            pi += 1;
+            ti += 1;
+        } else if let Some(sp) = star {
+            pi = sp + 1;
+            mark += 1;
+            ti = mark;
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,157 @@
+// 重複エントリの検出と対話的な統合（複数回のインポート後の整理用）
+use crate::{now_iso, Entry, Field, Vault};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+fn host(url: &str) -> String {
+    let s = url.split("://").last().unwrap_or(url);
+    let s = s.split(['/', '?', '#']).next().unwrap_or(s);
+    let s = s.rsplit('@').next().unwrap_or(s);
+    let s = s.split(':').next().unwrap_or(s);
+    s.trim_start_matches("www.").to_lowercase()
+}
+
+// 大文字小文字・記号・インポート時に付く「 (user)」「 2」の違いを無視
+fn norm_name(name: &str) -> String {
+    let base = name.split(" (").next().unwrap_or(name);
+    let base = base.trim_end_matches(|c: char| c.is_ascii_digit() || c == ' ');
+    let base = base.split("://").last().unwrap_or(base).trim_start_matches("www.");
+    base.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            cur.push((prev[j] + (ca != *cb) as usize).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+// SSH鍵・証明書・導出設定・パスキー・カード・個人情報・添付ファイルを持つエントリとセキュアメモは対象外
+// （統合で失われないように）
+fn is_login(e: &Entry) -> bool {
+    e.ssh_key.is_none() && e.cert.is_none() && e.derive.is_none() && e.passkey.is_none()
+        && e.card.is_none() && e.identity.is_none() && !e.secure_note && e.attachments.is_empty()
+}
+
+fn similar(a: &Entry, b: &Entry) -> bool {
+    if !is_login(a) || !is_login(b) { return false; }
+    let user_a = a.username.trim().to_lowercase();
+    let user_b = b.username.trim().to_lowercase();
+    let same_user = user_a == user_b;
+    if let (Some(ua), Some(ub)) = (&a.url, &b.url) {
+        let (ha, hb) = (host(ua), host(ub));
+        if !ha.is_empty() && ha == hb && same_user && !user_a.is_empty() { return true; }
+    }
+    let (na, nb) = (norm_name(&a.name), norm_name(&b.name));
+    let users_compatible = same_user || user_a.is_empty() || user_b.is_empty();
+    if na.is_empty() || !users_compatible { return false; }
+    na == nb || (na.len().min(nb.len()) >= 6 && levenshtein(&na, &nb) <= 2)
+}
+
+/// 重複候補のグループ（エントリのインデックス）
+pub fn find_groups(vault: &Vault) -> Vec<Vec<usize>> {
+    let n = vault.entries.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn root(p: &mut [usize], mut i: usize) -> usize {
+        while p[i] != i { p[i] = p[p[i]]; i = p[i]; }
+        i
+    }
+    for i in 0..n {
+        for j in i + 1..n {
+            if similar(&vault.entries[i], &vault.entries[j]) {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[rj] = ri;
+            }
+        }
+    }
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..n {
+        groups.entry(root(&mut parent, i)).or_default().push(i);
+    }
+    groups.into_values().filter(|g| g.len() > 1).collect()
+}
+
+// 残すエントリと異なる値はメモに追記、タグとフィールドは統合
+fn fold(keep: &mut Entry, other: &Entry) {
+    let mut lines = Vec::new();
+    if other.username != keep.username && !other.username.is_empty() { lines.push(format!("username: {}", other.username)); }
+    if other.password != keep.password && !other.password.is_empty() { lines.push(format!("password: {}", other.password)); }
+    match (&keep.url, &other.url) {
+        (None, Some(u)) => keep.url = Some(u.clone()),
+        (Some(a), Some(b)) if a != b => lines.push(format!("url: {b}")),
+        _ => {}
+    }
+    if let Some(n) = &other.notes {
+        if keep.notes.as_deref() != Some(n.as_str()) { lines.push(format!("notes: {n}")); }
+    }
+    if keep.otp.is_none() { keep.otp = other.otp.clone(); }
+    for t in &other.tags {
+        if !keep.tags.contains(t) { keep.tags.push(t.clone()); }
+    }
+    for f in &other.fields {
+        match keep.fields.iter().find(|k| k.name == f.name) {
+            None => keep.fields.push(f.clone()),
+            Some(k) if k.value == f.value => {}
+            Some(_) => keep.fields.push(Field { name: format!("{} ({})", f.name, other.name), ..f.clone() }),
+        }
+    }
+    if !lines.is_empty() {
+        let mut notes = keep.notes.take().unwrap_or_default();
+        if !notes.is_empty() { notes.push_str("\n\n"); }
+        notes.push_str(&format!("--- merged from {} ({}) ---\n{}", other.name, now_iso(), lines.join("\n")));
+        keep.notes = Some(notes);
+    }
+    keep.updated_at = now_iso();
+}
+
+fn ask(prompt: &str) -> Result<String> {
+    crate::prompt::require(prompt)?;
+    print!("{prompt}");
+    io::stdout().flush()?;
+    let mut s = String::new();
+    io::stdin().read_line(&mut s)?;
+    Ok(s.trim().to_lowercase())
+}
+
+/// 対話的に統合し、削除したエントリ数を返す
+pub fn run(vault: &mut Vault, dry_run: bool) -> Result<usize> {
+    let groups = find_groups(vault);
+    if groups.is_empty() {
+        println!("No duplicates found.");
+        return Ok(0);
+    }
+    let mut remove = Vec::new();
+    for (gi, group) in groups.iter().enumerate() {
+        println!("\nGroup {}/{}:", gi + 1, groups.len());
+        for (k, &i) in group.iter().enumerate() {
+            let e = &vault.entries[i];
+            println!("  [{}] {}  ({})  {}  updated {}", k + 1, e.name, e.username, e.url.as_deref().unwrap_or("-"), e.updated_at);
+        }
+        if dry_run { continue; }
+        let answer = ask(&format!("Keep which entry and merge the rest into it? [1-{}, s=skip, q=quit]: ", group.len()))?;
+        if answer == "q" { break; }
+        let Some(k) = answer.parse::<usize>().ok().filter(|k| (1..=group.len()).contains(k)) else {
+            println!("  skipped");
+            continue;
+        };
+        let keep = group[k - 1];
+        for &i in group.iter().filter(|&&i| i != keep) {
+            let other = vault.entries[i].clone();
+            fold(&mut vault.entries[keep], &other);
+            remove.push(i);
+        }
+        println!("  merged into {}", vault.entries[keep].name);
+    }
+    remove.sort_unstable();
+    for i in remove.iter().rev() {
+        vault.entries.remove(*i);
+    }
+    Ok(remove.len())
+}
@@ -0,0 +1,86 @@
+// LessPass互換の決定的パスワード導出（マスターパスワード + サイト + ログイン + カウンタ）
+use crate::error::err;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+const ITERATIONS: u32 = 100_000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digits,
+    Symbols,
+}
+
+impl CharClass {
+    fn chars(self) -> &'static [u8] {
+        match self {
+            CharClass::Lowercase => b"abcdefghijklmnopqrstuvwxyz",
+            CharClass::Uppercase => b"ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            CharClass::Digits => b"0123456789",
+            CharClass::Symbols => b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~",
+        }
+    }
+}
+
+/// サイトごとの導出設定（パスワード自体は保存しない）
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub login: String,
+    pub counter: u32,
+    pub length: usize,
+    pub classes: Vec<CharClass>,
+}
+
+impl Profile {
+    pub fn new(login: String) -> Self {
+        Profile {
+            login,
+            counter: 1,
+            length: 16,
+            classes: vec![CharClass::Lowercase, CharClass::Uppercase, CharClass::Digits, CharClass::Symbols],
+        }
+    }
+}
+
+// 256ビットのエントロピーを多倍長整数として扱い、小さな数で割って余りを取り出す
+fn divmod(entropy: &mut [u8], d: usize) -> usize {
+    let mut rem = 0usize;
+    for b in entropy.iter_mut() {
+        let cur = (rem << 8) | *b as usize;
+        *b = (cur / d) as u8;
+        rem = cur % d;
+    }
+    rem
+}
+
+pub fn derive(master: &str, site: &str, p: &Profile) -> Result<Zeroizing<String>> {
+    let mut classes = p.classes.clone();
+    classes.sort();
+    classes.dedup();
+    if classes.is_empty() { return Err(err!(InvalidInput, "at least one character class is required")); }
+    // LessPassと同じ範囲
+    if !(5..=35).contains(&p.length) { return Err(err!(InvalidInput, "length must be between 5 and 35")); }
+
+    let salt = format!("{}{}{:x}", site, p.login, p.counter);
+    let mut entropy = Zeroizing::new([0u8; 32]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(master.as_bytes(), salt.as_bytes(), ITERATIONS, entropy.as_mut());
+
+    let all: Vec<u8> = classes.iter().flat_map(|c| c.chars().iter().copied()).collect();
+    let mut out: Zeroizing<Vec<u8>> = Zeroizing::new(Vec::with_capacity(p.length));
+    for _ in 0..p.length - classes.len() {
+        out.push(all[divmod(entropy.as_mut(), all.len())]);
+    }
+    // 各クラスから1文字ずつ選び、残りのエントロピーで挿入位置を決める
+    let extra: Vec<u8> = classes.iter().map(|c| c.chars()[divmod(entropy.as_mut(), c.chars().len())]).collect();
+    for ch in extra {
+        let at = divmod(entropy.as_mut(), out.len());
+        out.insert(at, ch);
+    }
+    Ok(Zeroizing::new(String::from_utf8(std::mem::take(&mut *out))?))
+}
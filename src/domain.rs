@@ -0,0 +1,90 @@
+// URL のホストと登録可能ドメイン（Public Suffix List による eTLD+1）
+//
+// 「accounts.example.co.uk」と「www.example.co.uk」は同じサイト（example.co.uk）として扱い、
+// 「alice.github.io」と「bob.github.io」は別のサイトとして扱う。リストは Mozilla の
+// public_suffix_list.dat をそのまま埋め込む（更新は https://publicsuffix.org/list/ から差し替える）。
+// 規則のうち国際化ドメインは Unicode で書かれているので、xn-- のホストは既定の規則（最後のラベル）になる。
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+const LIST: &str = include_str!("../wordlists/public_suffix_list.dat");
+
+struct Rules {
+    exact: HashSet<&'static str>,
+    /// "*.ck" の "ck"
+    wildcard: HashSet<&'static str>,
+    /// "!www.ck" の "www.ck"
+    exception: HashSet<&'static str>,
+}
+
+fn rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let mut r = Rules { exact: HashSet::new(), wildcard: HashSet::new(), exception: HashSet::new() };
+        for line in LIST.lines() {
+            let Some(rule) = line.split_whitespace().next().filter(|l| !l.starts_with("//")) else { continue };
+            if let Some(rest) = rule.strip_prefix("*.") {
+                r.wildcard.insert(rest);
+            } else if let Some(rest) = rule.strip_prefix('!') {
+                r.exception.insert(rest);
+            } else {
+                r.exact.insert(rule);
+            }
+        }
+        r
+    })
+}
+
+/// URL（スキームは省略可）のホスト。小文字にし、ユーザー情報・ポート・末尾の "." は除く
+pub fn host(url: &str) -> Option<String> {
+    let s = url.trim();
+    let s = s.split_once("://").map_or(s, |(_, rest)| rest);
+    let s = s.split(['/', '?', '#']).next().unwrap_or(s);
+    let s = s.rsplit('@').next().unwrap_or(s);
+    let s = match s.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => s.split(':').next().unwrap_or(s),
+    };
+    let s = s.trim_end_matches('.').to_lowercase();
+    (!s.is_empty()).then_some(s)
+}
+
+// IP アドレスには登録可能ドメインが無い
+fn is_ip(host: &str) -> bool {
+    host.contains(':') || host.parse::<std::net::Ipv4Addr>().is_ok()
+}
+
+/// 登録可能ドメイン（公開サフィックスにラベルを1つ足したもの）。ホストが公開サフィックスそのものや
+/// IP アドレスなら None
+pub fn registrable(host: &str) -> Option<&str> {
+    if is_ip(host) { return None; }
+    let r = rules();
+    let labels: Vec<&str> = host.split('.').collect();
+    // 最も長く一致する規則で公開サフィックスのラベル数を決める（一致が無ければ最後のラベル）
+    let mut suffix = 1;
+    for i in 0..labels.len() {
+        let candidate = labels[i..].join(".");
+        if r.exception.contains(candidate.as_str()) {
+            suffix = labels.len() - i - 1;
+            break;
+        }
+        let parent = labels[i + 1..].join(".");
+        if r.exact.contains(candidate.as_str()) || (i + 1 < labels.len() && r.wildcard.contains(parent.as_str())) {
+            suffix = labels.len() - i;
+            break;
+        }
+    }
+    if labels.len() <= suffix { return None; }
+    let start: usize = labels[..labels.len() - suffix - 1].iter().map(|l| l.len() + 1).sum();
+    Some(&host[start..])
+}
+
+/// 2つのホストがどれだけ近いか（2: 同じホスト（www. の有無は無視）、1: 同じ登録可能ドメイン、0: 別のサイト）
+pub fn closeness(a: &str, b: &str) -> u8 {
+    let strip = |h: &str| h.strip_prefix("www.").unwrap_or(h).to_string();
+    if strip(a) == strip(b) { return 2; }
+    match (registrable(a), registrable(b)) {
+        (Some(x), Some(y)) if x == y => 1,
+        _ => 0,
+    }
+}
@@ -0,0 +1,100 @@
+// 種別付きエラー（--json 時に機械可読な形で出力する）
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    WrongPassword,
+    InvalidTotp,
+    KeyfileRequired,
+    IdentityRequired,
+    CorruptVault,
+    UnsupportedFormat,
+    KdfDowngrade,
+    NoVault,
+    NotFound,
+    AlreadyExists,
+    Locked,
+    Conflict,
+    ReadOnly,
+    InvalidInput,
+    Config,
+    Io,
+    Internal,
+}
+
+impl Kind {
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            Kind::WrongPassword => Some("check the master password (and keyboard layout)"),
+            Kind::InvalidTotp => Some("use the current code from your authenticator app; check the system clock"),
+            Kind::KeyfileRequired => Some("pass the vault's key file with --keyfile PATH"),
+            Kind::IdentityRequired => Some("pass an age identity file of one of the vault's recipients with --identity PATH (or RUSTPASS_IDENTITY)"),
+            Kind::CorruptVault => Some("restore from a backup: `rustpass backup verify --all`"),
+            Kind::UnsupportedFormat => Some("the vault was written by a newer rustpass; upgrade this binary"),
+            Kind::KdfDowngrade => Some("possible tampering; if you lowered the parameters yourself, update min_kdf in the config or pass --accept-weaker-kdf"),
+            Kind::NoVault => Some("create one with `rustpass new`"),
+            Kind::NotFound => Some("check the name with `rustpass list`"),
+            Kind::AlreadyExists => Some("choose another name"),
+            Kind::Locked => Some("another rustpass process (or the serve command) is using the vault; try again in a moment"),
+            Kind::Conflict => Some("run the command again; the other process's change was kept"),
+            Kind::ReadOnly => Some("run without --read-only (and remove read_only from the config) to make changes"),
+            Kind::Config => Some("fix or remove the config file"),
+            Kind::InvalidInput | Kind::Io | Kind::Internal => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub kind: Kind,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// anyhow! と同じ書式で種別付きエラーを作る（例: `err!(NotFound, "entry {name:?} not found")`）
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __err {
+    ($kind:ident, $($arg:tt)*) => {
+        anyhow::Error::new($crate::error::Error {
+            kind: $crate::error::Kind::$kind,
+            message: format!($($arg)*),
+        })
+    };
+}
+pub use crate::__err as err;
+
+pub fn kind_of(e: &anyhow::Error) -> Kind {
+    if let Some(k) = e.downcast_ref::<Error>().map(|x| x.kind) {
+        return k;
+    }
+    for cause in e.chain() {
+        if let Some(x) = cause.downcast_ref::<Error>() { return x.kind; }
+        if cause.is::<std::io::Error>() { return Kind::Io; }
+    }
+    Kind::Internal
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    code: Kind,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<&'a str>,
+}
+
+/// stderr に1行のJSONとして出力
+pub fn print_json(e: &anyhow::Error) {
+    let code = kind_of(e);
+    let report = Report { code, message: format!("{e:#}"), hint: code.hint() };
+    eprintln!("{}", serde_json::to_string(&report).unwrap_or_default());
+}
@@ -0,0 +1,153 @@
+// exec: エントリの秘密を環境変数に入れて子プロセスを実行する
+//
+// 金庫は子プロセスを起動する前に閉じ、ロックも外しておく（長く動くコマンドでもほかのコマンドを待たせない）。
+// --env-file では値を環境変数ではなく tmpfs（XDG_RUNTIME_DIR・/dev/shm）上の env ファイルに書き、
+// そのパスだけを環境変数で渡す。ファイルは子プロセスが終わったら消す。
+use crate::perms;
+use anyhow::Result;
+use rand::{rngs::OsRng, RngCore};
+use rustpass_core::error::err;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use zeroize::Zeroizing;
+
+/// 環境変数とそこに入れる項目（`--as VAR` または `--as VAR=ITEM`）
+pub struct Binding {
+    pub var: String,
+    pub item: String,
+}
+
+/// 環境変数の名前として使えるか（英数字と _、先頭は数字以外）
+pub fn check_var(var: &str) -> Result<()> {
+    let valid = var.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(err!(InvalidInput, "invalid environment variable name {var:?} (letters, digits and _, not starting with a digit)"));
+    }
+    Ok(())
+}
+
+impl Binding {
+    pub fn parse(s: &str) -> Result<Self> {
+        let (var, item) = s.split_once('=').unwrap_or((s, "password"));
+        check_var(var)?;
+        if item.is_empty() { return Err(err!(InvalidInput, "empty item in --as {s:?}")); }
+        Ok(Binding { var: var.to_string(), item: item.to_string() })
+    }
+}
+
+// POSIX シェルで読み込める形（`. file`・`set -a; . file`）。値は単一引用符で囲む
+fn render(vars: &[(String, Zeroizing<String>)]) -> Zeroizing<String> {
+    let mut s = Zeroizing::new(String::new());
+    for (var, value) in vars {
+        s.push_str(var);
+        s.push_str("='");
+        s.push_str(&value.replace('\'', r"'\''"));
+        s.push_str("'\n");
+    }
+    s
+}
+
+// メモリ上のファイルシステムにあるディレクトリ（無ければ書かずにエラーにする）
+#[cfg(target_os = "linux")]
+fn tmpfs_dir() -> Result<PathBuf> {
+    const TMPFS_MAGIC: libc::c_long = 0x0102_1994;
+    let is_tmpfs = |dir: &std::path::Path| {
+        let Ok(c) = std::ffi::CString::new(dir.as_os_str().as_encoded_bytes()) else { return false };
+        // SAFETY: statfs はすべてのフィールドが整数の構造体なので、0 で埋めた値も有効
+        let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+        // SAFETY: c は NUL 終端の文字列、st は有効な書き込み先
+        unsafe { libc::statfs(c.as_ptr(), &mut st) == 0 && st.f_type as libc::c_long == TMPFS_MAGIC }
+    };
+    dirs::runtime_dir().into_iter().chain([PathBuf::from("/dev/shm")])
+        .find(|d| is_tmpfs(d))
+        .map(|d| d.join("rustpass"))
+        .ok_or_else(|| err!(InvalidInput, "no tmpfs found (XDG_RUNTIME_DIR or /dev/shm); pass the secrets as environment variables instead"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn tmpfs_dir() -> Result<PathBuf> {
+    Err(err!(InvalidInput, "--env-file needs a tmpfs and is only available on Linux; pass the secrets as environment variables instead"))
+}
+
+// 子プロセスが終わったら（エラーでも）消す
+struct EnvFile(PathBuf);
+
+impl EnvFile {
+    fn write(vars: &[(String, Zeroizing<String>)]) -> Result<EnvFile> {
+        let dir = tmpfs_dir()?;
+        perms::private_dir(&dir)?;
+        let mut name = [0u8; 8];
+        OsRng.fill_bytes(&mut name);
+        let path = dir.join(format!("exec-{}.env", name.iter().map(|b| format!("{b:02x}")).collect::<String>()));
+        let mut opts = OpenOptions::new();
+        opts.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        let mut f = opts.open(&path)?;
+        let file = EnvFile(path);
+        f.write_all(render(vars).as_bytes())?;
+        Ok(file)
+    }
+}
+
+impl Drop for EnvFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.0) {
+            eprintln!("warning: could not remove {}: {e}", self.0.display());
+        }
+    }
+}
+
+// 子プロセスが動いている間は端末からの Ctrl-C・Ctrl-\ で自分だけ先に終わらない（system(3) と同じ。
+// 子プロセスには同じシグナルが届き、終わったら env ファイルを消せる）。起動した後に設定するので子プロセスには引き継がれない
+#[cfg(unix)]
+fn ignore_interrupts(ignore: bool) {
+    let handler = if ignore { libc::SIG_IGN } else { libc::SIG_DFL };
+    for sig in [libc::SIGINT, libc::SIGQUIT] {
+        // SAFETY: SIG_IGN / SIG_DFL に戻すだけで、Rust のハンドラは登録しない
+        unsafe { libc::signal(sig, handler); }
+    }
+}
+
+#[cfg(not(unix))]
+fn ignore_interrupts(_ignore: bool) {}
+
+/// 値を環境変数（env_file が Some ならその名前の変数に入れたファイルのパス）で渡して command を実行し、
+/// 終了コードを返す（シグナルで終わったときは 128 + シグナル番号）
+pub fn run(command: &[String], vars: Vec<(String, Zeroizing<String>)>, env_file: Option<&str>) -> Result<i32> {
+    let (prog, args) = command.split_first().ok_or_else(|| err!(InvalidInput, "no command given (rustpass exec ... -- COMMAND)"))?;
+    let mut cmd = Command::new(prog);
+    cmd.args(args);
+    let _file = match env_file {
+        Some(var) => {
+            let f = EnvFile::write(&vars)?;
+            cmd.env(var, &f.0);
+            Some(f)
+        }
+        None => {
+            cmd.envs(vars.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+            None
+        }
+    };
+    drop(vars);
+    let mut child = cmd.spawn().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => err!(Io, "command not found: {prog}"),
+        _ => err!(Io, "cannot run {prog}: {e}"),
+    })?;
+    ignore_interrupts(true);
+    let status = child.wait();
+    ignore_interrupts(false);
+    let status = status?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(sig) = status.signal() { return Ok(128 + sig); }
+    }
+    Ok(status.code().unwrap_or(1))
+}
@@ -0,0 +1,87 @@
+// Bitwarden / Vaultwarden の「.json（暗号化なし）」形式
+//
+// Bitwarden にタグは無いため、先頭のタグをフォルダにし、2つ目以降は tags フィールドに残す。
+// SSH鍵・証明書は鍵素材を非表示フィールドに入れたセキュアメモとして書き出す。
+use crate::{Entry, Vault};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const LOGIN: u8 = 1;
+const SECURE_NOTE: u8 = 2;
+const TEXT: u8 = 0;
+const HIDDEN: u8 = 1;
+
+fn field(name: &str, value: &str, kind: u8) -> Value {
+    json!({ "name": name, "value": value, "type": kind, "linkedId": null })
+}
+
+fn item(e: &Entry, folder: Option<&str>) -> Value {
+    let mut fields: Vec<Value> = e.fields.iter()
+        .map(|f| field(&f.name, &f.value, if f.json { TEXT } else { HIDDEN }))
+        .collect();
+    if e.tags.len() > 1 { fields.push(field("tags", &e.tags.join(", "), TEXT)); }
+    let mut notes = e.notes.clone();
+    let kind = if let Some(k) = &e.ssh_key {
+        fields.push(field("ssh algorithm", &k.algorithm, TEXT));
+        fields.push(field("ssh public key", &k.public_key, TEXT));
+        fields.push(field("ssh private key", &k.private_key, HIDDEN));
+        SECURE_NOTE
+    } else if let Some(c) = &e.cert {
+        fields.push(field("certificate", &c.cert_pem, TEXT));
+        fields.push(field("private key", &c.key_pem, HIDDEN));
+        if let Some(chain) = &c.chain_pem { fields.push(field("chain", chain, TEXT)); }
+        fields.push(field("expires", &c.not_after, TEXT));
+        SECURE_NOTE
+    } else {
+        // 導出設定のエントリはパスワードを持たないので、設定だけメモに残す
+        if let Some(d) = &e.derive {
+            let line = format!(
+                "rustpass derived password: login {:?}, counter {}, length {} (not exported)",
+                d.login, d.counter, d.length
+            );
+            notes = Some(match notes { Some(n) => format!("{n}\n\n{line}"), None => line });
+        }
+        LOGIN
+    };
+    let mut v = json!({
+        "id": e.id,
+        "organizationId": null,
+        "folderId": folder,
+        "type": kind,
+        "reprompt": 0,
+        "name": e.name,
+        "notes": notes,
+        "favorite": false,
+        "fields": fields,
+        "collectionIds": null,
+        "creationDate": e.created_at.as_deref().unwrap_or(&e.updated_at),
+        "revisionDate": e.updated_at,
+    });
+    if kind == LOGIN {
+        let uris: Vec<Value> = e.url.iter().map(|u| json!({ "match": null, "uri": u })).collect();
+        v["login"] = json!({
+            "uris": uris,
+            "username": (!e.username.is_empty()).then_some(&e.username),
+            "password": (!e.password.is_empty()).then_some(&e.password),
+            "totp": e.otp.as_ref().map(|o| o.uri(if e.username.is_empty() { &e.name } else { &e.username })),
+        });
+    } else {
+        v["secureNote"] = json!({ "type": 0 });
+    }
+    v
+}
+
+pub fn render(vault: &Vault) -> Result<String> {
+    let mut folders: BTreeMap<&str, String> = BTreeMap::new();
+    for t in vault.entries.iter().filter_map(|e| e.tags.first()) {
+        folders.entry(t.as_str()).or_insert_with(|| Uuid::new_v4().to_string());
+    }
+    let items: Vec<Value> = vault.entries.iter()
+        .map(|e| item(e, e.tags.first().map(|t| folders[t.as_str()].as_str())))
+        .collect();
+    let folders: Vec<Value> = folders.iter().map(|(name, id)| json!({ "id": id, "name": name })).collect();
+    let out = json!({ "encrypted": false, "folders": folders, "items": items });
+    Ok(serde_json::to_string_pretty(&out)?)
+}
@@ -0,0 +1,22 @@
+// ブラウザだけで開ける自己完結型の暗号化HTML
+//
+// 金庫は rpss スナップショットと同じ通常の金庫形式（Argon2id + ChaCha20-Poly1305）で埋め込み、
+// ページ内の wasm（web/ で rustpass_core を wasm にしたもの）で復号する。暗号の実装を JS で持たない。
+// web/ 以下の wasm と JS は web/build.sh で作り直してコミットする。
+use crate::Vault;
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+const TEMPLATE: &str = include_str!("vault.html");
+// wasm-bindgen --target no-modules の出力
+const GLUE: &str = include_str!("web/rustpass_web.js");
+const WASM: &[u8] = include_bytes!("web/rustpass_web_bg.wasm");
+
+pub fn render(vault: &Vault, passphrase: &str) -> Result<String> {
+    let data = rustpass_core::encrypt_vault(vault, passphrase)?;
+    // Base64 には _ が無いので、先に埋め込んでも後のプレースホルダと混ざらない
+    Ok(TEMPLATE
+        .replace("__VAULT__", &STANDARD.encode(data))
+        .replace("__WASM__", &STANDARD.encode(WASM))
+        .replace("__GLUE__", GLUE))
+}
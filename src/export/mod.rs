@@ -0,0 +1,135 @@
+// 他の形式への書き出し
+use crate::error::err;
+use crate::output::{self, Output};
+use crate::perms;
+use crate::{Entry, Vault};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use rand::{rngs::OsRng, RngCore};
+use std::{fs::{self, OpenOptions}, io::Write, path::Path};
+use zeroize::Zeroizing;
+
+mod bitwarden;
+mod html;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// ブラウザだけで復号・閲覧できる自己完結型の暗号化HTML（読み取り専用）
+    HtmlVault,
+    /// Bitwarden / Vaultwarden でインポートできるJSON（暗号化なし）
+    Bitwarden,
+    /// Aegis Authenticator の暗号化バックアップ（TOTPを持つエントリのみ）
+    Aegis,
+    /// Nextcloud Passwords でインポートできるJSON（暗号化なし）
+    Nextcloud,
+    /// Nextcloud Passwords でインポートできる見出し行付きCSV（暗号化なし）
+    #[value(name = "nextcloud-csv")]
+    NextcloudCsv,
+    /// pass（password-store）のディレクトリ（gpg で暗号化。先頭のタグがディレクトリになる）
+    Pass,
+    /// 全エントリをそのままの構造で（暗号化なし）
+    Json,
+    /// 全エントリをそのままの構造で（暗号化なし）
+    Yaml,
+    /// 全エントリをそのままの構造で（暗号化なし）
+    Toml,
+    /// 金庫全体の暗号化スナップショット（import --format rpss で復元）
+    Rpss,
+}
+
+impl Format {
+    /// 出力を書き出し用パスフレーズで暗号化する形式か
+    pub fn encrypted(self) -> bool {
+        match self {
+            Format::HtmlVault | Format::Aegis | Format::Rpss => true,
+            // gpg の鍵で暗号化するので、パスフレーズは使わない
+            Format::Pass => false,
+            Format::Bitwarden | Format::Nextcloud | Format::NextcloudCsv => false,
+            Format::Json | Format::Yaml | Format::Toml => false,
+        }
+    }
+
+    /// 金庫と同じ形式（同じKDF）で暗号化するか（マスターパスワードをそのまま使える）
+    pub fn vault_kdf(self) -> bool {
+        matches!(self, Format::HtmlVault | Format::Rpss)
+    }
+
+    /// --format を省略したときに出力先の拡張子から決める
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "rpss" => Some(Format::Rpss),
+            _ => None,
+        }
+    }
+
+    /// 書き出し対象になるエントリか
+    pub fn includes(self, e: &Entry) -> bool {
+        match self {
+            Format::Aegis => e.otp.is_some(),
+            Format::HtmlVault | Format::Bitwarden | Format::Nextcloud | Format::NextcloudCsv | Format::Rpss | Format::Pass => true,
+            Format::Json | Format::Yaml | Format::Toml => true,
+        }
+    }
+}
+
+/// 書き出す内容（平文の形式は使い終わったら消去されるように Zeroizing で返す）
+pub fn render(format: Format, vault: &Vault, passphrase: Option<&str>) -> Result<Zeroizing<Vec<u8>>> {
+    let text = match format {
+        // 通常の金庫ファイルと同じ形式（新しいデータ鍵とパスワードの鍵スロットだけ）
+        Format::Rpss => return rustpass_core::encrypt_vault(vault, passphrase.unwrap_or_default()).map(Zeroizing::new),
+        Format::HtmlVault => html::render(vault, passphrase.unwrap_or_default()),
+        Format::Bitwarden => bitwarden::render(vault),
+        Format::Aegis => crate::aegis::render(vault, passphrase.unwrap_or_default()),
+        Format::Nextcloud => crate::nextcloud::render(vault),
+        Format::NextcloudCsv => crate::nextcloud::render_csv(vault),
+        Format::Pass => return Err(err!(InvalidInput, "pass stores are directories; use passstore::write")),
+        Format::Json => structured(Output::Json, vault),
+        Format::Yaml => structured(Output::Yaml, vault),
+        Format::Toml => structured(Output::Toml, vault),
+    }?;
+    Ok(Zeroizing::new(text.into_bytes()))
+}
+
+fn structured(out: Output, vault: &Vault) -> Result<String> {
+    let doc = serde_json::json!({ "entries": vault.entries });
+    output::render(out, "entries", &doc)
+}
+
+// --force が無ければ既存ファイルは上書きしない。出力は自分だけが読み書きできるファイルにする
+// （--force では同じディレクトリの一時ファイルに書いてから置き換えるので、既存ファイルの権限は引き継がない）
+pub fn write(path: &Path, data: &[u8], force: bool) -> Result<()> {
+    if !force {
+        return write_new(path, data).map_err(|e| match e.downcast_ref::<std::io::Error>().map(|e| e.kind()) {
+            Some(std::io::ErrorKind::AlreadyExists) => err!(AlreadyExists, "{} already exists (use --force to overwrite)", path.display()),
+            _ => anyhow!("cannot create {}: {e}", path.display()),
+        });
+    }
+    let mut name = [0u8; 8];
+    OsRng.fill_bytes(&mut name);
+    let file_name = path.file_name().ok_or_else(|| err!(InvalidInput, "{} is not a file path", path.display()))?;
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", file_name.to_string_lossy(), name.iter().map(|b| format!("{b:02x}")).collect::<String>()));
+    let written = write_new(&tmp, data).map_err(|e| anyhow!("cannot create {}: {e}", tmp.display()))
+        .and_then(|()| fs::rename(&tmp, path).map_err(|e| anyhow!("cannot replace {}: {e}", path.display())));
+    if written.is_err() { let _ = fs::remove_file(&tmp); }
+    written
+}
+
+// 新しいファイルとして作り、書く前に権限を絞る（Unixでは 0600 で作成）
+fn write_new(path: &Path, data: &[u8]) -> Result<()> {
+    let mut opts = OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut f = opts.open(path)?;
+    let written = perms::private_file(path).and_then(|()| {
+        f.write_all(data)?;
+        f.sync_all()?;
+        Ok(())
+    });
+    // 書きかけのファイルは残さない
+    if written.is_err() { let _ = fs::remove_file(path); }
+    written
+}
@@ -0,0 +1,1234 @@
+// 金庫ファイル形式と鍵のラップ
+//
+// v1（旧形式）: MAGIC | 1 | m,t,p (u32 LE) | salt(16) | nonce(12) | ciphertext
+//   マスターパスワードから導出した鍵で本体を直接暗号化。ヘッダは認証されない。
+// v2: MAGIC | 2 | ヘッダ長 (u32 LE) | ヘッダ (JSON) | ciphertext
+//   本体はランダムなデータ鍵で暗号化し、MAGIC〜ヘッダ末尾をAADとして認証する。
+//   データ鍵はヘッダ内の鍵スロットでラップされる。
+// v3: v2 と同じヘッダ（chunk_size 付き）| チャンク...
+//   本体を chunk_size ごとに STREAM 構成で暗号化する（各チャンクに認証タグ、AADは v2 と同じ）。
+//   大きな金庫でも平文全体をメモリに置かずに読み書きでき、書きかけのファイルはチャンク単位で検出できる。
+use crate::error::{err, kind_of, Kind};
+use crate::{age, shamir, totp};
+use crate::Vault;
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20poly1305::{aead::{Aead, KeyInit, Payload}, ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use zeroize::Zeroizing;
+
+pub const MAGIC: &[u8] = b"RPSS";
+pub const VERSION: u8 = 3;
+const V2: u8 = 2;
+const LEGACY_VERSION: u8 = 1;
+
+/// v3 の本体を分割する大きさ（平文のバイト数）
+const CHUNK_SIZE: usize = 64 * 1024;
+const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+const TAG_LEN: usize = 16;
+
+const INFO_KEK: &[u8] = b"rustpass v2 kek";
+const INFO_TOTP: &[u8] = b"rustpass v2 totp";
+const INFO_KEYFILE: &[u8] = b"rustpass v2 keyfile";
+const INFO_TOKEN: &[u8] = b"rustpass v2 token";
+
+pub type DataKey = crate::secure::Locked<32>;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KdfParams {
+    /// メモリコスト (KiB)
+    pub m: u32,
+    /// 反復回数
+    pub t: u32,
+    /// 並列度
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    // 初期は控えめ。必要なら m/t を上げて総当たり耐性を強化
+    // m = 64 MiB, t = 3, p = 1
+    fn default() -> Self {
+        KdfParams { m: 64 * 1024, t: 3, p: 1 }
+    }
+}
+
+impl KdfParams {
+    /// どれか1つでも下回っていれば弱い
+    pub fn weaker_than(&self, other: &KdfParams) -> bool {
+        self.m < other.m || self.t < other.t || self.p < other.p
+    }
+
+    /// 各パラメータを floor 以上に引き上げる
+    pub fn at_least(self, floor: &KdfParams) -> KdfParams {
+        KdfParams { m: self.m.max(floor.m), t: self.t.max(floor.t), p: self.p.max(floor.p) }
+    }
+
+    /// Argon2 が受け付ける値か
+    pub fn check(&self) -> Result<()> {
+        self.argon().map(|_| ())
+    }
+
+    /// このマシンで1回の鍵導出にかかる時間（bench-kdf 用）
+    pub fn measure(&self) -> Result<std::time::Duration> {
+        let start = std::time::Instant::now();
+        derive_key_from_password("rustpass benchmark", &random::<16>(), self)?;
+        Ok(start.elapsed())
+    }
+
+    fn argon(&self) -> Result<Params> {
+        Params::new(self.m, self.t, self.p, None).map_err(|e| anyhow!("argon2 params invalid: {e:?}"))
+    }
+}
+
+pub(crate) mod b64 {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&STANDARD.encode(v))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(d)?;
+        STANDARD.decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// パスワードに追加するTOTP要素。TOTPシークレット自体がKEKの導出に混ぜ込まれるため、
+/// コード照合を飛ばすだけではデータ鍵を取り出せない。
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TotpFactor {
+    pub algorithm: totp::Algorithm,
+    pub digits: u32,
+    pub period: u64,
+    #[serde(with = "b64")]
+    nonce: Vec<u8>,
+    #[serde(with = "b64")]
+    wrapped_secret: Vec<u8>,
+}
+
+/// ロック解除に使うハードウェアトークン
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Token {
+    /// YubiKey の HMAC-SHA1 チャレンジレスポンス（OTPスロット 1 / 2）
+    Yubikey { slot: u8 },
+    /// FIDO2 認証器の hmac-secret 拡張（登録した資格情報で応答する）
+    Fido2 {
+        rp_id: String,
+        #[serde(with = "b64")]
+        credential_id: Vec<u8>,
+    },
+}
+
+impl Token {
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Yubikey { slot } => format!("YubiKey slot {slot}"),
+            Token::Fido2 { rp_id, .. } => format!("FIDO2 key ({rp_id})"),
+        }
+    }
+}
+
+/// パスワードに追加するハードウェアトークン要素。チャレンジへの応答がKEKの導出に混ぜ込まれる
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TokenFactor {
+    #[serde(flatten)]
+    pub token: Token,
+    #[serde(with = "b64")]
+    pub challenge: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Slot {
+    Password {
+        kdf: KdfParams,
+        #[serde(with = "b64")]
+        salt: Vec<u8>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        totp: Option<TotpFactor>,
+        /// キーファイルの内容もKEKの導出に混ぜ込む（ファイルが無ければ開けない）
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        keyfile: bool,
+        /// ハードウェアトークンの応答もKEKの導出に混ぜ込む（トークンが無ければ開けない）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<TokenFactor>,
+        #[serde(with = "b64")]
+        nonce: Vec<u8>,
+        #[serde(with = "b64")]
+        wrapped_key: Vec<u8>,
+    },
+    /// データ鍵をShamirで分割し、メンバーごとのパスワードでシェアをラップ（K-of-N）
+    Quorum {
+        threshold: u8,
+        shares: Vec<QuorumShare>,
+    },
+    /// データ鍵を age の X25519 受信者あてに包む（受信者の秘密鍵ファイルで開く）
+    Recipient {
+        /// `age1...`
+        recipient: String,
+        /// メンバー名（`members add --name`）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        /// データ鍵を受信者あてに暗号化した age ファイル
+        #[serde(with = "b64")]
+        wrapped_key: Vec<u8>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QuorumShare {
+    pub member: String,
+    pub kdf: KdfParams,
+    #[serde(with = "b64")]
+    salt: Vec<u8>,
+    #[serde(with = "b64")]
+    nonce: Vec<u8>,
+    /// x (1バイト) || y
+    #[serde(with = "b64")]
+    wrapped_share: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    slots: Vec<Slot>,
+    /// v2 は本体のnonce（12バイト）、v3 はチャンクのnonceの接頭辞（7バイト）
+    #[serde(with = "b64")]
+    nonce: Vec<u8>,
+    /// v3 のみ: 平文のチャンクの大きさ
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunk_size: Option<u32>,
+}
+
+/// ロック解除に必要な入力を要求する（対話プロンプトなど）
+pub trait Credentials {
+    fn password(&mut self) -> Result<Zeroizing<String>>;
+    fn totp_code(&mut self) -> Result<String>;
+    /// キーファイルの内容（None ならキーファイルなし）
+    fn keyfile(&mut self) -> Result<Option<Zeroizing<Vec<u8>>>> { Ok(None) }
+    /// ハードウェアトークンにチャレンジを送って応答を得る
+    fn token_response(&mut self, factor: &TokenFactor) -> Result<Zeroizing<Vec<u8>>> {
+        Err(err!(InvalidInput, "this vault needs a hardware token ({})", factor.token.describe()))
+    }
+    /// クォーラム解除でメンバーのパスワードを求める（None でスキップ）
+    fn member_password(&mut self, _member: &str) -> Result<Option<Zeroizing<String>>> { Ok(None) }
+    /// クォーラム解除でメンバーのパスワードが違った（続けて次のメンバーを求める）
+    fn member_rejected(&mut self, _member: &str) {}
+    /// 受信者のスロットを開く age の秘密鍵（空なら秘密鍵なし）
+    fn identities(&mut self) -> Result<Vec<age::Identity>> { Ok(Vec::new()) }
+    /// 許容するKDFパラメータの下限（ヘッダ改ざんによるダウングレード対策）
+    fn min_kdf(&self) -> Option<KdfParams> { None }
+}
+
+// KDFを計算する前にヘッダのパラメータを下限と比較する
+fn check_kdf(kdf: &KdfParams, creds: &dyn Credentials) -> Result<()> {
+    match creds.min_kdf() {
+        Some(min) if kdf.weaker_than(&min) => Err(err!(
+            KdfDowngrade,
+            "vault KDF parameters (m={} t={} p={}) are weaker than the pinned minimum (m={} t={} p={}); refusing to unlock without --accept-weaker-kdf",
+            kdf.m, kdf.t, kdf.p, min.m, min.t, min.p
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 新たに有効化するTOTPの設定
+pub struct TotpSecret {
+    pub secret: Zeroizing<Vec<u8>>,
+    pub algorithm: totp::Algorithm,
+    pub digits: u32,
+    pub period: u64,
+}
+
+/// ロック解除済みの状態（データ鍵と鍵スロット）
+pub struct Unlocked {
+    key: DataKey,
+    pub slots: Vec<Slot>,
+    /// ロック解除に使った追加の要素（パスワードスロットを作り直すときに引き継ぐ）
+    factors: Factors,
+    /// 読み込んだファイルの形式バージョン（保存時は常に最新形式）
+    pub version: u8,
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut b = [0u8; N];
+    OsRng.fill_bytes(&mut b);
+    b
+}
+
+// マスターパスワードから鍵を導出（Argon2id）
+fn derive_key_from_password(password: &str, salt: &[u8], kdf: &KdfParams) -> Result<DataKey> {
+    let argon = Argon2::new(Algorithm::Argon2id, Version::V0x13, kdf.argon()?);
+    let mut key = DataKey::zeroed();
+    argon
+        .hash_password_into(password.as_bytes(), salt, &mut key[..])
+        .map_err(|e| anyhow!("argon2 hash_password_into failed: {e:?}"))?;
+    Ok(key)
+}
+
+fn hkdf(ikm: &[u8], info: &[u8]) -> DataKey {
+    let mut out = DataKey::zeroed();
+    Hkdf::<Sha256>::new(None, ikm)
+        .expand(info, &mut out[..])
+        .expect("hkdf output length");
+    out
+}
+
+/// パスワードに加えて鍵の導出に混ぜ込む要素（キーファイル・ハードウェアトークン）
+#[derive(Clone, Default)]
+pub struct Factors {
+    keyfile: Option<DataKey>,
+    token: Option<(TokenFactor, DataKey)>,
+}
+
+impl Factors {
+    /// キーファイルの内容（どんなファイルでもよい）
+    pub fn keyfile(mut self, contents: &[u8]) -> Self {
+        self.keyfile = Some(hkdf(contents, INFO_KEYFILE));
+        self
+    }
+
+    /// トークンと、そのチャレンジへの応答
+    pub fn token(mut self, factor: TokenFactor, response: &[u8]) -> Self {
+        self.token = Some((factor, hkdf(response, INFO_TOKEN)));
+        self
+    }
+}
+
+// パスワード由来の鍵（+ TOTPシークレット + キーファイル + トークンの応答）→ データ鍵をラップする鍵
+fn kek(k0: &[u8; 32], totp_secret: Option<&[u8]>, factors: &Factors) -> DataKey {
+    let mut ikm = Zeroizing::new(k0.to_vec());
+    if let Some(s) = totp_secret { ikm.extend_from_slice(s); }
+    if let Some(k) = &factors.keyfile { ikm.extend_from_slice(k.as_ref()); }
+    if let Some((_, k)) = &factors.token { ikm.extend_from_slice(k.as_ref()); }
+    hkdf(&ikm, INFO_KEK)
+}
+
+fn wrap(key: &[u8; 32], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let nonce = random::<12>();
+    let ct = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|e| anyhow!("aead encrypt failed: {e:?}"))?;
+    Ok((nonce.to_vec(), ct))
+}
+
+fn unwrap(key: &[u8; 32], nonce: &[u8], ct: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ct)
+        .ok()
+        .map(Zeroizing::new)
+}
+
+fn to_data_key(bytes: &[u8]) -> Result<DataKey> {
+    DataKey::from_slice(bytes).ok_or_else(|| anyhow!("wrapped key has wrong length"))
+}
+
+fn password_slot(password: &str, kdf: KdfParams, salt: Vec<u8>, k0: Option<DataKey>, totp: Option<&TotpSecret>, factors: &Factors, key: &DataKey) -> Result<Slot> {
+    let k0 = match k0 {
+        Some(k) => k,
+        None => derive_key_from_password(password, &salt, &kdf)?,
+    };
+    let totp_factor = match totp {
+        Some(t) => {
+            let (nonce, wrapped_secret) = wrap(&hkdf(k0.as_ref(), INFO_TOTP), &t.secret)?;
+            Some(TotpFactor { algorithm: t.algorithm, digits: t.digits, period: t.period, nonce, wrapped_secret })
+        }
+        None => None,
+    };
+    let (nonce, wrapped_key) = wrap(&kek(&k0, totp.map(|t| t.secret.as_slice()), factors), key.as_ref())?;
+    let (keyfile, token) = (factors.keyfile.is_some(), factors.token.as_ref().map(|(f, _)| f.clone()));
+    Ok(Slot::Password { kdf, salt, totp: totp_factor, keyfile, token, nonce, wrapped_key })
+}
+
+fn quorum_slot(threshold: u8, members: &[(String, Zeroizing<String>)], kdf: KdfParams, key: &DataKey) -> Result<Slot> {
+    let n = u8::try_from(members.len()).map_err(|_| err!(InvalidInput, "too many quorum members"))?;
+    let parts = shamir::split(key.as_ref(), threshold, n).map_err(|e| err!(InvalidInput, "{e}"))?;
+    let mut shares = Vec::with_capacity(members.len());
+    for ((member, password), part) in members.iter().zip(parts) {
+        let salt = random::<16>().to_vec();
+        let k = derive_key_from_password(password, &salt, &kdf)?;
+        let mut plain = Zeroizing::new(vec![part.x]);
+        plain.extend_from_slice(&part.y);
+        let (nonce, wrapped_share) = wrap(&k, &plain)?;
+        shares.push(QuorumShare { member: member.clone(), kdf, salt, nonce, wrapped_share });
+    }
+    Ok(Slot::Quorum { threshold, shares })
+}
+
+fn recipient_slot(recipient: &age::Recipient, name: Option<String>, key: &DataKey) -> Result<Slot> {
+    Ok(Slot::Recipient { recipient: recipient.to_string(), name, wrapped_key: age::wrap(recipient, key.as_ref())? })
+}
+
+// 受信者が一致する秘密鍵で開く
+fn open_recipient_slot(recipient: &str, wrapped_key: &[u8], creds: &mut dyn Credentials) -> Result<DataKey> {
+    let ids = creds.identities()?;
+    if ids.is_empty() {
+        return Err(err!(IdentityRequired, "this vault is encrypted to age recipients; pass an identity file with --identity PATH"));
+    }
+    let id = ids.iter().find(|i| i.to_public().to_string() == recipient)
+        .ok_or_else(|| err!(WrongPassword, "none of the given identities matches the vault's recipients"))?;
+    let key = age::unwrap(id, wrapped_key)
+        .ok_or_else(|| err!(CorruptVault, "key slot for {recipient} is corrupted"))?;
+    to_data_key(&key)
+}
+
+// クォーラムの全メンバーのパスワードを確かめ、シェアの順に並べる（同じパスワードで作り直すため）
+fn quorum_passwords(shares: &[QuorumShare], given: &[(String, Zeroizing<String>)]) -> Result<Vec<(String, Zeroizing<String>)>> {
+    shares.iter().map(|s| {
+        let (_, password) = given.iter().find(|(m, _)| *m == s.member)
+            .ok_or_else(|| err!(InvalidInput, "the password of quorum member {:?} is needed to rewrap the quorum", s.member))?;
+        let k = derive_key_from_password(password, &s.salt, &s.kdf)?;
+        unwrap(&k, &s.nonce, &s.wrapped_share).ok_or_else(|| err!(WrongPassword, "wrong password for quorum member {:?}", s.member))?;
+        Ok((s.member.clone(), password.clone()))
+    }).collect()
+}
+
+// しきい値に達するまでメンバーのパスワードを順に求める
+fn open_quorum_slot(threshold: u8, shares: &[QuorumShare], creds: &mut dyn Credentials) -> Result<DataKey> {
+    let mut got = Vec::new();
+    let mut rejected = Vec::new();
+    for s in shares {
+        if got.len() >= threshold as usize { break; }
+        check_kdf(&s.kdf, creds)?;
+        let Some(password) = creds.member_password(&s.member)? else { continue };
+        let k = derive_key_from_password(&password, &s.salt, &s.kdf)?;
+        match unwrap(&k, &s.nonce, &s.wrapped_share) {
+            Some(p) if p.len() > 1 => got.push(shamir::Share { x: p[0], y: Zeroizing::new(p[1..].to_vec()) }),
+            _ => {
+                creds.member_rejected(&s.member);
+                rejected.push(s.member.as_str());
+            }
+        }
+    }
+    if got.len() < threshold as usize {
+        let wrong = if rejected.is_empty() { String::new() } else { format!("; wrong password for {}", rejected.join(", ")) };
+        return Err(err!(WrongPassword, "quorum not reached ({} of {} required shares){wrong}", got.len(), threshold));
+    }
+    to_data_key(&shamir::combine(&got)?)
+}
+
+// 開けたらデータ鍵と、使った追加の要素を返す
+#[allow(clippy::too_many_arguments)]
+fn open_password_slot(kdf: &KdfParams, salt: &[u8], totp: &Option<TotpFactor>, keyfile: bool, token: &Option<TokenFactor>, nonce: &[u8], wrapped_key: &[u8], creds: &mut dyn Credentials) -> Result<(DataKey, Factors)> {
+    check_kdf(kdf, creds)?;
+    // KDFを計算する前に、キーファイルが無ければすぐに失敗する
+    let mut factors = match (keyfile, creds.keyfile()?) {
+        (true, None) => return Err(err!(KeyfileRequired, "this vault requires its key file (pass it with --keyfile PATH)")),
+        (true, Some(contents)) => Factors::default().keyfile(&contents),
+        (false, _) => Factors::default(),
+    };
+    let password = creds.password()?;
+    if let Some(f) = token {
+        let response = creds.token_response(f)?;
+        factors = factors.token(f.clone(), &response);
+    }
+    let k0 = derive_key_from_password(&password, salt, kdf)?;
+    let secret = match totp {
+        Some(f) => {
+            let s = unwrap(&hkdf(k0.as_ref(), INFO_TOTP), &f.nonce, &f.wrapped_secret)
+                .ok_or_else(|| err!(WrongPassword, "wrong master password"))?;
+            let code = creds.totp_code()?;
+            if !totp::verify(&s, f.algorithm, f.digits, f.period, &code) {
+                return Err(err!(InvalidTotp, "invalid TOTP code"));
+            }
+            Some(s)
+        }
+        None => None,
+    };
+    let kek = kek(&k0, secret.as_ref().map(|s| s.as_slice()), &factors);
+    let key = unwrap(&kek, nonce, wrapped_key).ok_or_else(|| match (&factors.keyfile, &factors.token) {
+        (None, None) => err!(WrongPassword, "wrong master password"),
+        (Some(_), None) => err!(WrongPassword, "wrong master password or key file"),
+        (None, Some(_)) => err!(WrongPassword, "wrong master password or hardware token"),
+        (Some(_), Some(_)) => err!(WrongPassword, "wrong master password, key file or hardware token"),
+    })?;
+    Ok((to_data_key(&key)?, factors))
+}
+
+/// 新しいデータ鍵とパスワードスロットを作成
+pub fn create(password: &str, kdf: KdfParams) -> Result<Unlocked> {
+    create_with(password, Factors::default(), kdf)
+}
+
+/// create と同じ。factors（キーファイル・トークン）もすべて揃わないと開けない
+pub fn create_with(password: &str, factors: Factors, kdf: KdfParams) -> Result<Unlocked> {
+    let key = DataKey::new(random());
+    let slot = password_slot(password, kdf, random::<16>().to_vec(), None, None, &factors, &key)?;
+    Ok(Unlocked { key, slots: vec![slot], factors, version: VERSION })
+}
+
+/// 新しいデータ鍵を作り、受信者ごとのスロットで包む（パスワードなし。秘密鍵ファイルで開く）
+pub fn create_for(recipients: &[age::Recipient]) -> Result<Unlocked> {
+    if recipients.is_empty() { return Err(err!(InvalidInput, "at least one recipient is required")); }
+    let key = DataKey::new(random());
+    let slots = recipients.iter().map(|r| recipient_slot(r, None, &key)).collect::<Result<_>>()?;
+    Ok(Unlocked { key, slots, factors: Factors::default(), version: VERSION })
+}
+
+struct Legacy<'a> {
+    kdf: KdfParams,
+    salt: &'a [u8],
+    nonce: &'a [u8],
+    ciphertext: &'a [u8],
+}
+
+fn parse_legacy(data: &[u8]) -> Result<Legacy<'_>> {
+    if data.len() < 4+1+4*3+16+12 { return Err(err!(CorruptVault, "file too small")); }
+    let mut idx = 5;
+    let read_u32 = |i: usize| u32::from_le_bytes(data[i..i+4].try_into().unwrap());
+    let m = read_u32(idx); idx+=4;
+    let t = read_u32(idx); idx+=4;
+    let p = read_u32(idx); idx+=4;
+    let kdf = KdfParams { m, t, p };
+    kdf.argon().map_err(|e| err!(CorruptVault, "{e}"))?;
+
+    let salt = &data[idx..idx+16]; idx+=16;
+    let nonce = &data[idx..idx+12]; idx+=12;
+    let ciphertext = &data[idx..];
+    Ok(Legacy { kdf, salt, nonce, ciphertext })
+}
+
+fn nonce_len(version: u8) -> usize {
+    if version == V2 { 12 } else { 7 }
+}
+
+fn check_chunk_size(header: &Header, version: u8) -> Result<()> {
+    match (version, header.chunk_size) {
+        (V2, None) => Ok(()),
+        (V2, Some(_)) => Err(err!(CorruptVault, "header invalid: unexpected chunk size")),
+        (_, Some(c)) if (1..=MAX_CHUNK_SIZE).contains(&c) => Ok(()),
+        _ => Err(err!(CorruptVault, "header invalid: bad chunk size")),
+    }
+}
+
+// v2・v3: (AADとなる先頭部分, ヘッダ, 本体)
+fn parse_header(data: &[u8], version: u8) -> Result<(&[u8], Header, &[u8])> {
+    if data.len() < 9 { return Err(err!(CorruptVault, "file too small")); }
+    let len = u32::from_le_bytes(data[5..9].try_into().unwrap()) as usize;
+    let end = 9usize.checked_add(len).filter(|&e| e <= data.len()).ok_or_else(|| err!(CorruptVault, "header truncated"))?;
+    let header: Header = serde_json::from_slice(&data[9..end]).map_err(|e| err!(CorruptVault, "header invalid: {e}"))?;
+    if header.nonce.len() != nonce_len(version) { return Err(err!(CorruptVault, "header invalid: bad nonce")); }
+    if header.slots.is_empty() { return Err(err!(CorruptVault, "header invalid: no key slots")); }
+    check_chunk_size(&header, version)?;
+    Ok((&data[..end], header, &data[end..]))
+}
+
+fn check_magic(data: &[u8]) -> Result<u8> {
+    if data.len() < 5 { return Err(err!(CorruptVault, "file too small")); }
+    if &data[..4] != MAGIC { return Err(err!(CorruptVault, "bad magic")); }
+    match data[4] {
+        v @ (LEGACY_VERSION | V2 | VERSION) => Ok(v),
+        v => Err(err!(UnsupportedFormat, "unsupported version {v}")),
+    }
+}
+
+/// ヘッダから分かる情報（復号不要）
+pub struct Info {
+    pub version: u8,
+    pub slots: Vec<SlotInfo>,
+}
+
+pub struct SlotInfo {
+    pub kind: String,
+    /// パスワードから鍵を導出するスロットのKDFパラメータ（受信者のスロットは None）
+    pub kdf: Option<KdfParams>,
+    pub totp: bool,
+    pub keyfile: bool,
+    pub token: Option<Token>,
+}
+
+/// 鍵なしでヘッダを検証し、その内容を返す
+pub fn inspect(data: &[u8]) -> Result<Info> {
+    match check_magic(data)? {
+        LEGACY_VERSION => {
+            let l = parse_legacy(data)?;
+            let slot = SlotInfo { kind: "password".into(), kdf: Some(l.kdf), totp: false, keyfile: false, token: None };
+            Ok(Info { version: LEGACY_VERSION, slots: vec![slot] })
+        }
+        version => {
+            let (_, header, _) = parse_header(data, version)?;
+            let slots = header.slots.iter().map(|s| match s {
+                Slot::Password { kdf, totp, keyfile, token, .. } => SlotInfo {
+                    kind: "password".into(),
+                    kdf: Some(*kdf),
+                    totp: totp.is_some(),
+                    keyfile: *keyfile,
+                    token: token.as_ref().map(|t| t.token.clone()),
+                },
+                Slot::Quorum { threshold, shares } => SlotInfo {
+                    kind: format!("quorum {}-of-{} ({})", threshold, shares.len(),
+                        shares.iter().map(|s| s.member.as_str()).collect::<Vec<_>>().join(", ")),
+                    kdf: Some(shares.first().map(|s| s.kdf).unwrap_or_default()),
+                    totp: false,
+                    keyfile: false,
+                    token: None,
+                },
+                Slot::Recipient { recipient, name, .. } => SlotInfo {
+                    kind: match name {
+                        Some(n) => format!("age recipient {recipient} ({n})"),
+                        None => format!("age recipient {recipient}"),
+                    },
+                    kdf: None,
+                    totp: false,
+                    keyfile: false,
+                    token: None,
+                },
+            }).collect();
+            Ok(Info { version, slots })
+        }
+    }
+}
+
+/// 受信者のスロット（チームのメンバー）
+pub struct Member {
+    /// `age1...`
+    pub recipient: String,
+    pub name: Option<String>,
+}
+
+impl Member {
+    /// 名前か受信者が who と一致するか
+    pub fn is(&self, who: &str) -> bool {
+        self.recipient == who || self.name.as_deref() == Some(who)
+    }
+}
+
+fn members_of(slots: &[Slot]) -> Vec<Member> {
+    slots.iter().filter_map(|s| match s {
+        Slot::Recipient { recipient, name, .. } => Some(Member { recipient: recipient.clone(), name: name.clone() }),
+        _ => None,
+    }).collect()
+}
+
+/// ヘッダからメンバーの一覧を読む（復号不要）
+pub fn members(data: &[u8]) -> Result<Vec<Member>> {
+    match check_magic(data)? {
+        LEGACY_VERSION => Ok(Vec::new()),
+        version => Ok(members_of(&parse_header(data, version)?.1.slots)),
+    }
+}
+
+fn parse_vault(plaintext: &[u8]) -> Result<Vault> {
+    Ok(serde_json::from_slice(plaintext)?)
+}
+
+// 先頭から順に試し、最初に開けたスロットのデータ鍵（と追加の要素）を返す。
+// 秘密鍵ファイルが渡されていれば、パスワードを尋ねる前に受信者のスロットを試す
+fn open_slots(slots: &[Slot], creds: &mut dyn Credentials) -> Result<(DataKey, Factors)> {
+    let mut last_err = err!(CorruptVault, "no usable key slot");
+    let mut ordered: Vec<&Slot> = slots.iter().collect();
+    if ordered.iter().any(|s| matches!(s, Slot::Recipient { .. })) && !creds.identities()?.is_empty() {
+        ordered.sort_by_key(|s| !matches!(s, Slot::Recipient { .. }));
+    }
+    for slot in ordered {
+        let opened = match slot {
+            Slot::Password { kdf, salt, totp, keyfile, token, nonce, wrapped_key } => {
+                open_password_slot(kdf, salt, totp, *keyfile, token, nonce, wrapped_key, creds)
+            }
+            Slot::Quorum { threshold, shares } => open_quorum_slot(*threshold, shares, creds).map(|k| (k, Factors::default())),
+            Slot::Recipient { recipient, wrapped_key, .. } => {
+                open_recipient_slot(recipient, wrapped_key, creds).map(|k| (k, Factors::default()))
+            }
+        };
+        match opened {
+            Ok(k) => return Ok(k),
+            Err(e) => last_err = e,
+        }
+    }
+    Err(last_err)
+}
+
+// 本体を開いて金庫を読む（v2 は一括、v3 はチャンクごとに復号しながら読む）
+fn open_body(key: &DataKey, version: u8, header: &Header, aad: &[u8], body: &[u8]) -> Result<Vault> {
+    if version == V2 {
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+            .decrypt(Nonce::from_slice(&header.nonce), Payload { msg: body, aad })
+            .map(Zeroizing::new)
+            .map_err(|_| err!(CorruptVault, "vault corrupted (authentication failed)"))?;
+        return parse_vault(&plaintext);
+    }
+    let mut r = ChunkReader::new(key, header, aad, body);
+    let vault = serde_json::from_reader(&mut r);
+    r.check()?;
+    Ok(vault?)
+}
+
+pub fn unlock(data: &[u8], creds: &mut dyn Credentials) -> Result<(Vault, Unlocked)> {
+    match check_magic(data)? {
+        LEGACY_VERSION => {
+            let l = parse_legacy(data)?;
+            check_kdf(&l.kdf, creds)?;
+            let password = creds.password()?;
+            let k0 = derive_key_from_password(&password, l.salt, &l.kdf)?;
+            let plaintext = ChaCha20Poly1305::new(Key::from_slice(k0.as_ref()))
+                .decrypt(Nonce::from_slice(l.nonce), l.ciphertext)
+                .map(Zeroizing::new)
+                .map_err(|_| err!(WrongPassword, "wrong master password or corrupted vault"))?;
+            let vault = parse_vault(&plaintext)?;
+            // 次回保存時に最新形式へ移行。同じソルト・パラメータの鍵をそのまま使うのでKDFの再計算は不要
+            let key = DataKey::new(random());
+            let slot = password_slot(&password, l.kdf, l.salt.to_vec(), Some(k0), None, &Factors::default(), &key)?;
+            Ok((vault, Unlocked { key, slots: vec![slot], factors: Factors::default(), version: LEGACY_VERSION }))
+        }
+        version => {
+            let (aad, header, body) = parse_header(data, version)?;
+            let (key, factors) = open_slots(&header.slots, creds)?;
+            let vault = open_body(&key, version, &header, aad, body)?;
+            Ok((vault, Unlocked { key, slots: header.slots, factors, version }))
+        }
+    }
+}
+
+/// salvage で取り出せた平文と、その過程で分かったこと
+pub struct Recovered {
+    pub plaintext: Zeroizing<Vec<u8>>,
+    /// 認証タグで検証できなかった平文の範囲（一部の値が書き換わっている可能性がある）
+    pub unverified: Vec<Range<usize>>,
+    /// 修復した箇所・失われたもの（表示用）
+    pub notes: Vec<String>,
+    pub unlocked: Unlocked,
+}
+
+// ヘッダ長やマジックが壊れていてもヘッダのJSONを探して読む。読めないスロットは捨てる
+// 戻り値は (ヘッダのJSONの開始位置, 終了位置, 形式バージョン, ヘッダ)
+fn parse_header_relaxed(data: &[u8], notes: &mut Vec<String>) -> Result<(usize, usize, u8, Header)> {
+    // 本来の位置（9バイト目）になければ先頭付近から探す
+    let start = if data.get(9) == Some(&b'{') {
+        9
+    } else {
+        let window = &data[..data.len().min(64 * 1024)];
+        let at = window.windows(9).position(|w| w == b"{\"slots\":").ok_or_else(|| err!(CorruptVault, "header not found; the key slots are lost"))?;
+        notes.push(format!("header found at byte {at} instead of 9"));
+        at
+    };
+    let mut stream = serde_json::Deserializer::from_slice(&data[start..]).into_iter::<serde_json::Value>();
+    let value = match stream.next() {
+        Some(Ok(v)) => v,
+        _ => return Err(err!(CorruptVault, "header unreadable; the key slots are lost")),
+    };
+    let end = start + stream.byte_offset();
+    if data.len() >= 9 && u32::from_le_bytes(data[5..9].try_into().unwrap()) as usize != end - 9 {
+        notes.push("header length field was wrong; ignored it".into());
+    }
+    let mut slots = Vec::new();
+    for (i, s) in value.get("slots").and_then(|v| v.as_array()).into_iter().flatten().enumerate() {
+        match serde_json::from_value::<Slot>(s.clone()) {
+            Ok(slot) => slots.push(slot),
+            Err(_) => notes.push(format!("key slot {} unreadable; dropped", i + 1)),
+        }
+    }
+    if slots.is_empty() { return Err(err!(CorruptVault, "no readable key slot left; the vault cannot be decrypted")); }
+    // チャンクの大きさがあれば v3
+    let chunk_size = value.get("chunk_size").and_then(|c| c.as_u64()).and_then(|c| u32::try_from(c).ok());
+    let version = if chunk_size.is_some() { VERSION } else { V2 };
+    let nonce = value.get("nonce").and_then(|n| n.as_str())
+        .and_then(|n| STANDARD.decode(n).ok())
+        .filter(|n| n.len() == nonce_len(version))
+        .ok_or_else(|| err!(CorruptVault, "body nonce lost; the vault cannot be decrypted"))?;
+    let header = Header { slots, nonce, chunk_size };
+    check_chunk_size(&header, version)?;
+    Ok((start, end, version, header))
+}
+
+// 鍵ストリームだけで復号する（壊れたバイト以外はそのまま読める）。
+// ChaCha20-Poly1305 は先頭ブロック（カウンタ0）を認証鍵に使い、本文はカウンタ1から
+fn keystream_decrypt(key: &[u8; 32], nonce: &[u8], ct: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut buf = Zeroizing::new(ct.to_vec());
+    let mut cipher = chacha20::ChaCha20::new(key.into(), nonce.into());
+    cipher.seek(64u64);
+    cipher.apply_keystream(&mut buf);
+    buf
+}
+
+// 一括暗号化（v1・v2）の本体。認証できなければ認証なしで復号する
+// （末尾の認証タグ部分はゴミになるが、後段のJSON読み取りで捨てられる）
+fn salvage_single(key: &[u8; 32], nonce: &[u8], body: &[u8], aads: &[&[u8]], notes: &mut Vec<String>) -> (Zeroizing<Vec<u8>>, Vec<Range<usize>>) {
+    for aad in aads {
+        if let Ok(p) = ChaCha20Poly1305::new(Key::from_slice(key)).decrypt(Nonce::from_slice(nonce), Payload { msg: body, aad }) {
+            return (Zeroizing::new(p), Vec::new());
+        }
+    }
+    notes.push("body failed authentication; decrypted it without an integrity check".into());
+    let buf = keystream_decrypt(key, nonce, body);
+    let len = buf.len();
+    (buf, std::iter::once(0..len).collect())
+}
+
+// チャンク暗号化（v3）の本体。チャンクごとに認証し、通らないチャンクだけ認証なしで復号する
+fn salvage_chunks(key: &[u8; 32], header: &Header, aads: &[&[u8]], body: &[u8], notes: &mut Vec<String>) -> (Zeroizing<Vec<u8>>, Vec<Range<usize>>) {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let full = header.chunk_size.unwrap_or_default() as usize + TAG_LEN;
+    let mut out = Zeroizing::new(Vec::with_capacity(body.len()));
+    let mut unverified: Vec<Range<usize>> = Vec::new();
+    let mut ended = false;
+    for (i, ct) in body.chunks(full).enumerate() {
+        let Ok(counter) = u32::try_from(i) else { break };
+        if ended {
+            notes.push(format!("ignored {} bytes after the final chunk", body.len() - i * full));
+            break;
+        }
+        let opened = [false, true].into_iter().find_map(|last| {
+            let nonce = chunk_nonce(&header.nonce, counter, last);
+            aads.iter().find_map(|aad| cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: ct, aad }).ok()).map(|p| (p, last))
+        });
+        match opened {
+            Some((p, last)) => {
+                out.extend_from_slice(&p);
+                ended = last;
+            }
+            None => {
+                // 途中で切れたチャンクには認証タグが無いので、全体を本文として復号する
+                let text = if ct.len() == full { &ct[..full - TAG_LEN] } else { ct };
+                let p = keystream_decrypt(key, &chunk_nonce(&header.nonce, counter, ct.len() < full), text);
+                notes.push(if ct.len() < full {
+                    format!("final chunk {} failed authentication (damaged, or the file is truncated); decrypted it without an integrity check", i + 1)
+                } else {
+                    format!("chunk {} failed authentication; decrypted it without an integrity check", i + 1)
+                });
+                let at = out.len();
+                match unverified.last_mut() {
+                    Some(r) if r.end == at => r.end += p.len(),
+                    _ => unverified.push(at..at + p.len()),
+                }
+                out.extend_from_slice(&p);
+                ended = ct.len() < full;
+            }
+        }
+    }
+    if !ended {
+        notes.push(format!("the final chunk is missing (file truncated); data after byte {} of the contents is lost", out.len()));
+    }
+    (out, unverified)
+}
+
+/// 破損したファイルから、段階的に緩めた方法でできる限り平文を取り出す
+pub fn recover(data: &[u8], creds: &mut dyn Credentials) -> Result<Recovered> {
+    let mut notes = Vec::new();
+    let magic = check_magic(data);
+    // ヘッダのJSONが見当たらなければ v1 とみなす
+    let legacy = match magic {
+        Ok(v) => v == LEGACY_VERSION,
+        Err(_) => data.len() > 9 && data[9] != b'{' && !data.windows(9).take(64 * 1024).any(|w| w == b"{\"slots\":"),
+    };
+    if magic.is_err() && legacy { notes.push("bad magic or version; trying the legacy format".into()); }
+    if legacy {
+        let l = parse_legacy(data)?;
+        check_kdf(&l.kdf, creds)?;
+        let password = creds.password()?;
+        let k0 = derive_key_from_password(&password, l.salt, &l.kdf)?;
+        let (plaintext, unverified) = salvage_single(&k0, l.nonce, l.ciphertext, &[], &mut notes);
+        let key = DataKey::new(random());
+        let slot = password_slot(&password, l.kdf, l.salt.to_vec(), Some(k0), None, &Factors::default(), &key)?;
+        let unlocked = Unlocked { key, slots: vec![slot], factors: Factors::default(), version: LEGACY_VERSION };
+        return Ok(Recovered { plaintext, unverified, notes, unlocked });
+    }
+    // ヘッダ前の数バイトだけが壊れていた場合は、正しい値で作り直したAADで認証できる
+    let mut aads = Vec::new();
+    let parsed = magic.as_ref().map_err(|e| err!(CorruptVault, "{e}")).and_then(|&v| parse_header(data, v).map(|(aad, h, _)| (aad.len(), v, h)));
+    let (end, version, header) = match parsed {
+        Ok(p) => p,
+        Err(e) => {
+            notes.push(format!("{e}; re-reading the header with relaxed checks"));
+            let (start, end, version, header) = parse_header_relaxed(data, &mut notes)?;
+            let mut aad = MAGIC.to_vec();
+            aad.push(version);
+            aad.extend_from_slice(&((end - start) as u32).to_le_bytes());
+            aad.extend_from_slice(&data[start..end]);
+            aads.push(aad);
+            (end, version, header)
+        }
+    };
+    aads.push(data[..end].to_vec());
+    let (key, factors) = open_slots(&header.slots, creds)?;
+    let aads: Vec<&[u8]> = aads.iter().map(Vec::as_slice).collect();
+    let (plaintext, unverified) = if version == V2 {
+        salvage_single(&key, &header.nonce, &data[end..], &aads, &mut notes)
+    } else {
+        salvage_chunks(&key, &header, &aads, &data[end..], &mut notes)
+    };
+    let unlocked = Unlocked { key, slots: header.slots, factors, version };
+    Ok(Recovered { plaintext, unverified, notes, unlocked })
+}
+
+/// エージェントから受け取ったデータ鍵で開く（KDF・鍵スロットは使わない）
+pub fn unlock_with_key(data: &[u8], key: DataKey) -> Result<(Vault, Unlocked)> {
+    let version = check_magic(data)?;
+    if version == LEGACY_VERSION {
+        return Err(err!(UnsupportedFormat, "agent unlock needs a current-format vault"));
+    }
+    let (aad, header, body) = parse_header(data, version)?;
+    let vault = open_body(&key, version, &header, aad, body).map_err(|e| match kind_of(&e) {
+        Kind::CorruptVault => err!(WrongPassword, "agent key does not open this vault"),
+        _ => e,
+    })?;
+    Ok((vault, Unlocked { key, slots: header.slots, factors: Factors::default(), version }))
+}
+
+// STREAM 構成（Hoang ほか）: 接頭辞(7) || チャンク番号(u32 BE) || 最終チャンクなら1。
+// 最終チャンクの印があるので、チャンク境界で切れたファイルも検出できる
+fn chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> [u8; 12] {
+    let mut n = [0u8; 12];
+    n[..7].copy_from_slice(prefix);
+    n[7..11].copy_from_slice(&counter.to_be_bytes());
+    n[11] = last as u8;
+    n
+}
+
+// 平文をチャンクごとに暗号化して書き出す（最終チャンクは finish で書く）
+struct ChunkWriter<'a, W: Write> {
+    cipher: ChaCha20Poly1305,
+    prefix: &'a [u8],
+    aad: &'a [u8],
+    buf: Zeroizing<Vec<u8>>,
+    counter: u32,
+    out: W,
+}
+
+impl<W: Write> ChunkWriter<'_, W> {
+    fn emit(&mut self, last: bool) -> io::Result<()> {
+        let n = self.buf.len().min(CHUNK_SIZE);
+        let nonce = chunk_nonce(self.prefix, self.counter, last);
+        let ct = self.cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: &self.buf[..n], aad: self.aad })
+            .map_err(|e| io::Error::other(format!("aead encrypt failed: {e:?}")))?;
+        self.out.write_all(&ct)?;
+        self.buf.drain(..n);
+        self.counter = self.counter.checked_add(1).ok_or_else(|| io::Error::other("vault too large"))?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.emit(true)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: Write> Write for ChunkWriter<'_, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        // 最終チャンクかどうかは書き終わるまで分からないので、1チャンク分より多く溜まってから書く
+        while self.buf.len() > CHUNK_SIZE { self.emit(false)?; }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// チャンクごとに認証・復号しながら読む（平文は1チャンク分だけ保持する）
+struct ChunkReader<'a> {
+    cipher: ChaCha20Poly1305,
+    prefix: &'a [u8],
+    aad: &'a [u8],
+    rest: &'a [u8],
+    chunk_size: usize,
+    counter: u32,
+    plain: Zeroizing<Vec<u8>>,
+    pos: usize,
+    done: bool,
+    /// 認証に失敗したチャンク（serde_json のエラーに包まれる前に記録する）
+    failed: Option<u32>,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn new(key: &DataKey, header: &'a Header, aad: &'a [u8], body: &'a [u8]) -> Self {
+        ChunkReader {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key.as_ref())),
+            prefix: &header.nonce,
+            aad,
+            rest: body,
+            chunk_size: header.chunk_size.unwrap_or_default() as usize,
+            counter: 0,
+            plain: Zeroizing::new(Vec::new()),
+            pos: 0,
+            done: false,
+            failed: None,
+        }
+    }
+
+    // 1チャンク分より長く残っていれば途中のチャンク、そうでなければ最終チャンク
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let full = self.chunk_size + TAG_LEN;
+        let last = self.rest.len() <= full;
+        let (ct, rest) = self.rest.split_at(self.rest.len().min(full));
+        let nonce = chunk_nonce(self.prefix, self.counter, last);
+        let Ok(p) = self.cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: ct, aad: self.aad }) else {
+            self.failed = Some(self.counter);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "chunk authentication failed"));
+        };
+        self.plain = Zeroizing::new(p);
+        self.pos = 0;
+        self.rest = rest;
+        self.done = last;
+        self.counter = self.counter.checked_add(1).ok_or_else(|| io::Error::other("too many chunks"))?;
+        Ok(())
+    }
+
+    // 読み取り中の認証失敗を金庫のエラーにする
+    fn check(&self) -> Result<()> {
+        match self.failed {
+            Some(i) => Err(err!(CorruptVault, "vault corrupted or truncated at chunk {} (authentication failed)", i + 1)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.done { return Ok(0); }
+            self.next_chunk()?;
+        }
+        let n = out.len().min(self.plain.len() - self.pos);
+        out[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// 最新形式で暗号化して書き出す（本体のnonceは毎回新規。平文全体は保持しない）
+pub fn seal_to<W: Write>(vault: &Vault, u: &Unlocked, mut out: W) -> Result<W> {
+    let header = Header { slots: u.slots.clone(), nonce: random::<7>().to_vec(), chunk_size: Some(CHUNK_SIZE as u32) };
+    let header_bytes = serde_json::to_vec(&header)?;
+    let mut aad = Vec::with_capacity(9 + header_bytes.len());
+    aad.extend_from_slice(MAGIC);
+    aad.push(VERSION);
+    aad.extend_from_slice(&(header_bytes.len() as u32).to_le_bytes());
+    aad.extend_from_slice(&header_bytes);
+    out.write_all(&aad)?;
+
+    let mut w = ChunkWriter {
+        cipher: ChaCha20Poly1305::new(Key::from_slice(u.key.as_ref())),
+        prefix: &header.nonce,
+        aad: &aad,
+        buf: Zeroizing::new(Vec::with_capacity(2 * CHUNK_SIZE)),
+        counter: 0,
+        out,
+    };
+    serde_json::to_writer(&mut w, vault)?;
+    Ok(w.finish()?)
+}
+
+/// 最新形式で暗号化
+pub fn seal(vault: &Vault, u: &Unlocked) -> Result<Vec<u8>> {
+    seal_to(vault, u, Vec::new())
+}
+
+/// 書き込んだファイルを読み戻して、メモリ上のデータ鍵で認証タグまで検証する（KDFは不要）
+pub fn verify(data: &[u8], u: &Unlocked) -> Result<()> {
+    if check_magic(data)? != VERSION {
+        return Err(err!(CorruptVault, "unexpected format version"));
+    }
+    let (aad, header, body) = parse_header(data, VERSION)?;
+    let mut r = ChunkReader::new(&u.key, &header, aad, body);
+    let copied = io::copy(&mut r, &mut io::sink());
+    r.check()?;
+    copied?;
+    Ok(())
+}
+
+impl Unlocked {
+    pub fn data_key(&self) -> &DataKey {
+        &self.key
+    }
+
+    pub fn totp(&self) -> Option<&TotpFactor> {
+        self.slots.iter().find_map(|s| match s {
+            Slot::Password { totp, .. } => totp.as_ref(),
+            _ => None,
+        })
+    }
+
+    /// パスワードスロットがキーファイルを要求するか
+    pub fn keyfile_required(&self) -> bool {
+        self.slots.iter().any(|s| matches!(s, Slot::Password { keyfile: true, .. }))
+    }
+
+    /// パスワードスロットが要求するハードウェアトークン
+    pub fn token(&self) -> Option<&Token> {
+        self.slots.iter().find_map(|s| match s {
+            Slot::Password { token, .. } => token.as_ref().map(|t| &t.token),
+            _ => None,
+        })
+    }
+
+    // エージェントの鍵で開いた場合はキーファイルやトークンの応答が手元に無く、スロットを作り直せない
+    fn check_factors(&self) -> Result<()> {
+        if self.keyfile_required() && self.factors.keyfile.is_none() {
+            return Err(err!(KeyfileRequired, "changing the key slot needs the key file; run `rustpass lock` and pass --keyfile"));
+        }
+        if self.token().is_some() && self.factors.token.is_none() {
+            return Err(err!(InvalidInput, "changing the key slot needs the hardware token; run `rustpass lock` and try again"));
+        }
+        Ok(())
+    }
+
+    /// パスワードスロットのKDFパラメータ
+    pub fn kdf(&self) -> Option<KdfParams> {
+        self.slots.iter().find_map(|s| match s {
+            Slot::Password { kdf, .. } => Some(*kdf),
+            _ => None,
+        })
+    }
+
+    /// パスワードスロットを作り直す（新しいソルト。KDFパラメータとキーファイルは既存のものを引き継ぐ）
+    pub fn rewrap_password(&mut self, password: &str, totp: Option<&TotpSecret>) -> Result<()> {
+        self.check_factors()?;
+        self.rewrap(password, totp, self.kdf().unwrap_or_default())
+    }
+
+    fn rewrap(&mut self, password: &str, totp: Option<&TotpSecret>, kdf: KdfParams) -> Result<()> {
+        let slot = password_slot(password, kdf, random::<16>().to_vec(), None, totp, &self.factors, &self.key)?;
+        self.slots.retain(|s| !matches!(s, Slot::Password { .. }));
+        self.slots.insert(0, slot);
+        Ok(())
+    }
+
+    // パスワードスロットのTOTPシークレットを取り出す（作り直すときに引き継ぐため）
+    fn totp_secret(&self, password: &str) -> Result<Option<TotpSecret>> {
+        let Some(Slot::Password { kdf, salt, totp, .. }) = self.slots.iter().find(|s| matches!(s, Slot::Password { .. })) else {
+            return Err(err!(InvalidInput, "vault has no master password slot (it is quorum-only or encrypted to age recipients)"));
+        };
+        let Some(f) = totp else { return Ok(None) };
+        let k0 = derive_key_from_password(password, salt, kdf)?;
+        let s = unwrap(&hkdf(k0.as_ref(), INFO_TOTP), &f.nonce, &f.wrapped_secret)
+            .ok_or_else(|| err!(WrongPassword, "wrong master password"))?;
+        Ok(Some(TotpSecret { secret: s, algorithm: f.algorithm, digits: f.digits, period: f.period }))
+    }
+
+    /// マスターパスワードを変更する（新しいソルト。TOTP要素とキーファイルはそのまま引き継ぐ）
+    pub fn change_password(&mut self, current: &str, new: &str) -> Result<()> {
+        let secret = self.totp_secret(current)?;
+        self.rewrap_password(new, secret.as_ref())
+    }
+
+    /// キーファイルを設定・変更する（None で外す）。TOTP要素とトークンは引き継ぐ
+    pub fn set_keyfile(&mut self, password: &str, contents: Option<&[u8]>) -> Result<()> {
+        self.check_factors()?;
+        let secret = self.totp_secret(password)?;
+        self.factors.keyfile = None;
+        if let Some(c) = contents { self.factors = std::mem::take(&mut self.factors).keyfile(c); }
+        self.rewrap(password, secret.as_ref(), self.kdf().unwrap_or_default())
+    }
+
+    /// 新しいKDFパラメータとソルトでパスワードスロットを作り直す。ハードウェアトークンは
+    /// token に置き換える（None で外す。新しいチャレンジと応答を渡す）。TOTP要素とキーファイルは引き継ぐ
+    pub fn rekey(&mut self, password: &str, kdf: KdfParams, token: Option<(TokenFactor, &[u8])>) -> Result<()> {
+        kdf.check()?;
+        self.check_factors()?;
+        let secret = self.totp_secret(password)?;
+        self.factors.token = None;
+        if let Some((f, response)) = token { self.factors = std::mem::take(&mut self.factors).token(f, response); }
+        self.rewrap(password, secret.as_ref(), kdf)
+    }
+
+    pub fn has_password(&self) -> bool {
+        self.slots.iter().any(|s| matches!(s, Slot::Password { .. }))
+    }
+
+    /// 受信者のスロット（チームのメンバー）
+    pub fn members(&self) -> Vec<Member> {
+        members_of(&self.slots)
+    }
+
+    /// 今のデータ鍵を受信者あてに包んだスロットを足す
+    pub fn add_member(&mut self, recipient: &age::Recipient, name: Option<&str>) -> Result<()> {
+        let r = recipient.to_string();
+        if let Some(m) = self.members().iter().find(|m| m.is(&r) || name.is_some_and(|n| m.is(n))) {
+            return Err(err!(AlreadyExists, "{} is already a member", m.name.as_deref().unwrap_or(&m.recipient)));
+        }
+        let slot = recipient_slot(recipient, name.map(str::to_string), &self.key)?;
+        self.slots.push(slot);
+        Ok(())
+    }
+
+    /// 名前か受信者が who のメンバーのスロットを削除し、新しいデータ鍵で残りのスロットをすべて作り直す
+    /// （外したメンバーが以前の鍵を控えていても以後の版は開けない）。パスワードスロットがあれば password、
+    /// クォーラムスロットがあれば全メンバーのパスワード（quorum）が必要で、足りなければ何も変えずにエラーにする
+    pub fn remove_member(&mut self, who: &str, password: Option<&str>, quorum: &[(String, Zeroizing<String>)]) -> Result<()> {
+        if !self.members().iter().any(|m| m.is(who)) {
+            return Err(err!(NotFound, "no member {who:?}"));
+        }
+        let removed = |s: &Slot| matches!(s, Slot::Recipient { recipient, name, .. } if recipient == who || name.as_deref() == Some(who));
+        if self.slots.iter().all(removed) {
+            return Err(err!(InvalidInput, "refusing to remove the last key slot"));
+        }
+        let key = DataKey::new(random());
+        let mut slots = Vec::with_capacity(self.slots.len());
+        for s in self.slots.iter().filter(|s| !removed(s)) {
+            slots.push(match s {
+                Slot::Password { kdf, .. } => {
+                    let password = password.ok_or_else(|| err!(InvalidInput, "the master password is needed to rewrap the password slot"))?;
+                    let secret = self.check_password(password)?;
+                    password_slot(password, *kdf, random::<16>().to_vec(), None, secret.as_ref(), &self.factors, &key)?
+                }
+                Slot::Quorum { threshold, shares } => {
+                    let members = quorum_passwords(shares, quorum)?;
+                    quorum_slot(*threshold, &members, shares.first().map(|s| s.kdf).unwrap_or_default(), &key)?
+                }
+                Slot::Recipient { recipient, name, .. } => recipient_slot(&age::parse_recipient(recipient)?, name.clone(), &key)?,
+            });
+        }
+        self.slots = slots;
+        self.key = key;
+        Ok(())
+    }
+
+    // パスワードスロットを今の要素とこのパスワードで開けるか確かめ、TOTPシークレットを返す（作り直すときに引き継ぐ）
+    fn check_password(&self, password: &str) -> Result<Option<TotpSecret>> {
+        self.check_factors()?;
+        let Some(Slot::Password { kdf, salt, totp, nonce, wrapped_key, .. }) = self.slots.iter().find(|s| matches!(s, Slot::Password { .. })) else {
+            return Err(err!(InvalidInput, "vault has no master password slot"));
+        };
+        let k0 = derive_key_from_password(password, salt, kdf)?;
+        let secret = match totp {
+            Some(f) => Some(unwrap(&hkdf(k0.as_ref(), INFO_TOTP), &f.nonce, &f.wrapped_secret)
+                .ok_or_else(|| err!(WrongPassword, "wrong master password"))?),
+            None => None,
+        };
+        unwrap(&kek(&k0, secret.as_ref().map(|s| s.as_slice()), &self.factors), nonce, wrapped_key)
+            .ok_or_else(|| err!(WrongPassword, "wrong master password"))?;
+        Ok(totp.as_ref().zip(secret).map(|(f, secret)| TotpSecret { secret, algorithm: f.algorithm, digits: f.digits, period: f.period }))
+    }
+
+    pub fn quorum(&self) -> Option<(u8, Vec<&str>)> {
+        self.slots.iter().find_map(|s| match s {
+            Slot::Quorum { threshold, shares } => Some((*threshold, shares.iter().map(|x| x.member.as_str()).collect())),
+            _ => None,
+        })
+    }
+
+    /// クォーラムスロットを作り直す（既存のものは置き換え）
+    pub fn set_quorum(&mut self, threshold: u8, members: &[(String, Zeroizing<String>)], kdf: KdfParams) -> Result<()> {
+        let slot = quorum_slot(threshold, members, kdf, &self.key)?;
+        self.slots.retain(|s| !matches!(s, Slot::Quorum { .. }));
+        self.slots.push(slot);
+        Ok(())
+    }
+
+    pub fn remove_quorum(&mut self) -> Result<()> {
+        self.retain_slots(|s| !matches!(s, Slot::Quorum { .. }))
+    }
+
+    /// パスワードスロットを削除（クォーラムでのみロック解除できる金庫にする）
+    pub fn remove_password(&mut self) -> Result<()> {
+        self.retain_slots(|s| !matches!(s, Slot::Password { .. }))
+    }
+
+    fn retain_slots(&mut self, keep: impl Fn(&Slot) -> bool) -> Result<()> {
+        if !self.slots.iter().any(&keep) {
+            return Err(err!(InvalidInput, "refusing to remove the last key slot"));
+        }
+        self.slots.retain(keep);
+        Ok(())
+    }
+}
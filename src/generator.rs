@@ -0,0 +1,169 @@
+// 対話的なパスワード生成（キー操作で再生成・条件の切り替えを行い、確定またはコピー）
+use crate::picker::RawMode;
+use crate::{generate_password, layout, passphrase, strength, Avoid};
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+use std::io::{self, Write};
+use zeroize::Zeroizing;
+
+const DEFAULT_WORDS: usize = 6;
+const HELP: &str = "space reroll  s symbols  a ambiguous  +/- length  p passphrase  c copy  enter accept  esc quit";
+const HELP_LINES: &str = "r reroll, s symbols, a ambiguous, + longer, - shorter, p passphrase, c copy, empty line to accept, q quit";
+
+pub struct Settings {
+    pub len: usize,
+    pub symbols: bool,
+    pub allow_ambiguous: bool,
+    pub layout_safe: Vec<layout::Layout>,
+    pub avoid: Avoid,
+    /// Some なら単語数（パスフレーズモード）
+    pub words: Option<usize>,
+    pub wordlist: String,
+    pub separator: String,
+    pub style: passphrase::Style,
+}
+
+/// 確定・コピー・中止のいずれか
+pub enum Outcome {
+    Accept(Zeroizing<String>),
+    Copy(Zeroizing<String>),
+    Cancel,
+}
+
+struct State {
+    s: Settings,
+    list: Option<Vec<String>>,
+    current: Zeroizing<String>,
+    error: Option<String>,
+}
+
+impl State {
+    fn reroll(&mut self) -> Result<()> {
+        let r = match self.s.words {
+            Some(n) => {
+                if self.list.is_none() { self.list = Some(passphrase::load_wordlist(&self.s.wordlist)?); }
+                passphrase::generate_passphrase(self.list.as_deref().unwrap_or_default(), n, &self.s.separator, self.s.style)
+            }
+            None => generate_password(self.s.len, self.s.symbols, self.s.allow_ambiguous, &self.s.layout_safe, self.s.avoid),
+        };
+        // 条件を満たせない組み合わせは直前の候補を残してメッセージを出す
+        match r {
+            Ok(p) => { self.current = Zeroizing::new(p); self.error = None; }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let on = |b: bool| if b { "on" } else { "off" };
+        match (self.s.words, &self.list) {
+            (Some(n), Some(list)) => {
+                let bits = passphrase::bits_per_word(list) * n as f64 + self.s.style.extra_bits(n);
+                let est = strength::Estimate::from_bits(bits);
+                format!("passphrase: {n} words from {} (~{:.0} bits, crack time {})", self.s.wordlist, bits, est.crack_time())
+            }
+            _ => {
+                let est = strength::estimate(&self.current, &[]);
+                format!(
+                    "password: length {}, symbols {}, ambiguous characters {} ({}, crack time {})",
+                    self.s.len, on(self.s.symbols), on(self.s.allow_ambiguous), est.label(), est.crack_time()
+                )
+            }
+        }
+    }
+
+    // 1文字分のキー操作を反映。確定・中止なら Some
+    fn apply(&mut self, key: char) -> Result<Option<Outcome>> {
+        match key {
+            ' ' | 'r' => {}
+            's' => self.s.symbols = !self.s.symbols,
+            'a' => self.s.allow_ambiguous = !self.s.allow_ambiguous,
+            '+' | '=' => match &mut self.s.words {
+                Some(n) => *n += 1,
+                None => self.s.len += 1,
+            },
+            '-' => match &mut self.s.words {
+                Some(n) => *n = n.saturating_sub(1).max(1),
+                None => self.s.len = self.s.len.saturating_sub(1).max(4),
+            },
+            'p' => self.s.words = match self.s.words {
+                Some(_) => None,
+                None => Some(DEFAULT_WORDS),
+            },
+            'c' => return Ok(Some(Outcome::Copy(self.current.clone()))),
+            'q' => return Ok(Some(Outcome::Cancel)),
+            _ => return Ok(None),
+        }
+        self.reroll()?;
+        Ok(None)
+    }
+}
+
+fn draw(out: &mut impl Write, st: &State, drawn: bool) -> Result<()> {
+    if drawn { queue!(out, cursor::MoveUp(2))?; }
+    queue!(out, cursor::MoveToColumn(0), terminal::Clear(ClearType::FromCursorDown))?;
+    queue!(out, SetAttribute(Attribute::Bold), Print(st.current.as_str()), SetAttribute(Attribute::Reset), Print("\r\n"))?;
+    let status = st.error.as_deref().map_or_else(|| st.describe(), |e| format!("{}  ({e})", st.describe()));
+    queue!(out, Print(status), Print("\r\n"), Print(HELP))?;
+    out.flush()?;
+    Ok(())
+}
+
+fn run_keys(mut st: State) -> Result<Outcome> {
+    let mut out = io::stderr();
+    let _raw = RawMode::enable()?;
+    let mut drawn = false;
+    let result = loop {
+        draw(&mut out, &st, drawn)?;
+        drawn = true;
+        let Event::Key(KeyEvent { code, modifiers, kind, .. }) = event::read()? else { continue };
+        if kind == KeyEventKind::Release { continue; }
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        let key = match code {
+            KeyCode::Enter => break Outcome::Accept(st.current.clone()),
+            KeyCode::Esc => break Outcome::Cancel,
+            KeyCode::Char('c') if ctrl => break Outcome::Cancel,
+            KeyCode::Right | KeyCode::Up => '+',
+            KeyCode::Left | KeyCode::Down => '-',
+            KeyCode::Char(ch) => ch,
+            _ => continue,
+        };
+        if let Some(o) = st.apply(key)? { break o; }
+    };
+    queue!(out, cursor::MoveUp(2), cursor::MoveToColumn(0), terminal::Clear(ClearType::FromCursorDown))?;
+    out.flush()?;
+    Ok(result)
+}
+
+// --accessible 用：画面を書き換えず、1行ずつ入力する
+fn run_lines(mut st: State) -> Result<Outcome> {
+    eprintln!("{HELP_LINES}");
+    loop {
+        eprintln!("{}", st.describe());
+        if let Some(e) = &st.error { eprintln!("cannot apply: {e}"); }
+        eprintln!("candidate: {}", st.current.as_str());
+        eprint!("command: ");
+        io::stderr().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 { return Ok(Outcome::Cancel); }
+        let line = line.trim();
+        if line.is_empty() { return Ok(Outcome::Accept(st.current.clone())); }
+        for key in line.chars() {
+            if let Some(o) = st.apply(key)? { return Ok(o); }
+        }
+    }
+}
+
+pub fn run(settings: Settings, accessible: bool) -> Result<Outcome> {
+    crate::prompt::require("input for the interactive generator")?;
+    let mut st = State { s: settings, list: None, current: Zeroizing::new(String::new()), error: None };
+    st.reroll()?;
+    if let Some(e) = st.error.take() { return Err(anyhow::anyhow!(e)); }
+    if accessible { run_lines(st) } else { run_keys(st) }
+}
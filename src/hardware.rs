@@ -0,0 +1,154 @@
+// ハードウェアトークンのチャレンジレスポンス（YubiKey の HMAC-SHA1 / FIDO2 の hmac-secret）
+//
+// 金庫にはトークンの種類とチャレンジだけを保存し、応答（トークンの中の秘密から計算される）を
+// KEK の導出に混ぜ込む。応答は金庫にも端末にも残らないので、トークンが無ければ開けない。
+// デバイスとのやりとりは外部コマンドに任せる: YubiKey は ykchalresp（無ければ ykman）、
+// FIDO2 は libfido2 の fido2-token / fido2-cred / fido2-assert。
+use crate::error::{err, kind_of, Kind};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::{rngs::OsRng, RngCore};
+use rustpass_core::format::{Token, TokenFactor};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use zeroize::Zeroizing;
+
+/// FIDO2 の資格情報を登録するときの relying party ID（金庫ごとに別の資格情報を作る）
+const RP_ID: &str = "rustpass";
+
+fn random(n: usize) -> Vec<u8> {
+    let mut b = vec![0u8; n];
+    OsRng.fill_bytes(&mut b);
+    b
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    if s.is_empty() || !s.len().is_multiple_of(2) { return None; }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+// 外部コマンドを実行して標準出力を返す（見つからなければ NotFound）
+fn run(prog: &str, args: &[&str], stdin: Option<&str>, hint: &str) -> Result<Zeroizing<String>> {
+    let mut child = Command::new(prog)
+        .args(args)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => err!(NotFound, "{prog} not found in PATH ({hint})"),
+            _ => e.into(),
+        })?;
+    if let Some(s) = stdin {
+        child.stdin.take().ok_or(anyhow!("no stdin for {prog}"))?.write_all(s.as_bytes())?;
+    }
+    let out = child.wait_with_output()?;
+    let stdout = Zeroizing::new(String::from_utf8_lossy(&out.stdout).into_owned());
+    if !out.status.success() {
+        return Err(anyhow!("{prog} failed: {}", String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(stdout)
+}
+
+/// --token の指定
+#[derive(Clone, Copy)]
+pub enum Spec {
+    None,
+    Yubikey(u8),
+    Fido2,
+}
+
+/// "yubikey[:1|2]"（既定はスロット2）/ "fido2" / "none" を読む
+pub fn parse(spec: &str) -> Result<Spec> {
+    let (kind, arg) = spec.split_once(':').map_or((spec, None), |(k, a)| (k, Some(a)));
+    match (kind.to_ascii_lowercase().as_str(), arg) {
+        ("none", None) => Ok(Spec::None),
+        ("yubikey", None) => Ok(Spec::Yubikey(2)),
+        ("yubikey", Some("1")) => Ok(Spec::Yubikey(1)),
+        ("yubikey", Some("2")) => Ok(Spec::Yubikey(2)),
+        ("yubikey", Some(s)) => Err(err!(InvalidInput, "YubiKey slot must be 1 or 2, not {s:?}")),
+        ("fido2", None) => Ok(Spec::Fido2),
+        _ => Err(err!(InvalidInput, "unknown token {spec:?} (expected yubikey, yubikey:1, yubikey:2, fido2 or none)")),
+    }
+}
+
+/// トークンを登録し、新しいチャレンジと応答を返す（none なら None）
+pub fn enroll(spec: Spec) -> Result<Option<(TokenFactor, Zeroizing<Vec<u8>>)>> {
+    let token = match spec {
+        Spec::None => return Ok(None),
+        Spec::Yubikey(slot) => Token::Yubikey { slot },
+        Spec::Fido2 => fido2_register()?,
+    };
+    challenge(token).map(Some)
+}
+
+/// 登録済みのトークンに新しいチャレンジを作り、その応答を返す
+pub fn challenge(token: Token) -> Result<(TokenFactor, Zeroizing<Vec<u8>>)> {
+    let factor = TokenFactor { token, challenge: random(32) };
+    let response = respond(&factor)?;
+    Ok((factor, response))
+}
+
+/// チャレンジをトークンに送り、応答を返す
+pub fn respond(factor: &TokenFactor) -> Result<Zeroizing<Vec<u8>>> {
+    match &factor.token {
+        Token::Yubikey { slot } => yubikey_respond(*slot, &factor.challenge),
+        Token::Fido2 { rp_id, credential_id } => fido2_respond(rp_id, credential_id, &factor.challenge),
+    }
+}
+
+fn yubikey_respond(slot: u8, challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    eprintln!("Touch your YubiKey if it blinks (slot {slot})...");
+    let (slot, challenge) = (slot.to_string(), hex(challenge));
+    let hint = "install yubikey-personalization or yubikey-manager; the slot must be set up for HMAC-SHA1 challenge-response";
+    let out = match run("ykchalresp", &[&format!("-{slot}"), "-x", &challenge], None, hint) {
+        Err(e) if kind_of(&e) == Kind::NotFound => {
+            run("ykman", &["otp", "calculate", &slot, &challenge], None, hint)?
+        }
+        r => r?,
+    };
+    unhex(&out).map(Zeroizing::new).ok_or_else(|| anyhow!("unexpected YubiKey response: {:?}", out.trim()))
+}
+
+const FIDO2_HINT: &str = "install libfido2's command line tools (fido2-tools)";
+
+// 最初に見つかった FIDO2 デバイス（"/dev/hidraw0: vendor=..." の行）
+fn fido2_device() -> Result<String> {
+    let out = run("fido2-token", &["-L"], None, FIDO2_HINT)?;
+    out.lines()
+        .find_map(|l| l.split_once(": ").map(|(dev, _)| dev.trim().to_string()))
+        .ok_or_else(|| err!(NotFound, "no FIDO2 security key found; plug it in and try again"))
+}
+
+// hmac-secret を有効にした資格情報を作る（出力の5行目が資格情報ID）
+fn fido2_register() -> Result<Token> {
+    let device = fido2_device()?;
+    eprintln!("Touch your security key to register a credential for rustpass...");
+    let input = format!("{}\n{RP_ID}\nrustpass\n{}\n", STANDARD.encode(random(32)), STANDARD.encode(random(32)));
+    let out = run("fido2-cred", &["-M", "-h", &device], Some(&input), FIDO2_HINT)?;
+    let credential_id = out.lines().nth(4)
+        .and_then(|l| STANDARD.decode(l.trim()).ok())
+        .ok_or_else(|| anyhow!("fido2-cred returned no credential ID"))?;
+    Ok(Token::Fido2 { rp_id: RP_ID.into(), credential_id })
+}
+
+// チャレンジを hmac-secret の salt にしてアサーションを取る（出力の最終行が hmac-secret）
+fn fido2_respond(rp_id: &str, credential_id: &[u8], challenge: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let device = fido2_device()?;
+    eprintln!("Touch your security key...");
+    let input = format!(
+        "{}\n{rp_id}\n{}\n{}\n",
+        STANDARD.encode(random(32)), STANDARD.encode(credential_id), STANDARD.encode(challenge),
+    );
+    let out = run("fido2-assert", &["-G", "-h", &device], Some(&input), FIDO2_HINT)?;
+    out.lines().last()
+        .and_then(|l| STANDARD.decode(l.trim()).ok())
+        .filter(|s| s.len() == 32)
+        .map(Zeroizing::new)
+        .ok_or_else(|| anyhow!("fido2-assert returned no hmac-secret (does the key support the extension?)"))
+}
@@ -0,0 +1,133 @@
+// Have I Been Pwned のパスワード漏洩チェック（k-匿名性）
+//
+// パスワードの SHA-1 のうち先頭5文字だけを送り、同じ先頭を持つハッシュの残り35文字と出現回数の
+// 一覧を受け取って手元で照合する。パスワードやハッシュ全体は端末の外に出ない。
+// オンラインでは curl で Pwned Passwords の range API を使う（応答の長さから推測されないよう
+// Add-Padding を付ける。水増し分は出現回数 0 で返る）。オフラインではダウンロードしたデータを使う。
+use crate::error::err;
+use crate::Vault;
+use anyhow::{anyhow, Result};
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::process::Command;
+
+const RANGE_API: &str = "https://api.pwnedpasswords.com/range/";
+
+pub struct Breached {
+    pub name: String,
+    /// 漏洩データに現れた回数
+    pub count: u64,
+}
+
+fn sha1_hex(password: &str) -> String {
+    Sha1::digest(password.as_bytes()).iter().map(|b| format!("{b:02X}")).collect()
+}
+
+// "SUFFIX:COUNT" の行を読む（水増しの行は回数が 0 なので除く）
+fn parse_range(text: &str, prefix: &str, found: &mut HashMap<String, u64>) {
+    for line in text.lines() {
+        let Some((suffix, count)) = line.trim().split_once(':') else { continue };
+        let Ok(count) = count.trim().parse::<u64>() else { continue };
+        if count > 0 { found.insert(format!("{prefix}{}", suffix.to_ascii_uppercase()), count); }
+    }
+}
+
+fn fetch_range(prefix: &str) -> Result<String> {
+    let out = Command::new("curl")
+        .args(["-sSf", "--max-time", "20", "-A", "rustpass", "-H", "Add-Padding: true"])
+        .arg(format!("{RANGE_API}{prefix}"))
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => err!(NotFound, "curl not found in PATH (needed for the online check; or use --offline)"),
+            _ => e.into(),
+        })?;
+    if !out.status.success() {
+        return Err(anyhow!("HIBP range request failed: {}", String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+// 先頭5文字ごとのファイル（haveibeenpwned-downloader の個別出力: XXXXX.txt）
+fn read_range_file(dir: &Path, prefix: &str) -> Result<String> {
+    for name in [format!("{prefix}.txt"), prefix.to_string()] {
+        match fs::read_to_string(dir.join(&name)) {
+            Ok(s) => return Ok(s),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(err!(NotFound, "{} has no range file for {prefix} (expected {prefix}.txt)", dir.display()))
+}
+
+// ハッシュ順に並んだ "HASH:COUNT" の1ファイル（数十GB）を二分探索する
+fn lookup_sorted(file: &mut File, len: u64, hash: &str) -> Result<Option<u64>> {
+    // pos 以降の最初の完全な行（pos が行頭なら pos から）
+    let line_at = |file: &mut File, pos: u64| -> Result<Option<(u64, u64, String)>> {
+        let start = if pos == 0 { 0 } else { pos - 1 };
+        file.seek(SeekFrom::Start(start))?;
+        let mut r = BufReader::new(file.take(512));
+        let mut skipped = String::new();
+        let mut offset = start;
+        if pos > 0 { offset += r.read_line(&mut skipped)? as u64; }
+        let mut line = String::new();
+        let n = r.read_line(&mut line)?;
+        if n == 0 { return Ok(None); }
+        Ok(Some((offset, n as u64, line.trim_end().to_string())))
+    };
+    let (mut lo, mut hi) = (0u64, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let Some((at, raw_len, line)) = line_at(file, mid)? else { hi = mid; continue };
+        if at >= hi { hi = mid; continue; }
+        let (h, count) = line.split_once(':').ok_or_else(|| anyhow!("unexpected line in the HIBP file: {line:?}"))?;
+        match h.to_ascii_uppercase().as_str().cmp(hash) {
+            std::cmp::Ordering::Equal => return Ok(count.trim().parse().ok()),
+            std::cmp::Ordering::Less => lo = at + raw_len,
+            std::cmp::Ordering::Greater => hi = mid,
+        }
+    }
+    Ok(None)
+}
+
+/// パスワードを持つエントリを照合する。offline はダウンロードしたデータ（ファイルまたはディレクトリ）
+pub fn check(vault: &Vault, offline: Option<&Path>) -> Result<(usize, Vec<Breached>)> {
+    // 同じパスワードは1回だけ照合する
+    let mut by_hash: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for e in vault.entries.iter().filter(|e| !e.password.is_empty()) {
+        by_hash.entry(sha1_hex(&e.password)).or_default().push(&e.name);
+    }
+    let checked = by_hash.values().map(Vec::len).sum();
+    let mut found = HashMap::new();
+    match offline {
+        Some(path) if path.is_file() => {
+            let mut file = File::open(path)?;
+            let len = file.metadata()?.len();
+            for hash in by_hash.keys() {
+                if let Some(n) = lookup_sorted(&mut file, len, hash)?.filter(|&n| n > 0) { found.insert(hash.clone(), n); }
+            }
+        }
+        _ => {
+            let mut prefixes: Vec<&str> = by_hash.keys().map(|h| &h[..5]).collect();
+            prefixes.dedup();
+            if offline.is_none() {
+                eprintln!("Checking {checked} passwords ({} range requests; only the first 5 characters of each SHA-1 are sent)...", prefixes.len());
+            }
+            for prefix in prefixes {
+                let text = match offline {
+                    Some(dir) => read_range_file(dir, prefix)?,
+                    None => fetch_range(prefix)?,
+                };
+                parse_range(&text, prefix, &mut found);
+            }
+        }
+    }
+    let mut breached: Vec<Breached> = by_hash.iter()
+        .filter_map(|(h, names)| Some((found.get(h).copied()?, names)))
+        .flat_map(|(count, names)| names.iter().map(move |n| Breached { name: n.to_string(), count }))
+        .collect();
+    breached.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    Ok((checked, breached))
+}
@@ -0,0 +1,66 @@
+// ローカル用途の最小限HTTP/1.1処理（外部クレートなし）
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const MAX_HEADER_LINES: usize = 64;
+const MAX_BODY: usize = 64 * 1024;
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// ヘッダの値（名前は大文字小文字を区別しない）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+pub fn read_request(stream: &TcpStream) -> Result<Request> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().ok_or(anyhow!("empty request"))?.to_string();
+    let path = parts.next().ok_or(anyhow!("missing path"))?.to_string();
+
+    let mut len = 0;
+    let mut headers = Vec::new();
+    for _ in 0..MAX_HEADER_LINES {
+        let mut h = String::new();
+        reader.read_line(&mut h)?;
+        let h = h.trim_end();
+        if h.is_empty() { break; }
+        if let Some((k, v)) = h.split_once(':') {
+            if k.trim().eq_ignore_ascii_case("content-length") {
+                len = v.trim().parse().unwrap_or(0);
+            }
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+    // 本文は使わないが、接続を正しく閉じるため読み捨てる
+    if len > MAX_BODY { return Err(anyhow!("request body too large")); }
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request { method, path, headers })
+}
+
+pub fn respond(mut stream: &TcpStream, status: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nX-Content-Type-Options: nosniff\r\nReferrer-Policy: no-referrer\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
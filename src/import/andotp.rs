@@ -0,0 +1,100 @@
+// andOTP のバックアップ（平文の .json、またはパスワードで暗号化した .json.aes）
+//
+// 暗号化版は [反復回数 (4バイト BE)][salt 12バイト][IV 12バイト][暗号文 + タグ] で、
+// PBKDF2-HMAC-SHA1 で導出した鍵の AES-256-GCM。
+use crate::aegis::otp_entry;
+use crate::error::err;
+use crate::totp::{self, Algorithm, Otp};
+use crate::Entry;
+use crate::prompt;
+use aes_gcm::{aead::{Aead, KeyInit}, Aes256Gcm, Nonce};
+use anyhow::Result;
+use serde::Deserialize;
+use sha1::Sha1;
+use std::{fs, path::Path};
+use zeroize::Zeroizing;
+
+// 極端な値で長時間止まらないようにする
+const MAX_ITERATIONS: u32 = 10_000_000;
+
+#[derive(Deserialize)]
+struct Item {
+    secret: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    digits: Option<u32>,
+    #[serde(default)]
+    period: Option<u64>,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    algorithm: Option<Algorithm>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn decrypt(data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if data.len() < 4 + 12 + 12 + 16 { return Err(err!(InvalidInput, "not an andOTP backup: file too short")); }
+    let iterations = u32::from_be_bytes(data[..4].try_into().unwrap());
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(err!(InvalidInput, "not an andOTP backup: bad iteration count {iterations}"));
+    }
+    let (salt, rest) = data[4..].split_at(12);
+    let (iv, body) = rest.split_at(12);
+    for _ in 0..3 {
+        let password = Zeroizing::new(prompt::password("andOTP backup password: ")?);
+        let mut key = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, iterations, key.as_mut());
+        if let Ok(plain) = Aes256Gcm::new_from_slice(key.as_ref())?.decrypt(Nonce::from_slice(iv), body) {
+            return Ok(Zeroizing::new(plain));
+        }
+        eprintln!("Wrong password.");
+    }
+    Err(err!(WrongPassword, "wrong andOTP backup password"))
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let data = fs::read(path)?;
+    let plain = if data.trim_ascii_start().starts_with(b"[") { Zeroizing::new(data) } else { decrypt(&data)? };
+    let items: Vec<Item> = serde_json::from_slice(&plain)
+        .map_err(|e| err!(InvalidInput, "not an andOTP backup: {e}"))?;
+    let mut out = Vec::new();
+    let mut skipped = 0;
+    for it in items {
+        if !it.kind.is_empty() && !it.kind.eq_ignore_ascii_case("totp") { skipped += 1; continue; }
+        // 古い版はラベルが「発行者:アカウント」
+        let (issuer, account) = match (it.issuer.trim(), it.label.split_once(':')) {
+            ("", Some((i, a))) => (i.trim().to_string(), a.trim().to_string()),
+            (i, _) => (i.to_string(), it.label.trim().to_string()),
+        };
+        let secret: String = it.secret.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+        if totp::base32_decode(&secret).is_none() {
+            return Err(err!(InvalidInput, "{account:?}: TOTP secret is not valid base32"));
+        }
+        // otpauth:// URI と同じ範囲だけを受け付ける（周期 0 や桁数が大きすぎるとコードを計算できない）
+        let digits = it.digits.unwrap_or(6);
+        if !(6..=8).contains(&digits) {
+            return Err(err!(InvalidInput, "{account:?}: TOTP digits must be 6-8, got {digits}"));
+        }
+        let period = it.period.unwrap_or(30);
+        if period == 0 {
+            return Err(err!(InvalidInput, "{account:?}: TOTP period must be greater than 0"));
+        }
+        let otp = Otp {
+            secret,
+            algorithm: it.algorithm.unwrap_or_default(),
+            digits,
+            period,
+            issuer: Some(issuer.clone()).filter(|s| !s.is_empty()),
+            account: Some(account.clone()).filter(|s| !s.is_empty()),
+        };
+        let mut e = otp_entry(&issuer, &account, otp);
+        e.tags = it.tags;
+        out.push(e);
+    }
+    if skipped > 0 { eprintln!("warning: skipped {skipped} non-TOTP entries (HOTP / Steam are not supported)"); }
+    Ok(out)
+}
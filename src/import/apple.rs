@@ -0,0 +1,39 @@
+// Apple パスワード / Safari のCSV（Title,URL,Username,Password,Notes,OTPAuth）
+use crate::error::err;
+use crate::{totp, Entry};
+use anyhow::Result;
+use std::path::Path;
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_lowercase()).collect();
+    let col = |name: &str| headers.iter().position(|h| h == name);
+    let (title, url, user, pass) = match (col("title"), col("url"), col("username"), col("password")) {
+        (Some(t), Some(u), Some(n), Some(p)) => (t, u, n, p),
+        _ => return Err(err!(InvalidInput, "not an Apple Passwords CSV (expected Title,URL,Username,Password,Notes,OTPAuth)")),
+    };
+    let (notes, otp) = (col("notes"), col("otpauth"));
+
+    let mut out = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let rec = rec?;
+        let get = |c: Option<usize>| c.and_then(|c| rec.get(c)).map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+        let url_v = get(Some(url));
+        let name = get(Some(title))
+            .or_else(|| url_v.as_deref().map(|u| u.split("://").last().unwrap_or(u).trim_end_matches('/').to_string()))
+            .unwrap_or_else(|| format!("apple-{}", i + 1));
+        let otp = match get(otp) {
+            Some(uri) => Some(totp::parse_uri(&uri).map_err(|e| err!(InvalidInput, "row {}: {e}", i + 2))?),
+            None => None,
+        };
+        out.push(Entry {
+            username: get(Some(user)).unwrap_or_default(),
+            password: get(Some(pass)).unwrap_or_default(),
+            url: url_v,
+            notes: get(notes),
+            otp,
+            ..Entry::new(name)
+        });
+    }
+    Ok(out)
+}
@@ -0,0 +1,270 @@
+// Bitwarden / Vaultwarden の書き出し（.json（暗号化なし）または .csv）
+//
+// JSON は items の type で種類が分かれる（1 ログイン・2 セキュアメモ・3 カード・4 ID・5 SSH鍵）。
+// フォルダ（組織の書き出しではコレクション）はタグにする。フォルダ名の「親/子」はそのまま入れ子のタグになる。
+// カードとIDは rustpass に対応する項目が無いので取り込まず、件数だけ表示する。
+use crate::error::err;
+use crate::sshkey::SshKey;
+use crate::{now_iso, Entry, Field};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::{fs, path::Path};
+
+use super::protonpass::otp;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    password_protected: bool,
+    #[serde(default)]
+    folders: Vec<Named>,
+    #[serde(default)]
+    collections: Vec<Named>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Named {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    folder_id: Option<String>,
+    #[serde(default)]
+    collection_ids: Option<Vec<String>>,
+    #[serde(default)]
+    fields: Option<Vec<ItemField>>,
+    #[serde(default)]
+    login: Option<Login>,
+    #[serde(default)]
+    ssh_key: Option<ItemSshKey>,
+    #[serde(default)]
+    creation_date: Option<String>,
+    #[serde(default)]
+    revision_date: Option<String>,
+    /// ゴミ箱の項目は削除日時を持つ
+    #[serde(default)]
+    deleted_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Login {
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    totp: Option<String>,
+    #[serde(default)]
+    uris: Option<Vec<Uri>>,
+    #[serde(default)]
+    fido2_credentials: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    #[serde(default)]
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ItemField {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+    /// 0 テキスト・1 非表示・2 真偽値・3 リンク（他の項目の参照。値を持たない）
+    #[serde(rename = "type", default)]
+    kind: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ItemSshKey {
+    private_key: String,
+    public_key: String,
+}
+
+const LOGIN: u8 = 1;
+const SECURE_NOTE: u8 = 2;
+const CARD: u8 = 3;
+const IDENTITY: u8 = 4;
+const SSH_KEY: u8 = 5;
+
+// カスタムフィールドの種類
+const HIDDEN: u8 = 1;
+const LINKED: u8 = 3;
+
+// 取り込まなかったものの件数
+#[derive(Default)]
+struct Skipped {
+    cards: usize,
+    identities: usize,
+    other: usize,
+    passkeys: usize,
+}
+
+impl Skipped {
+    fn report(&self) {
+        let plural = |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+        let mut kinds = Vec::new();
+        if self.cards > 0 { kinds.push(plural(self.cards, "card", "cards")); }
+        if self.identities > 0 { kinds.push(plural(self.identities, "identity", "identities")); }
+        if self.other > 0 { kinds.push(plural(self.other, "item of an unknown type", "items of unknown types")); }
+        if !kinds.is_empty() {
+            eprintln!("note: skipped {} (rustpass stores logins, notes and SSH keys only)", kinds.join(" and "));
+        }
+        if self.passkeys > 0 {
+            eprintln!("note: {} not imported (the private keys cannot leave Bitwarden; register new ones)", plural(self.passkeys, "passkey was", "passkeys were"));
+        }
+    }
+}
+
+fn text(s: Option<String>) -> Option<String> {
+    s.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn tag_list(s: &str) -> Vec<String> {
+    s.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect()
+}
+
+// URL は1つ目を url に、2つ目以降を「url 2」などのフィールドにする
+fn set_urls(e: &mut Entry, urls: Vec<String>) {
+    for (i, u) in urls.into_iter().enumerate() {
+        if i == 0 { e.url = Some(u); } else { e.fields.push(Field { name: format!("url {}", i + 1), value: u, json: false, hidden: false }); }
+    }
+}
+
+fn set_otp(e: &mut Entry, value: Option<String>) -> Result<()> {
+    let Some(value) = text(value) else { return Ok(()) };
+    // Steam Guard のコードは TOTP と桁・文字が違うので、値をそのままフィールドに残す
+    if value.starts_with("steam://") {
+        e.fields.push(Field { name: "steam guard".into(), value, json: false, hidden: false });
+        return Ok(());
+    }
+    e.otp = Some(otp(&value)?);
+    Ok(())
+}
+
+// カスタムフィールド（rustpass の書き出しが付ける tags フィールドはタグに戻す）
+fn set_field(e: &mut Entry, name: String, value: String, hidden: bool) {
+    if name == "tags" {
+        e.tags = tag_list(&value);
+    } else {
+        e.fields.push(Field { name, value, json: false, hidden });
+    }
+}
+
+fn json_entry(item: Item, i: usize, folders: &HashMap<String, String>, collections: &HashMap<String, String>, skipped: &mut Skipped) -> Result<Option<Entry>> {
+    match item.kind {
+        LOGIN | SECURE_NOTE | SSH_KEY => {}
+        CARD => { skipped.cards += 1; return Ok(None); }
+        IDENTITY => { skipped.identities += 1; return Ok(None); }
+        _ => { skipped.other += 1; return Ok(None); }
+    }
+    let name = text(Some(item.name)).unwrap_or_else(|| format!("bitwarden-{}", i + 1));
+    let mut e = Entry::new(name);
+    e.notes = text(item.notes);
+    e.tags.extend(item.folder_id.and_then(|id| folders.get(&id).cloned()));
+    e.tags.extend(item.collection_ids.unwrap_or_default().iter().filter_map(|id| collections.get(id).cloned()));
+    e.created_at = item.creation_date.or(e.created_at);
+    e.updated_at = item.revision_date.unwrap_or_else(now_iso);
+    if let Some(login) = item.login {
+        e.username = text(login.username).unwrap_or_default();
+        e.password = login.password.unwrap_or_default();
+        set_urls(&mut e, login.uris.unwrap_or_default().into_iter().filter_map(|u| text(u.uri)).collect());
+        set_otp(&mut e, login.totp)?;
+        skipped.passkeys += login.fido2_credentials.map_or(0, |c| c.len());
+    }
+    if let Some(k) = item.ssh_key {
+        let algorithm = k.public_key.split_whitespace().next().unwrap_or_default().to_string();
+        e.ssh_key = Some(SshKey { algorithm, private_key: k.private_key, public_key: k.public_key });
+    }
+    for f in item.fields.unwrap_or_default() {
+        if f.kind == LINKED { continue; }
+        let (Some(name), Some(value)) = (text(f.name), f.value) else { continue };
+        set_field(&mut e, name, value, f.kind == HIDDEN);
+    }
+    Ok(Some(e))
+}
+
+fn read_json(data: &[u8]) -> Result<Vec<Entry>> {
+    let export: Export = serde_json::from_slice(data)
+        .map_err(|e| err!(InvalidInput, "not a Bitwarden JSON export: {e}"))?;
+    if export.encrypted || export.password_protected {
+        return Err(err!(InvalidInput, "encrypted Bitwarden exports are not supported; export as \".json\" (not \".json (Encrypted)\")"));
+    }
+    let names = |v: Vec<Named>| v.into_iter().map(|n| (n.id, n.name)).collect::<HashMap<_, _>>();
+    let (folders, collections) = (names(export.folders), names(export.collections));
+    let mut skipped = Skipped::default();
+    let mut out = Vec::new();
+    for (i, item) in export.items.into_iter().enumerate() {
+        if item.deleted_date.is_some() { continue; }
+        let name = item.name.clone();
+        if let Some(e) = json_entry(item, i, &folders, &collections, &mut skipped).map_err(|e| err!(InvalidInput, "{name:?}: {e}"))? {
+            out.push(e);
+        }
+    }
+    skipped.report();
+    Ok(out)
+}
+
+// 見出し: folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp
+// （組織の書き出しは folder と favorite の代わりに collections）。CSV にはカードとIDは含まれない
+fn read_csv(data: &[u8]) -> Result<Vec<Entry>> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_lowercase()).collect();
+    let col = |name: &str| headers.iter().position(|h| h == name);
+    let (Some(name), Some(kind)) = (col("name"), col("type")) else {
+        return Err(err!(InvalidInput, "not a Bitwarden CSV export (expected folder,favorite,type,name,notes,fields,...,login_uri,login_username,login_password,login_totp)"));
+    };
+    let (folder, collections, notes, fields) = (col("folder"), col("collections"), col("notes"), col("fields"));
+    let (uri, user, pass, totp) = (col("login_uri"), col("login_username"), col("login_password"), col("login_totp"));
+    let mut skipped = Skipped::default();
+    let mut out = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let rec = rec?;
+        let get = |c: Option<usize>| text(c.and_then(|c| rec.get(c)).map(String::from));
+        match get(Some(kind)).as_deref() {
+            Some("login" | "note") => {}
+            _ => { skipped.other += 1; continue; }
+        }
+        let mut e = Entry::new(get(Some(name)).unwrap_or_else(|| format!("bitwarden-{}", i + 1)));
+        e.notes = get(notes);
+        e.tags = get(folder).into_iter().chain(get(collections).iter().flat_map(|c| tag_list(c))).collect();
+        e.username = get(user).unwrap_or_default();
+        e.password = get(pass).unwrap_or_default();
+        set_urls(&mut e, get(uri).map(|u| tag_list(&u)).unwrap_or_default());
+        set_otp(&mut e, get(totp)).map_err(|err| err!(InvalidInput, "row {}: {err}", i + 2))?;
+        // カスタムフィールドは「名前: 値」を1行ずつ
+        for line in get(fields).unwrap_or_default().lines() {
+            let Some((k, v)) = line.split_once(": ") else { continue };
+            set_field(&mut e, k.trim().to_string(), v.to_string(), false);
+        }
+        out.push(e);
+    }
+    skipped.report();
+    Ok(out)
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let data = fs::read(path)?;
+    let body = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&data);
+    if body.trim_ascii_start().starts_with(b"{") { read_json(body) } else { read_csv(body) }
+}
@@ -0,0 +1,160 @@
+// 見出し行付きの CSV（LastPass・Chrome・Firefox の書き出しは見出しから判別、それ以外は --map で列を指定）
+//
+// LastPass: url,username,password,totp,extra,name,grouping,fav（url が http://sn の行はセキュアメモ）
+// Chrome:   name,url,username,password,note
+// Firefox:  url,username,password,httpRealm,formActionOrigin,guid,timeCreated,timePasswordChanged,timeLastUsed
+use crate::error::err;
+use crate::{Entry, Field};
+use anyhow::Result;
+use std::path::Path;
+use time::OffsetDateTime;
+
+use super::protonpass::otp;
+
+// --map で指定できる項目
+const KEYS: [&str; 7] = ["name", "user", "pass", "url", "notes", "totp", "tags"];
+
+// 項目ごとの列番号
+#[derive(Default)]
+struct Columns {
+    name: Option<usize>,
+    user: Option<usize>,
+    pass: Option<usize>,
+    url: Option<usize>,
+    notes: Option<usize>,
+    totp: Option<usize>,
+    tags: Option<usize>,
+    created: Option<usize>,
+    updated: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    LastPass,
+    Chrome,
+    Firefox,
+    Mapped,
+}
+
+impl Source {
+    fn label(self) -> &'static str {
+        match self {
+            Source::LastPass => "LastPass",
+            Source::Chrome => "Chrome",
+            Source::Firefox => "Firefox",
+            Source::Mapped => "mapped",
+        }
+    }
+}
+
+fn position(headers: &[String], name: &str) -> Option<usize> {
+    headers.iter().position(|h| h.eq_ignore_ascii_case(name))
+}
+
+// 見出しから書き出し元を判別する
+fn detect(headers: &[String]) -> Option<(Source, Columns)> {
+    let has = |n: &str| position(headers, n).is_some();
+    let col = |n: &str| position(headers, n);
+    let login = has("url") && has("username") && has("password");
+    if login && has("grouping") && has("extra") && has("name") {
+        let c = Columns { name: col("name"), user: col("username"), pass: col("password"), url: col("url"), notes: col("extra"), totp: col("totp"), tags: col("grouping"), ..Default::default() };
+        return Some((Source::LastPass, c));
+    }
+    if login && (has("httprealm") || has("formactionorigin")) {
+        let c = Columns { user: col("username"), pass: col("password"), url: col("url"), created: col("timecreated"), updated: col("timepasswordchanged"), ..Default::default() };
+        return Some((Source::Firefox, c));
+    }
+    if login && has("name") && headers.len() <= 5 {
+        let c = Columns { name: col("name"), user: col("username"), pass: col("password"), url: col("url"), notes: col("note"), ..Default::default() };
+        return Some((Source::Chrome, c));
+    }
+    None
+}
+
+// "name=Title,user=Login,pass=Password" を列番号にする
+fn mapped(map: &str, headers: &[String]) -> Result<Columns> {
+    let mut c = Columns::default();
+    for pair in map.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, column) = pair.split_once('=')
+            .ok_or_else(|| err!(InvalidInput, "--map expects FIELD=COLUMN pairs, got {pair:?}"))?;
+        let i = position(headers, column.trim()).ok_or_else(|| {
+            err!(InvalidInput, "column {:?} not found in the CSV header (columns: {})", column.trim(), headers.join(", "))
+        })?;
+        let slot = match key.trim().to_ascii_lowercase().as_str() {
+            "name" | "title" => &mut c.name,
+            "user" | "username" | "login" => &mut c.user,
+            "pass" | "password" => &mut c.pass,
+            "url" => &mut c.url,
+            "notes" | "note" => &mut c.notes,
+            "totp" | "otp" => &mut c.totp,
+            "tags" | "folder" => &mut c.tags,
+            k => return Err(err!(InvalidInput, "unknown --map field {k:?} (expected {})", KEYS.join(", "))),
+        };
+        *slot = Some(i);
+    }
+    if c.name.is_none() && c.url.is_none() {
+        return Err(err!(InvalidInput, "--map needs name= or url= (entries are named after the URL when there is no name column)"));
+    }
+    Ok(c)
+}
+
+// Firefox の日時はミリ秒の UNIX 時刻
+fn iso_ms(ms: &str) -> Option<String> {
+    let t = OffsetDateTime::from_unix_timestamp(ms.parse::<i64>().ok()? / 1000).ok()?;
+    t.format(&time::format_description::well_known::Rfc3339).ok()
+}
+
+fn host(url: &str) -> String {
+    let rest = url.split("://").last().unwrap_or(url);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest).to_string()
+}
+
+pub fn read(path: &Path, map: Option<&str>) -> Result<Vec<Entry>> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().trim_start_matches('\u{feff}').to_string()).collect();
+    let (source, cols) = match map {
+        Some(m) => (Source::Mapped, mapped(m, &headers)?),
+        None => detect(&headers).ok_or_else(|| err!(
+            InvalidInput,
+            "unrecognised CSV header ({}); pass --map name=COLUMN,user=COLUMN,pass=COLUMN,...",
+            headers.join(", ")
+        ))?,
+    };
+    if source != Source::Mapped { eprintln!("note: detected a {} CSV export", source.label()); }
+    // --map のときは対応させなかった列もカスタムフィールドとして残す
+    let used = [cols.name, cols.user, cols.pass, cols.url, cols.notes, cols.totp, cols.tags];
+    let mut out = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let rec = rec?;
+        let get = |c: Option<usize>| c.and_then(|c| rec.get(c)).map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+        // LastPass のセキュアメモは URL の代わりに http://sn が入っている
+        let url = get(cols.url).filter(|u| !(source == Source::LastPass && u == "http://sn"));
+        let name = get(cols.name)
+            .or_else(|| url.as_deref().map(host).filter(|h| !h.is_empty()))
+            .unwrap_or_else(|| format!("csv-{}", i + 1));
+        let mut e = Entry::new(name);
+        e.username = get(cols.user).unwrap_or_default();
+        e.password = get(cols.pass).unwrap_or_default();
+        e.url = url;
+        e.notes = get(cols.notes);
+        // LastPass のフォルダは「親\子」
+        let tags = get(cols.tags).unwrap_or_default();
+        e.tags = match source {
+            Source::LastPass => vec![tags.replace('\\', "/")],
+            _ => tags.split(',').map(|t| t.trim().to_string()).collect(),
+        };
+        e.tags.retain(|t| !t.is_empty());
+        if let Some(value) = get(cols.totp) {
+            e.otp = Some(otp(&value).map_err(|err| err!(InvalidInput, "row {}: {err}", i + 2))?);
+        }
+        e.created_at = get(cols.created).and_then(|t| iso_ms(&t)).or(e.created_at);
+        if let Some(t) = get(cols.updated).and_then(|t| iso_ms(&t)) { e.updated_at = t; }
+        if source == Source::Mapped {
+            for (c, h) in headers.iter().enumerate().filter(|(c, _)| !used.contains(&Some(*c))) {
+                if let Some(value) = get(Some(c)) { e.fields.push(Field { name: h.clone(), value, json: false, hidden: false }); }
+            }
+        }
+        out.push(e);
+    }
+    Ok(out)
+}
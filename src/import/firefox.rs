@@ -0,0 +1,209 @@
+// Firefoxプロファイル（logins.json + key4.db）から直接読み込む
+//
+// key4.db の metaData に鍵のチェック値、nssPrivate に logins.json を暗号化した鍵があり、
+// どちらもプライマリパスワード（未設定なら空文字）から導出した鍵で暗号化されている。
+use crate::error::err;
+use crate::Entry;
+use crate::prompt;
+use aes::Aes256;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use des::TdesEde3;
+use hmac::{Hmac, Mac};
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::{fs, path::Path};
+use zeroize::Zeroizing;
+
+const OID_PBES2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+const OID_PBE_SHA1_3DES: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x05, 0x01, 0x03];
+const OID_AES256_CBC: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a];
+const OID_3DES_CBC: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x03, 0x07];
+
+// 最小限のDER（SEQUENCE / OCTET STRING / OID / INTEGER のみ使う）
+struct Der<'a> {
+    tag: u8,
+    body: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn parse(data: &'a [u8]) -> Result<(Der<'a>, &'a [u8])> {
+        let bad = || anyhow!("malformed DER in Firefox key data");
+        let tag = *data.first().ok_or_else(bad)?;
+        let first = *data.get(1).ok_or_else(bad)?;
+        let (len, hdr) = if first & 0x80 == 0 {
+            (first as usize, 2)
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 { return Err(bad()); }
+            let bytes = data.get(2..2 + n).ok_or_else(bad)?;
+            (bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize), 2 + n)
+        };
+        let body = data.get(hdr..hdr + len).ok_or_else(bad)?;
+        Ok((Der { tag, body }, &data[hdr + len..]))
+    }
+
+    fn children(&self) -> Result<Vec<Der<'a>>> {
+        if self.tag != 0x30 { return Err(anyhow!("expected DER SEQUENCE in Firefox key data")); }
+        let mut out = Vec::new();
+        let mut rest = self.body;
+        while !rest.is_empty() {
+            let (d, r) = Der::parse(rest)?;
+            out.push(d);
+            rest = r;
+        }
+        Ok(out)
+    }
+
+    fn uint(&self) -> u32 {
+        self.body.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32)
+    }
+}
+
+fn child<'a>(v: &[Der<'a>], i: usize) -> Result<&'a [u8]> {
+    v.get(i).map(|d| d.body).ok_or(anyhow!("unexpected Firefox key data structure"))
+}
+
+fn cbc_decrypt<C>(key: &[u8], iv: &[u8], ct: &[u8]) -> Option<Zeroizing<Vec<u8>>>
+where
+    cbc::Decryptor<C>: KeyIvInit + BlockDecryptMut,
+    C: cbc::cipher::BlockDecryptMut + cbc::cipher::BlockCipher,
+{
+    let mut buf = Zeroizing::new(ct.to_vec());
+    let dec = cbc::Decryptor::<C>::new_from_slices(key, iv).ok()?;
+    let n = dec.decrypt_padded_mut::<Pkcs7>(&mut buf).ok()?.len();
+    buf.truncate(n);
+    Some(buf)
+}
+
+// key4.db の暗号化アイテム（PBES2 / 旧形式の pbeWithSha1AndTripleDES-CBC）を復号
+fn decrypt_pbe(item: &[u8], global_salt: &[u8], password: &str) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    let (top, _) = Der::parse(item)?;
+    let top = top.children()?;
+    let alg = top.first().ok_or(anyhow!("empty PBE item"))?.children()?;
+    let ct = child(&top, 1)?;
+    let oid = child(&alg, 0)?;
+
+    let mut hp = Sha1::new();
+    hp.update(global_salt);
+    hp.update(password.as_bytes());
+    let hp = hp.finalize();
+
+    if oid == OID_PBES2 {
+        let params = alg.get(1).ok_or(anyhow!("missing PBES2 params"))?.children()?;
+        let kdf = params.first().ok_or(anyhow!("missing PBES2 kdf"))?.children()?;
+        let kdf_params = kdf.get(1).ok_or(anyhow!("missing PBKDF2 params"))?.children()?;
+        let salt = child(&kdf_params, 0)?;
+        let iterations = kdf_params.get(1).ok_or(anyhow!("missing PBKDF2 iterations"))?.uint();
+        let enc = params.get(1).ok_or(anyhow!("missing PBES2 cipher"))?.children()?;
+        if child(&enc, 0)? != OID_AES256_CBC { return Err(anyhow!("unsupported PBES2 cipher in key4.db")); }
+        // NSSはIVの先頭2バイト（OCTET STRINGのヘッダ）を省いて保存している
+        let mut iv = vec![0x04, 0x0e];
+        iv.extend_from_slice(child(&enc, 1)?);
+        let mut key = Zeroizing::new([0u8; 32]);
+        pbkdf2::pbkdf2_hmac::<Sha256>(&hp, salt, iterations, key.as_mut());
+        Ok(cbc_decrypt::<Aes256>(key.as_ref(), &iv, ct))
+    } else if oid == OID_PBE_SHA1_3DES {
+        let params = alg.get(1).ok_or(anyhow!("missing PBE params"))?.children()?;
+        let entry_salt = child(&params, 0)?;
+        let mut chp = Sha1::new();
+        chp.update(hp);
+        chp.update(entry_salt);
+        let chp = chp.finalize();
+        let mut pes = entry_salt.to_vec();
+        pes.resize(20.max(entry_salt.len()), 0);
+        let hmac = |parts: &[&[u8]]| {
+            let mut m = Hmac::<Sha1>::new_from_slice(&chp).expect("hmac key");
+            for p in parts { m.update(p); }
+            m.finalize().into_bytes()
+        };
+        let k1 = hmac(&[&pes, entry_salt]);
+        let tk = hmac(&[&pes]);
+        let k2 = hmac(&[&tk, entry_salt]);
+        let mut k = Zeroizing::new(k1.to_vec());
+        k.extend_from_slice(&k2);
+        Ok(cbc_decrypt::<TdesEde3>(&k[..24], &k[k.len() - 8..], ct))
+    } else {
+        Err(anyhow!("unsupported encryption in key4.db"))
+    }
+}
+
+// key4.db を開き、logins.json の復号鍵を取り出す
+fn master_key(profile: &Path) -> Result<Zeroizing<Vec<u8>>> {
+    let db = Connection::open_with_flags(profile.join("key4.db"), OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let (global_salt, check): (Vec<u8>, Vec<u8>) =
+        db.query_row("SELECT item1, item2 FROM metadata WHERE id = 'password'", [], |r| Ok((r.get(0)?, r.get(1)?)))?;
+
+    // まず空のプライマリパスワードで試し、ダメなら入力を求める
+    let mut password = Zeroizing::new(String::new());
+    let mut attempts = 0;
+    loop {
+        let ok = decrypt_pbe(&check, &global_salt, &password)?
+            .is_some_and(|p| p.starts_with(b"password-check"));
+        if ok { break; }
+        if attempts == 3 { return Err(err!(WrongPassword, "wrong Firefox primary password")); }
+        attempts += 1;
+        password = Zeroizing::new(prompt::password("Firefox primary password: ")?);
+    }
+
+    let mut stmt = db.prepare("SELECT a11 FROM nssPrivate")?;
+    let rows: Vec<Vec<u8>> = stmt.query_map([], |r| r.get(0))?.collect::<Result<_, _>>()?;
+    for a11 in rows {
+        if let Some(key) = decrypt_pbe(&a11, &global_salt, &password)? {
+            return Ok(key);
+        }
+    }
+    Err(anyhow!("no usable key found in key4.db"))
+}
+
+fn decrypt_field(key: &[u8], b64: &str) -> Result<String> {
+    let data = STANDARD.decode(b64)?;
+    let (top, _) = Der::parse(&data)?;
+    let top = top.children()?;
+    let alg = top.get(1).ok_or(anyhow!("malformed encrypted login"))?.children()?;
+    let (oid, iv, ct) = (child(&alg, 0)?, child(&alg, 1)?, child(&top, 2)?);
+    let plain = if oid == OID_3DES_CBC {
+        cbc_decrypt::<TdesEde3>(key.get(..24).ok_or(anyhow!("key too short"))?, iv, ct)
+    } else if oid == OID_AES256_CBC {
+        cbc_decrypt::<Aes256>(key.get(..32).ok_or(anyhow!("key too short"))?, iv, ct)
+    } else {
+        return Err(anyhow!("unsupported login encryption"));
+    };
+    let plain = plain.ok_or(anyhow!("failed to decrypt login"))?;
+    Ok(String::from_utf8(plain.to_vec())?)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Logins {
+    logins: Vec<Login>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Login {
+    hostname: String,
+    encrypted_username: String,
+    encrypted_password: String,
+}
+
+pub fn read(profile: &Path) -> Result<Vec<Entry>> {
+    let json = fs::read_to_string(profile.join("logins.json"))
+        .map_err(|e| err!(NotFound, "cannot read logins.json in {}: {e}", profile.display()))?;
+    let logins: Logins = serde_json::from_str(&json)?;
+    let key = master_key(profile)?;
+    let mut out = Vec::new();
+    for l in logins.logins {
+        let name = l.hostname.split("://").last().unwrap_or(&l.hostname).trim_end_matches('/').to_string();
+        out.push(Entry {
+            username: decrypt_field(&key, &l.encrypted_username)?,
+            password: decrypt_field(&key, &l.encrypted_password)?,
+            url: Some(l.hostname),
+            ..Entry::new(name)
+        });
+    }
+    Ok(out)
+}
@@ -0,0 +1,641 @@
+// KeePass / KeePassXC のデータベース（KDBX 3.1 / 4.x）
+//
+// 複合鍵 = SHA-256(SHA-256(パスワード) || キーファイルの鍵) を KDF（AES-KDF / Argon2）で変換し、
+// マスターシードと合わせた鍵で本体を AES-256-CBC または ChaCha20 で復号する（Twofish は未対応）。
+// 3.1 は復号後の先頭に照合用のバイト列とハッシュ付きブロック、4.x はヘッダの HMAC と
+// 暗号文側の HMAC 付きブロックを持つ。中身は（gzip 圧縮された）XML で、パスワードなど
+// 保護された値は内側のストリーム暗号（Salsa20 / ChaCha20）で、文書に現れる順に XOR されている。
+use crate::error::err;
+use crate::totp::{self, Otp};
+use crate::{Entry, Field};
+use crate::prompt;
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use chacha20::cipher::StreamCipher;
+use hmac::{Hmac, Mac};
+use quick_xml::events::Event;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::io::Read;
+use std::{fs, path::Path};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use zeroize::Zeroizing;
+
+const SIG1: u32 = 0x9AA2_D903;
+const SIG2: u32 = 0xB54B_FB67;
+
+const CIPHER_AES256: &str = "31c1f2e6bf714350be5805216afc5aff";
+const CIPHER_CHACHA20: &str = "d6038a2b8b6f4cb5a524339a31dbb59a";
+const CIPHER_TWOFISH: &str = "ad68f29f576f4bb9a36ad47af965346c";
+const KDF_AES_KDBX3: &str = "c9d9f39a628a4460bf740d08c18a4fea";
+const KDF_AES_KDBX4: &str = "7c02bb8279a74ac0927d114a00648238";
+const KDF_ARGON2D: &str = "ef636ddf8c29444b91f7a9a403e30a0c";
+const KDF_ARGON2ID: &str = "9e298b1956db4773b23dfc3ec6f0a1e6";
+
+const SALSA20_NONCE: [u8; 8] = [0xE8, 0x30, 0x09, 0x4B, 0x97, 0x20, 0x5D, 0x2A];
+// 0001-01-01 から 1970-01-01 までの秒数（4.x の日時）
+const EPOCH_OFFSET: i64 = 62_135_596_800;
+
+// 極端な値で長時間止まらないようにする
+const MAX_AES_ROUNDS: u64 = 1_000_000_000;
+const MAX_ARGON2_MEMORY: u64 = 4 << 30;
+
+fn bad(msg: &str) -> anyhow::Error {
+    err!(InvalidInput, "not a valid KDBX file: {msg}")
+}
+
+fn hex(b: &[u8]) -> String {
+    b.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) { return None; }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+struct Cur<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cur<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Cur { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let s = self.data.get(self.pos..self.pos.saturating_add(n)).ok_or_else(|| bad("truncated"))?;
+        self.pos += n;
+        Ok(s)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+fn le_u32(v: &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(v.try_into().map_err(|_| bad("bad integer field"))?))
+}
+
+fn le_u64(v: &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(v.try_into().map_err(|_| bad("bad integer field"))?))
+}
+
+enum Kdf {
+    Aes { seed: Vec<u8>, rounds: u64 },
+    Argon2 { algorithm: argon2::Algorithm, salt: Vec<u8>, memory: u64, iterations: u64, parallelism: u32, version: u32 },
+}
+
+impl Kdf {
+    // 4.x の KDF パラメータ（VariantDictionary）
+    fn from_dict(d: &HashMap<String, Vec<u8>>) -> Result<Kdf> {
+        let get = |k: &str| d.get(k).ok_or_else(|| bad(&format!("KDF parameter {k} missing")));
+        match hex(get("$UUID")?).as_str() {
+            KDF_AES_KDBX3 | KDF_AES_KDBX4 => Ok(Kdf::Aes { seed: get("S")?.clone(), rounds: le_u64(get("R")?)? }),
+            u @ (KDF_ARGON2D | KDF_ARGON2ID) => Ok(Kdf::Argon2 {
+                algorithm: if u == KDF_ARGON2D { argon2::Algorithm::Argon2d } else { argon2::Algorithm::Argon2id },
+                salt: get("S")?.clone(),
+                memory: le_u64(get("M")?)?,
+                iterations: le_u64(get("I")?)?,
+                parallelism: le_u32(get("P")?)?,
+                version: le_u32(get("V")?)?,
+            }),
+            u => Err(err!(UnsupportedFormat, "unsupported KDBX key derivation {u}")),
+        }
+    }
+
+    fn transform(&self, composite: &[u8; 32]) -> Result<Zeroizing<[u8; 32]>> {
+        let mut out = Zeroizing::new([0u8; 32]);
+        match self {
+            Kdf::Aes { seed, rounds } => {
+                if *rounds > MAX_AES_ROUNDS { return Err(bad("AES-KDF rounds too large")); }
+                let cipher = aes::Aes256::new_from_slice(seed).map_err(|_| bad("AES-KDF seed must be 32 bytes"))?;
+                let mut blocks = [GenericArray::clone_from_slice(&composite[..16]), GenericArray::clone_from_slice(&composite[16..])];
+                for _ in 0..*rounds { cipher.encrypt_blocks(&mut blocks); }
+                let mut h = Sha256::new();
+                h.update(blocks[0]);
+                h.update(blocks[1]);
+                out.copy_from_slice(&h.finalize());
+            }
+            Kdf::Argon2 { algorithm, salt, memory, iterations, parallelism, version } => {
+                if *memory > MAX_ARGON2_MEMORY { return Err(bad("Argon2 memory too large")); }
+                let iterations = u32::try_from(*iterations).map_err(|_| bad("Argon2 iterations too large"))?;
+                let params = argon2::Params::new((*memory / 1024) as u32, iterations, *parallelism, Some(32))
+                    .map_err(|e| bad(&format!("Argon2 parameters: {e}")))?;
+                let version = if *version == 0x10 { argon2::Version::V0x10 } else { argon2::Version::V0x13 };
+                argon2::Argon2::new(*algorithm, version, params)
+                    .hash_password_into(composite, salt, out.as_mut())
+                    .map_err(|e| anyhow!("argon2 hash_password_into failed: {e:?}"))?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+struct Header {
+    major: u16,
+    /// ヘッダのバイト数（4.x はこの範囲のハッシュと HMAC が続く）
+    len: usize,
+    cipher: String,
+    compressed: bool,
+    master_seed: Vec<u8>,
+    iv: Vec<u8>,
+    kdf: Kdf,
+    // 以下は 3.1 のみ（4.x は復号後の内側ヘッダにある）
+    stream_key: Vec<u8>,
+    stream_start: Vec<u8>,
+    stream_id: u32,
+}
+
+fn variant_dict(v: &[u8]) -> Result<HashMap<String, Vec<u8>>> {
+    let mut c = Cur::new(v);
+    if c.u16()? >> 8 != 1 { return Err(err!(UnsupportedFormat, "unsupported KDBX parameter dictionary version")); }
+    let mut m = HashMap::new();
+    while c.u8()? != 0 {
+        let klen = c.u32()? as usize;
+        let k = String::from_utf8_lossy(c.take(klen)?).into_owned();
+        let vlen = c.u32()? as usize;
+        m.insert(k, c.take(vlen)?.to_vec());
+    }
+    Ok(m)
+}
+
+fn parse_header(data: &[u8]) -> Result<Header> {
+    let mut c = Cur::new(data);
+    if c.u32().ok() != Some(SIG1) || c.u32().ok() != Some(SIG2) {
+        return Err(err!(InvalidInput, "not a KeePass database (KDBX)"));
+    }
+    let minor = c.u16()?;
+    let major = c.u16()?;
+    if major != 3 && major != 4 {
+        return Err(err!(UnsupportedFormat, "KDBX {major}.{minor} is not supported (3.1 and 4.x are)"));
+    }
+    let (mut cipher, mut compressed, mut master_seed, mut iv) = (String::new(), false, Vec::new(), Vec::new());
+    let (mut seed, mut rounds, mut dict) = (None, None, None);
+    let (mut stream_key, mut stream_start, mut stream_id) = (Vec::new(), Vec::new(), 0);
+    loop {
+        let id = c.u8()?;
+        let len = if major >= 4 { c.u32()? as usize } else { c.u16()? as usize };
+        let v = c.take(len)?;
+        match id {
+            0 => break,
+            2 => cipher = hex(v),
+            3 => compressed = le_u32(v)? == 1,
+            4 => master_seed = v.to_vec(),
+            5 => seed = Some(v.to_vec()),
+            6 => rounds = Some(le_u64(v)?),
+            7 => iv = v.to_vec(),
+            8 => stream_key = v.to_vec(),
+            9 => stream_start = v.to_vec(),
+            10 => stream_id = le_u32(v)?,
+            11 => dict = Some(variant_dict(v)?),
+            _ => {}
+        }
+    }
+    let kdf = match (major, dict, seed, rounds) {
+        (4, Some(d), _, _) => Kdf::from_dict(&d)?,
+        (3, _, Some(seed), Some(rounds)) => Kdf::Aes { seed, rounds },
+        _ => return Err(bad("key derivation parameters missing")),
+    };
+    if master_seed.len() != 32 { return Err(bad("master seed must be 32 bytes")); }
+    Ok(Header { major, len: c.pos, cipher, compressed, master_seed, iv, kdf, stream_key, stream_start, stream_id })
+}
+
+// キーファイル: KeePass の XML（1.0 は base64、2.0 は16進）、32バイトの生データ、64文字の16進、それ以外はファイルの SHA-256
+fn keyfile_key(data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let t = text.trim_start_matches('\u{feff}').trim();
+        if t.starts_with("<?xml") || t.starts_with("<KeyFile") {
+            let start = t.find("<Data").and_then(|i| t[i..].find('>').map(|j| i + j + 1));
+            let end = t.find("</Data>");
+            let (Some(s), Some(e)) = (start, end) else { return Err(err!(InvalidInput, "key file has no <Data> element")) };
+            let body: String = t[s..e].chars().filter(|c| !c.is_whitespace()).collect();
+            let key = if t.contains("<Version>2.") { unhex(&body) } else { STANDARD.decode(&body).ok() };
+            return key.filter(|k| k.len() == 32).map(Zeroizing::new).ok_or_else(|| err!(InvalidInput, "key file <Data> is not a 32-byte key"));
+        }
+        if data.len() == 64 {
+            if let Some(k) = unhex(text) { return Ok(Zeroizing::new(k)); }
+        }
+    }
+    if data.len() == 32 { return Ok(Zeroizing::new(data.to_vec())); }
+    Ok(Zeroizing::new(Sha256::digest(data).to_vec()))
+}
+
+fn composite_key(password: Option<&str>, keyfile: Option<&[u8]>) -> Result<Zeroizing<[u8; 32]>> {
+    let mut h = Sha256::new();
+    if let Some(p) = password { h.update(Sha256::digest(p.as_bytes())); }
+    if let Some(k) = keyfile { h.update(keyfile_key(k)?.as_slice()); }
+    Ok(Zeroizing::new(h.finalize().into()))
+}
+
+fn sha256(parts: &[&[u8]]) -> Zeroizing<[u8; 32]> {
+    let mut h = Sha256::new();
+    for p in parts { h.update(p); }
+    Zeroizing::new(h.finalize().into())
+}
+
+// 復号できなければ None（AES-CBC のパディング不一致 = 鍵違い）
+fn decrypt_body(h: &Header, key: &[u8; 32], data: &[u8]) -> Result<Option<Vec<u8>>> {
+    match h.cipher.as_str() {
+        CIPHER_AES256 => {
+            let dec = cbc::Decryptor::<aes::Aes256>::new_from_slices(key, &h.iv).map_err(|_| bad("bad IV length"))?;
+            let mut buf = data.to_vec();
+            Ok(dec.decrypt_padded_mut::<Pkcs7>(&mut buf).ok().map(|p| p.len()).map(|n| { buf.truncate(n); buf }))
+        }
+        CIPHER_CHACHA20 => {
+            let mut buf = data.to_vec();
+            chacha20::ChaCha20::new_from_slices(key, &h.iv).map_err(|_| bad("bad IV length"))?.apply_keystream(&mut buf);
+            Ok(Some(buf))
+        }
+        CIPHER_TWOFISH => Err(err!(
+            UnsupportedFormat,
+            "Twofish-encrypted KDBX files are not supported; switch the database encryption to AES or ChaCha20 in KeePass/KeePassXC and try again"
+        )),
+        c => Err(err!(UnsupportedFormat, "unsupported KDBX cipher {c}")),
+    }
+}
+
+// 3.1: 復号 → 照合用バイト列 → ハッシュ付きブロック
+fn open_v3(h: &Header, data: &[u8], transformed: &[u8; 32]) -> Result<Option<Vec<u8>>> {
+    let key = sha256(&[&h.master_seed, transformed]);
+    let Some(plain) = decrypt_body(h, &key, &data[h.len..])? else { return Ok(None) };
+    if h.stream_start.is_empty() || !plain.starts_with(&h.stream_start) { return Ok(None); }
+    let mut c = Cur::new(&plain[h.stream_start.len()..]);
+    let mut out = Vec::with_capacity(plain.len());
+    loop {
+        c.u32()?;
+        let hash = c.take(32)?;
+        let size = c.u32()? as usize;
+        if size == 0 { break; }
+        let block = c.take(size)?;
+        if Sha256::digest(block).as_slice() != hash { return Err(bad("block checksum mismatch (file damaged)")); }
+        out.extend_from_slice(block);
+    }
+    Ok(Some(out))
+}
+
+fn block_mac(base: &[u8], index: u64, parts: &[&[u8]]) -> Hmac<Sha256> {
+    let key = Sha512::new().chain_update(index.to_le_bytes()).chain_update(base).finalize();
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("hmac accepts any key length");
+    for p in parts { mac.update(p); }
+    mac
+}
+
+// 4.x: ヘッダのハッシュと HMAC → HMAC 付きブロック → 復号
+fn open_v4(h: &Header, data: &[u8], transformed: &[u8; 32]) -> Result<Option<Vec<u8>>> {
+    let header = &data[..h.len];
+    let mut c = Cur::new(&data[h.len..]);
+    if Sha256::digest(header).as_slice() != c.take(32)? { return Err(bad("header checksum mismatch (file damaged)")); }
+    let base = Zeroizing::new(Sha512::new().chain_update(&h.master_seed).chain_update(transformed).chain_update([1u8]).finalize().to_vec());
+    if block_mac(&base, u64::MAX, &[header]).verify_slice(c.take(32)?).is_err() { return Ok(None); }
+    let mut body = Vec::new();
+    for i in 0u64.. {
+        let mac = c.take(32)?;
+        let size = c.u32()?;
+        let block = c.take(size as usize)?;
+        if block_mac(&base, i, &[&i.to_le_bytes(), &size.to_le_bytes(), block]).verify_slice(mac).is_err() {
+            return Err(bad("block authentication failed (file damaged)"));
+        }
+        if size == 0 { break; }
+        body.extend_from_slice(block);
+    }
+    let key = sha256(&[&h.master_seed, transformed]);
+    decrypt_body(h, &key, &body)?.map(Some).ok_or_else(|| bad("payload failed to decrypt (file damaged)"))
+}
+
+// 3.1 の内側ストリームで使う Salsa20（64バイトのブロックを順に使う）
+struct Salsa20 {
+    input: [u32; 16],
+    block: [u8; 64],
+    used: usize,
+}
+
+impl Salsa20 {
+    fn new(key: &[u8; 32], nonce: &[u8; 8]) -> Self {
+        let w = |b: &[u8], i: usize| u32::from_le_bytes(b[i * 4..i * 4 + 4].try_into().unwrap());
+        let input = [
+            0x6170_7865, w(key, 0), w(key, 1), w(key, 2),
+            w(key, 3), 0x3320_646e, w(nonce, 0), w(nonce, 1),
+            0, 0, 0x7962_2d32, w(key, 4),
+            w(key, 5), w(key, 6), w(key, 7), 0x6b20_6574,
+        ];
+        Salsa20 { input, block: [0; 64], used: 64 }
+    }
+
+    fn next_block(&mut self) {
+        fn qr(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+            x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+            x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+            x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+            x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+        }
+        let mut x = self.input;
+        for _ in 0..10 {
+            qr(&mut x, 0, 4, 8, 12);
+            qr(&mut x, 5, 9, 13, 1);
+            qr(&mut x, 10, 14, 2, 6);
+            qr(&mut x, 15, 3, 7, 11);
+            qr(&mut x, 0, 1, 2, 3);
+            qr(&mut x, 5, 6, 7, 4);
+            qr(&mut x, 10, 11, 8, 9);
+            qr(&mut x, 15, 12, 13, 14);
+        }
+        for (i, v) in x.iter().enumerate() {
+            self.block[i * 4..i * 4 + 4].copy_from_slice(&v.wrapping_add(self.input[i]).to_le_bytes());
+        }
+        // 64ビットのブロックカウンタ
+        self.input[8] = self.input[8].wrapping_add(1);
+        if self.input[8] == 0 { self.input[9] = self.input[9].wrapping_add(1); }
+        self.used = 0;
+    }
+
+    fn apply(&mut self, data: &mut [u8]) {
+        for b in data {
+            if self.used == 64 { self.next_block(); }
+            *b ^= self.block[self.used];
+            self.used += 1;
+        }
+    }
+}
+
+enum Inner {
+    Plain,
+    Salsa20(Box<Salsa20>),
+    ChaCha20(Box<chacha20::ChaCha20>),
+}
+
+impl Inner {
+    fn new(id: u32, key: &[u8]) -> Result<Inner> {
+        match id {
+            0 => Ok(Inner::Plain),
+            2 => Ok(Inner::Salsa20(Box::new(Salsa20::new(&sha256(&[key]), &SALSA20_NONCE)))),
+            3 => {
+                let h = Zeroizing::new(Sha512::digest(key).to_vec());
+                Ok(Inner::ChaCha20(Box::new(chacha20::ChaCha20::new_from_slices(&h[..32], &h[32..44]).map_err(|_| bad("inner stream key"))?)))
+            }
+            id => Err(err!(UnsupportedFormat, "unsupported KDBX inner stream cipher {id}")),
+        }
+    }
+
+    fn apply(&mut self, data: &mut [u8]) {
+        match self {
+            Inner::Plain => {}
+            Inner::Salsa20(s) => s.apply(data),
+            Inner::ChaCha20(c) => c.apply_keystream(data),
+        }
+    }
+}
+
+// 3.1 は ISO 8601、4.x は 0001-01-01 からの秒数（i64 LE）の base64
+fn timestamp(s: &str) -> Option<String> {
+    let s = s.trim();
+    let t = match OffsetDateTime::parse(s, &Rfc3339) {
+        Ok(t) => t,
+        Err(_) => {
+            let secs = i64::from_le_bytes(STANDARD.decode(s).ok()?.try_into().ok()?);
+            OffsetDateTime::from_unix_timestamp(secs - EPOCH_OFFSET).ok()?
+        }
+    };
+    t.format(&Rfc3339).ok()
+}
+
+// KeePassXC の otp 欄（otpauth:// URI、または古い key=...&step=...&size=... 形式）
+fn otp_from_keepassxc(v: &str) -> Option<Otp> {
+    if v.trim_start().starts_with("otpauth://") { return totp::parse_uri(v).ok(); }
+    let mut otp = totp::parse_secret(v.split('&').find_map(|p| p.strip_prefix("key="))?).ok()?;
+    for (k, x) in v.split('&').filter_map(|p| p.split_once('=')) {
+        match k {
+            "step" => otp.period = x.parse().ok().filter(|&p| p > 0)?,
+            "size" => otp.digits = x.parse().ok().filter(|d| (6..=8).contains(d))?,
+            _ => {}
+        }
+    }
+    Some(otp)
+}
+
+// KeePass 2.47 以降の TimeOtp-* 欄（Base32 のシークレットのみ）
+fn otp_from_keepass(strings: &[(String, String)]) -> Option<Otp> {
+    let get = |k: &str| strings.iter().find(|(n, _)| n == k).map(|(_, v)| v.as_str());
+    let mut otp = totp::parse_secret(get("TimeOtp-Secret-Base32")?).ok()?;
+    if let Some(d) = get("TimeOtp-Length") { otp.digits = d.parse().ok().filter(|d| (6..=8).contains(d))?; }
+    if let Some(p) = get("TimeOtp-Period") { otp.period = p.parse().ok().filter(|&p| p > 0)?; }
+    if let Some(a) = get("TimeOtp-Algorithm") { otp.algorithm = a.trim_start_matches("HMAC-").replace('-', "").parse().ok()?; }
+    Some(otp)
+}
+
+fn to_entry(strings: Vec<(String, String)>, protected: &[String], group: &str, tags: &str, times: (Option<String>, Option<String>), autotype: Option<String>) -> Entry {
+    let mut e = Entry::new(String::new());
+    let keepass_otp = otp_from_keepass(&strings);
+    for (k, v) in strings {
+        match k.as_str() {
+            "Title" => e.name = v.trim().to_string(),
+            "UserName" => e.username = v,
+            "Password" => e.password = v,
+            "URL" => e.url = Some(v).filter(|u| !u.trim().is_empty()),
+            "Notes" => e.notes = Some(v).filter(|n| !n.trim().is_empty()),
+            "otp" if otp_from_keepassxc(&v).is_some() => e.otp = otp_from_keepassxc(&v),
+            k if k.starts_with("TimeOtp-") && keepass_otp.is_some() => {}
+            _ if v.is_empty() => {}
+            _ => e.fields.push(Field { hidden: protected.contains(&k), name: k, value: v, json: false }),
+        }
+    }
+    if e.otp.is_none() { e.otp = keepass_otp; }
+    if e.name.is_empty() { e.name = e.url.clone().unwrap_or_else(|| "untitled".into()); }
+    if !group.is_empty() { e.tags.push(group.to_string()); }
+    for t in tags.split([';', ',']).map(str::trim).filter(|t| !t.is_empty()) {
+        if !e.tags.iter().any(|x| x == t) { e.tags.push(t.to_string()); }
+    }
+    let (created, modified) = times;
+    if let Some(c) = created { e.created_at = Some(c); }
+    if let Some(m) = modified { e.updated_at = m; }
+    // KeePass の自動入力シーケンスは rustpass と同じ書式（読めないものは取り込まない）
+    e.autotype = autotype.filter(|s| crate::autotype::validate(s).is_ok());
+    e
+}
+
+struct Group {
+    name: String,
+    skip: bool,
+}
+
+#[derive(Default)]
+struct Pending {
+    strings: Vec<(String, String)>,
+    /// 保護された（KeePass で伏せて表示する）値のキー
+    protected: Vec<String>,
+    tags: String,
+    created: Option<String>,
+    modified: Option<String>,
+    autotype: Option<String>,
+}
+
+struct Parsed {
+    entries: Vec<Entry>,
+    attachments: usize,
+}
+
+fn parse_xml(xml: &[u8], inner: &mut Inner) -> Result<Parsed> {
+    let mut reader = quick_xml::Reader::from_reader(xml);
+    reader.config_mut().expand_empty_elements = true;
+    let xml_err = |e: &dyn std::fmt::Display| err!(InvalidInput, "KDBX XML is malformed: {e}");
+    let mut path: Vec<String> = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let (mut recycle_bin, mut recycle_enabled) = (None, true);
+    let mut pending: Option<Pending> = None;
+    let (mut key, mut value, mut protected, mut hidden) = (String::new(), String::new(), false, false);
+    let mut text = String::new();
+    let mut out = Parsed { entries: Vec::new(), attachments: 0 };
+    loop {
+        match reader.read_event().map_err(|e| xml_err(&e))? {
+            Event::Eof => break,
+            Event::Start(s) => {
+                let name = String::from_utf8_lossy(s.local_name().as_ref()).into_owned();
+                let parent = path.last().map(String::as_str);
+                match (parent, name.as_str()) {
+                    (_, "Group") => {
+                        let skip = groups.last().is_some_and(|g| g.skip);
+                        groups.push(Group { name: String::new(), skip });
+                    }
+                    // 履歴（History の中の Entry）は取り込まない
+                    (Some("Group"), "Entry") => pending = Some(Pending::default()),
+                    (_, "Value") => {
+                        protected = s.try_get_attribute("Protected").map_err(|e| xml_err(&e))?
+                            .is_some_and(|a| a.value.as_ref() == b"True");
+                    }
+                    _ => {}
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Text(t) => text.push_str(&t.xml10_content().map_err(|e| xml_err(&e))?),
+            Event::CData(t) => text.push_str(&t.decode().map_err(|e| xml_err(&e))?),
+            Event::GeneralRef(r) => {
+                let name = r.decode().map_err(|e| xml_err(&e))?;
+                match r.resolve_char_ref().map_err(|e| xml_err(&e))? {
+                    Some(c) => text.push(c),
+                    None => text.push_str(quick_xml::escape::resolve_predefined_entity(&name).ok_or_else(|| xml_err(&format!("unknown entity &{name};")))?),
+                }
+            }
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let in_history = path.iter().any(|p| p == "History");
+                let own = |p: &Option<Pending>| p.is_some() && !in_history;
+                match (path.last().map(String::as_str), name.as_str()) {
+                    (Some("Meta"), "RecycleBinUUID") => recycle_bin = Some(text.trim().to_string()),
+                    (Some("Meta"), "RecycleBinEnabled") => recycle_enabled = text.trim().eq_ignore_ascii_case("true"),
+                    (Some("Group"), "UUID") if recycle_enabled && recycle_bin.as_deref() == Some(text.trim()) => {
+                        if let Some(g) = groups.last_mut() { g.skip = true; }
+                    }
+                    (Some("Group"), "Name") => if let Some(g) = groups.last_mut() { g.name = text.trim().to_string(); },
+                    (Some("String"), "Key") => key = std::mem::take(&mut text),
+                    // 保護された値は履歴の中のものも含めて文書順にストリームを進める
+                    (_, "Value") if protected => {
+                        let mut b = STANDARD.decode(text.trim()).map_err(|_| bad("protected value is not base64"))?;
+                        inner.apply(&mut b);
+                        value = String::from_utf8(b).map_err(|_| err!(WrongPassword, "protected value failed to decrypt"))?;
+                        protected = false;
+                        hidden = true;
+                    }
+                    (_, "Value") => value = std::mem::take(&mut text),
+                    (Some("Entry"), "String") if own(&pending) => {
+                        if let Some(p) = pending.as_mut() {
+                            if hidden { p.protected.push(key.clone()); }
+                            p.strings.push((std::mem::take(&mut key), std::mem::take(&mut value)));
+                        }
+                        hidden = false;
+                    }
+                    (Some("Entry"), "Tags") if own(&pending) => if let Some(p) = pending.as_mut() { p.tags = text.clone(); },
+                    (Some("Entry"), "Binary") if own(&pending) => out.attachments += 1,
+                    (Some("Times"), "CreationTime") if own(&pending) && path.len() >= 2 && path[path.len() - 2] == "Entry" => {
+                        if let Some(p) = pending.as_mut() { p.created = timestamp(&text); }
+                    }
+                    (Some("Times"), "LastModificationTime") if own(&pending) && path.len() >= 2 && path[path.len() - 2] == "Entry" => {
+                        if let Some(p) = pending.as_mut() { p.modified = timestamp(&text); }
+                    }
+                    (Some("AutoType"), "DefaultSequence") if own(&pending) => {
+                        if let Some(p) = pending.as_mut() { p.autotype = Some(text.trim().to_string()).filter(|s| !s.is_empty()); }
+                    }
+                    (Some("Group"), "Entry") => {
+                        let p = pending.take().unwrap_or_default();
+                        if !groups.last().is_some_and(|g| g.skip) {
+                            // 最上位のグループ（データベース名）は除いたパスをタグにする
+                            let group = groups.iter().skip(1).map(|g| g.name.as_str()).collect::<Vec<_>>().join("/");
+                            out.entries.push(to_entry(p.strings, &p.protected, &group, &p.tags, (p.created, p.modified), p.autotype));
+                        }
+                    }
+                    (_, "Group") => { groups.pop(); }
+                    _ => {}
+                }
+                text.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+fn decompress(h: &Header, payload: Vec<u8>) -> Result<Vec<u8>> {
+    if !h.compressed { return Ok(payload); }
+    let mut out = Vec::new();
+    flate2::read::GzDecoder::new(payload.as_slice()).read_to_end(&mut out).map_err(|_| bad("payload failed to decompress"))?;
+    Ok(out)
+}
+
+fn parse(h: &Header, payload: Vec<u8>) -> Result<Parsed> {
+    let payload = Zeroizing::new(decompress(h, payload)?);
+    if h.major == 3 {
+        return parse_xml(&payload, &mut Inner::new(h.stream_id, &h.stream_key)?);
+    }
+    // 4.x の内側ヘッダ（ストリーム暗号の種類と鍵、添付ファイル）
+    let mut c = Cur::new(&payload);
+    let (mut id, mut key) = (0, Zeroizing::new(Vec::new()));
+    loop {
+        let field = c.u8()?;
+        let len = c.u32()? as usize;
+        let v = c.take(len)?;
+        match field {
+            0 => break,
+            1 => id = le_u32(v)?,
+            2 => key = Zeroizing::new(v.to_vec()),
+            _ => {}
+        }
+    }
+    parse_xml(&payload[c.pos..], &mut Inner::new(id, &key)?)
+}
+
+pub fn read(path: &Path, keyfile: Option<&Path>) -> Result<Vec<Entry>> {
+    let data = fs::read(path)?;
+    let h = parse_header(&data)?;
+    let keyfile = keyfile.map(fs::read).transpose()?.map(Zeroizing::new);
+    let label = if keyfile.is_some() { "KeePass password (empty if the key file alone unlocks it): " } else { "KeePass password: " };
+    for _ in 0..3 {
+        let password = Zeroizing::new(prompt::password(label)?);
+        // キーファイルがある場合、空のパスワードは「パスワードなし」
+        let password = (keyfile.is_none() || !password.is_empty()).then_some(password.as_str());
+        let composite = composite_key(password, keyfile.as_deref().map(Vec::as_slice))?;
+        let transformed = h.kdf.transform(&composite)?;
+        let payload = if h.major == 3 { open_v3(&h, &data, &transformed)? } else { open_v4(&h, &data, &transformed)? };
+        if let Some(payload) = payload {
+            let parsed = parse(&h, payload)?;
+            match parsed.attachments {
+                0 => {}
+                1 => eprintln!("note: 1 attachment was not imported (rustpass does not store files)"),
+                n => eprintln!("note: {n} attachments were not imported (rustpass does not store files)"),
+            }
+            return Ok(parsed.entries);
+        }
+        eprintln!("Wrong password or key file.");
+    }
+    Err(err!(WrongPassword, "wrong KeePass password or key file"))
+}
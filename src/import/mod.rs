@@ -0,0 +1,124 @@
+// 他のパスワードマネージャ・ブラウザからの取り込み
+use crate::{Entry, Vault};
+use anyhow::Result;
+use clap::ValueEnum;
+use std::path::Path;
+
+mod andotp;
+mod apple;
+mod bitwarden;
+mod csvfile;
+mod firefox;
+mod kdbx;
+mod onepassword;
+mod protonpass;
+mod rpss;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// 見出し行付きのCSV（LastPass・Chrome・Firefox の書き出しは自動判別、それ以外は --map で列を指定）
+    Csv,
+    /// Firefoxのプロファイルディレクトリ（logins.json + key4.db）
+    Firefox,
+    /// Apple パスワード / Safari のCSV（OTPAuth列のTOTPも取り込む）
+    Apple,
+    /// Bitwarden / Vaultwarden の書き出し（.json（暗号化なし）または .csv。カードとIDは取り込まない）
+    Bitwarden,
+    /// 1Password の書き出し（.1pux、または CSV。複数の保管庫はそれぞれタグになる）
+    #[value(name = "1password")]
+    OnePassword,
+    /// Proton Pass の書き出し（zip または data.json。暗号化なしで書き出したもの）
+    #[value(name = "protonpass")]
+    ProtonPass,
+    /// Aegis Authenticator のバックアップ（暗号化・平文どちらも。TOTPのみ）
+    Aegis,
+    /// andOTP のバックアップ（.json または パスワード暗号化の .json.aes。TOTPのみ）
+    #[value(name = "andotp")]
+    AndOtp,
+    /// Nextcloud Passwords の書き出し（暗号化なしのJSON、または見出し行付きのCSV）
+    Nextcloud,
+    /// KeePass / KeePassXC のデータベース（.kdbx、3.1 / 4.x。--keyfile 可）
+    Kdbx,
+    /// pass（password-store）のディレクトリ（gpg で復号。ディレクトリのパスはタグになる）
+    Pass,
+    /// rustpass の暗号化スナップショット（export --format rpss で書き出したもの）
+    Rpss,
+}
+
+/// 同じ名前のエントリが既にある場合の扱い
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum Duplicates {
+    /// 「名前 (ユーザー名)」などに名前を変えて追加
+    #[default]
+    Rename,
+    /// 取り込まない
+    Skip,
+    /// 既存のエントリを置き換える（IDと作成日時は残す）
+    Replace,
+}
+
+/// 取り込みの結果
+#[derive(Default)]
+pub struct Merged {
+    /// 追加・置き換えたエントリの名前
+    pub names: Vec<String>,
+    pub renamed: usize,
+    pub skipped: usize,
+    pub replaced: usize,
+}
+
+pub fn read(format: Format, path: &Path, keyfile: Option<&Path>, map: Option<&str>) -> Result<Vec<Entry>> {
+    match format {
+        Format::Csv => csvfile::read(path, map),
+        Format::Firefox => firefox::read(path),
+        Format::Apple => apple::read(path),
+        Format::Bitwarden => bitwarden::read(path),
+        Format::OnePassword => onepassword::read(path),
+        Format::ProtonPass => protonpass::read(path),
+        Format::Aegis => crate::aegis::read(path),
+        Format::AndOtp => andotp::read(path),
+        Format::Nextcloud => crate::nextcloud::read(path),
+        Format::Kdbx => kdbx::read(path, keyfile),
+        Format::Pass => crate::passstore::read(path),
+        Format::Rpss => Ok(std::mem::take(&mut rpss::read(path)?.entries)),
+    }
+}
+
+/// rpss スナップショットを金庫全体（ポリシーなども含む）として読む
+pub fn read_snapshot(path: &Path) -> Result<Vault> {
+    rpss::read(path)
+}
+
+// 名前が重複したら「名前 (ユーザー名)」、それでも重複なら連番を付ける
+pub(crate) fn unique_name(vault: &Vault, e: &Entry) -> String {
+    let taken = |n: &str| vault.entries.iter().any(|x| x.name == n);
+    if !taken(&e.name) { return e.name.clone(); }
+    let base = if e.username.is_empty() { e.name.clone() } else { format!("{} ({})", e.name, e.username) };
+    if !taken(&base) { return base; }
+    (2..).map(|i| format!("{base} {i}")).find(|n| !taken(n)).unwrap()
+}
+
+/// 取り込んだエントリを追加する
+pub fn merge(vault: &mut Vault, entries: Vec<Entry>, duplicates: Duplicates) -> Merged {
+    let mut m = Merged::default();
+    for mut e in entries {
+        match (duplicates, vault.entries.iter_mut().find(|x| x.name == e.name)) {
+            (Duplicates::Skip, Some(_)) => { m.skipped += 1; continue; }
+            (Duplicates::Replace, Some(old)) => {
+                e.id = old.id.clone();
+                e.created_at = old.created_at.clone();
+                *old = e;
+                m.replaced += 1;
+                m.names.push(old.name.clone());
+                continue;
+            }
+            _ => {}
+        }
+        let name = unique_name(vault, &e);
+        if name != e.name { m.renamed += 1; }
+        e.name = name;
+        m.names.push(e.name.clone());
+        vault.entries.push(e);
+    }
+    m
+}
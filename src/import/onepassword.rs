@@ -0,0 +1,347 @@
+// 1Password の書き出し（.1pux、または CSV）
+//
+// 1PUX は zip の中の export.data（JSON）で、accounts → vaults → items の入れ子になっている。
+// 項目は categoryUuid で種類が分かれ、ログインの入力欄は details.loginFields（designation で
+// ユーザー名・パスワードが分かる）、それ以外の項目は details.sections の fields に入る。
+// 節の項目の値は {"concealed": "..."} のように値の種類を鍵にしたオブジェクトになっている。
+use crate::error::err;
+use crate::sshkey::SshKey;
+use crate::{Entry, Field};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{fs, io::Read, path::Path};
+use time::OffsetDateTime;
+
+use super::protonpass::otp;
+
+#[derive(Deserialize)]
+struct Export {
+    accounts: Vec<Account>,
+}
+
+#[derive(Deserialize)]
+struct Account {
+    #[serde(default)]
+    attrs: Map<String, Value>,
+    #[serde(default)]
+    vaults: Vec<Vault>,
+}
+
+#[derive(Deserialize)]
+struct Vault {
+    #[serde(default)]
+    attrs: Map<String, Value>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(default)]
+    category_uuid: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    created_at: Option<i64>,
+    #[serde(default)]
+    updated_at: Option<i64>,
+    #[serde(default)]
+    details: Details,
+    #[serde(default)]
+    overview: Overview,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Details {
+    #[serde(default)]
+    login_fields: Vec<LoginField>,
+    #[serde(default)]
+    notes_plain: Option<String>,
+    #[serde(default)]
+    sections: Vec<Section>,
+    /// 「パスワード」の種類の項目だけが持つ
+    #[serde(default)]
+    password: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginField {
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    name: String,
+    /// T テキスト・E メール・P パスワード・C チェックボックス・B ボタンなど
+    #[serde(default)]
+    field_type: String,
+    #[serde(default)]
+    designation: String,
+}
+
+#[derive(Deserialize)]
+struct Section {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    fields: Vec<SectionField>,
+}
+
+#[derive(Deserialize)]
+struct SectionField {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    value: Map<String, Value>,
+}
+
+#[derive(Deserialize, Default)]
+struct Overview {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    urls: Vec<Url>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Url {
+    #[serde(default)]
+    url: String,
+}
+
+const LOGIN: &str = "001";
+const SECURE_NOTE: &str = "003";
+const PASSWORD: &str = "005";
+const SSH_KEY: &str = "114";
+
+// ログイン・パスワード・セキュアメモ以外の種類は、種類名をタグにする
+fn category(uuid: &str) -> &'static str {
+    match uuid {
+        "002" => "credit card",
+        "004" => "identity",
+        "006" => "document",
+        "100" => "software license",
+        "101" => "bank account",
+        "102" => "database",
+        "103" => "driver license",
+        "104" => "outdoor license",
+        "105" => "membership",
+        "106" => "passport",
+        "107" => "reward program",
+        "108" => "social security number",
+        "109" => "wireless router",
+        "110" => "server",
+        "111" => "email account",
+        "112" => "api credential",
+        "113" => "medical record",
+        "114" => "ssh key",
+        "115" => "crypto wallet",
+        _ => "other",
+    }
+}
+
+fn load(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(b"PK") { return Ok(bytes); }
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let mut out = Vec::new();
+    zip.by_name("export.data")
+        .map_err(|_| err!(InvalidInput, "no export.data in {} (is it a .1pux file?)", path.display()))?
+        .read_to_end(&mut out)?;
+    Ok(out)
+}
+
+fn iso(ts: Option<i64>) -> Option<String> {
+    let t = OffsetDateTime::from_unix_timestamp(ts?).ok()?;
+    t.format(&time::format_description::well_known::Rfc3339).ok()
+}
+
+fn attr(attrs: &Map<String, Value>, key: &str) -> Option<String> {
+    attrs.get(key).and_then(Value::as_str).map(str::trim).filter(|s| !s.is_empty()).map(String::from)
+}
+
+// 節の項目の値を種類と文字列にする（添付ファイルや他の項目への参照は取り込まない）
+fn section_value(value: &Map<String, Value>) -> Option<(&str, String)> {
+    let (kind, v) = value.iter().next()?;
+    let s = match (kind.as_str(), v) {
+        (_, Value::String(s)) => s.clone(),
+        ("email", Value::Object(m)) => m.get("email_address")?.as_str()?.to_string(),
+        ("date", Value::Number(n)) => {
+            let t = OffsetDateTime::from_unix_timestamp(n.as_i64()?).ok()?;
+            format!("{:04}-{:02}-{:02}", t.year(), u8::from(t.month()), t.day())
+        }
+        ("monthYear", Value::Number(n)) => {
+            let n = n.as_u64()?;
+            format!("{:04}/{:02}", n / 100, n % 100)
+        }
+        ("address", Value::Object(m)) => {
+            let part = |k: &str| m.get(k).and_then(Value::as_str).filter(|s| !s.is_empty());
+            let parts: Vec<&str> = ["street", "city", "state", "zip", "country"].into_iter().filter_map(part).collect();
+            parts.join(", ")
+        }
+        ("sshKey", Value::Object(m)) => m.get("privateKey")?.as_str()?.to_string(),
+        _ => return None,
+    };
+    let s = s.trim().to_string();
+    (!s.is_empty()).then_some((kind.as_str(), s))
+}
+
+fn ssh_key(value: &Map<String, Value>) -> Option<SshKey> {
+    let key = value.get("sshKey")?.as_object()?;
+    let private_key = key.get("privateKey")?.as_str()?.to_string();
+    let public_key = key.get("metadata").and_then(|m| m.get("publicKey")).and_then(Value::as_str).unwrap_or_default().to_string();
+    let algorithm = public_key.split_whitespace().next().unwrap_or_default().to_string();
+    Some(SshKey { algorithm, private_key, public_key })
+}
+
+fn entry(item: Item, tags: &[String]) -> Result<Entry> {
+    let Item { category_uuid, state, created_at, updated_at, details, overview } = item;
+    let mut e = Entry::new(overview.title.trim().to_string());
+    e.notes = details.notes_plain.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    e.tags = tags.to_vec();
+    e.tags.extend(overview.tags.into_iter().filter(|t| !t.is_empty() && !e.tags.contains(t)).collect::<Vec<_>>());
+    if !matches!(category_uuid.as_str(), LOGIN | PASSWORD | SECURE_NOTE) {
+        e.tags.push(category(&category_uuid).to_string());
+    }
+    if state == "archived" { e.tags.push("archived".into()); }
+    e.created_at = iso(created_at).or(e.created_at);
+    e.updated_at = iso(updated_at).unwrap_or(e.updated_at);
+
+    // URL は overview.url を先頭に、残りを「url 2」などのフィールドにする
+    let mut urls: Vec<String> = overview.url.into_iter().chain(overview.urls.into_iter().map(|u| u.url))
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .collect();
+    urls.dedup();
+    let mut extra_urls = Vec::new();
+    for (i, u) in urls.into_iter().enumerate() {
+        if i == 0 { e.url = Some(u); } else { extra_urls.push(u); }
+    }
+
+    // ログインの入力欄: designation の付いたものがユーザー名とパスワード、残りはカスタムフィールド
+    for f in details.login_fields {
+        let value = f.value.trim().to_string();
+        if value.is_empty() { continue; }
+        match (f.designation.as_str(), f.field_type.as_str()) {
+            ("username", _) if e.username.is_empty() => e.username = value,
+            ("password", _) if e.password.is_empty() => e.password = f.value,
+            // ボタンやチェックボックスは入力値ではない
+            (_, "B" | "C" | "I") => {}
+            _ => {
+                let name = if f.name.is_empty() { "field".to_string() } else { f.name };
+                e.fields.push(Field { name, value, json: false, hidden: f.field_type == "P" });
+            }
+        }
+    }
+    if let Some(p) = details.password.filter(|p| !p.is_empty() && e.password.is_empty()) {
+        e.password = p;
+    }
+
+    // 節の項目: TOTP は最初の1つをシークレットに、SSH鍵は鍵に、それ以外はカスタムフィールド
+    for section in details.sections {
+        for f in section.fields {
+            if category_uuid == SSH_KEY && e.ssh_key.is_none() {
+                if let Some(k) = ssh_key(&f.value) { e.ssh_key = Some(k); continue; }
+            }
+            let Some((kind, value)) = section_value(&f.value) else { continue };
+            if kind == "totp" && e.otp.is_none() {
+                e.otp = Some(otp(&value)?);
+                continue;
+            }
+            if kind == "url" { extra_urls.push(value); continue; }
+            let name = match (section.title.trim(), f.title.trim()) {
+                (_, "") => kind.to_string(),
+                ("", t) => t.to_string(),
+                (s, t) => format!("{s}/{t}"),
+            };
+            e.fields.push(Field { name, value, json: false, hidden: kind == "concealed" });
+        }
+    }
+    let first = if e.url.is_some() { 2 } else { 1 };
+    for (i, u) in extra_urls.into_iter().enumerate() {
+        if e.url.is_none() { e.url = Some(u); continue; }
+        e.fields.push(Field { name: format!("url {}", i + first), value: u, json: false, hidden: false });
+    }
+    Ok(e)
+}
+
+fn read_1pux(data: &[u8]) -> Result<Vec<Entry>> {
+    let export: Export = serde_json::from_slice(data)
+        .map_err(|e| err!(InvalidInput, "not a 1Password export: {e}"))?;
+    // 複数のアカウントを含む書き出しでは「アカウント/保管庫」をタグにする
+    let several = export.accounts.len() > 1;
+    let mut out = Vec::new();
+    for account in export.accounts {
+        let account_name = attr(&account.attrs, "accountName").or_else(|| attr(&account.attrs, "name"));
+        for vault in account.vaults {
+            let vault_name = attr(&vault.attrs, "name").unwrap_or_else(|| "1Password".into());
+            let tag = match (&account_name, several) {
+                (Some(a), true) => format!("{a}/{vault_name}"),
+                _ => vault_name,
+            };
+            for item in vault.items {
+                if item.state == "deleted" { continue; }
+                let name = item.overview.title.clone();
+                out.push(entry(item, std::slice::from_ref(&tag)).map_err(|e| err!(InvalidInput, "{name:?}: {e}"))?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+// 1Password 8 の CSV（Title,Url,Username,Password,OTPAuth,Favorite,Archived,Tags,Notes）と、
+// 1Password 7 の CSV（title,website,username,password,notes などを選んで書き出したもの）。
+// 見出しに無い列はカスタムフィールドにする
+fn read_csv(data: &[u8]) -> Result<Vec<Entry>> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_string()).collect();
+    let col = |names: &[&str]| headers.iter().position(|h| names.iter().any(|n| h.eq_ignore_ascii_case(n)));
+    let Some(title) = col(&["title"]) else {
+        return Err(err!(InvalidInput, "not a 1Password CSV export (expected a header row with Title,Url,Username,Password,...)"));
+    };
+    let url = col(&["url", "website", "urls"]);
+    let user = col(&["username"]);
+    let pass = col(&["password"]);
+    let otpauth = col(&["otpauth", "one-time password"]);
+    let tags = col(&["tags"]);
+    let notes = col(&["notes", "notesplain"]);
+    let archived = col(&["archived"]);
+    let favorite = col(&["favorite"]);
+    let known = [Some(title), url, user, pass, otpauth, tags, notes, archived, favorite];
+    let mut out = Vec::new();
+    for (i, rec) in rdr.records().enumerate() {
+        let rec = rec?;
+        let get = |c: Option<usize>| c.and_then(|c| rec.get(c)).map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+        let mut e = Entry::new(get(Some(title)).unwrap_or_else(|| format!("1password-{}", i + 1)));
+        e.username = get(user).unwrap_or_default();
+        e.password = get(pass).unwrap_or_default();
+        e.notes = get(notes);
+        e.tags = get(tags).map(|t| t.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect()).unwrap_or_default();
+        if get(archived).is_some_and(|a| a.eq_ignore_ascii_case("true")) { e.tags.push("archived".into()); }
+        // 複数のURLは改行またはカンマ区切り
+        let urls: Vec<String> = get(url).map(|u| u.split([',', '\n']).map(|u| u.trim().to_string()).filter(|u| !u.is_empty()).collect()).unwrap_or_default();
+        for (k, u) in urls.into_iter().enumerate() {
+            if k == 0 { e.url = Some(u); } else { e.fields.push(Field { name: format!("url {}", k + 1), value: u, json: false, hidden: false }); }
+        }
+        if let Some(uri) = get(otpauth) {
+            e.otp = Some(otp(&uri).map_err(|err| err!(InvalidInput, "row {}: {err}", i + 2))?);
+        }
+        for (c, h) in headers.iter().enumerate().filter(|(c, _)| !known.contains(&Some(*c))) {
+            if let Some(v) = get(Some(c)) { e.fields.push(Field { name: h.clone(), value: v, json: false, hidden: false }); }
+        }
+        out.push(e);
+    }
+    Ok(out)
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let data = load(path)?;
+    let body = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&data);
+    if body.trim_ascii_start().starts_with(b"{") { read_1pux(body) } else { read_csv(body) }
+}
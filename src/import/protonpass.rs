@@ -0,0 +1,184 @@
+// Proton Pass の書き出し（zip 内の Proton Pass/data.json、または data.json そのもの）
+//
+// vaults → items → data { metadata, content, extraFields, type } という入れ子構造で、
+// type ごとに content の中身が異なる。PGPで暗号化した書き出しには対応しない。
+use crate::error::err;
+use crate::{now_iso, totp, Entry, Field};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::{fs, io::Read, path::Path};
+use time::OffsetDateTime;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    vaults: BTreeMap<String, Vault>,
+}
+
+#[derive(Deserialize)]
+struct Vault {
+    name: String,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    data: Data,
+    /// 1 = 通常、2 = ゴミ箱
+    #[serde(default)]
+    state: u8,
+    #[serde(default)]
+    alias_email: Option<String>,
+    #[serde(default)]
+    create_time: Option<i64>,
+    #[serde(default)]
+    modify_time: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Data {
+    metadata: Metadata,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    content: Map<String, Value>,
+    #[serde(default)]
+    extra_fields: Vec<ExtraField>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    name: String,
+    #[serde(default)]
+    note: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtraField {
+    field_name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    data: Map<String, Value>,
+}
+
+const TRASHED: u8 = 2;
+
+fn load(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(b"PK") { return Ok(bytes); }
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let name = zip.file_names()
+        .filter_map(|n| n.ok())
+        .find(|n| n.ends_with("data.json"))
+        .map(|n| n.into_owned())
+        .ok_or_else(|| err!(InvalidInput, "no data.json in {}", path.display()))?;
+    let mut out = Vec::new();
+    zip.by_name(&name)?.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+fn str_of<'a>(m: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    m.get(key).and_then(Value::as_str).map(str::trim).filter(|s| !s.is_empty())
+}
+
+fn iso(ts: Option<i64>) -> Option<String> {
+    let t = OffsetDateTime::from_unix_timestamp(ts?).ok()?;
+    t.format(&time::format_description::well_known::Rfc3339).ok()
+}
+
+// otpauth:// URI のほか、シークレットだけが入っている場合もある
+pub(super) fn otp(value: &str) -> Result<totp::Otp> {
+    if value.starts_with("otpauth://") { return totp::parse_uri(value); }
+    let secret: String = value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+    if totp::base32_decode(&secret).is_none() {
+        return Err(err!(InvalidInput, "TOTP secret is not valid base32"));
+    }
+    Ok(totp::Otp { secret, algorithm: Default::default(), digits: 6, period: 30, issuer: None, account: None })
+}
+
+// カード・身分証などは content の文字列項目をそのままカスタムフィールドにする
+fn content_fields(content: &Map<String, Value>) -> Vec<Field> {
+    content.iter()
+        .filter_map(|(k, v)| Some((k, v.as_str()?.trim())))
+        .filter(|(_, v)| !v.is_empty())
+        .map(|(k, v)| Field { name: k.clone(), value: v.to_string(), json: false, hidden: false })
+        .collect()
+}
+
+fn entry(item: Item, vault: &str) -> Result<Entry> {
+    let Data { metadata, kind, content, extra_fields } = item.data;
+    let mut e = Entry::new(metadata.name.trim().to_string());
+    e.notes = Some(metadata.note.trim().to_string()).filter(|s| !s.is_empty());
+    e.tags = vec![vault.to_string()];
+    e.created_at = iso(item.create_time).or(e.created_at);
+    e.updated_at = iso(item.modify_time).unwrap_or_else(now_iso);
+    match kind.as_str() {
+        "login" => {
+            // 新しい書き出しは itemUsername / itemEmail、古いものは username
+            let user = str_of(&content, "itemUsername").or(str_of(&content, "username"));
+            let email = str_of(&content, "itemEmail");
+            e.username = user.or(email).unwrap_or_default().to_string();
+            if let (Some(_), Some(mail)) = (user, email) {
+                e.fields.push(Field { name: "email".into(), value: mail.into(), json: false, hidden: false });
+            }
+            e.password = str_of(&content, "password").unwrap_or_default().to_string();
+            let urls: Vec<&str> = content.get("urls").and_then(Value::as_array)
+                .map(|a| a.iter().filter_map(Value::as_str).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default();
+            e.url = urls.first().map(|u| u.to_string());
+            for (i, u) in urls.iter().enumerate().skip(1) {
+                e.fields.push(Field { name: format!("url {}", i + 1), value: u.to_string(), json: false, hidden: false });
+            }
+            if let Some(uri) = str_of(&content, "totpUri") { e.otp = Some(otp(uri)?); }
+        }
+        "alias" => {
+            e.username = item.alias_email.unwrap_or_default();
+            e.tags.push("alias".into());
+        }
+        "note" => {}
+        _ => {
+            e.fields.extend(content_fields(&content));
+            e.tags.push(kind);
+        }
+    }
+    for f in extra_fields {
+        let value = match f.kind.as_str() {
+            "totp" => str_of(&f.data, "totpUri"),
+            _ => str_of(&f.data, "content"),
+        };
+        let Some(value) = value else { continue };
+        if f.kind == "totp" && e.otp.is_none() {
+            e.otp = Some(otp(value)?);
+            continue;
+        }
+        e.fields.push(Field { name: f.field_name, value: value.to_string(), json: false, hidden: f.kind == "hidden" });
+    }
+    Ok(e)
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let data = load(path)?;
+    let export: Export = serde_json::from_slice(&data)
+        .map_err(|e| err!(InvalidInput, "not a Proton Pass export: {e}"))?;
+    if export.encrypted {
+        return Err(err!(InvalidInput, "PGP-encrypted Proton Pass exports are not supported; export without encryption"));
+    }
+    let mut out = Vec::new();
+    for vault in export.vaults.into_values() {
+        for item in vault.items {
+            if item.state == TRASHED { continue; }
+            let name = item.data.metadata.name.clone();
+            out.push(entry(item, &vault.name).map_err(|e| err!(InvalidInput, "{name:?}: {e}"))?);
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,22 @@
+// rustpass の暗号化スナップショット（export --format rpss）の読み込み
+//
+// 中身は通常の金庫ファイルと同じ形式で、パスワードの鍵スロットだけを持つ。
+use crate::error::{err, kind_of, Kind};
+use crate::Vault;
+use crate::prompt;
+use anyhow::Result;
+use std::{fs, path::Path};
+use zeroize::Zeroizing;
+
+pub fn read(path: &Path) -> Result<Vault> {
+    let data = fs::read(path)?;
+    for _ in 0..3 {
+        let passphrase = Zeroizing::new(prompt::password("Snapshot passphrase: ")?);
+        match rustpass_core::decrypt_vault(&data, &passphrase) {
+            Ok(v) => return Ok(v),
+            Err(e) if kind_of(&e) == Kind::WrongPassword => eprintln!("Wrong passphrase."),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(err!(WrongPassword, "wrong snapshot passphrase"))
+}
@@ -0,0 +1,91 @@
+// OS のキーチェーンに金庫のデータ鍵を覚えておく（unlock --remember / forget）
+//
+// keyring クレートで、Linux などでは Secret Service、macOS ではキーチェーン、
+// Windows では資格情報マネージャーに、金庫ごとに（金庫のパスをアカウント名にして）保存する。
+// キーチェーンを見に行くのは覚えさせた金庫だけ（金庫の隣の目印のファイルで判断する）。
+// 毎回問い合わせると、キーリングのロック解除を求める画面が出ることがあるため。
+use crate::error::err;
+use crate::format::{DataKey, Unlocked};
+use crate::vault_path;
+use anyhow::{anyhow, Result};
+use keyring::Entry;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+const SERVICE: &str = "rustpass";
+
+// 覚えさせたことの目印（中身は空）
+fn marker(vault: &Path) -> PathBuf {
+    let mut s = vault.as_os_str().to_owned();
+    s.push(".keychain");
+    PathBuf::from(s)
+}
+
+/// 今の金庫の鍵を覚えさせているか
+pub fn remembered() -> Result<bool> {
+    Ok(marker(&vault_path()?).exists())
+}
+
+fn entry(vault: &Path) -> Result<Entry> {
+    Entry::new(SERVICE, &vault.display().to_string()).map_err(|e| anyhow!("OS keychain: {e}"))
+}
+
+/// データ鍵だけで開けてはいけない金庫か（キーファイル・TOTP・ハードウェアトークンも要る）。
+/// キーチェーンの鍵はそれらを飛ばして金庫を開けてしまうので、覚えさせない・使わない
+pub fn check(u: &Unlocked) -> Result<()> {
+    let mut factors = Vec::new();
+    if u.keyfile_required() { factors.push("a key file"); }
+    if u.totp().is_some() { factors.push("a TOTP code"); }
+    if u.token().is_some() { factors.push("a hardware token"); }
+    if factors.is_empty() { return Ok(()); }
+    Err(err!(InvalidInput, "this vault also needs {}, which a key in the OS keychain would skip; use `rustpass unlock` (the agent) instead", factors.join(" and ")))
+}
+
+/// 今の金庫のデータ鍵をキーチェーンに保存する
+pub fn remember(key: &DataKey) -> Result<()> {
+    let vault = vault_path()?;
+    entry(&vault)?.set_secret(key.as_ref()).map_err(|e| anyhow!("OS keychain: {e}"))?;
+    fs::write(marker(&vault), b"")?;
+    Ok(())
+}
+
+fn lookup(vault: &Path) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    match entry(vault)?.get_secret() {
+        Ok(secret) => Ok(Some(Zeroizing::new(secret))),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!("OS keychain: {e}")),
+    }
+}
+
+/// 覚えさせた鍵（無い・読めないときは理由を表示して None）
+pub fn key() -> Option<DataKey> {
+    let vault = vault_path().ok()?;
+    if !marker(&vault).exists() { return None; }
+    match lookup(&vault) {
+        Ok(Some(secret)) => DataKey::from_slice(&secret),
+        Ok(None) => {
+            eprintln!("note: the key is no longer in the OS keychain; run `rustpass forget` to stop looking for it");
+            None
+        }
+        Err(e) => {
+            eprintln!("note: remembered key not used ({e})");
+            None
+        }
+    }
+}
+
+/// キーチェーンから今の金庫の鍵を消す（覚えさせていなければ false）
+pub fn forget() -> Result<bool> {
+    let vault = vault_path()?;
+    let marked = marker(&vault).exists();
+    let removed = match entry(&vault)?.delete_credential() {
+        Ok(()) => true,
+        Err(keyring::Error::NoEntry) => false,
+        // 覚えさせていなければ、キーチェーンが使えなくても消すものは無い
+        Err(_) if !marked => false,
+        Err(e) => return Err(anyhow!("OS keychain: {e}")),
+    };
+    if marked { fs::remove_file(marker(&vault))?; }
+    Ok(marked || removed)
+}
@@ -0,0 +1,30 @@
+// キーボード配列をまたいで同じキー・同じシフト状態で入力できる文字
+//
+// BIOS・コンソール・ホテルの端末などで配列が分からなくても入力できるパスワード用。
+// 英字と数字はどの配列でも同じ位置にあるので、記号だけを配列ごとに判定する。
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Layout {
+    /// 英語 (US)
+    Us,
+    /// 日本語 (JIS)
+    Jp,
+    /// US International（' " ` ~ ^ がデッドキー）
+    Intl,
+}
+
+// US配列と同じ位置・シフト状態で入力される記号
+fn stable_symbols(layout: Layout) -> &'static str {
+    match layout {
+        Layout::Us => "!@#$%^&*()-_=+[]{};:'\",.<>/?`~\\|",
+        // JIS は Shift+数字の記号と [ ] : ' " @ \ などが別の位置にある
+        Layout::Jp => "!#$%-;,.<>/?",
+        Layout::Intl => "!@#$%&*()-_=+[]{};:,.<>/?\\|",
+    }
+}
+
+/// 選んだ全ての配列で同じキーにある文字か
+pub fn is_safe(c: char, layouts: &[Layout]) -> bool {
+    c.is_ascii_alphanumeric() || layouts.iter().all(|&l| stable_symbols(l).contains(c))
+}
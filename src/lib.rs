@@ -0,0 +1,374 @@
+//! rustpass のコア（金庫の形式・エントリの型・パスワード生成）
+//!
+//! CLI（`rustpass` バイナリ）もこのライブラリを使っている。自分のツールから金庫を読み書きする場合は
+//! [`decrypt_vault`] / [`encrypt_vault`] を使う。金庫をファイル以外（アプリのデータベースなど）に
+//! 置く場合は [`storage::VaultStorage`] を実装して [`load_vault`] / [`store_vault`] に渡す。
+//! 鍵スロット（TOTP・クォーラム）や KDFパラメータを細かく扱う場合は [`format`](mod@format) を直接使う。
+//!
+//! ```no_run
+//! use rustpass_core::{decrypt_vault, encrypt_vault, generate_password, Avoid, Entry};
+//!
+//! let data = std::fs::read("vault.bin")?;
+//! let mut vault = decrypt_vault(&data, "master password")?;
+//! let mut e = Entry::new("example".into());
+//! e.password = generate_password(20, true, false, &[], Avoid::default())?;
+//! vault.entries.push(e);
+//! std::fs::write("vault.bin", encrypt_vault(&vault, "master password")?)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use anyhow::Result;
+use rand::{rngs::OsRng, Rng};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zeroize::{Zeroize, Zeroizing};
+
+use error::err;
+use storage::VaultStorage;
+
+pub mod age;
+pub mod attachment;
+pub mod canary;
+pub mod card;
+pub mod cert;
+pub mod derive;
+pub mod error;
+pub mod format;
+pub mod identity;
+pub mod layout;
+pub mod passkey;
+pub mod policy;
+pub mod secure;
+pub mod shamir;
+pub mod sshkey;
+pub mod storage;
+pub mod totp;
+pub mod trash;
+
+/// 金庫の1エントリ
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entry {
+    pub id: String,
+    pub name: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub ssh_key: Option<sshkey::SshKey>,
+    #[serde(default)]
+    pub cert: Option<cert::Certificate>,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    /// TOTP（2段階認証）の設定
+    #[serde(default)]
+    pub otp: Option<totp::Otp>,
+    /// 決定的導出の設定（パスワードは保存しない）
+    #[serde(default)]
+    pub derive: Option<derive::Profile>,
+    /// パスキーの登録情報（秘密鍵は認証器にあり、保存しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passkey: Option<passkey::Passkey>,
+    /// クレジットカード
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card: Option<card::Card>,
+    /// 個人情報
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<identity::Identity>,
+    /// セキュアメモ（本文は notes）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secure_note: bool,
+    /// 添付ファイル
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<attachment::Attachment>,
+    /// パスワードの有効期限（YYYY-MM-DD）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// パスワードの最大年齢（日数。設定の max_password_age より優先）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u32>,
+    /// パスワードを最後に変更した日時（パスワードの年齢の基準。無い古いエントリは updated_at で代用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_changed_at: Option<String>,
+    /// 自動入力のシーケンス（未設定なら {USERNAME}{TAB}{PASSWORD}{ENTER}）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autotype: Option<String>,
+    /// おとりのエントリ（参照されたら記録してフックを実行）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub canary: bool,
+    pub updated_at: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// 最終アクセス日時（設定 track_access = true の場合のみ記録）
+    #[serde(default)]
+    pub last_accessed: Option<String>,
+}
+
+/// エントリの種類（保存はせず、持っている値から決まる）
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    Login,
+    Note,
+    Card,
+    Identity,
+    SshKey,
+    Certificate,
+    Passkey,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Login => "login",
+            Kind::Note => "secure note",
+            Kind::Card => "card",
+            Kind::Identity => "identity",
+            Kind::SshKey => "ssh key",
+            Kind::Certificate => "certificate",
+            Kind::Passkey => "passkey",
+        }
+    }
+}
+
+/// エントリのカスタムフィールド
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+    /// 値がJSONドキュメントか（--query で参照可能）
+    #[serde(default)]
+    pub json: bool,
+    /// 秘密の値か（セキュリティの質問の答え・APIキー・暗証番号など。--show のときだけ表示）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+impl Entry {
+    /// 新しいID・作成日時で空のエントリを作る
+    pub fn new(name: String) -> Self {
+        Entry {
+            id: Uuid::new_v4().to_string(),
+            name,
+            username: String::new(),
+            password: String::new(),
+            url: None, notes: None,
+            tags: Vec::new(),
+            ssh_key: None,
+            cert: None,
+            fields: Vec::new(),
+            otp: None,
+            derive: None,
+            passkey: None,
+            card: None,
+            identity: None,
+            secure_note: false,
+            attachments: Vec::new(),
+            expires_at: None,
+            max_age_days: None,
+            password_changed_at: None,
+            autotype: None,
+            canary: false,
+            updated_at: now_iso(),
+            created_at: Some(now_iso()),
+            last_accessed: None,
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        if self.card.is_some() { Kind::Card }
+        else if self.identity.is_some() { Kind::Identity }
+        else if self.ssh_key.is_some() { Kind::SshKey }
+        else if self.cert.is_some() { Kind::Certificate }
+        else if self.passkey.is_some() { Kind::Passkey }
+        else if self.secure_note { Kind::Note }
+        else { Kind::Login }
+    }
+
+    /// パスワードを置き換え、変わったときは変更日時を記録する（updated_at は呼び出し側で更新する）
+    pub fn set_password(&mut self, password: String) {
+        if password != self.password {
+            self.password = password;
+            self.password_changed_at = Some(now_iso());
+        }
+    }
+
+    /// 名前の衝突を伝えるときの説明（種類・ユーザー名・更新日）
+    pub fn summary(&self) -> String {
+        let mut s = self.kind().label().to_string();
+        if !self.username.is_empty() { s.push_str(&format!(", user {}", self.username)); }
+        s.push_str(&format!(", updated {}", self.updated_at.get(..10).unwrap_or(&self.updated_at)));
+        s
+    }
+
+    /// 秘密の値（パスワード・メモ・カスタムフィールド・SSH秘密鍵・証明書の秘密鍵・TOTPシークレット・カード番号・添付ファイルなど）を消去する
+    pub fn wipe(&mut self) {
+        self.password.zeroize();
+        if let Some(n) = &mut self.notes { n.zeroize(); }
+        for f in &mut self.fields { f.value.zeroize(); }
+        if let Some(k) = &mut self.ssh_key { k.private_key.zeroize(); }
+        if let Some(c) = &mut self.cert { c.key_pem.zeroize(); }
+        if let Some(o) = &mut self.otp { o.secret.zeroize(); }
+        for a in &mut self.attachments { a.data.zeroize(); }
+        if let Some(c) = &mut self.card {
+            c.number.zeroize();
+            if let Some(s) = &mut c.cvv { s.zeroize(); }
+            if let Some(s) = &mut c.pin { s.zeroize(); }
+        }
+    }
+}
+
+/// 金庫の中身（暗号化される平文）
+#[derive(Serialize, Deserialize, Default)]
+pub struct Vault {
+    pub entries: Vec<Entry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<policy::Policy>,
+    /// カナリアエントリの参照記録
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub canary_events: Vec<canary::Event>,
+    /// 削除・上書きしたエントリ（保持期間を過ぎると消去）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trash: Vec<trash::Trashed>,
+}
+
+// 破棄するときにエントリの秘密の値を消去する
+impl Drop for Vault {
+    fn drop(&mut self) {
+        for e in &mut self.entries { e.wipe(); }
+        for t in &mut self.trash { t.entry.wipe(); }
+    }
+}
+
+/// 現在時刻（UTC、RFC 3339）
+pub fn now_iso() -> String {
+    OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap()
+}
+
+// パスワードだけで開く（TOTP・クォーラムの入力は求めない）
+struct Password(Zeroizing<String>);
+
+impl format::Credentials for Password {
+    fn password(&mut self) -> Result<Zeroizing<String>> {
+        Ok(self.0.clone())
+    }
+
+    fn totp_code(&mut self) -> Result<String> {
+        Err(err!(InvalidTotp, "vault requires a TOTP code; use format::unlock with your own Credentials"))
+    }
+}
+
+/// 金庫ファイルの内容をマスターパスワードで復号する
+///
+/// TOTP を要求する金庫やクォーラム専用の金庫は開けない（[`format::unlock`] を使う）。
+/// 失敗した場合のエラーは [`error::kind_of`] で種別（パスワード違い・破損など）を取り出せる。
+pub fn decrypt_vault(data: &[u8], password: &str) -> Result<Vault> {
+    let (vault, _) = format::unlock(data, &mut Password(Zeroizing::new(password.to_string())))?;
+    Ok(vault)
+}
+
+/// 金庫をマスターパスワードで暗号化し、ファイルに書く内容を返す
+///
+/// 呼び出すたびに新しいデータ鍵・ソルト・ノンスを使い、KDFは既定のパラメータになる。
+/// 既存の金庫の鍵スロット（TOTP・クォーラム）を保ったまま保存する場合は [`format::seal`] を使う。
+pub fn encrypt_vault(vault: &Vault, password: &str) -> Result<Vec<u8>> {
+    if password.is_empty() { return Err(err!(InvalidInput, "master password is empty")); }
+    format::seal(vault, &format::create(password, format::KdfParams::default())?)
+}
+
+/// 保存先から金庫を読み、マスターパスワードで復号する（まだ保存されていなければ None）
+///
+/// 開けない場合は [`decrypt_vault`] と同じ。
+pub fn load_vault(storage: &dyn VaultStorage, password: &str) -> Result<Option<Vault>> {
+    storage.load()?.map(|data| decrypt_vault(&data, password)).transpose()
+}
+
+/// 金庫をマスターパスワードで暗号化して保存先に書く
+///
+/// [`encrypt_vault`] と同じく鍵スロットは作り直す。[`format::unlock`] で開いた金庫を鍵スロットごと
+/// 保存する場合は、[`format::seal`] の結果を [`VaultStorage::store`] に渡す。
+pub fn store_vault(storage: &dyn VaultStorage, vault: &Vault, password: &str) -> Result<()> {
+    storage.store(&encrypt_vault(vault, password)?)
+}
+
+/// 古いシステムが拒否する並び（生成後に判定し、該当すれば作り直す）
+#[derive(Clone, Copy, Default)]
+pub struct Avoid {
+    /// 同じ文字の連続（aa、11）
+    pub repeat: bool,
+    /// 3文字以上の連番・キーボードの並び（abc、321、qwe）
+    pub sequences: bool,
+}
+
+const KEY_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+impl Avoid {
+    pub fn rejects(&self, pw: &[u8]) -> bool {
+        if self.repeat && pw.windows(2).any(|w| w[0] == w[1]) { return true; }
+        if !self.sequences { return false; }
+        pw.windows(3).any(|w| {
+            let w: Vec<u8> = w.iter().map(u8::to_ascii_lowercase).collect();
+            let same_class = w.iter().all(u8::is_ascii_digit) || w.iter().all(u8::is_ascii_lowercase);
+            let step = |d: i16| w.windows(2).all(|p| p[1] as i16 - p[0] as i16 == d);
+            let rev: Vec<u8> = w.iter().rev().copied().collect();
+            let on_row = |s: &[u8]| KEY_ROWS.iter().any(|r| r.as_bytes().windows(3).any(|x| x == s));
+            (same_class && (step(1) || step(-1))) || on_row(&w) || on_row(&rev)
+        })
+    }
+}
+
+// 条件を満たす候補が出るまでの試行回数の上限
+const MAX_REROLLS: usize = 10_000;
+
+/// ランダムパスワードを生成する（小文字・大文字・数字・記号の各カテゴリから最低1文字）
+///
+/// `allow_ambiguous` が false なら O/0・l/1 のような紛らわしい文字を除く。
+/// `layouts` を指定すると、記号はそのすべてのキーボード配列で同じキーにあるものだけを使う。
+pub fn generate_password(len: usize, use_symbols: bool, allow_ambiguous: bool, layouts: &[layout::Layout], avoid: Avoid) -> Result<String> {
+    if len < 4 { return Err(err!(InvalidInput, "len must be >= 4")); }
+
+    let mut lower = "abcdefghijklmnopqrstuvwxyz".to_string();
+    let mut upper = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string();
+    let mut digits = "0123456789".to_string();
+    let mut symbols = "!@#$%^&*()-_=+[]{};:,.<>/?~".to_string();
+
+    if !allow_ambiguous {
+        let ambiguous = "O0o1lI|`'\"{}[]()/\\;:.,<>";
+        let strip = |s: &mut String| s.retain(|c| !ambiguous.contains(c));
+        strip(&mut lower); strip(&mut upper); strip(&mut digits);
+        if use_symbols { strip(&mut symbols); }
+    }
+    symbols.retain(|c| layout::is_safe(c, layouts));
+
+    let mut pools: Vec<Vec<u8>> = vec![
+        lower.as_bytes().to_vec(),
+        upper.as_bytes().to_vec(),
+        digits.as_bytes().to_vec(),
+    ];
+    if use_symbols { pools.push(symbols.as_bytes().to_vec()); }
+    if pools.iter().any(|p| p.is_empty()) {
+        return Err(err!(InvalidInput, "character pool empty; try --allow-ambiguous or disable --symbols"));
+    }
+
+    let mut all = Vec::new();
+    for p in &pools { all.extend_from_slice(p); }
+
+    let mut rng = OsRng;
+    for _ in 0..MAX_REROLLS {
+        let mut bytes: Vec<u8> = Vec::with_capacity(len);
+        for p in &pools {
+            let idx = rng.gen_range(0..p.len());
+            bytes.push(p[idx]);
+        }
+        for _ in bytes.len()..len {
+            let idx = rng.gen_range(0..all.len());
+            bytes.push(all[idx]);
+        }
+        bytes.shuffle(&mut rng);
+        if !avoid.rejects(&bytes) { return Ok(String::from_utf8(bytes)?); }
+    }
+    Err(err!(InvalidInput, "could not generate a password without repeats/sequences; use a larger character set or a shorter length"))
+}
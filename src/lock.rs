@@ -0,0 +1,106 @@
+// 金庫ファイルの排他制御（vault.bin.lock へのアドバイザリロック）
+//
+// 読むときは共有ロックを取り、ロックを解除して開けたら、コマンドの終わりまで排他ロックを持つ
+// （パスワードの入力中は他のプロセスを待たせない。待っている間に書き換えられていたら読み直す）。
+// 保存時には排他ロックの中で「読んだときから変わっていないか」も確かめ、別のプロセスの変更を
+// 黙って上書きしない。
+// 金庫ファイルそのものは一時ファイルからの rename で置き換わるので、ロックは別のファイルに掛ける。
+use crate::backup::sha256_hex;
+use crate::error::err;
+use anyhow::{anyhow, Result};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 他のプロセスのロックが外れるのを待つ時間
+const WAIT: Duration = Duration::from_secs(10);
+
+// このプロセスが最後に読んだ（または書いた）金庫の SHA-256
+static LOADED: Mutex<Option<String>> = Mutex::new(None);
+
+// コマンドの終わりまで持つ排他ロック（プロセスの終了で解放される）
+static HELD: Mutex<Option<Guard>> = Mutex::new(None);
+
+// 同じプロセスで同じファイルをもう一度ロックすると自分を待ち続けるので、持っていれば取り直さない
+fn held() -> bool {
+    HELD.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+fn lock_path(vault: &Path) -> PathBuf {
+    let mut s = vault.as_os_str().to_owned();
+    s.push(".lock");
+    PathBuf::from(s)
+}
+
+/// ロックを持っている間だけ生きる（drop で解放）
+pub struct Guard(#[allow(dead_code)] File);
+
+fn acquire(vault: &Path, exclusive: bool) -> Result<Guard> {
+    let path = lock_path(vault);
+    let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)
+        .map_err(|e| anyhow!("cannot open the lock file {}: {e}", path.display()))?;
+    let deadline = Instant::now() + WAIT;
+    let mut noted = false;
+    loop {
+        let r = if exclusive { file.try_lock() } else { file.try_lock_shared() };
+        match r {
+            Ok(()) => return Ok(Guard(file)),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                if !noted {
+                    eprintln!("note: waiting for another rustpass process to finish with the vault...");
+                    noted = true;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(err!(Locked, "vault is locked by another process (waited {}s); try again when it finishes", WAIT.as_secs()));
+            }
+            Err(TryLockError::Error(e)) => return Err(anyhow!("cannot lock {}: {e}", path.display())),
+        }
+    }
+}
+
+/// 共有ロックの中で金庫を読み、保存時の確認のために内容を覚えておく
+pub fn read(vault: &Path) -> Result<Vec<u8>> {
+    let _guard = if held() { None } else { Some(acquire(vault, false)?) };
+    let data = fs::read(vault)?;
+    *LOADED.lock().unwrap_or_else(|e| e.into_inner()) = Some(sha256_hex(&data));
+    Ok(data)
+}
+
+/// このコマンドの終わりまで排他ロックを持つ。read の後に書き換えられていれば新しい内容を返す
+pub fn hold(vault: &Path) -> Result<Option<Vec<u8>>> {
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    if held.is_some() { return Ok(None); }
+    let guard = acquire(vault, true)?;
+    let data = fs::read(vault)?;
+    let hash = sha256_hex(&data);
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    let changed = loaded.as_deref() != Some(hash.as_str());
+    *loaded = Some(hash);
+    *held = Some(guard);
+    Ok(changed.then_some(data))
+}
+
+/// hold したロックを手放す（serve のように長く動き続けるコマンドが、要求ごとにロックを取り直す前に呼ぶ）
+pub fn release() {
+    *HELD.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// 保存用の排他ロックを取り（hold していればそれを使う）、読んだときから金庫が変わっていないことを確かめる
+pub fn for_save(vault: &Path) -> Result<Option<Guard>> {
+    let guard = if held() { None } else { Some(acquire(vault, true)?) };
+    let loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let (Some(loaded), Ok(current)) = (loaded, fs::read(vault)) {
+        if sha256_hex(&current) != loaded {
+            return Err(err!(Conflict, "the vault was changed by another process after this command read it; nothing was saved"));
+        }
+    }
+    Ok(guard)
+}
+
+/// 保存した内容を覚え直す（同じコマンドの中で続けて保存できるように）
+pub fn saved(data: &[u8]) {
+    *LOADED.lock().unwrap_or_else(|e| e.into_inner()) = Some(sha256_hex(data));
+}
@@ -1,272 +1,3190 @@
 use anyhow::{anyhow, Result};
-use argon2::{Argon2, Algorithm, Params, Version};
-use chacha20poly1305::{aead::{Aead, KeyInit}, ChaCha20Poly1305, Key, Nonce};
-use clap::{Parser, Subcommand};
-use rand::{rngs::OsRng, Rng};
-use rand::seq::SliceRandom;
-use rpassword::prompt_password;
-use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf, io::{self, Write}};
-use time::OffsetDateTime;
-use uuid::Uuid;
-use zeroize::Zeroize;
-
-const MAGIC: &[u8] = b"RPSS";
-const VERSION: u8 = 1;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::{fs, path::PathBuf, io::{self, IsTerminal, Read}};
+use zeroize::{Zeroize, Zeroizing};
+
+use rustpass_core::{age, attachment, canary, card, cert, derive, error, format, identity, layout, passkey, policy, sshkey, totp, trash};
+use rustpass_core::storage::VaultStorage;
+use rustpass_core::{generate_password, now_iso, Avoid, Entry, Field, Kind, Vault};
+use error::err;
+
+mod aegis;
+mod agefile;
+mod agent;
+mod audit;
+mod autotype;
+mod backup;
+mod bench;
+mod browser;
+mod clipboard;
+mod completions;
+mod config;
+mod dedupe;
+mod domain;
+mod exec;
+mod export;
+mod generator;
+mod hardware;
+mod hibp;
+mod http;
+mod import;
+mod keychain;
+mod lock;
+mod merge;
+mod mfa;
+mod nextcloud;
+mod output;
+mod passphrase;
+mod passstore;
+mod perms;
+mod menu;
+mod picker;
+mod prompt;
+mod query;
+mod readonly;
+mod salvage;
+mod scrypt;
+mod search;
+mod serve;
+mod share;
+mod spell;
+mod strength;
+mod status;
+mod remote;
+mod sync;
+mod tui;
+mod vaults;
 
 #[derive(Parser)]
 #[command(name="rustpass", about="Local-only password vault (Rust)")]
 struct Cli {
+    /// 出力をJSONにする（list / get / find / audit など。秘密は --show のときだけ）。エラーも code / message / hint のJSONで標準エラー出力に出す
+    #[arg(long, global = true)] json: bool,
+    /// 設定の min_kdf より弱いKDFパラメータの金庫も開く
+    #[arg(long, global = true)] accept_weaker_kdf: bool,
+    /// スクリーンリーダー向けの出力（表・記号に頼らず、件数を先に読み上げる）
+    #[arg(long, global = true)] accessible: bool,
+    /// マスターパスワードをファイルの1行目から読む（スクリプト・CI 用。環境変数 RUSTPASS_PASSWORD も可）
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "password_fd")] password_file: Option<PathBuf>,
+    /// マスターパスワードを開いているファイル記述子から読む（例: --password-fd 3 3<secret）
+    #[arg(long, global = true, value_name = "N")] password_fd: Option<u32>,
+    /// 入力を求めずにエラーにする（足りない入力は --password-file などや各オプションで渡す）
+    #[arg(long, global = true)] non_interactive: bool,
+    /// 金庫のキーファイル（new では無ければ作成し、パスワードに加えて必要にする）
+    #[arg(long, global = true, value_name = "PATH")] keyfile: Option<PathBuf>,
+    /// age の秘密鍵ファイル（受信者あてに暗号化した金庫を開く。age -p で暗号化したものも可。環境変数 RUSTPASS_IDENTITY も可）
+    #[arg(long, global = true, value_name = "PATH")] identity: Option<PathBuf>,
+    /// 使う金庫の名前またはパス（環境変数 RUSTPASS_VAULT も可。省略時は既定の金庫）
+    #[arg(long, global = true, value_name = "NAME|PATH")] vault: Option<String>,
+    /// 金庫を書き換えるコマンドをすべてエラーにする（設定の read_only と同じ）
+    #[arg(long, global = true)] read_only: bool,
     #[command(subcommand)] cmd: Cmd
 }
 
+// 起動時に1つ作るだけなので、add の種類ごとの引数で大きくなっても構わない
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Cmd {
     /// 新規ボールトを作成
-    New,
-    /// エントリ追加（--genでランダム生成して保存）
+    New {
+        /// ハードウェアトークンの応答も必要にする（yubikey / yubikey:1 / yubikey:2 / fido2）
+        #[arg(long, value_name = "TOKEN")] token: Option<String>,
+        /// Argon2id のメモリコスト（MiB。省略時は設定の kdf、無ければ m=64MiB t=3 p=1）
+        #[arg(long, value_name = "MIB")] kdf_memory: Option<u32>,
+        /// Argon2id の反復回数（省略時は設定の kdf、無ければ m=64MiB t=3 p=1）
+        #[arg(long, value_name = "N")] kdf_time: Option<u32>,
+        /// Argon2id の並列度（省略時は設定の kdf、無ければ m=64MiB t=3 p=1）
+        #[arg(long, value_name = "N")] kdf_parallelism: Option<u32>,
+        /// パスワードの代わりに age の受信者（age1...）あてに暗号化する（複数指定可。秘密鍵ファイルで開く）
+        #[arg(long = "age-recipient", value_name = "RECIPIENT", conflicts_with_all = ["token", "kdf_memory", "kdf_time", "kdf_parallelism"])]
+        age_recipients: Vec<String>,
+    },
+    /// 名前付きの金庫の一覧・作成・削除
+    Vaults {
+        #[command(subcommand)] cmd: VaultsCmd,
+    },
+    /// エントリ追加（--genでランダム生成して保存）。add note / card / identity / ssh-key で他の種類
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Add {
-        name: String,
+        #[command(subcommand)] item: Option<AddItem>,
+        #[arg(required = true)] name: Option<String>,
         #[arg(short, long)] user: Option<String>,
+        #[arg(long)] url: Option<String>,
+        #[arg(long)] notes: Option<String>,
+        /// タグ（複数指定またはカンマ区切り）
+        #[arg(long = "tag", value_name = "TAG", value_delimiter = ',')] tags: Vec<String>,
         #[arg(long)] gen: bool,
-        #[arg(long, default_value_t = 20)] len: usize,
+        /// 長さ（省略時は設定の [generator] length、無ければ20）
+        #[arg(long)] len: Option<usize>,
+        /// 記号を含める（設定の [generator] symbols を --no-symbols で打ち消せる）
         #[arg(long)] symbols: bool,
+        #[arg(long, conflicts_with = "symbols")] no_symbols: bool,
         #[arg(long)] allow_ambiguous: bool,
+        /// 入力したパスワードが弱ければ保存しない（既定は警告のみ）
+        #[arg(long, conflicts_with = "gen")] strict: bool,
+        /// パスワードの有効期限（YYYY-MM-DD、または今日からの期間 90d など）
+        #[arg(long, value_name = "DATE")] expires: Option<String>,
+        /// パスワードの最大年齢（例: 90d。設定の max_password_age より優先）
+        #[arg(long, value_name = "DURATION")] max_age: Option<String>,
+        /// 同じ名前のエントリがあれば置き換える（元のエントリはゴミ箱へ）
+        #[arg(long)] force: bool,
+        /// 文字ではなく単語のパスフレーズを生成（単語数。テレビやスマホで入力しやすい）
+        #[arg(long, requires = "gen", conflicts_with_all = ["symbols", "allow_ambiguous"])] words: Option<usize>,
+        /// ワードリスト: eff-large | eff-short | jp-romaji | ファイルパス
+        #[arg(long, default_value = passphrase::DEFAULT_WORDLIST, requires = "words")] wordlist: String,
+        /// 単語の区切り文字（none で区切りなし）
+        #[arg(long, default_value = "-", requires = "words")] separator: String,
+        /// 各単語の先頭を大文字に
+        #[arg(long, requires = "words")] capitalize: bool,
+        /// ランダムな単語の末尾に数字を1桁付ける
+        #[arg(long, requires = "words")] include_number: bool,
+        /// 区切りなしで2語目以降の先頭を大文字に（camelCase）
+        #[arg(long, requires = "words", conflicts_with = "separator")] camel_case: bool,
     },
     /// 一覧表示
-    List,
-    /// 取得（--show でパスワード表示）
-    Get { name: String, #[arg(long)] show: bool },
-    /// ランダムパスワード生成のみ
+    List {
+        /// 作成・更新・最終アクセス日時も表示
+        #[arg(long)] long: bool,
+        /// タグで絞り込み（複数指定はすべてを持つもの。「親」は「親/子」のタグにも一致）
+        #[arg(long = "tag", value_name = "TAG", value_delimiter = ',')] tags: Vec<String>,
+        /// 種類で絞り込み
+        #[arg(long = "type", value_enum, value_name = "TYPE")] kind: Option<Kind>,
+        /// 構造化して出力（json / yaml / toml）
+        #[arg(long, value_enum, value_name = "FORMAT")] output: Option<output::Output>,
+    },
+    /// 名前・ユーザー名・URL・メモを検索（部分一致とあいまい一致、良い順に表示）
+    Find {
+        /// 検索語（複数の語はすべてに一致するものだけ）
+        #[arg(required = true)] query: Vec<String>,
+        /// 構造化して出力（json / yaml / toml）
+        #[arg(long, value_enum, value_name = "FORMAT")] output: Option<output::Output>,
+    },
+    /// 取得（別名 show。--show でパスワード表示、--peek で先頭と末尾のみ表示。名前を省略すると絞り込み検索してコピー）
+    #[command(visible_alias = "show")]
+    Get {
+        name: Option<String>,
+        /// 名前の代わりに URL で探す（登録可能ドメインが同じエントリのうち、ホストが一致するものを優先）
+        #[arg(long, value_name = "URL", conflicts_with = "name")] url: Option<String>,
+        #[arg(long, conflicts_with_all = ["peek", "spell"])] show: bool,
+        #[arg(long, conflicts_with = "spell")] peek: bool,
+        /// 1文字ずつNATOフォネティックで読み上げ表示
+        #[arg(long)] spell: bool,
+        /// 指定した項目（password / username / url / notes / otp）またはカスタムフィールドの値のみ表示
+        #[arg(long, conflicts_with_all = ["show", "peek", "spell"])] field: Option<String>,
+        /// JSONフィールドをjq風のパス式で参照（例: '.database.password'）
+        #[arg(long, requires = "field")] query: Option<String>,
+        /// --field の値だけを改行なしで出力（$(...) で使う。--json より優先）
+        #[arg(long, requires = "field", conflicts_with = "copy")] raw: bool,
+        /// ユーザー名・パスワード・現在のTOTPコードをまとめて出力（自動入力スクリプト向け。--json でJSON）
+        #[arg(long, conflicts_with_all = ["show", "peek", "spell", "field"])] login_bundle: bool,
+        /// 構造化して出力（json / yaml / toml。パスワードは --show のときだけ含める）
+        #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["peek", "spell", "field", "login_bundle"])]
+        output: Option<output::Output>,
+        /// 表示せずにパスワード（--field ならその値）をクリップボードへコピー
+        #[arg(short, long, conflicts_with_all = ["show", "peek", "spell", "login_bundle", "output"])] copy: bool,
+        /// クリップボードを消去するまでの秒数（省略時は設定の clipboard_timeout）
+        #[arg(long, value_name = "SECS", requires = "copy", value_parser = clap::value_parser!(u64).range(1..))] timeout: Option<u64>,
+    },
+    /// ランダムパスワード生成のみ（--words でパスフレーズ）
     Gen {
-        #[arg(long, default_value_t = 20)] len: usize,
+        /// 長さ（省略時は設定の [generator] length、無ければ20）
+        #[arg(long)] len: Option<usize>,
+        /// 記号を含める（設定の [generator] symbols を --no-symbols で打ち消せる）
         #[arg(long)] symbols: bool,
+        #[arg(long, conflicts_with = "symbols")] no_symbols: bool,
         #[arg(long)] allow_ambiguous: bool,
+        /// 単語数（指定するとパスフレーズを生成）
+        #[arg(long)] words: Option<usize>,
+        /// ワードリスト: eff-large | eff-short | jp-romaji | ファイルパス
+        #[arg(long, default_value = passphrase::DEFAULT_WORDLIST, requires = "words")] wordlist: String,
+        /// 単語の区切り文字（none で区切りなし）
+        #[arg(long, default_value = "-", requires = "words")] separator: String,
+        /// 各単語の先頭を大文字に
+        #[arg(long, requires = "words")] capitalize: bool,
+        /// ランダムな単語の末尾に数字を1桁付ける
+        #[arg(long, requires = "words")] include_number: bool,
+        /// 区切りなしで2語目以降の先頭を大文字に（camelCase）
+        #[arg(long, requires = "words", conflicts_with = "separator")] camel_case: bool,
+        /// 指定した配列（カンマ区切りで複数可）のどれでも同じキーにある文字だけを使う
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "LAYOUTS")] layout_safe: Vec<layout::Layout>,
+        /// 同じ文字を続けない
+        #[arg(long)] no_repeat: bool,
+        /// abc・321・qwe のような3文字以上の並びを含めない
+        #[arg(long)] no_sequences: bool,
+        /// 対話モード（キー操作で再生成・条件を変更し、確定またはコピー）
+        #[arg(short, long)] interactive: bool,
+    },
+    /// パスワードを一度だけ閲覧できるURLで共有（閲覧後/期限切れで終了）
+    ShareOnce {
+        name: String,
+        /// localhostではなくLANに公開
+        #[arg(long)] lan: bool,
+        #[arg(long, default_value_t = 0)] port: u16,
+        /// 有効期限（秒）
+        #[arg(long, default_value_t = 120)] ttl: u64,
+    },
+    /// エントリ1件を相手の公開鍵（age の受信者・SSH の公開鍵）あてに暗号化して書き出す（receive で取り込む）
+    Share {
+        name: String,
+        /// 宛先（age1...・"ssh-ed25519 AAAA..."・"ssh-rsa AAAA..."、またはそれを1行ずつ書いたファイル。複数指定可）
+        #[arg(long, required = true, value_name = "RECIPIENT|PATH")] to: Vec<String>,
+        /// 書き出し先（省略時は標準出力。ASCII armor 付きなのでチャットなどにそのまま貼れる）
+        #[arg(short, long, value_name = "PATH")] out: Option<PathBuf>,
+        /// 既存のファイルを上書きする
+        #[arg(long, requires = "out")] force: bool,
+    },
+    /// share で受け取ったファイルを復号してエントリを取り込む（秘密鍵は --identity で。- で標準入力）
+    Receive {
+        file: PathBuf,
+        /// 別の名前で取り込む
+        #[arg(long)] name: Option<String>,
+        /// 同じ名前のエントリがあれば置き換える（元のエントリはゴミ箱へ）
+        #[arg(long)] force: bool,
+    },
+    /// エントリの秘密を環境変数に入れてコマンドを実行（例: exec --entry db-prod --as DB_PASSWORD -- ./deploy.sh）
+    Exec {
+        /// 使うエントリ（--as と同じ順に対応。繰り返し指定可）
+        #[arg(long = "entry", value_name = "NAME", required = true)] entries: Vec<String>,
+        /// 環境変数名（VAR でパスワード、VAR=ITEM で username / url / notes / otp / カスタムフィールド）
+        #[arg(long = "as", value_name = "VAR[=ITEM]", required = true)] vars: Vec<String>,
+        /// 値を環境変数ではなく tmpfs 上の env ファイルに書き、そのパスを VAR（省略時 RUSTPASS_ENV_FILE）で渡す
+        #[arg(long, value_name = "VAR", num_args = 0..=1, default_missing_value = "RUSTPASS_ENV_FILE")] env_file: Option<String>,
+        /// 実行するコマンドと引数（-- の後に）
+        #[arg(last = true, required = true)] command: Vec<String>,
+    },
+    /// 金庫を監査（設定のパスワード年齢ポリシー違反など）
+    Audit {
+        #[command(subcommand)] cmd: Option<AuditCmd>,
+        /// 金庫のパスワードポリシーに違反するエントリも報告
+        #[arg(long)] policy: bool,
+        /// パスキーがまだ無いログインも報告
+        #[arg(long)] passkeys: bool,
+    },
+    /// バックアップ（スナップショット）の作成・検証
+    Backup {
+        #[command(subcommand)] cmd: BackupCmd,
+    },
+    /// git リポジトリを介して金庫を同期（暗号化されたまま pull・コミット・push）
+    Sync {
+        #[command(subcommand)] cmd: Option<SyncCmd>,
+        /// 両側で同じ項目が変更されたエントリを対話せずに解決する
+        #[arg(long, value_enum)] prefer: Option<merge::Prefer>,
+    },
+    /// SSH鍵ペアの生成・表示
+    Sshkey {
+        #[command(subcommand)] cmd: SshKeyCmd,
+    },
+    /// TLS証明書/秘密鍵の保存・書き出し
+    Cert {
+        #[command(subcommand)] cmd: CertCmd,
+    },
+    /// パスキーの登録情報（どのサイトにどの認証器で登録したか）
+    Passkey {
+        #[command(subcommand)] cmd: PasskeyCmd,
+    },
+    /// カスタムフィールドの操作
+    Field {
+        #[command(subcommand)] cmd: FieldCmd,
+    },
+    /// 添付ファイル（金庫と一緒に暗号化して保存）
+    Attach {
+        #[command(subcommand)] cmd: AttachCmd,
+    },
+    /// ロック解除にTOTPコードを要求する（2要素）
+    Mfa {
+        #[command(subcommand)] cmd: MfaCmd,
+    },
+    /// エントリの現在のTOTPコードを表示（--set でシークレットを保存）
+    Totp {
+        name: String,
+        /// 保存するシークレット（otpauth:// URI または Base32。- で非表示プロンプト）
+        #[arg(long, value_name = "URI|SECRET", conflicts_with = "remove")] set: Option<String>,
+        /// アルゴリズム（sha1 / sha256 / sha512）
+        #[arg(long, requires = "set")] algorithm: Option<totp::Algorithm>,
+        /// 桁数（6〜8）
+        #[arg(long, requires = "set", value_parser = clap::value_parser!(u32).range(6..=8))] digits: Option<u32>,
+        /// 有効期間（秒）
+        #[arg(long, requires = "set", value_parser = clap::value_parser!(u64).range(1..))] period: Option<u64>,
+        /// TOTPの設定を削除
+        #[arg(long)] remove: bool,
+    },
+    /// サイトとログインからパスワードを決定的に導出（LessPass互換。設定のみ金庫に保存）
+    Derive {
+        site: String,
+        login: String,
+        /// 長さ（5〜35、デフォルト16）
+        #[arg(long)] length: Option<usize>,
+        /// カウンタ（パスワード変更時に増やす、デフォルト1）
+        #[arg(long)] counter: Option<u32>,
+        /// 使用する文字種（カンマ区切り、デフォルトは全て）
+        #[arg(long, value_enum, value_delimiter = ',')] charset: Option<Vec<derive::CharClass>>,
+    },
+    /// エントリを編集（IDと作成日時は保持。項目を指定しなければ1項目ずつ対話的に尋ねる）
+    Edit {
+        name: String,
+        #[arg(short, long)] user: Option<String>,
+        /// 新しいパスワードを非表示プロンプトで入力
+        #[arg(long, conflicts_with = "gen")] password: bool,
+        /// 新しいパスワードをランダム生成
+        #[arg(long)] gen: bool,
+        /// 長さ（省略時は設定の [generator] length、無ければ20）
+        #[arg(long, requires = "gen")] len: Option<usize>,
+        /// 記号を含める（設定の [generator] symbols を --no-symbols で打ち消せる）
+        #[arg(long, requires = "gen")] symbols: bool,
+        #[arg(long, requires = "gen", conflicts_with = "symbols")] no_symbols: bool,
+        #[arg(long, requires = "gen")] allow_ambiguous: bool,
+        /// 空文字で削除
+        #[arg(long)] url: Option<String>,
+        /// 空文字で削除
+        #[arg(long)] notes: Option<String>,
+    },
+    /// エントリの名前を変える（IDと中身はそのまま。新しい名前が使われていればエラー）
+    Rename { old: String, new: String },
+    /// エントリをゴミ箱へ移す（確認あり）
+    Rm {
+        #[arg(required = true)] names: Vec<String>,
+        /// 確認せずに削除（スクリプト向け）
+        #[arg(short, long)] force: bool,
+    },
+    /// ゴミ箱（削除・上書きしたエントリ。設定の trash_retention の期間だけ残す）
+    Trash {
+        #[command(subcommand)] cmd: TrashCmd,
+    },
+    /// キーファイル（マスターパスワードに加えて必要なファイル）の設定
+    Keyfile {
+        #[command(subcommand)] cmd: KeyfileCmd,
+    },
+    /// age の鍵ペアを作る（age-keygen と同じ形式。受信者は new --age-recipient、秘密鍵は --identity に）
+    AgeKeygen {
+        /// 秘密鍵の書き出し先（0600。省略時は標準出力）
+        #[arg(short, long, value_name = "PATH")] output: Option<PathBuf>,
+    },
+    /// 新しいソルトとトークンのチャレンジで鍵スロットを作り直す（KDFの強化、--token でトークンを変更・none で外す）
+    Rekey {
+        #[arg(long, value_name = "TOKEN")] token: Option<String>,
+        /// Argon2id のメモリコスト（MiB。省略時は設定の kdf、無ければ今の値）
+        #[arg(long, value_name = "MIB")] kdf_memory: Option<u32>,
+        /// Argon2id の反復回数（省略時は設定の kdf、無ければ今の値）
+        #[arg(long, value_name = "N")] kdf_time: Option<u32>,
+        /// Argon2id の並列度（省略時は設定の kdf、無ければ今の値）
+        #[arg(long, value_name = "N")] kdf_parallelism: Option<u32>,
+    },
+    /// 古い形式（v1・v2）の金庫を最新の形式で書き直す（ヘッダ全体を認証する形式になる）
+    Migrate,
+    /// このマシンで目標のロック解除時間になるKDFパラメータを測って提案する
+    BenchKdf {
+        /// 目標の時間（ミリ秒）
+        #[arg(long, value_name = "MS", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))] target_ms: u64,
+        /// 使ってよいメモリの上限（MiB）
+        #[arg(long, value_name = "MIB", default_value_t = 1024, value_parser = clap::value_parser!(u32).range(1..))] max_memory: u32,
+        /// 並列度
+        #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))] parallelism: u32,
+    },
+    /// マスターパスワードを変更（新しいソルトで鍵スロットを作り直す）
+    #[command(visible_alias = "change-master")]
+    Passwd,
+    /// エントリの1項目だけを更新（username / password / url / notes / tags / autotype / expires / max_age / カスタムフィールド）
+    Set {
+        name: String,
+        key: String,
+        /// 値（- で password は非表示プロンプト、それ以外は標準入力から読む。空文字で削除）
+        value: String,
+    },
+    /// フォーカス中のウィンドウへユーザー名・パスワードなどを自動入力（xdotool / wtype / osascript / PowerShell を使用）
+    Autotype {
+        name: String,
+        /// 入力を始めるまでの秒数（その間に入力先のウィンドウを選ぶ）
+        #[arg(long, default_value_t = 3)] wait: u64,
+    },
+    /// エントリを選択メニュー（rofi / dmenu / fzf）で選び、パスワードをコピー（または自動入力）
+    Menu {
+        /// 使うメニュー（省略時は端末なら fzf、それ以外は rofi → dmenu の順に探し、無ければ端末内の選択画面）
+        #[arg(long, value_enum)] picker: Option<menu::Picker>,
+        /// エントリ名を1行ずつ出力するだけ（他のメニューやスクリプトに渡す用）
+        #[arg(long, conflicts_with_all = ["picker", "autotype", "timeout"])] print: bool,
+        /// コピーの代わりにフォーカス中のウィンドウへ自動入力（エントリのシーケンスを使用）
+        #[arg(long)] autotype: bool,
+        /// 自動入力を始めるまでの秒数
+        #[arg(long, default_value_t = 0, requires = "autotype")] wait: u64,
+        /// クリップボードを消去するまでの秒数（省略時は設定の clipboard_timeout）
+        #[arg(long, value_name = "SECS", conflicts_with = "autotype", value_parser = clap::value_parser!(u64).range(1..))] timeout: Option<u64>,
+    },
+    /// おとりのエントリ（参照されたら記録し、設定のフックを実行）
+    Canary {
+        #[command(subcommand)] cmd: CanaryCmd,
+    },
+    /// 複数メンバーのパスワードを揃えてロック解除（K-of-N）
+    Quorum {
+        #[command(subcommand)] cmd: QuorumCmd,
+    },
+    /// 金庫を開ける age の受信者（チームのメンバー）を追加・削除・一覧
+    Members {
+        #[command(subcommand)] cmd: MembersCmd,
+    },
+    /// エントリのタグを追加・削除・一覧
+    Tag {
+        #[command(subcommand)] cmd: TagCmd,
+    },
+    /// 金庫のパスワードポリシー（保存時に強制）
+    Policy {
+        #[command(subcommand)] cmd: PolicyCmd,
+    },
+    /// 重複していそうなエントリを探して対話的に統合
+    Dedupe {
+        /// 候補を表示するだけで変更しない
+        #[arg(long)] dry_run: bool,
+    },
+    /// 別の金庫ファイル（同期の競合コピーなど）を統合
+    Merge {
+        path: PathBuf,
+        /// 両側で変更されたエントリを対話せずに解決する
+        #[arg(long, value_enum)] prefer: Option<merge::Prefer>,
+    },
+    /// 壊れた金庫ファイルから読めるエントリを取り出して新しい金庫に書く（元のファイルは変更しない）
+    Salvage {
+        path: PathBuf,
+        /// 書き出す金庫ファイル
+        #[arg(short, long)] out: PathBuf,
+        #[arg(long)] force: bool,
+    },
+    /// 他の形式へ書き出す
+    Export {
+        /// 省略時は --out の拡張子から（.rpss のみ）
+        #[arg(long, value_enum)] format: Option<export::Format>,
+        #[arg(short, long)] out: PathBuf,
+        /// rpss / html-vault: マスターパスワードではなく別のパスフレーズで暗号化
+        #[arg(long)] passphrase: bool,
+        /// pass: 暗号化に使うGPG鍵（複数可。省略時は --out の .gpg-id）
+        #[arg(long = "gpg-id", value_name = "KEY")] gpg_ids: Vec<String>,
+        /// 既存ファイルを上書き
+        #[arg(long)] force: bool,
+    },
+    /// 他のパスワードマネージャ・ブラウザから取り込む
+    Import {
+        #[arg(long, value_enum)] format: import::Format,
+        /// 取り込むファイル（firefox ではプロファイルディレクトリ、pass ではストアのディレクトリ）
+        path: PathBuf,
+        /// KeePass のキーファイル（kdbx のみ）
+        #[arg(long, value_name = "PATH")] kdbx_keyfile: Option<PathBuf>,
+        /// 同じ名前のエントリが既にある場合: rename（名前を変えて追加）/ skip / replace
+        #[arg(long, value_enum, default_value_t = import::Duplicates::Rename)] duplicates: import::Duplicates,
+        /// CSV の列の対応（csv のみ。例: name=Title,user=Login,pass=Password,url=Website,notes=Comments,totp=OTP,tags=Folder）
+        #[arg(long, value_name = "FIELD=COLUMN,...")] map: Option<String>,
+        /// 取り込む内容を表示するだけで保存しない
+        #[arg(long)] dry_run: bool,
+    },
+    /// 127.0.0.1 でHTTP APIを提供（秘密は返さず、クリップボードへのコピーのみ）
+    Serve {
+        /// 検索・閲覧・コピー用の読み取り専用Web UIも提供
+        #[arg(long)] web: bool,
+        /// 待ち受けポート（0で自動）
+        #[arg(long, default_value_t = 0)] port: u16,
+        /// 最後の要求から終了（ロック）までの秒数
+        #[arg(long, default_value_t = serve::DEFAULT_TIMEOUT)] timeout: u64,
+    },
+    /// 全画面のエントリブラウザ（絞り込み・詳細表示・コピー・編集・パスワード生成）
+    Tui,
+    /// 金庫の状態（形式・KDF・バックアップなど）を表示
+    Status {
+        /// ロック解除してエントリ数も表示
+        #[arg(long)] unlock: bool,
+    },
+    /// ロック解除した状態を保持するエージェント
+    Agent {
+        #[command(subcommand)] cmd: AgentCmd,
+    },
+    /// エージェントを起動してロック解除（agent start と同じ）。--remember で代わりに OS のキーチェーンに鍵を覚えさせる
+    Unlock {
+        /// 金庫のデータ鍵を OS のキーチェーンに保存し、以後パスワードを尋ねない（forget で取り消す。キーファイル・TOTP・トークンを使う金庫では不可）
+        #[arg(long)] remember: bool,
+        /// 最後に使われてからロックするまでの秒数（省略時は設定の [agent] timeout、無ければ900）
+        #[arg(long, conflicts_with = "remember")] timeout: Option<u64>,
+    },
+    /// エージェントを停止してすぐにロック（agent stop と同じ）
+    Lock,
+    /// unlock --remember で OS のキーチェーンに保存した鍵を消す
+    Forget,
+    /// エージェント本体（内部で使用）
+    #[command(hide = true)]
+    AgentServe {
+        #[arg(long, default_value_t = agent::DEFAULT_TIMEOUT)] timeout: u64,
+    },
+    /// ブラウザ拡張のネイティブメッセージングのホスト（ブラウザが起動する。エージェントの鍵で開く）
+    BrowserHost {
+        /// ブラウザが渡す呼び出し元（拡張のオリジンやマニフェストのパス）
+        #[arg(hide = true, trailing_var_arg = true, allow_hyphen_values = true)] caller: Vec<String>,
+    },
+    /// シェル補完スクリプトを出力（エントリ名の補完はエージェントが動いているときのみ）
+    Completions {
+        #[arg(value_enum)] shell: completions::Shell,
+    },
+    /// エントリ名の一覧（シェル補完用。エージェントが動いているときのみ、内部で使用）
+    #[command(name = "__complete-names", hide = true)]
+    CompleteNames,
+    /// クリップボード保持用（内部で使用）
+    #[command(hide = true)]
+    ClipboardHold {
+        #[arg(long, default_value_t = clipboard::DEFAULT_TIMEOUT)] timeout: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum VaultsCmd {
+    /// 作成済みの金庫を表示（* が今選んでいる金庫）
+    List,
+    /// 名前付きの金庫を作成（別のマスターパスワードを設定する。以降は --vault NAME で使う）
+    Create {
+        name: String,
+        /// ハードウェアトークンの応答も必要にする（yubikey / yubikey:1 / yubikey:2 / fido2）
+        #[arg(long, value_name = "TOKEN")] token: Option<String>,
+        /// Argon2id のメモリコスト（MiB。省略時は設定の kdf、無ければ m=64MiB t=3 p=1）
+        #[arg(long, value_name = "MIB")] kdf_memory: Option<u32>,
+        /// Argon2id の反復回数（省略時は設定の kdf、無ければ m=64MiB t=3 p=1）
+        #[arg(long, value_name = "N")] kdf_time: Option<u32>,
+        /// Argon2id の並列度（省略時は設定の kdf、無ければ m=64MiB t=3 p=1）
+        #[arg(long, value_name = "N")] kdf_parallelism: Option<u32>,
+        /// パスワードの代わりに age の受信者（age1...）あてに暗号化する（複数指定可。秘密鍵ファイルで開く）
+        #[arg(long = "age-recipient", value_name = "RECIPIENT", conflicts_with_all = ["token", "kdf_memory", "kdf_time", "kdf_parallelism"])]
+        age_recipients: Vec<String>,
+    },
+    /// 名前付きの金庫を削除（直前の版とバックアップも消える）
+    Delete {
+        name: String,
+        /// 確認せずに削除（スクリプト向け）
+        #[arg(short, long)] force: bool,
     },
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct Entry {
-    id: String,
-    name: String,
-    username: String,
-    password: String,
-    url: Option<String>,
-    notes: Option<String>,
-    updated_at: String,
+#[derive(Subcommand)]
+enum AgentCmd {
+    /// 金庫をロック解除してエージェントを起動（以降のコマンドはパスワード不要）
+    Start {
+        /// 最後に使われてからロックするまでの秒数（既定は設定の [agent] timeout、無ければ900）
+        #[arg(long)] timeout: Option<u64>,
+    },
+    /// エージェントを停止してロック
+    Stop,
+    /// 動作状況を表示
+    Status,
 }
 
-#[derive(Serialize, Deserialize, Default)]
-struct Vault { entries: Vec<Entry> }
+#[derive(Subcommand)]
+enum CanaryCmd {
+    /// エントリをカナリアにする
+    Mark { name: String },
+    /// カナリアの印を外す
+    Unmark { name: String },
+    /// カナリアのエントリを一覧
+    List,
+    /// 参照の記録を表示（--clear で消去）
+    Log {
+        #[arg(long)] clear: bool,
+    },
+}
 
-fn vault_path() -> Result<PathBuf> {
-    let base = dirs::data_local_dir().ok_or(anyhow!("data dir not found"))?;
-    let dir = base.join("rustpass");
-    fs::create_dir_all(&dir)?;
-    Ok(dir.join("vault.bin"))
+#[derive(Subcommand)]
+enum AuditCmd {
+    /// 全エントリのパスワードの強度を推定し、弱い順に表示
+    Strength {
+        /// 弱いもの（4段階で good 未満）だけ表示
+        #[arg(long)] weak: bool,
+    },
+    /// 古いパスワード・期限切れのパスワードを古い順に表示（エントリの期限と設定の max_password_age）
+    Age {
+        /// この期間より古いパスワードも表示（例: 90d、1y）
+        #[arg(long, value_name = "DURATION")] older_than: Option<String>,
+    },
+    /// 同じパスワードを使い回しているエントリと、ユーザー名・エントリ名と同じパスワードを報告
+    Reuse,
+    /// Have I Been Pwned で漏洩したパスワードを探す（SHA-1 の先頭5文字だけを送る）
+    Hibp {
+        /// ダウンロードしたデータで照合（ハッシュ順の "HASH:COUNT" ファイル、または先頭5文字ごとのファイルのディレクトリ）
+        #[arg(long, value_name = "PATH")] offline: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagCmd {
+    /// タグを追加（「親/子」の形でフォルダのように使える）
+    Add {
+        name: String,
+        #[arg(required = true)] tags: Vec<String>,
+    },
+    /// タグを削除
+    Remove {
+        name: String,
+        #[arg(required = true)] tags: Vec<String>,
+    },
+    /// 使われているタグと件数の一覧
+    List,
 }
 
-// マスターパスワードから鍵を導出（Argon2id）
-fn derive_key_from_password(password: &str, salt: &[u8], params: &Params) -> Result<[u8;32]> {
-    let argon = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone());
-      let mut key = [0u8; 32];
-      argon
-          .hash_password_into(password.as_bytes(), salt, &mut key)
-          .map_err(|e| anyhow!("argon2 hash_password_into failed: {e:?}"))?;
-      Ok(key)
+#[derive(Subcommand)]
+enum PolicyCmd {
+    /// ポリシーを設定（指定しなかった項目は無効になる）
+    Set {
+        #[arg(long)] min_length: Option<usize>,
+        /// 必須の文字種（カンマ区切り）
+        #[arg(long, value_enum, value_delimiter = ',')] require: Vec<derive::CharClass>,
+        /// 禁止する語（カンマ区切り、大文字小文字を区別しない）
+        #[arg(long, value_delimiter = ',')] ban: Vec<String>,
+        /// 他のエントリと同じパスワードを禁止
+        #[arg(long)] no_reuse: bool,
+    },
+    /// 現在のポリシーを表示
+    Show,
+    /// ポリシーを削除
+    Clear,
 }
 
+#[derive(Subcommand)]
+enum QuorumCmd {
+    /// メンバーごとのパスワードを登録し、任意のK人でロック解除できるようにする
+    Setup {
+        /// 必要な人数 K
+        #[arg(long)] threshold: u8,
+        /// メンバー名（N人分繰り返し指定）
+        #[arg(long = "member", required = true)] members: Vec<String>,
+        /// マスターパスワードでのロック解除を無効化（クォーラムのみで開く）
+        #[arg(long)] remove_password: bool,
+    },
+    /// クォーラムを解除（マスターパスワードが無ければ新たに設定）
+    Remove,
+}
 
-fn default_params() -> Params {
-    // 初期は控えめ。必要なら m/t を上げて総当たり耐性を強化
-    // m = 64 MiB, t = 3, p = 1
-    Params::new(64 * 1024, 3, 1, None).expect("argon2 params")
+#[derive(Subcommand)]
+enum MembersCmd {
+    /// 受信者（age1...）を追加し、データ鍵をその公開鍵あてに包む
+    Add {
+        recipient: String,
+        /// メンバー名（list に表示し、remove で指定できる）
+        #[arg(long)] name: Option<String>,
+    },
+    /// メンバーを外す（名前か受信者で指定）。残りが受信者だけならデータ鍵を作り直す
+    Remove { member: String },
+    /// メンバーの一覧（ヘッダから読むのでロック解除は不要）
+    List,
 }
 
-fn now_iso() -> String {
-    OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap()
+#[derive(Subcommand)]
+enum KeyfileCmd {
+    /// キーファイルを設定・変更（ファイルが無ければ64バイトの乱数で作成。変更時は今のものを --keyfile で）
+    Set { path: PathBuf },
+    /// キーファイルを外す（マスターパスワードだけで開けるようにする）
+    Remove,
 }
 
-fn encrypt_vault(vault: &Vault, password: &str, params: Params) -> Result<Vec<u8>> {
-    let mut salt = [0u8;16];
-    OsRng.fill(&mut salt);
-    let key_bytes = derive_key_from_password(password, &salt, &params)?;
-    let key = Key::from_slice(&key_bytes);
-    let cipher = ChaCha20Poly1305::new(key);
+#[derive(Subcommand)]
+enum MfaCmd {
+    /// 認証アプリにシークレットを登録して有効化
+    Enable,
+    /// 無効化（パスワードのみでロック解除）
+    Disable,
+}
 
-    let mut nonce_bytes = [0u8;12];
-    OsRng.fill(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
+#[derive(Subcommand)]
+enum FieldCmd {
+    /// フィールドを設定（値に - を指定すると標準入力から読み、省略すると伏せ字で尋ねる）
+    Set {
+        entry: String,
+        key: String,
+        value: Option<String>,
+        /// 値をJSONドキュメントとして検証・保存
+        #[arg(long)] json: bool,
+        /// 秘密の値にする（get では --show のときだけ表示。省略時は今の設定のまま）
+        #[arg(long, conflicts_with = "visible")] hidden: bool,
+        /// 秘密の値の設定を外す
+        #[arg(long)] visible: bool,
+    },
+    /// フィールドの値を表示（秘密の値は --show のときだけ）
+    Get {
+        entry: String,
+        key: String,
+        #[arg(long)] show: bool,
+        /// 表示せずにクリップボードへコピー
+        #[arg(short, long, conflicts_with = "show")] copy: bool,
+    },
+    /// フィールドを削除
+    Rm {
+        entry: String,
+        key: String,
+    },
+}
 
-    let plaintext = serde_json::to_vec(vault)?;
-    let ciphertext = cipher
-    .encrypt(nonce, plaintext.as_ref())
-    .map_err(|e| anyhow!("aead encrypt failed: {e:?}"))?;
+#[derive(Subcommand)]
+enum TrashCmd {
+    /// ゴミ箱の中身（消去されるまでの日数つき）
+    List,
+    /// エントリを金庫へ戻す（同じ名前が複数あれば最後に捨てたもの）
+    Restore {
+        name: String,
+    },
+    /// ゴミ箱を空にする（確認あり）
+    Empty {
+        /// 確認せずに消去（スクリプト向け）
+        #[arg(short, long)] force: bool,
+    },
+}
 
+#[derive(Subcommand)]
+enum AttachCmd {
+    /// ファイルを添付（1ファイル5MiB・金庫全体で25MiBまで）
+    Add {
+        entry: String,
+        file: PathBuf,
+        /// 添付ファイルの名前（省略時はファイル名）
+        #[arg(long)] name: Option<String>,
+        /// 同じ名前の添付ファイルを置き換える
+        #[arg(long)] force: bool,
+    },
+    /// 添付ファイルの一覧（エントリを省略すると金庫全体）
+    List {
+        entry: Option<String>,
+    },
+    /// 添付ファイルを書き出す（0600。--out - で標準出力）
+    Get {
+        entry: String,
+        name: String,
+        #[arg(long, value_name = "PATH")] out: PathBuf,
+        /// 既存のファイルを上書き
+        #[arg(long)] force: bool,
+    },
+    /// 添付ファイルを削除
+    Rm {
+        entry: String,
+        name: String,
+    },
+}
 
-    let mut out = Vec::with_capacity(4+1+4*3+16+12+ciphertext.len());
-    out.extend_from_slice(MAGIC);
-    out.push(VERSION);
-    out.extend_from_slice(&(params.m_cost() as u32).to_le_bytes());
-    out.extend_from_slice(&(params.t_cost() as u32).to_le_bytes());
-    out.extend_from_slice(&(params.p_cost() as u32).to_le_bytes());
-    out.extend_from_slice(&salt);
-    out.extend_from_slice(&nonce_bytes);
-    out.extend_from_slice(&ciphertext);
+#[derive(Subcommand)]
+enum PasskeyCmd {
+    /// パスキーの登録情報を追加
+    Add {
+        name: String,
+        /// Relying Party ID（例: github.com）
+        #[arg(long)] rp: String,
+        /// クレデンシャルID（base64url）
+        #[arg(long)] credential_id: String,
+        /// アカウント名
+        #[arg(long)] user: Option<String>,
+        /// ユーザーハンドル（base64url）
+        #[arg(long)] user_handle: Option<String>,
+        /// 保存先の認証器（例: "iCloud Keychain", "YubiKey 5C"）
+        #[arg(long)] authenticator: Option<String>,
+        /// バックアップ（同期）可能なパスキー
+        #[arg(long)] backup_eligible: bool,
+        /// 登録日（YYYY-MM-DD、省略時は今日）
+        #[arg(long)] created: Option<String>,
+    },
+    /// パスキーの一覧（サイトごと）
+    List,
+}
 
-    // 秘匿データの消去（最低限）
-    let mut pw = password.to_string();
-    pw.zeroize();
-    // key_bytes はスコープアウトで破棄
-    Ok(out)
+#[derive(Subcommand)]
+enum CertCmd {
+    /// PEMファイルから証明書エントリを追加
+    Add {
+        name: String,
+        #[arg(long)] cert: PathBuf,
+        #[arg(long)] key: PathBuf,
+        /// 中間証明書チェーン
+        #[arg(long)] chain: Option<PathBuf>,
+        /// 有効期限（YYYY-MM-DD、省略時は証明書から読み取り）
+        #[arg(long)] expires: Option<String>,
+    },
+    /// cert.pem / key.pem / chain.pem / fullchain.pem を書き出し
+    Export {
+        name: String,
+        #[arg(long, value_name = "DIR")] out: PathBuf,
+        /// 既存ファイルを上書き
+        #[arg(long)] force: bool,
+    },
 }
 
-fn decrypt_vault(data: &[u8], password: &str) -> Result<Vault> {
-    if data.len() < 4+1+4*3+16+12 { return Err(anyhow!("file too small")); }
-    if &data[..4] != MAGIC { return Err(anyhow!("bad magic")); }
-    if data[4] != VERSION { return Err(anyhow!("unsupported version")); }
-    let mut idx = 5;
-    let read_u32 = |i: usize| u32::from_le_bytes(data[i..i+4].try_into().unwrap());
-    let m = read_u32(idx) as u32; idx+=4;
-    let t = read_u32(idx) as u32; idx+=4;
-    let p = read_u32(idx) as u32; idx+=4;
-    let params = Params::new(m, t, p, None)
-    .map_err(|e| anyhow!("argon2 params invalid: {e:?}"))?;
+#[derive(Subcommand)]
+enum AddItem {
+    /// セキュアメモ（本文は --text、無ければ標準入力から最後まで読む）
+    Note {
+        name: String,
+        #[arg(long)] text: Option<String>,
+        /// タグ（複数指定またはカンマ区切り）
+        #[arg(long = "tag", value_name = "TAG", value_delimiter = ',')] tags: Vec<String>,
+    },
+    /// クレジットカード（番号とセキュリティコードは伏せ字で尋ねる）
+    Card {
+        name: String,
+        /// 名義
+        #[arg(long)] holder: Option<String>,
+        /// 有効期限（MM/YY）
+        #[arg(long)] expires: Option<String>,
+        /// 暗証番号も尋ねて保存
+        #[arg(long)] pin: bool,
+        #[arg(long)] notes: Option<String>,
+        /// タグ（複数指定またはカンマ区切り）
+        #[arg(long = "tag", value_name = "TAG", value_delimiter = ',')] tags: Vec<String>,
+    },
+    /// 個人情報（氏名・連絡先・住所など。フォームへの入力用）
+    Identity {
+        name: String,
+        #[arg(long)] full_name: Option<String>,
+        #[arg(long)] email: Option<String>,
+        #[arg(long)] phone: Option<String>,
+        /// 住所（改行は \n）
+        #[arg(long)] address: Option<String>,
+        /// 生年月日（YYYY-MM-DD）
+        #[arg(long)] birthday: Option<String>,
+        #[arg(long)] company: Option<String>,
+        #[arg(long)] notes: Option<String>,
+        /// タグ（複数指定またはカンマ区切り）
+        #[arg(long = "tag", value_name = "TAG", value_delimiter = ',')] tags: Vec<String>,
+    },
+    /// SSH鍵ペアを生成（sshkey new と同じ）
+    SshKey {
+        name: String,
+        #[arg(long = "type", value_enum, default_value_t = sshkey::KeyType::Ed25519)]
+        key_type: sshkey::KeyType,
+        /// 鍵のコメント（デフォルトはエントリ名）
+        #[arg(short = 'C', long)] comment: Option<String>,
+        /// 秘密鍵をOpenSSH形式で一時的にファイル出力（0600）
+        #[arg(long, value_name = "PATH")] export_openssh: Option<PathBuf>,
+    },
+}
 
-    let salt = &data[idx..idx+16]; idx+=16;
-    let nonce_bytes = &data[idx..idx+12]; idx+=12;
-    let ciphertext = &data[idx..];
+#[derive(Subcommand)]
+enum SshKeyCmd {
+    /// 鍵ペアを生成して金庫に保存し、公開鍵を表示
+    New {
+        name: String,
+        #[arg(long = "type", value_enum, default_value_t = sshkey::KeyType::Ed25519)]
+        key_type: sshkey::KeyType,
+        /// 鍵のコメント（デフォルトはエントリ名）
+        #[arg(short = 'C', long)] comment: Option<String>,
+        /// 秘密鍵をOpenSSH形式で一時的にファイル出力（0600）
+        #[arg(long, value_name = "PATH")] export_openssh: Option<PathBuf>,
+    },
+    /// 公開鍵を表示
+    Show {
+        name: String,
+        /// 秘密鍵をOpenSSH形式で一時的にファイル出力（0600）
+        #[arg(long, value_name = "PATH")] export_openssh: Option<PathBuf>,
+    },
+}
 
-    let key_bytes = derive_key_from_password(password, salt, &params)?;
-    let key = Key::from_slice(&key_bytes);
-    let cipher = ChaCha20Poly1305::new(key);
-    let nonce = Nonce::from_slice(nonce_bytes);
+#[derive(Subcommand)]
+enum BackupCmd {
+    /// 現在の金庫のスナップショットを作成
+    Create {
+        /// rcloneでリモートにも送る（例: gdrive:rustpass）
+        #[arg(long, value_name = "REMOTE:PATH")] rclone: Option<String>,
+        /// 新しいものからこの数だけ残し、古いスナップショットを消す（省略時は設定の backup_keep）
+        #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))] keep: Option<usize>,
+    },
+    /// 最新のバックアップを検証（--all で全て）
+    Verify {
+        #[arg(long)] all: bool,
+    },
+}
 
-    let plaintext = cipher
-    .decrypt(nonce, ciphertext)
-    .map_err(|e| anyhow!("aead decrypt failed (bad password or corrupted file): {e:?}"))?;
+#[derive(Subcommand)]
+enum SyncCmd {
+    /// 同期先の git リポジトリを設定（リモートに金庫があれば取り出す）
+    Init {
+        /// 例: git@github.com:me/vault.git
+        remote: String,
+        #[arg(long, default_value = "main")] branch: String,
+    },
+    /// 同期先と、最後の同期からの変更の有無を表示
+    Status,
+}
 
-    let vault: Vault = serde_json::from_slice(&plaintext)?;
-    Ok(vault)
+fn vault_path() -> Result<PathBuf> {
+    vaults::path()
 }
 
-fn load_or_init(password: &str) -> Result<Vault> {
-    let path = vault_path()?;
-    if path.exists() {
-        let data = fs::read(path)?;
-        decrypt_vault(&data, password)
+// 対話プロンプト（同じコマンド内では入力を使い回す）
+#[derive(Default)]
+struct Prompter {
+    password: Option<Zeroizing<String>>,
+    totp_code: Option<String>,
+    keyfile: Option<PathBuf>,
+    identity: Option<PathBuf>,
+    identities: Option<Vec<agefile::Identity>>,
+    min_kdf: Option<format::KdfParams>,
+    /// 新規作成時のKDFパラメータ（設定の kdf）
+    kdf: Option<format::KdfParams>,
+}
+
+impl format::Credentials for Prompter {
+    fn password(&mut self) -> Result<Zeroizing<String>> {
+        if self.password.is_none() {
+            if !prompt::interactive() {
+                return Err(err!(InvalidInput, "master password is required but --non-interactive is set; pass it with --password-file, --password-fd or RUSTPASS_PASSWORD"));
+            }
+            self.password = Some(Zeroizing::new(prompt::password("Master password: ")?));
+        }
+        Ok(self.password.clone().unwrap())
+    }
+
+    fn totp_code(&mut self) -> Result<String> {
+        if self.totp_code.is_none() {
+            self.totp_code = Some(mfa::read_code("TOTP code: ")?);
+        }
+        Ok(self.totp_code.clone().unwrap())
+    }
+
+    fn keyfile(&mut self) -> Result<Option<Zeroizing<Vec<u8>>>> {
+        self.keyfile.as_deref().map(read_keyfile).transpose()
+    }
+
+    fn identities(&mut self) -> Result<Vec<age::Identity>> {
+        Ok(self.identity_keys()?.into_iter().filter_map(|id| match id {
+            agefile::Identity::X25519(id) => Some(id),
+            agefile::Identity::Ssh(_) => None,
+        }).collect())
+    }
+
+    fn token_response(&mut self, factor: &format::TokenFactor) -> Result<Zeroizing<Vec<u8>>> {
+        hardware::respond(factor)
+    }
+
+    fn member_password(&mut self, member: &str) -> Result<Option<Zeroizing<String>>> {
+        let pw = Zeroizing::new(prompt::password(format!("Password for {member} (empty to skip): "))?);
+        Ok(if pw.is_empty() { None } else { Some(pw) })
+    }
+
+    fn member_rejected(&mut self, member: &str) {
+        eprintln!("wrong password for {member}");
+    }
+
+    fn min_kdf(&self) -> Option<format::KdfParams> {
+        self.min_kdf
+    }
+}
+
+// --kdf-memory などで base の一部を置き換える（メモリは MiB で指定）
+fn kdf_override(base: format::KdfParams, memory: Option<u32>, time: Option<u32>, parallelism: Option<u32>) -> Result<format::KdfParams> {
+    let kdf = format::KdfParams {
+        m: memory.map_or(base.m, |mib| mib.saturating_mul(1024)),
+        t: time.unwrap_or(base.t),
+        p: parallelism.unwrap_or(base.p),
+    };
+    kdf.check().map_err(|e| err!(InvalidInput, "{e}"))?;
+    Ok(kdf)
+}
+
+// 新規作成時のKDFパラメータ（設定の下限を下回らない）
+fn new_kdf(creds: &Prompter) -> format::KdfParams {
+    let kdf = creds.kdf.unwrap_or_default();
+    match &creds.min_kdf {
+        Some(min) => kdf.at_least(min),
+        None => kdf,
+    }
+}
+
+fn read_keyfile(path: &std::path::Path) -> Result<Zeroizing<Vec<u8>>> {
+    let data = Zeroizing::new(fs::read(path).map_err(|e| anyhow!("cannot read the key file {}: {e}", path.display()))?);
+    if data.is_empty() { return Err(err!(InvalidInput, "key file {} is empty", path.display())); }
+    Ok(data)
+}
+
+// age の秘密鍵ファイル（または OpenSSH の秘密鍵）を読む。age -p で暗号化したものは
+// パスフレーズ（--password-file などで渡されたものがあればそれ）で開く
+fn read_identities(path: &std::path::Path, password: Option<&Zeroizing<String>>) -> Result<Vec<agefile::Identity>> {
+    let data = Zeroizing::new(fs::read(path).map_err(|e| anyhow!("cannot read the identity file {}: {e}", path.display()))?);
+    let text = if agefile::is_age(&data) {
+        agefile::decrypt(&data, &[], &mut || match password {
+            Some(p) => Ok(p.clone()),
+            None => Ok(Zeroizing::new(prompt::password(format!("Passphrase for {}: ", path.display()))?)),
+        })?
     } else {
-        Ok(Vault::default())
+        data
+    };
+    let text = std::str::from_utf8(&text).map_err(|_| err!(InvalidInput, "identity file {} is not text", path.display()))?;
+    agefile::parse_identities(text).map_err(|e| err!(InvalidInput, "{}: {e}", path.display()))
+}
+
+// 既存のファイルはそのまま使い、無ければ64バイトの乱数で作る
+fn new_keyfile(path: &std::path::Path) -> Result<Zeroizing<Vec<u8>>> {
+    if path.exists() { return read_keyfile(path); }
+    let mut data = Zeroizing::new(vec![0u8; 64]);
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut data);
+    export::write(path, &data, false)?;
+    eprintln!("Created key file {}; keep a copy somewhere safe, the vault cannot be opened without it", path.display());
+    Ok(data)
+}
+
+impl Prompter {
+    // --identity の秘密鍵（SSH の鍵も含む。ファイルは1回だけ読む）
+    fn identity_keys(&mut self) -> Result<Vec<agefile::Identity>> {
+        if self.identities.is_none() {
+            self.identities = Some(match &self.identity {
+                Some(path) => read_identities(path, self.password.as_ref())?,
+                None => Vec::new(),
+            });
+        }
+        Ok(self.identities.clone().unwrap())
     }
 }
 
-fn save(password: &str, vault: &Vault, params: Params) -> Result<()> {
-    let bytes = encrypt_vault(vault, password, params)?;
-    let path = vault_path()?;
-    fs::write(path, bytes)?;
-    Ok(())
+// 新しい鍵スロット（--keyfile があればキーファイルも、token があればトークンの応答も必要にする）
+fn new_unlocked(creds: &Prompter, password: &str, token: Option<(format::TokenFactor, Zeroizing<Vec<u8>>)>) -> Result<format::Unlocked> {
+    let mut factors = format::Factors::default();
+    if let Some(path) = &creds.keyfile { factors = factors.keyfile(&new_keyfile(path)?); }
+    if let Some((f, response)) = token { factors = factors.token(f, &response); }
+    format::create_with(password, factors, new_kdf(creds))
+}
+
+// 新しいマスターパスワードを2回入力させる（打ち間違いで開けない金庫を作らないように）
+fn new_master_password(creds: &mut Prompter) -> Result<Zeroizing<String>> {
+    // --password-file などで渡されたものはそのまま使う（確認の再入力はしない）
+    if let Some(pw) = creds.password.clone() {
+        if pw.is_empty() { return Err(err!(InvalidInput, "master password is empty")); }
+        return Ok(pw);
+    }
+    if !prompt::interactive() {
+        return Err(err!(InvalidInput, "new master password is required but --non-interactive is set; pass it with --password-file, --password-fd or RUSTPASS_PASSWORD"));
+    }
+    let pw = Zeroizing::new(prompt::password("New master password: ")?);
+    if pw.is_empty() { return Err(err!(InvalidInput, "master password is empty")); }
+    let again = Zeroizing::new(prompt::password("Repeat master password: ")?);
+    if pw != again { return Err(err!(InvalidInput, "passwords do not match; nothing was saved")); }
+    creds.password = Some(pw.clone());
+    Ok(pw)
 }
 
-// ランダムパスワード生成（各カテゴリ最低1文字保証）
-fn generate_password(len: usize, use_symbols: bool, allow_ambiguous: bool) -> Result<String> {
-    if len < 4 { return Err(anyhow!("len must be >= 4")); }
+// list での種類ごとの要約（ログインはユーザー名）
+fn summary(e: &Entry) -> String {
+    match (e.kind(), &e.card, &e.identity) {
+        (Kind::Login, ..) => format!("({})", e.username),
+        (Kind::Passkey, ..) => format!("({})  [passkey]", e.username),
+        (Kind::Card, Some(c), _) => format!("[card: {}]", c.masked()),
+        (Kind::Identity, _, Some(i)) if i.full_name.is_some() => format!("[identity: {}]", i.full_name.as_deref().unwrap_or_default()),
+        (k, ..) => format!("[{}]", k.label()),
+    }
+}
+
+// --tag の値（カンマ区切りも分ける。空は除く）
+fn split_tags(tags: &[String]) -> Vec<String> {
+    tags.iter()
+        .flat_map(|t| t.split(','))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
 
-    let mut lower = "abcdefghijklmnopqrstuvwxyz".to_string();
-    let mut upper = "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string();
-    let mut digits = "0123456789".to_string();
-    let mut symbols = "!@#$%^&*()-_=+[]{};:,.<>/?~".to_string();
+// --max-age の値（90d・12w・1y など）を日数にする
+fn max_age_days(s: &str) -> Result<u32> {
+    u32::try_from(config::parse_days(s)?).ok().filter(|d| *d > 0)
+        .ok_or_else(|| err!(InvalidInput, "max age must be a positive duration like 90d"))
+}
+
+// 大文字小文字を区別せず、「親」は「親/子」のタグにも一致する
+fn has_tag(e: &Entry, tag: &str) -> bool {
+    let tag = tag.trim().trim_end_matches('/').to_lowercase();
+    e.tags.iter().any(|t| {
+        let t = t.to_lowercase();
+        t == tag || t.strip_prefix(&tag).is_some_and(|rest| rest.starts_with('/'))
+    })
+}
 
-    if !allow_ambiguous {
-        let ambiguous = "O0o1lI|`'\"{}[]()/\\;:.,<>";
-        let strip = |s: &mut String| s.retain(|c| !ambiguous.contains(c));
-        strip(&mut lower); strip(&mut upper); strip(&mut digits);
-        if use_symbols { strip(&mut symbols); }
+// 金庫を開く（未作成なら入力されたパスワードで新規作成）
+fn open_vault(creds: &mut Prompter) -> Result<(Vault, format::Unlocked)> {
+    let file = VaultFile::current()?;
+    let mut local = file.load()?;
+    let mut opened = None;
+    // リモートに保存している金庫は、開けることを確かめたリモートの版で手元のファイルを置き換える
+    if let Some(data) = remote::configured()?.map(|r| r.load()).transpose()?.flatten() {
+        if local.as_ref() != Some(&data) {
+            opened = Some(open_data(&data, creds)?);
+            if !readonly::enabled() { file.store(&data)?; }
+            local = Some(data);
+        }
     }
+    let Some(data) = local else {
+        if readonly::enabled() { return Err(err!(NoVault, "no vault at {}", file.0.display())); }
+        eprintln!("No vault yet; creating one at {}", file.0.display());
+        let password = new_master_password(creds)?;
+        return Ok((Vault::default(), new_unlocked(creds, &password, None)?));
+    };
+    let opened = match opened {
+        Some(opened) => opened,
+        None => open_data(&data, creds)?,
+    };
+    // ここから保存まで他のプロセスに書き込ませない。待っている間に書き換えられていたら、
+    // 入力済みのパスワードなどで開き直す
+    match lock::hold(&file.0)? {
+        Some(data) => open_data(&data, creds),
+        None => Ok(opened),
+    }
+}
 
-    let mut pools: Vec<Vec<u8>> = vec![
-        lower.as_bytes().to_vec(),
-        upper.as_bytes().to_vec(),
-        digits.as_bytes().to_vec(),
-    ];
-    if use_symbols { pools.push(symbols.as_bytes().to_vec()); }
-    if pools.iter().any(|p| p.is_empty()) {
-        return Err(anyhow!("character pool empty; try --allow-ambiguous or disable --symbols"));
+fn open_data(data: &[u8], creds: &mut Prompter) -> Result<(Vault, format::Unlocked)> {
+    if let Some(key) = agent::key() {
+        match format::unlock_with_key(data, key) {
+            Ok(opened) => return Ok(opened),
+            Err(e) => eprintln!("note: agent key rejected ({e}); asking for the password"),
+        }
+    }
+    if let Some(key) = keychain::key() {
+        match format::unlock_with_key(data, key).and_then(|opened| keychain::check(&opened.1).map(|()| opened)) {
+            Ok(opened) => return Ok(opened),
+            Err(e) => eprintln!("note: key in the OS keychain rejected ({e}); asking for the password"),
+        }
+    }
+    let (vault, unlocked) = format::unlock(data, creds)?;
+    if creds.keyfile.is_some() && !unlocked.keyfile_required() {
+        eprintln!("note: this vault does not use a key file; --keyfile was ignored");
     }
+    if unlocked.version < format::VERSION {
+        eprintln!("note: vault uses format v{}; it will be upgraded on the next save (or run `rustpass migrate`)", unlocked.version);
+    }
+    audit::note_expired(&vault);
+    Ok((vault, unlocked))
+}
 
-    let mut all = Vec::new();
-    for p in &pools { all.extend_from_slice(p); }
+// 別の金庫ファイル（競合コピーや同期相手）を開く。パスワードが違えばそのパスワードを尋ねる
+fn open_other(data: &[u8], creds: &mut Prompter, what: &str) -> Result<Vault> {
+    if let Some(key) = agent::key() {
+        if let Ok((v, _)) = format::unlock_with_key(data, key) { return Ok(v); }
+    }
+    match format::unlock(data, creds) {
+        Err(e) if error::kind_of(&e) == error::Kind::WrongPassword => {
+            eprintln!("{what} uses a different password.");
+            let mut other = Prompter { min_kdf: creds.min_kdf, ..Default::default() };
+            Ok(format::unlock(data, &mut other)?.0)
+        }
+        r => Ok(r?.0),
+    }
+}
 
-    let mut rng = OsRng;
-    let mut bytes: Vec<u8> = Vec::with_capacity(len);
-    for p in &pools {
-        let idx = rng.gen_range(0..p.len());
-        bytes.push(p[idx]);
+// 金庫を暗号化し、開けることを確かめてから保存する。リモートの保存先があれば先にアップロードし、
+// 失敗したら手元も元のまま
+fn save(vault: &Vault, unlocked: &format::Unlocked) -> Result<()> {
+    readonly::check()?;
+    audit::note_expired(vault);
+    let data = format::seal(vault, unlocked)?;
+    format::verify(&data, unlocked)
+        .map_err(|e| err!(CorruptVault, "vault verification before write failed ({e}); the previous vault was kept"))?;
+    if let Some(remote) = remote::configured()? {
+        remote.store(&data)?;
     }
-    for _ in bytes.len()..len {
-        let idx = rng.gen_range(0..all.len());
-        bytes.push(all[idx]);
+    VaultFile::current()?.store(&data)
+}
+
+// この端末の金庫ファイル。読むときと保存するときは lock でほかのプロセスと排他する
+struct VaultFile(PathBuf);
+
+impl VaultFile {
+    fn current() -> Result<VaultFile> {
+        Ok(VaultFile(vault_path()?))
     }
-    bytes.shuffle(&mut rng);
 
-    Ok(String::from_utf8(bytes)?)
+    // 金庫を読む（まだ無ければエラー）
+    fn read(&self) -> Result<Vec<u8>> {
+        self.load()?.ok_or_else(|| err!(NoVault, "no vault at {}", self.0.display()))
+    }
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let password = prompt_password("Master password: ")?;
-    let params = default_params();
+impl VaultStorage for VaultFile {
+    fn load(&self) -> Result<Option<Vec<u8>>> {
+        if !self.0.exists() { return Ok(None); }
+        lock::read(&self.0).map(Some)
+    }
 
-    match cli.cmd {
-        Cmd::New => {
-            if vault_path()?.exists() {
-                return Err(anyhow!("vault already exists"));
-            }
-            save(&password, &Vault::default(), params)?;
-            println!("Created new vault at {:?}", vault_path()?);
-        }
-        Cmd::Add { name, user, gen, len, symbols, allow_ambiguous } => {
-            let mut v = load_or_init(&password)?;
-            let username = user.unwrap_or_else(|| {
-                print!("Username: "); io::stdout().flush().unwrap();
-                let mut s = String::new(); io::stdin().read_line(&mut s).unwrap(); s.trim().to_string()
-            });
-            let pass = if gen {
-                let g = generate_password(len, symbols, allow_ambiguous)?;
-                println!("Generated password (len={}): {}", len, g); // 必要なら伏せてもOK
-                g
-            } else {
-                prompt_password("Password (hidden): ")?
-            };
-            v.entries.retain(|e| e.name != name);
-            v.entries.push(Entry {
-                id: Uuid::new_v4().to_string(),
-                name, username,
-                password: pass,
-                url: None, notes: None,
-                updated_at: now_iso(),
-            });
-            save(&password, &v, params)?;
-            println!("Saved.");
-        }
-        Cmd::List => {
-            let v = load_or_init(&password)?;
-            for e in v.entries.iter() {
-                println!("{}  ({})  updated {}", e.name, e.username, e.updated_at);
-            }
+    // 同じディレクトリの一時ファイルに書いて fsync し、読み戻して確かめてから置き換える
+    // （直前の版は vault.bin.bak.1 などに残す）。途中で落ちたりディスクが一杯になったりしても、
+    // 元のファイルはそのまま残る
+    fn store(&self, data: &[u8]) -> Result<()> {
+        readonly::check()?;
+        let path = &self.0;
+        // 書き込みから置き換えまでは他のプロセスに読ませも書かせもしない
+        let _lock = lock::for_save(path)?;
+        let tmp = path.with_extension("bin.tmp");
+        let written = write_synced(&tmp, data).and_then(|()| {
+            if fs::read(&tmp)? == data { return Ok(()); }
+            Err(err!(CorruptVault, "vault verification after write failed (the file reads back differently); the previous vault was kept"))
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
         }
-        Cmd::Get { name, show } => {
-            let v = load_or_init(&password)?;
-            if let Some(e) = v.entries.iter().find(|e| e.name == name) {
-                println!("username: {}", e.username);
-                if show { println!("password: {}", e.password); }
-                else { println!("password: ******  (use --show to reveal)"); }
-            } else {
-                println!("not found");
-            }
+        if path.exists() {
+            backup::rotate(path, config::load()?.vault_backups())?;
         }
-        Cmd::Gen { len, symbols, allow_ambiguous } => {
-            let s = generate_password(len, symbols, allow_ambiguous)?;
-            println!("{}", s);
+        fs::rename(&tmp, path)?;
+        perms::private_file(path)?;
+        // 置き換え（ディレクトリエントリの更新）も永続化する
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() { fs::File::open(dir)?.sync_all()?; }
+        lock::saved(data);
+        Ok(())
+    }
+}
+
+fn write_synced(tmp: &std::path::Path, data: &[u8]) -> Result<()> {
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut f = opts.open(tmp)?;
+    io::Write::write_all(&mut f, data)?;
+    f.sync_all()?;
+    Ok(())
+}
+
+// get --field・exec で名前を指定できる項目（それ以外の名前はカスタムフィールド）
+const ENTRY_ITEMS: [&str; 6] = ["password", "username", "url", "notes", "otp", "totp"];
+
+// エントリの項目の値（password / username / url / notes / otp（現在のコード。totp も可）、それ以外はカスタムフィールド）
+fn entry_value(e: &Entry, item: &str) -> Result<Zeroizing<String>> {
+    let v = match item {
+        "password" => e.password.clone(),
+        "username" => e.username.clone(),
+        "url" => e.url.clone().unwrap_or_default(),
+        "notes" => e.notes.clone().unwrap_or_default(),
+        "otp" | "totp" => e.otp.as_ref().ok_or_else(|| err!(NotFound, "entry {:?} has no TOTP", e.name))?.now()?.0,
+        key => e.fields.iter().find(|f| f.name == key)
+            .map(|f| f.value.clone())
+            .ok_or_else(|| err!(NotFound, "field {key:?} not found in {:?} (password, username, url, notes, otp or a custom field)", e.name))?,
+    };
+    if v.is_empty() { return Err(err!(NotFound, "entry {:?} has no {item}", e.name)); }
+    Ok(Zeroizing::new(v))
+}
+
+// 名前が使われていればエラーにする（同じ名前のエントリは作らない。衝突したエントリと remedy を示す）
+fn name_free(vault: &Vault, name: &str, remedy: &str) -> Result<()> {
+    match vault.entries.iter().find(|e| e.name == name) {
+        Some(e) => Err(err!(AlreadyExists, "entry {name:?} already exists ({}); {remedy}", e.summary())),
+        None => Ok(()),
+    }
+}
+
+// 参照を記録（保存が発生するため設定で有効にした場合のみ）
+fn record_access(vault: &mut Vault, unlocked: &format::Unlocked, cfg: &config::Config, name: &str, action: &str) -> Result<()> {
+    let Some(i) = vault.entries.iter().position(|e| e.name == name) else { return Ok(()) };
+    let canary = vault.entries[i].canary;
+    if canary { canary::trip(vault, cfg.canary.hook.as_deref(), name, action); }
+    if cfg.track_access { vault.entries[i].last_accessed = Some(now_iso()); }
+    if (canary || cfg.track_access) && !readonly::enabled() { save(vault, unlocked)?; }
+    Ok(())
+}
+
+// 先頭と末尾の2文字だけ表示（長さは伏せるためマスク幅は固定）。
+// 4文字以下では全部（か大半）が見えてしまうので何も出さない
+fn peek_ends(secret: &str) -> (Vec<char>, Vec<char>) {
+    let chars: Vec<char> = secret.chars().collect();
+    let n = if chars.len() > 4 { 2 } else { 0 };
+    (chars[..n].to_vec(), chars[chars.len() - n..].to_vec())
+}
+
+fn peek_mask(secret: &str) -> String {
+    let (head, tail) = peek_ends(secret);
+    format!("{}********{}", String::from_iter(head), String::from_iter(tail))
+}
+
+// --accessible 用：伏せ字を読み上げさせず、見えている文字を言葉で伝える
+fn peek_spoken(secret: &str) -> String {
+    let (head, tail) = peek_ends(secret);
+    if head.is_empty() { return "hidden (too short to peek)".into(); }
+    let say = |cs: Vec<char>| cs.into_iter().map(spell::describe).collect::<Vec<_>>().join(", ");
+    format!("hidden, starts with {}; ends with {}", say(head), say(tail))
+}
+
+fn main() -> Result<()> {
+    // 落ちたときに金庫の中身がコアダンプに残らないように（できなくても続ける）
+    let _ = rustpass_core::secure::no_core_dumps();
+    let cli = Cli::parse();
+    let json = cli.json;
+    match run(cli) {
+        Err(e) if json => {
+            error::print_json(&e);
+            std::process::exit(1);
+        }
+        r => {
+            if r.is_ok() { audit::warn_expired(); }
+            r
+        }
+    }
+}
+
+// スクリプト用に渡されたマスターパスワード（ファイル > fd > 環境変数）。改行までを使う
+fn scripted_password(file: Option<&std::path::Path>, fd: Option<u32>) -> Result<Option<Zeroizing<String>>> {
+    let first_line = |data: Zeroizing<Vec<u8>>, from: &str| -> Result<Zeroizing<String>> {
+        let line = data.split(|&b| b == b'\n').next().unwrap_or_default();
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let pw = std::str::from_utf8(line).map_err(|_| err!(InvalidInput, "the password from {from} is not valid UTF-8"))?;
+        Ok(Zeroizing::new(pw.to_string()))
+    };
+    // 子プロセス（フックなど）に引き継がないよう環境変数は消しておく
+    let env = std::env::var("RUSTPASS_PASSWORD").ok().map(Zeroizing::new);
+    std::env::remove_var("RUSTPASS_PASSWORD");
+    if let Some(path) = file {
+        let data = fs::read(path).map_err(|e| anyhow!("cannot read the password file {}: {e}", path.display()))?;
+        return first_line(Zeroizing::new(data), &path.display().to_string()).map(Some);
+    }
+    if let Some(fd) = fd {
+        // /dev/fd/N を開き直す（Linux / macOS）
+        let data = fs::read(format!("/dev/fd/{fd}")).map_err(|e| anyhow!("cannot read the password from file descriptor {fd}: {e}"))?;
+        return first_line(Zeroizing::new(data), &format!("file descriptor {fd}")).map(Some);
+    }
+    Ok(env)
+}
+
+// 新しい金庫を作る（new と vaults create）
+fn new_vault(creds: &mut Prompter, token: Option<String>, recipients: &[String]) -> Result<()> {
+    readonly::check()?;
+    let path = vault_path()?;
+    if path.exists() {
+        return Err(err!(AlreadyExists, "vault already exists at {}", path.display()));
+    }
+    if !recipients.is_empty() {
+        return new_recipient_vault(creds, &path, recipients);
+    }
+    let token = token.as_deref().map(hardware::parse).transpose()?;
+    let kdf = new_kdf(creds);
+    println!("Vault file: {}", path.display());
+    println!(
+        "Key derivation: argon2id, memory {} MiB, {} iterations, parallelism {}{}",
+        kdf.m / 1024, kdf.t, kdf.p,
+        if kdf != creds.kdf.unwrap_or_default() { " (raised to the configured min_kdf)" } else { "" },
+    );
+    // パスワードを入力させる前に、トークンが使えることを確かめる
+    let token = token.map(hardware::enroll).transpose()?.flatten();
+    if let Some((f, _)) = &token {
+        println!("Hardware token: {} (the vault cannot be opened without it)", f.token.describe());
+    }
+    let password = new_master_password(creds)?;
+    let unlocked = new_unlocked(creds, &password, token)?;
+    save(&Vault::default(), &unlocked)?;
+    println!("Created new vault at {}", path.display());
+    println!();
+    println!("The master password cannot be recovered or reset. Before storing anything:");
+    println!("  - write it down with the vault location and keep it somewhere safe (an emergency kit)");
+    println!("  - keep a copy off this machine: `rustpass backup create`");
+    Ok(())
+}
+
+// share で渡す中身（エントリ1件）
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Shared {
+    rustpass_share: u32,
+    entry: Entry,
+}
+
+const SHARE_VERSION: u32 = 1;
+
+// share --to の値を宛先にする（ファイルなら1行ずつ。# で始まる行と空行は飛ばす）
+fn share_recipients(to: &[String]) -> Result<Vec<agefile::Recipient>> {
+    let mut out = Vec::new();
+    for t in to {
+        let path = std::path::Path::new(t);
+        if t.contains(char::is_whitespace) || t.starts_with("age1") || !path.is_file() {
+            out.push(agefile::Recipient::parse(t)?);
+            continue;
+        }
+        let text = fs::read_to_string(path).map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?;
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            out.push(agefile::Recipient::parse(line)?);
+        }
+    }
+    Ok(out)
+}
+
+// パスワードの代わりに age の受信者あてに暗号化した金庫を作る
+fn new_recipient_vault(creds: &Prompter, path: &std::path::Path, recipients: &[String]) -> Result<()> {
+    if creds.keyfile.is_some() {
+        return Err(err!(InvalidInput, "--keyfile cannot be combined with --age-recipient"));
+    }
+    let recipients = recipients.iter().map(|r| age::parse_recipient(r)).collect::<Result<Vec<_>>>()?;
+    println!("Vault file: {}", path.display());
+    for r in &recipients {
+        println!("Recipient:  {r}");
+    }
+    save(&Vault::default(), &format::create_for(&recipients)?)?;
+    println!("Created new vault at {}", path.display());
+    println!();
+    println!("The vault has no master password; it opens with the identity file of one of the recipients.");
+    println!("  - pass it with --identity PATH (or RUSTPASS_IDENTITY, or identity in the config)");
+    println!("  - keep a copy of the identity file off this machine; without it the vault cannot be opened");
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let mut cfg = config::load()?;
+    cfg.accessible |= cli.accessible;
+    let json = cli.json;
+    if cfg.warn_expired && !json { audit::enable_expired_warning(); }
+    if cli.read_only || cfg.read_only { readonly::enable(); }
+    let mut creds = Prompter::default();
+    if cli.non_interactive { prompt::disable(); }
+    creds.password = scripted_password(cli.password_file.as_deref(), cli.password_fd)?;
+    creds.keyfile = cli.keyfile;
+    creds.identity = cli.identity
+        .or_else(|| std::env::var_os("RUSTPASS_IDENTITY").filter(|s| !s.is_empty()).map(PathBuf::from))
+        .or_else(|| cfg.identity.clone());
+    // vaults create / delete は対象の金庫を選ぶ（--vault は使わない）
+    let vault = match &cli.cmd {
+        Cmd::Vaults { cmd: VaultsCmd::Create { name, .. } | VaultsCmd::Delete { name, .. } } => Some(name.clone()),
+        _ => cli.vault
+            .or_else(|| std::env::var("RUSTPASS_VAULT").ok().filter(|s| !s.is_empty()))
+            .or_else(|| cfg.vault.clone()),
+    };
+    vaults::select(vault.as_deref())?;
+    if !cli.accept_weaker_kdf {
+        creds.min_kdf = cfg.min_kdf;
+    }
+    creds.kdf = cfg.kdf;
+
+    match cli.cmd {
+        Cmd::New { token, kdf_memory, kdf_time, kdf_parallelism, age_recipients } => {
+            creds.kdf = Some(kdf_override(creds.kdf.unwrap_or_default(), kdf_memory, kdf_time, kdf_parallelism)?);
+            new_vault(&mut creds, token, &age_recipients)?;
+        }
+        Cmd::Vaults { cmd: VaultsCmd::List } => {
+            let current = vault_path()?;
+            let mut found = vaults::list()?;
+            // --vault でパスを指定した金庫も表示する
+            if current.exists() && !found.iter().any(|(_, p)| *p == current) {
+                found.push((current.display().to_string(), current.clone()));
+            }
+            if json {
+                let doc: Vec<_> = found.iter()
+                    .map(|(name, path)| serde_json::json!({ "name": name, "path": path, "selected": *path == current }))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&doc)?);
+            } else if found.is_empty() {
+                println!("No vaults yet; create one with `rustpass new` or `rustpass vaults create NAME`.");
+            } else {
+                let width = found.iter().map(|(n, _)| n.chars().count()).max().unwrap_or(0);
+                for (name, path) in &found {
+                    let mark = if *path == current { "*" } else { " " };
+                    println!("{mark} {name:<width$}  {}", path.display());
+                }
+            }
+        }
+        Cmd::Vaults { cmd: VaultsCmd::Create { name, token, kdf_memory, kdf_time, kdf_parallelism, age_recipients } } => {
+            creds.kdf = Some(kdf_override(creds.kdf.unwrap_or_default(), kdf_memory, kdf_time, kdf_parallelism)?);
+            new_vault(&mut creds, token, &age_recipients)?;
+            println!("Use it with `rustpass --vault {name} ...` or RUSTPASS_VAULT={name}.");
+        }
+        Cmd::Vaults { cmd: VaultsCmd::Delete { name, force } } => {
+            let path = vaults::named(&name)?;
+            if !path.exists() {
+                return Err(err!(NotFound, "no vault named {name:?}"));
+            }
+            if !force {
+                prompt::require("confirmation (pass --force)")?;
+                let answer = prompt::line(&format!("Delete vault {name:?} with its backups? This cannot be undone. Type the name to confirm: "))?;
+                if answer != name {
+                    println!("Aborted.");
+                    return Ok(());
+                }
+            }
+            // 削除する金庫の鍵を持つエージェントも止める
+            agent::stop()?;
+            let dir = vaults::delete(&name)?;
+            println!("Deleted vault {name:?} ({}).", dir.display());
+        }
+        Cmd::Add { item: None, name, user, url, notes, tags, gen, len, symbols, no_symbols, allow_ambiguous, strict, expires, max_age, force, words, wordlist, separator, capitalize, include_number, camel_case } => {
+            let name = name.expect("required without a subcommand");
+            let expires_at = expires.as_deref().map(audit::parse_expiry).transpose()?;
+            let max_age_days = max_age.as_deref().map(max_age_days).transpose()?;
+            let (len, symbols) = (cfg.generator.length(len), cfg.generator.symbols(symbols, no_symbols));
+            let (mut v, u) = open_vault(&mut creds)?;
+            // パスワードなどを入力させる前に確かめる
+            if !force { name_free(&v, &name, "use --force to replace it (the old entry goes to the trash), or `rustpass rename` it first")?; }
+            // --user が無ければ対話モード：指定されなかった項目も順に尋ねる（空欄で省略）
+            let interactive = user.is_none();
+            let ask = |label: &str| prompt::line(&format!("{label}: "));
+            let username = match user { Some(u) => u, None => ask("Username")? };
+            let non_empty = |s: String| (!s.is_empty()).then_some(s);
+            let url = match url { None if interactive => non_empty(ask("URL (optional)")?), u => u };
+            let notes = match notes { None if interactive => non_empty(ask("Notes (optional)")?), n => n };
+            let tags = if tags.is_empty() && interactive { vec![ask("Tags, comma separated (optional)")?] } else { tags };
+            let tags = split_tags(&tags);
+            let pass = if let Some(n) = words {
+                let list = passphrase::load_wordlist(&wordlist)?;
+                let style = passphrase::Style { capitalize, include_number, camel_case };
+                let g = passphrase::generate_passphrase(&list, n, passphrase::separator(&separator), style)?;
+                println!("Generated passphrase ({n} words from {wordlist}): {g}");
+                g
+            } else if gen {
+                let g = generate_password(len, symbols, allow_ambiguous, &[], Avoid::default())?;
+                println!("Generated password (len={}): {}", len, g); // 必要なら伏せてもOK
+                g
+            } else {
+                let p = prompt::password("Password (hidden): ")?;
+                let est = strength::estimate(&p, &[&name, &username, url.as_deref().unwrap_or_default()]);
+                if est.score < 3 {
+                    let reasons = est.warnings.join("; ");
+                    if strict {
+                        return Err(err!(InvalidInput, "password is {}: {reasons}; nothing was saved", est.summary()));
+                    }
+                    eprintln!("warning: password is {}", est.summary());
+                    if !reasons.is_empty() { eprintln!("         {reasons}"); }
+                }
+                p
+            };
+            policy::enforce(&v, &name, &pass)?;
+            let mut trashed = false;
+            if let Some(i) = v.entries.iter().position(|e| e.name == name) {
+                let old = v.entries.remove(i);
+                trashed = trash::discard(&mut v, old, cfg.trash_retention_days()?);
+            }
+            v.entries.push(Entry { username, password: pass, url, notes, tags, expires_at, max_age_days, password_changed_at: Some(now_iso()), ..Entry::new(name) });
+            save(&v, &u)?;
+            if trashed { println!("Saved (the previous entry was moved to the trash)."); }
+            else { println!("Saved."); }
+        }
+        Cmd::Add { item: Some(AddItem::Note { name, text, tags }), .. } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            name_free(&v, &name, "choose another name, or `rustpass rename` the existing entry first")?;
+            let text = match text {
+                Some(t) => t,
+                None => {
+                    if io::stdin().is_terminal() {
+                        prompt::require("the note text")?;
+                        eprintln!("Enter the note, then press Ctrl-D:");
+                    }
+                    let mut s = String::new();
+                    io::stdin().read_to_string(&mut s)?;
+                    s.trim_end().to_string()
+                }
+            };
+            if text.trim().is_empty() {
+                return Err(err!(InvalidInput, "the note is empty; nothing was saved"));
+            }
+            v.entries.push(Entry { notes: Some(text), tags: split_tags(&tags), secure_note: true, ..Entry::new(name) });
+            save(&v, &u)?;
+            println!("Saved.");
+        }
+        Cmd::Add { item: Some(AddItem::Card { name, holder, expires, pin, notes, tags }), .. } => {
+            let expires = expires.as_deref().map(card::expiry).transpose()?;
+            let (mut v, u) = open_vault(&mut creds)?;
+            name_free(&v, &name, "choose another name, or `rustpass rename` the existing entry first")?;
+            let number = card::number(&Zeroizing::new(prompt::password("Card number (hidden): ")?))?;
+            if !card::luhn(&number) {
+                eprintln!("warning: the card number fails its check digit; check it for typos");
+            }
+            let cvv = prompt::password("Security code (hidden, empty to skip): ")?;
+            let pin = if pin { Some(prompt::password("PIN (hidden): ")?) } else { None };
+            let c = card::Card { holder, number, expires, cvv: (!cvv.is_empty()).then_some(cvv), pin };
+            println!("{}", c.masked());
+            v.entries.push(Entry { card: Some(c), notes, tags: split_tags(&tags), ..Entry::new(name) });
+            save(&v, &u)?;
+            println!("Saved.");
+        }
+        Cmd::Add { item: Some(AddItem::Identity { name, full_name, email, phone, address, birthday, company, notes, tags }), .. } => {
+            if let Some(b) = &birthday {
+                time::Date::parse(b, time::macros::format_description!("[year]-[month]-[day]"))
+                    .map_err(|_| err!(InvalidInput, "--birthday must be YYYY-MM-DD"))?;
+            }
+            let id = identity::Identity { full_name, email, phone, address: address.map(|a| a.replace("\\n", "\n")), birthday, company };
+            if id.items().is_empty() {
+                return Err(err!(InvalidInput, "give at least one of --full-name, --email, --phone, --address, --birthday, --company"));
+            }
+            let (mut v, u) = open_vault(&mut creds)?;
+            name_free(&v, &name, "choose another name, or `rustpass rename` the existing entry first")?;
+            v.entries.push(Entry { identity: Some(id), notes, tags: split_tags(&tags), ..Entry::new(name) });
+            save(&v, &u)?;
+            println!("Saved.");
+        }
+        Cmd::List { long, tags, kind, output } => {
+            let (v, _) = open_vault(&mut creds)?;
+            let entries: Vec<&Entry> = v.entries.iter()
+                .filter(|e| tags.iter().all(|t| has_tag(e, t)) && kind.is_none_or(|k| e.kind() == k))
+                .collect();
+            if let Some(out) = output.or(json.then_some(output::Output::Json)) {
+                let items = entries.iter().map(|e| output::list_item(e, &cfg)).collect::<Result<Vec<_>>>()?;
+                println!("{}", output::render(out, "entries", &serde_json::Value::from(items))?.trim_end());
+                return Ok(());
+            }
+            if cfg.accessible { println!("{} entries.", entries.len()); }
+            for e in entries {
+                let age = audit::age_violation(e, &cfg)?;
+                if cfg.accessible {
+                    // 記号や桁揃えに頼らず、項目名を添えてカンマ区切りで読み上げる
+                    let mut parts = vec![e.name.clone()];
+                    if e.kind() != Kind::Login { parts.push(e.kind().label().to_string()); }
+                    if !e.username.is_empty() { parts.push(format!("username {}", e.username)); }
+                    if long { parts.push(format!("created {}", e.created_at.as_deref().unwrap_or("unknown"))); }
+                    parts.push(format!("updated {}", e.updated_at));
+                    if long { parts.push(format!("last accessed {}", e.last_accessed.as_deref().unwrap_or("never"))); }
+                    if let Some(a) = age { parts.push(format!("warning: password older than {} days", a.max_days)); }
+                    println!("{}.", parts.join(", "));
+                    continue;
+                }
+                let flag = match age {
+                    Some(a) => format!("  [password older than {}d]", a.max_days),
+                    None => String::new(),
+                };
+                if long {
+                    println!(
+                        "{}  {}  created {}  updated {}  accessed {}{}",
+                        e.name, summary(e),
+                        e.created_at.as_deref().unwrap_or("-"),
+                        e.updated_at,
+                        e.last_accessed.as_deref().unwrap_or("never"),
+                        flag,
+                    );
+                } else {
+                    println!("{}  {}  updated {}{}", e.name, summary(e), e.updated_at, flag);
+                }
+            }
+        }
+        Cmd::Find { query, output } => {
+            let (v, _) = open_vault(&mut creds)?;
+            let hits = search::find(&v, &query.join(" "));
+            if let Some(out) = output.or(json.then_some(output::Output::Json)) {
+                let items = hits.iter().map(|h| {
+                    let mut item = output::list_item(&v.entries[h.index], &cfg)?;
+                    item["matched"] = h.matched.label().into();
+                    Ok(item)
+                }).collect::<Result<Vec<_>>>()?;
+                println!("{}", output::render(out, "entries", &serde_json::Value::from(items))?.trim_end());
+                return Ok(());
+            }
+            if hits.is_empty() { return Err(err!(NotFound, "no entries match {:?}", query.join(" "))); }
+            if cfg.accessible { println!("{} matches.", hits.len()); }
+            for h in &hits {
+                let e = &v.entries[h.index];
+                if cfg.accessible {
+                    let mut parts = vec![e.name.clone()];
+                    if !e.username.is_empty() { parts.push(format!("username {}", e.username)); }
+                    if let Some(url) = &e.url { parts.push(format!("url {url}")); }
+                    parts.push(format!("matched {}", h.matched.label()));
+                    println!("{}.", parts.join(", "));
+                } else {
+                    let url = e.url.as_deref().map(|u| format!("  {u}")).unwrap_or_default();
+                    let via = if h.matched == search::Matched::Name { String::new() } else { format!("  [{}]", h.matched.label()) };
+                    println!("{}  ({}){url}{via}", e.name, e.username);
+                }
+            }
+        }
+        Cmd::Get { name: None, url: None, .. } => {
+            if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+                return Err(err!(InvalidInput, "entry name required when not on a terminal"));
+            }
+            let (mut v, u) = open_vault(&mut creds)?;
+            let items: Vec<String> = v.entries.iter().map(|e| {
+                if e.username.is_empty() { e.name.clone() } else { format!("{}  ({})", e.name, e.username) }
+            }).collect();
+            let Some(i) = picker::pick(&items, cfg.accessible)? else { return Ok(()) };
+            let e = &v.entries[i];
+            if e.password.is_empty() {
+                return Err(err!(NotFound, "entry {:?} has no password", e.name));
+            }
+            clipboard::copy(&e.password, cfg.clipboard_timeout())?;
+            println!("Copied password of {} to clipboard (clears in {}s).", e.name, cfg.clipboard_timeout());
+            let name = e.name.clone();
+            record_access(&mut v, &u, &cfg, &name, "get")?;
+        }
+        Cmd::Get { name, url, show, peek, spell, field, query, raw, login_bundle, output, copy, timeout } => {
+            let timeout = timeout.unwrap_or(cfg.clipboard_timeout());
+            let (mut v, u) = open_vault(&mut creds)?;
+            let name = match (name, url) {
+                (Some(name), _) => search::resolve(&v, &name)?,
+                (None, Some(url)) => search::resolve_url(&v, &url)?,
+                (None, None) => unreachable!("handled above"),
+            };
+            let e = v.entries.iter().find(|e| e.name == name)
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            if login_bundle {
+                // 1回のロック解除で必要な値をすべて返す（TOTPが無ければ null / 空行）
+                let otp = e.otp.as_ref().map(|o| o.now()).transpose()?;
+                if json {
+                    let bundle = serde_json::json!({
+                        "name": e.name,
+                        "username": e.username,
+                        "password": e.password,
+                        "url": e.url,
+                        "totp": otp.as_ref().map(|(code, left)| serde_json::json!({ "code": code, "expires_in": left })),
+                    });
+                    println!("{bundle}");
+                } else {
+                    println!("{}", e.username);
+                    println!("{}", e.password);
+                    println!("{}", otp.map(|(code, _)| code).unwrap_or_default());
+                }
+            } else if let Some(out) = output.or((json && field.is_none() && !peek && !spell && !copy).then_some(output::Output::Json)) {
+                println!("{}", output::render(out, "entry", &output::show_item(e, show)?)?.trim_end());
+            } else if let Some(key) = field {
+                // 決まった項目の名前はカスタムフィールドより優先する
+                let custom = if ENTRY_ITEMS.contains(&key.as_str()) { None } else { e.fields.iter().find(|f| f.name == key) };
+                let doc: Option<serde_json::Value> = match custom {
+                    Some(f) if f.json => Some(serde_json::from_str(&f.value)?),
+                    _ => None,
+                };
+                let selected = match (&query, &doc) {
+                    (Some(q), None) => return Err(err!(InvalidInput, "field {key:?} is not JSON; --query needs a field set with --json ({q})")),
+                    (Some(q), Some(doc)) => Some(query::query(doc, q)?),
+                    (None, doc) => doc.as_ref(),
+                };
+                let value = match selected {
+                    Some(s) => Zeroizing::new(query::render(s)),
+                    None => entry_value(e, &key)?,
+                };
+                if raw {
+                    let mut out = io::stdout().lock();
+                    io::Write::write_all(&mut out, value.as_bytes())?;
+                    io::Write::flush(&mut out)?;
+                } else if json && !copy {
+                    // JSONのフィールドは文字列ではなく構造のまま
+                    let value = selected.cloned().unwrap_or_else(|| value.as_str().into());
+                    println!("{}", serde_json::json!({ "name": name, "field": key, "value": value }));
+                } else if copy {
+                    clipboard::copy(&value, timeout)?;
+                    println!("Copied field {key} of {name} to clipboard (clears in {timeout}s).");
+                } else {
+                    println!("{}", *value);
+                }
+            } else if copy {
+                // カードはパスワードの代わりにカード番号
+                let (what, secret) = match &e.card {
+                    Some(c) if e.password.is_empty() => ("card number", &c.number),
+                    _ => ("password", &e.password),
+                };
+                if secret.is_empty() {
+                    return Err(err!(NotFound, "entry {name:?} has no password"));
+                }
+                clipboard::copy(secret, timeout)?;
+                println!("Copied {what} of {name} to clipboard (clears in {timeout}s).");
+            } else {
+                let kind = e.kind();
+                if kind != Kind::Login { println!("type: {}", kind.label()); }
+                if kind == Kind::Login || !e.username.is_empty() { println!("username: {}", e.username); }
+                if let Some(c) = &e.card {
+                    println!("card: {}", c.masked());
+                    if let Some(h) = &c.holder { println!("holder: {h}"); }
+                    if let Some(x) = &c.expires { println!("expires: {x}{}", if c.is_expired() { "  (expired)" } else { "" }); }
+                    if show {
+                        println!("number: {}", c.grouped());
+                        if let Some(cvv) = &c.cvv { println!("security code: {cvv}"); }
+                        if let Some(pin) = &c.pin { println!("pin: {pin}"); }
+                    } else if cfg.accessible {
+                        println!("number: hidden (use --show to reveal)");
+                    } else {
+                        println!("number: ******  (use --show to reveal)");
+                    }
+                }
+                if let Some(id) = &e.identity {
+                    for (k, val) in id.items() {
+                        match val.split_once('\n') {
+                            // 複数行の住所は字下げして続ける
+                            Some(_) => println!("{k}:\n  {}", val.replace('\n', "\n  ")),
+                            None => println!("{k}: {val}"),
+                        }
+                    }
+                }
+                if let Some(url) = &e.url { println!("url: {url}"); }
+                if !e.tags.is_empty() { println!("tags: {}", e.tags.join(", ")); }
+                match &e.notes {
+                    Some(notes) if kind == Kind::Note => println!("\n{notes}\n"),
+                    Some(notes) => println!("notes: {notes}"),
+                    None => {}
+                }
+                if let Some(seq) = &e.autotype { println!("autotype: {seq}"); }
+                if let Some(x) = &e.expires_at {
+                    let mark = if audit::expires_in(e).is_some_and(|d| d < 0) { "  (expired)" } else { "" };
+                    println!("expires: {x}{mark}");
+                }
+                if let Some(d) = e.max_age_days { println!("max age: {d} {}", if d == 1 { "day" } else { "days" }); }
+                if !e.attachments.is_empty() {
+                    let list: Vec<String> = e.attachments.iter().map(|a| format!("{} ({})", a.name, attachment::human(a.data.len()))).collect();
+                    println!("attachments: {}", list.join(", "));
+                }
+                if let Some(k) = &e.passkey {
+                    println!("passkey: {} on {}", k.rp_id, k.authenticator.as_deref().unwrap_or("unknown authenticator"));
+                    println!("credential id: {}", k.credential_id);
+                    if let Some(h) = &k.user_handle { println!("user handle: {h}"); }
+                    println!("backup eligible: {}", if k.backup_eligible { "yes" } else { "no" });
+                    if let Some(c) = &k.created { println!("registered: {c}"); }
+                }
+                if let Some(otp) = &e.otp {
+                    let (code, left) = otp.now()?;
+                    if cfg.accessible { println!("totp: {}, {left} seconds left", spell::digits(&code)); }
+                    else { println!("totp: {code}  ({left}s left)"); }
+                }
+                // ログイン以外はパスワードがあるときだけ表示する
+                if kind == Kind::Login || !e.password.is_empty() {
+                    if show { println!("password: {}", e.password); }
+                    else if peek && cfg.accessible { println!("password: {}", peek_spoken(&e.password)); }
+                    else if peek { println!("password: {}", peek_mask(&e.password)); }
+                    else if spell {
+                        println!("password:");
+                        let lines = if cfg.accessible { spell::spell_plain(&e.password) } else { spell::spell(&e.password) };
+                        for line in lines { println!("{line}"); }
+                    }
+                    else if cfg.accessible { println!("password: hidden (use --show to reveal)"); }
+                    else { println!("password: ******  (use --show to reveal)"); }
+                }
+                for f in &e.fields {
+                    if f.json { println!("{}: <JSON>  (use --field {} [--query ...])", f.name, f.name); }
+                    else if f.hidden && !show && cfg.accessible { println!("{}: hidden (use --show to reveal)", f.name); }
+                    else if f.hidden && !show { println!("{}: ******  (use --show to reveal)", f.name); }
+                    else { println!("{}: {}", f.name, f.value); }
+                }
+                if let Some(c) = &e.created_at { println!("created: {c}"); }
+                println!("updated: {}", e.updated_at);
+                if let Some(a) = &e.last_accessed { println!("last accessed: {a}"); }
+            }
+            record_access(&mut v, &u, &cfg, &name, "get")?;
+        }
+        Cmd::Gen { len, symbols, no_symbols, allow_ambiguous, words, wordlist, separator, capitalize, include_number, camel_case, layout_safe, no_repeat, no_sequences, interactive: true } => {
+            let (len, symbols) = (cfg.generator.length(len), cfg.generator.symbols(symbols, no_symbols));
+            if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+                return Err(err!(InvalidInput, "gen -i needs a terminal"));
+            }
+            let style = passphrase::Style { capitalize, include_number, camel_case };
+            let separator = passphrase::separator(&separator).to_string();
+            let avoid = Avoid { repeat: no_repeat, sequences: no_sequences };
+            let settings = generator::Settings { len, symbols, allow_ambiguous, layout_safe, avoid, words, wordlist, separator, style };
+            match generator::run(settings, cfg.accessible)? {
+                generator::Outcome::Accept(s) => println!("{}", s.as_str()),
+                generator::Outcome::Copy(s) => {
+                    clipboard::copy(&s, cfg.clipboard_timeout())?;
+                    eprintln!("Copied to clipboard (clears in {}s).", cfg.clipboard_timeout());
+                }
+                generator::Outcome::Cancel => {}
+            }
+        }
+        Cmd::Gen { len, symbols, no_symbols, allow_ambiguous, words, wordlist, separator, capitalize, include_number, camel_case, layout_safe, no_repeat, no_sequences, interactive: false } => {
+            let (len, symbols) = (cfg.generator.length(len), cfg.generator.symbols(symbols, no_symbols));
+            let s = match words {
+                Some(n) => {
+                    let list = passphrase::load_wordlist(&wordlist)?;
+                    let style = passphrase::Style { capitalize, include_number, camel_case };
+                    let bits = passphrase::bits_per_word(&list);
+                    let total = bits * n as f64 + style.extra_bits(n);
+                    eprintln!("{} words from {} ({:.1} bits/word, ~{:.0} bits total)", n, wordlist, bits, total);
+                    eprintln!("strength: {}", strength::Estimate::from_bits(total).summary());
+                    passphrase::generate_passphrase(&list, n, passphrase::separator(&separator), style)?
+                }
+                None => {
+                    let p = generate_password(len, symbols, allow_ambiguous, &layout_safe, Avoid { repeat: no_repeat, sequences: no_sequences })?;
+                    eprintln!("strength: {}", strength::estimate(&p, &[]).summary());
+                    p
+                }
+            };
+            println!("{}", s);
+        }
+        Cmd::ShareOnce { name, lan, port, ttl } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter().find(|e| e.name == name).ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            share::serve_once(Zeroizing::new(e.password.clone()), lan, port, std::time::Duration::from_secs(ttl))?;
+            record_access(&mut v, &u, &cfg, &name, "share-once")?;
+        }
+        Cmd::Share { name, to, out, force } => {
+            let recipients = share_recipients(&to)?;
+            let (mut v, u) = open_vault(&mut creds)?;
+            let mut entry = v.entries.iter().find(|e| e.name == name).cloned()
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            // 受け取る側には関係のない記録は渡さない
+            entry.canary = false;
+            entry.last_accessed = None;
+            let mut shared = Shared { rustpass_share: SHARE_VERSION, entry };
+            let plain = Zeroizing::new(serde_json::to_vec(&shared)?);
+            shared.entry.wipe();
+            let armored = agefile::encrypt(&plain, &recipients)?;
+            match &out {
+                Some(path) => export::write(path, armored.as_bytes(), force)?,
+                None => print!("{armored}"),
+            }
+            for r in &recipients {
+                eprintln!("Encrypted {name:?} to {r}");
+            }
+            record_access(&mut v, &u, &cfg, &name, "share")?;
+        }
+        Cmd::Receive { file, name, force } => {
+            let data = if file.as_os_str() == "-" {
+                let mut buf = Vec::new();
+                io::stdin().read_to_end(&mut buf)?;
+                buf
+            } else {
+                fs::read(&file).map_err(|e| anyhow!("cannot read {}: {e}", file.display()))?
+            };
+            if !agefile::is_age(&data) {
+                return Err(err!(InvalidInput, "{} is not an age-encrypted file", file.display()));
+            }
+            let ids = creds.identity_keys()?;
+            let plain = agefile::decrypt(&data, &ids, &mut || Err(err!(InvalidInput, "{} is passphrase-encrypted, not a rustpass share", file.display())))?;
+            let Shared { rustpass_share, entry: mut e } = serde_json::from_slice(&plain)
+                .map_err(|_| err!(InvalidInput, "{} is not a rustpass share", file.display()))?;
+            if rustpass_share != SHARE_VERSION {
+                e.wipe();
+                return Err(err!(UnsupportedFormat, "share format {rustpass_share} is not supported by this rustpass"));
+            }
+            if let Some(n) = name { e.name = n; }
+            // 送り手の金庫のエントリと区別する
+            e.id = Entry::new(String::new()).id;
+            let (mut v, u) = open_vault(&mut creds)?;
+            let mut trashed = false;
+            if let Some(i) = v.entries.iter().position(|x| x.name == e.name) {
+                if !force {
+                    let conflict = name_free(&v, &e.name, "receive it with --name NEW, or replace it with --force");
+                    e.wipe();
+                    return conflict;
+                }
+                let old = v.entries.remove(i);
+                trashed = trash::discard(&mut v, old, cfg.trash_retention_days()?);
+            }
+            let received = e.name.clone();
+            v.entries.push(e);
+            save(&v, &u)?;
+            println!("Received {received:?}{}.", if trashed { " (the previous entry was moved to the trash)" } else { "" });
+        }
+        Cmd::Exec { entries, vars, env_file, command } => {
+            if entries.len() != vars.len() {
+                return Err(err!(InvalidInput, "give one --as for each --entry ({} --entry, {} --as)", entries.len(), vars.len()));
+            }
+            let bindings = vars.iter().map(|s| exec::Binding::parse(s)).collect::<Result<Vec<_>>>()?;
+            let mut seen = std::collections::HashSet::new();
+            if let Some(b) = bindings.iter().find(|b| !seen.insert(b.var.as_str())) {
+                return Err(err!(InvalidInput, "duplicate variable {:?}", b.var));
+            }
+            if let Some(var) = &env_file { exec::check_var(var)?; }
+            let (mut v, u) = open_vault(&mut creds)?;
+            let mut values = Vec::new();
+            for (name, b) in entries.iter().zip(&bindings) {
+                let e = v.entries.iter().find(|e| &e.name == name)
+                    .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+                values.push((b.var.clone(), entry_value(e, &b.item)?));
+            }
+            let mut recorded = std::collections::HashSet::new();
+            for name in entries.iter().filter(|n| recorded.insert(n.as_str())) {
+                record_access(&mut v, &u, &cfg, name, "exec")?;
+            }
+            // 子プロセスが動いている間は金庫を持たず、ほかのコマンドも待たせない
+            drop((v, u));
+            lock::release();
+            let code = exec::run(&command, values, env_file.as_deref())?;
+            if code != 0 { std::process::exit(code); }
+        }
+        Cmd::Audit { cmd: None, policy, passkeys } => {
+            let (v, _) = open_vault(&mut creds)?;
+            audit::run(&v, &cfg, policy, passkeys, json)?;
+        }
+        Cmd::Audit { cmd: Some(AuditCmd::Hibp { offline }), .. } => {
+            if let Some(p) = offline.as_deref().filter(|p| !p.exists()) {
+                return Err(err!(NotFound, "{} not found", p.display()));
+            }
+            let (v, _) = open_vault(&mut creds)?;
+            let (checked, breached) = hibp::check(&v, offline.as_deref())?;
+            if json {
+                let out: Vec<serde_json::Value> = breached.iter().map(|b| serde_json::json!({ "name": b.name, "count": b.count })).collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            } else {
+                if cfg.accessible { println!("{} of {checked} passwords found in breaches.", breached.len()); }
+                for b in &breached {
+                    println!("{}: password seen {} {} in known breaches; change it", b.name, b.count, if b.count == 1 { "time" } else { "times" });
+                }
+                if !cfg.accessible { println!("{} of {checked} passwords found in breaches.", breached.len()); }
+            }
+        }
+        Cmd::Audit { cmd: Some(AuditCmd::Strength { weak }), .. } => {
+            let (v, _) = open_vault(&mut creds)?;
+            audit::strength(&v, &cfg, weak, json)?;
+        }
+        Cmd::Audit { cmd: Some(AuditCmd::Age { older_than }), .. } => {
+            let older_than = older_than.as_deref().map(config::parse_days).transpose()?;
+            let (v, _) = open_vault(&mut creds)?;
+            audit::age(&v, &cfg, older_than, json)?;
+        }
+        Cmd::Audit { cmd: Some(AuditCmd::Reuse), .. } => {
+            let (v, _) = open_vault(&mut creds)?;
+            audit::reuse(&v, &cfg, json)?;
+        }
+        Cmd::Sync { cmd: Some(SyncCmd::Init { remote, branch }), .. } => {
+            let path = vault_path()?;
+            let local = VaultFile::current()?.load()?;
+            let (repo, joined) = sync::init(&path, &remote, &branch, local.as_deref())?;
+            match joined {
+                sync::Joined::Empty => println!("Sync set up with {remote} (branch {branch}); run `rustpass sync` to push this vault."),
+                sync::Joined::Fetched(data) => {
+                    // 開けることを確かめてから使う
+                    let (v, _) = open_data(&data, &mut creds)?;
+                    VaultFile::current()?.store(&data)?;
+                    println!("Fetched the vault ({} entries) from {remote}.", v.entries.len());
+                }
+                sync::Joined::Same => println!("Sync set up with {remote}; this vault matches the copy there."),
+                sync::Joined::Differs => {
+                    println!("Sync set up with {remote}, which already has a different copy of the vault.");
+                    println!("Run `rustpass sync` to reconcile them.");
+                }
+            }
+            println!("Repository: {}", repo.dir.display());
+        }
+        Cmd::Sync { cmd: Some(SyncCmd::Status), .. } => {
+            let path = vault_path()?;
+            let repo = sync::Repo::open(&path)?;
+            println!("Remote:     {}", repo.remote()?);
+            println!("Branch:     {}", repo.branch);
+            println!("Repository: {}", repo.dir.display());
+            println!("Last sync:  {}", repo.last()?);
+            let changed = VaultFile::current()?.load()?.is_some_and(|data| repo.committed() != Some(data));
+            println!("This vault: {}", if changed { "changed since the last sync" } else { "unchanged since the last sync" });
+            repo.fetch()?;
+            println!("Remote:     {}", match repo.state()? {
+                sync::State::Behind | sync::State::Diverged => "has changes to pull",
+                sync::State::UpToDate | sync::State::Ahead => "nothing new",
+            });
+        }
+        Cmd::Sync { cmd: None, prefer } => {
+            // 同期は手元の金庫もリモートの金庫も書き換えうる
+            readonly::check()?;
+            let path = vault_path()?;
+            let repo = sync::Repo::open(&path)?;
+            if !path.exists() {
+                return Err(err!(NoVault, "no vault at {}", path.display()));
+            }
+            // パスワードを確かめ、同期が終わるまで他のプロセスに書き込ませない
+            let (mut v, u) = open_vault(&mut creds)?;
+            let remote = repo.remote()?;
+            let ours = VaultFile::current()?.read()?;
+            repo.fetch()?;
+            repo.commit(&ours)?;
+            match (repo.state()?, prefer) {
+                (sync::State::UpToDate, _) => println!("Already up to date with {remote}."),
+                (sync::State::Ahead, _) => {
+                    repo.push()?;
+                    println!("Pushed this vault to {remote}.");
+                }
+                (sync::State::Behind, _) => {
+                    let data = repo.fast_forward()?;
+                    let (v, _) = open_data(&data, &mut creds)?;
+                    VaultFile::current()?.store(&data)?;
+                    println!("Pulled the vault from {remote} ({} entries).", v.entries.len());
+                }
+                (sync::State::Diverged, _) => {
+                    let theirs = open_other(&repo.theirs()?, &mut creds, "The copy on the remote")?;
+                    let s = match repo.base()? {
+                        Some(data) => {
+                            // 祖先の金庫は古いパスワードで暗号化されていることがある
+                            let base = if data.is_empty() { Ok(Vault::default()) } else { open_other(&data, &mut creds, "The last synced vault") };
+                            match base {
+                                Ok(base) => merge::three_way(&mut v, &base, theirs, prefer, cfg.accessible)?,
+                                Err(e) => {
+                                    eprintln!("note: cannot open the last synced vault ({e:#}); comparing the two copies directly");
+                                    merge::run(&mut v, theirs, prefer, cfg.accessible)?
+                                }
+                            }
+                        }
+                        // 別々に作った金庫（sync init で違う金庫があった）
+                        None => merge::run(&mut v, theirs, prefer, cfg.accessible)?,
+                    };
+                    save(&v, &u)?;
+                    repo.merge(&VaultFile::current()?.read()?, &format!("Merge vault changes from {remote}"))?;
+                    repo.push()?;
+                    println!(
+                        "Merged with {remote}: {} added, {} updated, {} deleted, {} kept as copies, {} kept local, {} restored.",
+                        s.added, s.updated, s.deleted, s.kept_both, s.kept_local, s.restored
+                    );
+                    if s.restored > 0 {
+                        eprintln!("note: kept {} that was deleted on one side but changed on the other", if s.restored == 1 { "1 entry".to_string() } else { format!("{} entries", s.restored) });
+                    }
+                }
+            }
+        }
+        Cmd::Backup { cmd: BackupCmd::Create { rclone, keep } } => {
+            let path = backup::create()?;
+            println!("Backup written to {:?}", path);
+            if let Some(remote) = rclone {
+                let object = backup::push_rclone(&path, &remote)?;
+                println!("Uploaded to {object} (sha256 verified)");
+            }
+            // 手元のスナップショットだけを整理する（リモートはそのまま）
+            if let Some(keep) = keep.or(cfg.backup_keep) {
+                let removed = backup::prune(keep)?;
+                if !removed.is_empty() { println!("Removed {} old backups (keeping {keep}).", removed.len()); }
+            }
+        }
+        Cmd::Backup { cmd: BackupCmd::Verify { all } } => {
+            backup::verify(&mut creds, all)?;
+        }
+        Cmd::Sshkey { cmd: SshKeyCmd::New { name, key_type, comment, export_openssh } }
+        | Cmd::Add { item: Some(AddItem::SshKey { name, key_type, comment, export_openssh }), .. } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            name_free(&v, &name, "choose another name, or `rustpass rename` the existing entry first")?;
+            let key = sshkey::generate(key_type, comment.as_deref().unwrap_or(&name))?;
+            if let Some(path) = &export_openssh {
+                sshkey::export_private(&key, path)?;
+                eprintln!("Private key written to {:?} (delete it when done)", path);
+            }
+            println!("{}", key.public_key);
+            v.entries.push(Entry { ssh_key: Some(key), ..Entry::new(name) });
+            save(&v, &u)?;
+        }
+        Cmd::Sshkey { cmd: SshKeyCmd::Show { name, export_openssh } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let key = v.entries.iter()
+                .find(|e| e.name == name)
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?
+                .ssh_key.as_ref()
+                .ok_or_else(|| err!(NotFound, "entry {name:?} has no ssh key"))?;
+            if let Some(path) = &export_openssh {
+                sshkey::export_private(key, path)?;
+                eprintln!("Private key written to {:?} (delete it when done)", path);
+            }
+            println!("{}", key.public_key);
+            if export_openssh.is_some() {
+                record_access(&mut v, &u, &cfg, &name, "sshkey show")?;
+            }
+        }
+        Cmd::Cert { cmd: CertCmd::Add { name, cert, key, chain, expires } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            name_free(&v, &name, "choose another name, or `rustpass rename` the existing entry first")?;
+            let c = cert::load(&cert, &key, chain.as_deref(), expires.as_deref())?;
+            println!("Certificate expires {}", c.not_after);
+            v.entries.push(Entry { cert: Some(c), ..Entry::new(name) });
+            save(&v, &u)?;
+            println!("Saved.");
+        }
+        Cmd::Cert { cmd: CertCmd::Export { name, out, force } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let c = v.entries.iter()
+                .find(|e| e.name == name)
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?
+                .cert.as_ref()
+                .ok_or_else(|| err!(NotFound, "entry {name:?} has no certificate"))?;
+            for f in cert::export(c, &out, force)? {
+                println!("{}", out.join(f).display());
+            }
+            record_access(&mut v, &u, &cfg, &name, "cert export")?;
+        }
+        Cmd::Passkey { cmd: PasskeyCmd::Add { name, rp, credential_id, user, user_handle, authenticator, backup_eligible, created } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            name_free(&v, &name, "choose another name, or `rustpass rename` the existing entry first")?;
+            let created = created.unwrap_or_else(|| now_iso()[..10].to_string());
+            let p = passkey::Passkey::new(&rp, &credential_id, user_handle.as_deref(), authenticator, backup_eligible, Some(&created))?;
+            if v.entries.iter().filter_map(|e| e.passkey.as_ref()).any(|k| k.rp_id == p.rp_id && k.credential_id == p.credential_id) {
+                return Err(err!(AlreadyExists, "a passkey with this credential id is already recorded for {}", p.rp_id));
+            }
+            let url = Some(format!("https://{}", p.rp_id));
+            v.entries.push(Entry { username: user.unwrap_or_default(), url, passkey: Some(p), ..Entry::new(name) });
+            save(&v, &u)?;
+            println!("Saved.");
+        }
+        Cmd::Passkey { cmd: PasskeyCmd::List } => {
+            let (v, _) = open_vault(&mut creds)?;
+            let mut keys: Vec<(&Entry, &passkey::Passkey)> = v.entries.iter().filter_map(|e| Some((e, e.passkey.as_ref()?))).collect();
+            keys.sort_by(|a, b| (&a.1.rp_id, &a.0.username).cmp(&(&b.1.rp_id, &b.0.username)));
+            if json {
+                let out: Vec<serde_json::Value> = keys.iter().map(|(e, k)| serde_json::json!({
+                    "name": e.name,
+                    "username": e.username,
+                    "rp_id": k.rp_id,
+                    "credential_id": k.credential_id,
+                    "user_handle": k.user_handle,
+                    "authenticator": k.authenticator,
+                    "backup_eligible": k.backup_eligible,
+                    "created": k.created,
+                })).collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+                return Ok(());
+            }
+            if keys.is_empty() { println!("No passkeys recorded."); }
+            for (e, k) in keys {
+                println!(
+                    "{}  {}  ({})  {}  {}  created {}",
+                    k.rp_id,
+                    if e.username.is_empty() { "-" } else { &e.username },
+                    e.name,
+                    k.authenticator.as_deref().unwrap_or("unknown authenticator"),
+                    if k.backup_eligible { "synced" } else { "device-bound" },
+                    k.created.as_deref().unwrap_or("-"),
+                );
+            }
+        }
+        Cmd::Field { cmd: FieldCmd::Set { entry, key, value, json, hidden, visible } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == entry).ok_or_else(|| err!(NotFound, "entry {entry:?} not found"))?;
+            let value = match value.as_deref() {
+                Some("-") => {
+                    let mut s = String::new();
+                    io::stdin().read_to_string(&mut s)?;
+                    s
+                }
+                Some(_) => value.unwrap_or_default(),
+                None => prompt::password(format!("Value of {key} (hidden): "))?,
+            };
+            if json {
+                serde_json::from_str::<serde_json::Value>(&value)
+                    .map_err(|e| err!(InvalidInput, "value is not valid JSON: {e}"))?;
+            }
+            match e.fields.iter_mut().find(|f| f.name == key) {
+                Some(f) => {
+                    f.value = value;
+                    f.json = json;
+                    if hidden || visible { f.hidden = hidden; }
+                }
+                None => e.fields.push(Field { name: key, value, json, hidden }),
+            }
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Saved.");
+        }
+        Cmd::Field { cmd: FieldCmd::Get { entry, key, show, copy } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter().find(|e| e.name == entry).ok_or_else(|| err!(NotFound, "entry {entry:?} not found"))?;
+            let f = e.fields.iter().find(|f| f.name == key)
+                .ok_or_else(|| err!(NotFound, "field {key:?} not found in {entry:?}"))?;
+            let masked = f.hidden && !show && !copy;
+            if copy {
+                clipboard::copy(&f.value, cfg.clipboard_timeout())?;
+                println!("Copied field {key} of {entry} to clipboard (clears in {}s).", cfg.clipboard_timeout());
+            } else if json {
+                let value = (!masked).then_some(&f.value);
+                println!("{}", serde_json::json!({ "name": entry, "field": key, "value": value, "hidden": f.hidden }));
+            } else if masked {
+                println!("{}", if cfg.accessible { "hidden (use --show to reveal)" } else { "******  (use --show to reveal)" });
+            } else {
+                println!("{}", f.value);
+            }
+            if !masked { record_access(&mut v, &u, &cfg, &entry, "field get")?; }
+        }
+        Cmd::Field { cmd: FieldCmd::Rm { entry, key } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == entry).ok_or_else(|| err!(NotFound, "entry {entry:?} not found"))?;
+            let i = e.fields.iter().position(|f| f.name == key)
+                .ok_or_else(|| err!(NotFound, "field {key:?} not found in {entry:?}"))?;
+            e.fields.remove(i);
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Removed field {key} from {entry}.");
+        }
+        Cmd::Trash { cmd: TrashCmd::List } => {
+            let retention = cfg.trash_retention_days()?;
+            let (mut v, u) = open_vault(&mut creds)?;
+            if trash::purge(&mut v, retention) > 0 { save(&v, &u)?; }
+            let left = |t: &trash::Trashed| t.days_left(retention).unwrap_or(retention).max(0);
+            if json {
+                let out: Vec<_> = v.trash.iter().map(|t| serde_json::json!({
+                    "name": t.entry.name, "type": t.entry.kind(), "deleted": t.deleted, "days_left": left(t),
+                })).collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+                return Ok(());
+            }
+            if v.trash.is_empty() {
+                println!("The trash is empty.");
+                return Ok(());
+            }
+            for t in &v.trash {
+                let days = match left(t) { 1 => "1 day".to_string(), n => format!("{n} days") };
+                if cfg.accessible { println!("{}, deleted {}, {days} left.", t.entry.name, t.deleted); }
+                else { println!("{}  deleted {}  ({days} left)", t.entry.name, t.deleted); }
+            }
+        }
+        Cmd::Trash { cmd: TrashCmd::Restore { name } } => {
+            let retention = cfg.trash_retention_days()?;
+            let (mut v, u) = open_vault(&mut creds)?;
+            trash::purge(&mut v, retention);
+            trash::restore(&mut v, &name)?;
+            save(&v, &u)?;
+            println!("Restored {name}.");
+        }
+        Cmd::Trash { cmd: TrashCmd::Empty { force } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            if v.trash.is_empty() {
+                println!("The trash is already empty.");
+                return Ok(());
+            }
+            if !force {
+                prompt::require("confirmation (pass --force)")?;
+                let n = v.trash.len();
+                let s = prompt::line(&format!("Permanently delete {n} {}? [y/N]: ", if n == 1 { "entry" } else { "entries" }))?;
+                if !matches!(s.to_lowercase().as_str(), "y" | "yes") {
+                    println!("Cancelled.");
+                    return Ok(());
+                }
+            }
+            let n = trash::empty(&mut v);
+            save(&v, &u)?;
+            println!("Deleted {n} {} permanently.", if n == 1 { "entry" } else { "entries" });
+        }
+        Cmd::Attach { cmd: AttachCmd::Add { entry, file, name, force } } => {
+            let name = match name {
+                Some(n) => n,
+                None => file.file_name().map(|n| n.to_string_lossy().into_owned())
+                    .ok_or_else(|| err!(InvalidInput, "{} has no file name; give one with --name", file.display()))?,
+            };
+            let size = fs::metadata(&file)?.len();
+            let data = Zeroizing::new(fs::read(&file)?);
+            let (mut v, u) = open_vault(&mut creds)?;
+            let i = v.entries.iter().position(|e| e.name == entry).ok_or_else(|| err!(NotFound, "entry {entry:?} not found"))?;
+            let existing = v.entries[i].attachments.iter().position(|a| a.name == name);
+            if existing.is_some() && !force {
+                return Err(err!(AlreadyExists, "{entry:?} already has an attachment named {name:?} (use --force to replace it)"));
+            }
+            let replacing = existing.map_or(0, |j| v.entries[i].attachments[j].data.len());
+            attachment::check(&v, data.len(), replacing)?;
+            let a = attachment::Attachment { name: name.clone(), data: data.to_vec(), added: now_iso() };
+            let e = &mut v.entries[i];
+            match existing {
+                Some(j) => e.attachments[j] = a,
+                None => e.attachments.push(a),
+            }
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Attached {name} ({}) to {entry}.", attachment::human(size as usize));
+        }
+        Cmd::Attach { cmd: AttachCmd::List { entry } } => {
+            let (v, _) = open_vault(&mut creds)?;
+            if let Some(name) = &entry {
+                if !v.entries.iter().any(|e| &e.name == name) {
+                    return Err(err!(NotFound, "entry {name:?} not found"));
+                }
+            }
+            let items: Vec<(&Entry, &attachment::Attachment)> = v.entries.iter()
+                .filter(|e| entry.as_ref().is_none_or(|n| &e.name == n))
+                .flat_map(|e| e.attachments.iter().map(move |a| (e, a)))
+                .collect();
+            if json {
+                let out: Vec<_> = items.iter().map(|(e, a)| serde_json::json!({
+                    "entry": e.name, "name": a.name, "size": a.data.len(), "added": a.added,
+                })).collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+                return Ok(());
+            }
+            if cfg.accessible { println!("{} attachments.", items.len()); }
+            for (e, a) in &items {
+                if cfg.accessible { println!("{}, in {}, {}, added {}.", a.name, e.name, attachment::human(a.data.len()), a.added); }
+                else { println!("{}  {}  {}  added {}", e.name, a.name, attachment::human(a.data.len()), a.added); }
+            }
+            if entry.is_none() {
+                println!("{} of {} used.", attachment::human(attachment::total(&v)), attachment::human(attachment::MAX_TOTAL));
+            }
+        }
+        Cmd::Attach { cmd: AttachCmd::Get { entry, name, out, force } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter().find(|e| e.name == entry).ok_or_else(|| err!(NotFound, "entry {entry:?} not found"))?;
+            let a = e.attachments.iter().find(|a| a.name == name)
+                .ok_or_else(|| err!(NotFound, "attachment {name:?} not found in {entry:?}"))?;
+            if out.as_os_str() == "-" {
+                io::Write::write_all(&mut io::stdout(), &a.data)?;
+            } else {
+                export::write(&out, &a.data, force)?;
+                eprintln!("Wrote {name} to {} (delete it when done).", out.display());
+            }
+            record_access(&mut v, &u, &cfg, &entry, "attach get")?;
+        }
+        Cmd::Attach { cmd: AttachCmd::Rm { entry, name } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == entry).ok_or_else(|| err!(NotFound, "entry {entry:?} not found"))?;
+            let i = e.attachments.iter().position(|a| a.name == name)
+                .ok_or_else(|| err!(NotFound, "attachment {name:?} not found in {entry:?}"))?;
+            e.attachments.remove(i).data.zeroize();
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Removed {name} from {entry}.");
+        }
+        Cmd::Passwd => {
+            let path = vault_path()?;
+            if !path.exists() {
+                return Err(err!(NotFound, "no vault at {}", path.display()));
+            }
+            // エージェントの鍵では開かず、現在のパスワードを必ず確認する
+            let (v, mut u) = format::unlock(&VaultFile::current()?.read()?, &mut creds)?;
+            if !u.has_password() {
+                return Err(err!(InvalidInput, "vault has no master password slot (it is quorum-only or encrypted to age recipients)"));
+            }
+            let current = format::Credentials::password(&mut creds)?;
+            // --password-file などで渡されたのは今のパスワードなので、新しいものは必ず入力させる
+            creds.password = None;
+            let new = new_master_password(&mut creds)?;
+            if new == current {
+                return Err(err!(InvalidInput, "new master password is the same as the current one"));
+            }
+            u.change_password(&current, &new)?;
+            save(&v, &u)?;
+            println!("Master password changed.");
+        }
+        Cmd::Keyfile { cmd } => {
+            let path = vault_path()?;
+            if !path.exists() {
+                return Err(err!(NotFound, "no vault at {}", path.display()));
+            }
+            // エージェントの鍵では開かず、パスワードと今のキーファイルを確認する
+            let (v, mut u) = format::unlock(&VaultFile::current()?.read()?, &mut creds)?;
+            if !u.has_password() {
+                return Err(err!(InvalidInput, "vault has no master password slot (it is quorum-only or encrypted to age recipients)"));
+            }
+            let password = format::Credentials::password(&mut creds)?;
+            match cmd {
+                KeyfileCmd::Set { path } => {
+                    let contents = new_keyfile(&path)?;
+                    u.set_keyfile(&password, Some(&contents))?;
+                    save(&v, &u)?;
+                    println!("Key file set; unlocking now needs the master password and {}.", path.display());
+                }
+                KeyfileCmd::Remove => {
+                    if !u.keyfile_required() {
+                        return Err(err!(NotFound, "vault does not use a key file"));
+                    }
+                    u.set_keyfile(&password, None)?;
+                    save(&v, &u)?;
+                    println!("Key file removed; the master password alone unlocks the vault.");
+                }
+            }
+        }
+        Cmd::AgeKeygen { output } => {
+            let id = age::Identity::generate();
+            let recipient = id.to_public();
+            let text = Zeroizing::new(format!("# created: {}\n# public key: {recipient}\n{}\n", now_iso(), *age::secret_string(&id)));
+            match &output {
+                Some(path) => {
+                    export::write(path, text.as_bytes(), false)?;
+                    eprintln!("Identity written to {}; keep a copy somewhere safe", path.display());
+                }
+                None => print!("{}", *text),
+            }
+            eprintln!("Public key: {recipient}");
+        }
+        Cmd::Rekey { token, kdf_memory, kdf_time, kdf_parallelism } => {
+            let spec = token.as_deref().map(hardware::parse).transpose()?;
+            let path = vault_path()?;
+            if !path.exists() {
+                return Err(err!(NotFound, "no vault at {}", path.display()));
+            }
+            // エージェントの鍵では開かず、パスワードと今のトークンで確認する
+            let (v, mut u) = format::unlock(&VaultFile::current()?.read()?, &mut creds)?;
+            if !u.has_password() {
+                return Err(err!(InvalidInput, "vault has no master password slot (it is quorum-only or encrypted to age recipients)"));
+            }
+            let password = format::Credentials::password(&mut creds)?;
+            let current = u.kdf().unwrap_or_default();
+            let kdf = kdf_override(cfg.kdf.unwrap_or(current), kdf_memory, kdf_time, kdf_parallelism)?;
+            let kdf = match &creds.min_kdf {
+                Some(min) => kdf.at_least(min),
+                None => kdf,
+            };
+            if kdf != current {
+                println!("Key derivation: argon2id m={}KiB t={} p={} -> m={}KiB t={} p={}", current.m, current.t, current.p, kdf.m, kdf.t, kdf.p);
+                if kdf.weaker_than(&current) {
+                    eprintln!("warning: the new parameters are weaker than the current ones in at least one cost");
+                }
+            }
+            // --token が無ければ今のトークンのまま、新しいチャレンジにする
+            let token = match spec {
+                Some(spec) => hardware::enroll(spec)?,
+                None => u.token().cloned().map(hardware::challenge).transpose()?,
+            };
+            u.rekey(&password, kdf, token.as_ref().map(|(f, r)| (f.clone(), r.as_slice())))?;
+            save(&v, &u)?;
+            match u.token() {
+                Some(t) => println!("Key slot rebuilt; unlocking now needs the master password and the {}.", t.describe()),
+                None => println!("Key slot rebuilt with a new salt; no hardware token is needed to unlock."),
+            }
+        }
+        Cmd::Mfa { cmd: MfaCmd::Enable } => {
+            let (v, mut u) = open_vault(&mut creds)?;
+            if u.totp().is_some() {
+                return Err(err!(AlreadyExists, "TOTP unlock is already enabled"));
+            }
+            if !u.has_password() {
+                return Err(err!(InvalidInput, "TOTP unlock needs a master password slot (vault is quorum-only or encrypted to age recipients)"));
+            }
+            let secret = mfa::enroll()?;
+            let password = format::Credentials::password(&mut creds)?;
+            u.rewrap_password(&password, Some(&secret))?;
+            save(&v, &u)?;
+            println!("TOTP unlock enabled.");
+        }
+        Cmd::Mfa { cmd: MfaCmd::Disable } => {
+            let (v, mut u) = open_vault(&mut creds)?;
+            if u.totp().is_none() {
+                return Err(err!(NotFound, "TOTP unlock is not enabled"));
+            }
+            let password = format::Credentials::password(&mut creds)?;
+            u.rewrap_password(&password, None)?;
+            save(&v, &u)?;
+            println!("TOTP unlock disabled.");
+        }
+        Cmd::Totp { name, set: Some(secret), algorithm, digits, period, .. } => {
+            let secret = if secret == "-" { Zeroizing::new(prompt::password("TOTP secret or otpauth:// URI: ")?) } else { Zeroizing::new(secret) };
+            let mut otp = totp::parse_secret(&secret)?;
+            if let Some(a) = algorithm { otp.algorithm = a; }
+            if let Some(d) = digits { otp.digits = d; }
+            if let Some(p) = period { otp.period = p; }
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == name).ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            let (code, _) = otp.now()?;
+            let replaced = e.otp.replace(otp).is_some();
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("{} TOTP of {name}; current code: {code}", if replaced { "Replaced" } else { "Saved" });
+        }
+        Cmd::Totp { name, remove: true, .. } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == name).ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            if e.otp.take().is_none() {
+                return Err(err!(NotFound, "entry {name:?} has no TOTP"));
+            }
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Removed TOTP of {name}.");
+        }
+        Cmd::Totp { name, .. } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter().find(|e| e.name == name).ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            let otp = e.otp.as_ref().ok_or_else(|| err!(NotFound, "entry {name:?} has no TOTP (add one with --set)"))?;
+            let (code, left) = otp.now()?;
+            if json {
+                println!("{}", serde_json::json!({ "code": code, "expires_in": left }));
+            } else if cfg.accessible {
+                println!("{}, {left} seconds left", spell::digits(&code));
+            } else {
+                println!("{code}");
+                if io::stderr().is_terminal() { eprintln!("({left}s left)"); }
+            }
+            record_access(&mut v, &u, &cfg, &name, "totp")?;
+        }
+        Cmd::Derive { site, login, length, counter, charset } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let existing = v.entries.iter().position(|e| {
+                e.name == site && e.derive.as_ref().is_some_and(|p| p.login == login)
+            });
+            let stored = existing.and_then(|i| v.entries[i].derive.clone());
+            let mut profile = stored.clone().unwrap_or_else(|| derive::Profile::new(login.clone()));
+            if let Some(n) = length { profile.length = n; }
+            if let Some(n) = counter { profile.counter = n; }
+            if let Some(c) = charset { profile.classes = c; }
+
+            let password = format::Credentials::password(&mut creds)?;
+            let derived = derive::derive(&password, &site, &profile)?;
+            if stored.as_ref() != Some(&profile) {
+                match existing {
+                    Some(i) => {
+                        v.entries[i].derive = Some(profile);
+                        v.entries[i].updated_at = now_iso();
+                    }
+                    None => v.entries.push(Entry { username: login, derive: Some(profile), ..Entry::new(site) }),
+                }
+                save(&v, &u)?;
+                eprintln!("Site settings saved.");
+            }
+            println!("{}", derived.as_str());
+        }
+        Cmd::Edit { name, user, password, gen, len, symbols, no_symbols, allow_ambiguous, url, notes } => {
+            let (len, symbols) = (cfg.generator.length(len), cfg.generator.symbols(symbols, no_symbols));
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter().find(|e| e.name == name)
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            let ask = |label: &str, current: &str| -> Result<String> {
+                if current.is_empty() { prompt::line(&format!("{label}: ")) } else { prompt::line(&format!("{label} [{current}]: ")) }
+            };
+            // 何も指定しなければ対話モード：空欄で現在の値を残し、url / notes は - で削除
+            let interactive = user.is_none() && !password && !gen && url.is_none() && notes.is_none();
+            let (mut user, mut url, mut notes, mut new_password) = (user, url, notes, None);
+            if interactive {
+                let keep = |s: String| (!s.is_empty()).then_some(s);
+                let clear = |s: String| match s.as_str() { "" => None, "-" => Some(String::new()), _ => Some(s) };
+                user = keep(ask("Username", &e.username)?);
+                url = clear(ask("URL (- to clear)", e.url.as_deref().unwrap_or_default())?);
+                notes = clear(ask("Notes (- to clear)", e.notes.as_deref().unwrap_or_default())?);
+                new_password = keep(prompt::password("New password (hidden, empty to keep): ")?);
+            } else if password {
+                new_password = Some(prompt::password("New password (hidden): ")?);
+            } else if gen {
+                let g = generate_password(len, symbols, allow_ambiguous, &[], Avoid::default())?;
+                println!("Generated password (len={}): {}", len, g);
+                new_password = Some(g);
+            }
+            if let Some(p) = &new_password {
+                policy::enforce(&v, &name, p)?;
+            }
+            let e = v.entries.iter_mut().find(|e| e.name == name).unwrap();
+            let opt = |s: String| if s.is_empty() { None } else { Some(s) };
+            let mut changed = Vec::new();
+            if let Some(x) = user.filter(|x| *x != e.username) { e.username = x; changed.push("username"); }
+            if let Some(x) = new_password.filter(|x| *x != e.password) { e.set_password(x); changed.push("password"); }
+            if let Some(x) = url.map(opt).filter(|x| *x != e.url) { e.url = x; changed.push("url"); }
+            if let Some(x) = notes.map(opt).filter(|x| *x != e.notes) { e.notes = x; changed.push("notes"); }
+            if changed.is_empty() {
+                println!("No changes.");
+                return Ok(());
+            }
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Updated {} of {name}.", changed.join(", "));
+        }
+        Cmd::Rename { old, new } => {
+            let new = new.trim().to_string();
+            if new.is_empty() { return Err(err!(InvalidInput, "the new name is empty")); }
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter().find(|e| e.name == old)
+                .ok_or_else(|| err!(NotFound, "entry {old:?} not found"))?;
+            if new == old {
+                println!("{old} already has that name.");
+                return Ok(());
+            }
+            // derive はサイト名（＝エントリ名）から導出するので、名前を変えると別のパスワードになる
+            if e.derive.is_some() {
+                return Err(err!(InvalidInput, "entry {old:?} holds derive settings for the site {old:?}; renaming it would change the derived password"));
+            }
+            name_free(&v, &new, "choose another name, or rename or remove that entry first")?;
+            let e = v.entries.iter_mut().find(|e| e.name == old).unwrap();
+            e.name = new.clone();
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Renamed {old} to {new}.");
+        }
+        Cmd::Rm { names, force } => {
+            let names: Vec<String> = names.iter().enumerate().filter(|(i, n)| !names[..*i].contains(n)).map(|(_, n)| n.clone()).collect();
+            let (mut v, u) = open_vault(&mut creds)?;
+            if let Some(n) = names.iter().find(|n| !v.entries.iter().any(|e| &e.name == *n)) {
+                return Err(err!(NotFound, "entry {n:?} not found"));
+            }
+            if !force {
+                prompt::require("confirmation (pass --force)")?;
+                let count = if names.len() == 1 { "1 entry".to_string() } else { format!("{} entries", names.len()) };
+                let s = prompt::line(&format!("Remove {count} ({})? [y/N]: ", names.join(", ")))?;
+                if !matches!(s.to_lowercase().as_str(), "y" | "yes") {
+                    println!("Cancelled.");
+                    return Ok(());
+                }
+            }
+            let retention = cfg.trash_retention_days()?;
+            let (gone, kept): (Vec<Entry>, Vec<Entry>) = std::mem::take(&mut v.entries).into_iter().partition(|e| names.contains(&e.name));
+            v.entries = kept;
+            let n = gone.len();
+            let mut trashed = false;
+            for e in gone { trashed = trash::discard(&mut v, e, retention); }
+            save(&v, &u)?;
+            let count = format!("{n} {}", if n == 1 { "entry" } else { "entries" });
+            if trashed { println!("Moved {count} to the trash (restore with `rustpass trash restore NAME`)."); }
+            else { println!("Removed {count}."); }
+        }
+        Cmd::Set { name, key, value } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            if !v.entries.iter().any(|e| e.name == name) {
+                return Err(err!(NotFound, "entry {name:?} not found"));
+            }
+            let value = match (key.as_str(), value.as_str()) {
+                ("password", "-") => prompt::password("Password (hidden): ")?,
+                (_, "-") => {
+                    let mut s = String::new();
+                    io::stdin().read_to_string(&mut s)?;
+                    s.trim_end_matches(['\r', '\n']).to_string()
+                }
+                _ => value,
+            };
+            if key == "password" {
+                policy::enforce(&v, &name, &value)?;
+            }
+            if key == "autotype" {
+                autotype::validate(&value)?;
+            }
+            let e = v.entries.iter_mut().find(|e| e.name == name).unwrap();
+            let opt = |s: String| if s.is_empty() { None } else { Some(s) };
+            match key.as_str() {
+                "username" | "user" => e.username = value,
+                "password" => e.set_password(value),
+                "url" => e.url = opt(value),
+                "notes" => e.notes = opt(value),
+                "autotype" => e.autotype = opt(value),
+                // 期限の設定は更新日時を変えない
+                "expires" | "expires_at" => {
+                    e.expires_at = opt(value).as_deref().map(audit::parse_expiry).transpose()?;
+                    save(&v, &u)?;
+                    println!("Updated {key} of {name}.");
+                    return Ok(());
+                }
+                "max_age" | "max_age_days" => {
+                    e.max_age_days = opt(value).as_deref().map(max_age_days).transpose()?;
+                    save(&v, &u)?;
+                    println!("Updated {key} of {name}.");
+                    return Ok(());
+                }
+                "tags" => {
+                    e.tags = value.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+                }
+                // それ以外はカスタムフィールド（空文字で削除）
+                _ => match e.fields.iter().position(|f| f.name == key) {
+                    Some(i) if value.is_empty() => { e.fields.remove(i); }
+                    Some(i) => { e.fields[i].value = value; e.fields[i].json = false; }
+                    None if value.is_empty() => return Err(err!(NotFound, "field {key:?} not found in {name:?}")),
+                    None => e.fields.push(Field { name: key.clone(), value, json: false, hidden: false }),
+                },
+            }
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Updated {key} of {name}.");
+        }
+        Cmd::Autotype { name, wait } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter().find(|e| e.name == name)
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            eprintln!("Typing into the focused window in {wait}s...");
+            autotype::run(e, wait)?;
+            record_access(&mut v, &u, &cfg, &name, "autotype")?;
+        }
+        Cmd::Menu { picker, print, autotype, wait, timeout } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let names: Vec<String> = v.entries.iter().map(|e| e.name.clone()).collect();
+            if print {
+                for n in &names { println!("{n}"); }
+                return Ok(());
+            }
+            let picker = picker.unwrap_or_else(menu::Picker::detect);
+            let Some(i) = menu::choose(picker, &names, cfg.accessible)? else { return Ok(()) };
+            let e = &v.entries[i];
+            if autotype {
+                autotype::run(e, wait)?;
+            } else {
+                if e.password.is_empty() {
+                    return Err(err!(NotFound, "entry {:?} has no password", e.name));
+                }
+                let timeout = timeout.unwrap_or(cfg.clipboard_timeout());
+                clipboard::copy(&e.password, timeout)?;
+                println!("Copied password of {} to clipboard (clears in {timeout}s).", e.name);
+            }
+            let name = e.name.clone();
+            record_access(&mut v, &u, &cfg, &name, "menu")?;
+        }
+        Cmd::Quorum { cmd: QuorumCmd::Setup { threshold, members, remove_password } } => {
+            let (v, mut u) = open_vault(&mut creds)?;
+            let mut seen = std::collections::HashSet::new();
+            if let Some(m) = members.iter().find(|m| !seen.insert(m.as_str())) {
+                return Err(err!(InvalidInput, "duplicate member {m:?}"));
+            }
+            if threshold < 2 || threshold as usize > members.len() {
+                return Err(err!(InvalidInput, "--threshold must be between 2 and the number of members ({})", members.len()));
+            }
+            let mut pairs = Vec::new();
+            for m in members {
+                let pw = Zeroizing::new(prompt::password(format!("Password for {m}: "))?);
+                let again = Zeroizing::new(prompt::password(format!("Repeat password for {m}: "))?);
+                if pw != again { return Err(err!(InvalidInput, "passwords for {m:?} do not match")); }
+                if pw.is_empty() { return Err(err!(InvalidInput, "password for {m:?} is empty")); }
+                pairs.push((m, pw));
+            }
+            u.set_quorum(threshold, &pairs, new_kdf(&creds))?;
+            if remove_password { u.remove_password()?; }
+            save(&v, &u)?;
+            println!("Quorum enabled: any {} of {} members can unlock.", threshold, pairs.len());
+            if remove_password { println!("Master password unlock removed."); }
+        }
+        Cmd::Quorum { cmd: QuorumCmd::Remove } => {
+            let (v, mut u) = open_vault(&mut creds)?;
+            if u.quorum().is_none() {
+                return Err(err!(NotFound, "quorum unlock is not enabled"));
+            }
+            if !u.has_password() {
+                let pw = Zeroizing::new(prompt::password("New master password: ")?);
+                let again = Zeroizing::new(prompt::password("Repeat new master password: ")?);
+                if pw != again { return Err(err!(InvalidInput, "passwords do not match")); }
+                u.rewrap_password(&pw, None)?;
+            }
+            u.remove_quorum()?;
+            save(&v, &u)?;
+            println!("Quorum unlock removed.");
+        }
+        Cmd::Members { cmd: MembersCmd::Add { recipient, name } } => {
+            let r = age::parse_recipient(&recipient)?;
+            if name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+                return Err(err!(InvalidInput, "member name is empty"));
+            }
+            let (v, mut u) = open_vault(&mut creds)?;
+            u.add_member(&r, name.as_deref())?;
+            save(&v, &u)?;
+            println!("Added {} ({} member(s)).", name.as_deref().unwrap_or(&recipient), u.members().len());
+        }
+        Cmd::Members { cmd: MembersCmd::Remove { member } } => {
+            // データ鍵を作り直してパスワードスロットも包み直すので、エージェントの鍵では開かない
+            let (v, mut u) = format::unlock(&VaultFile::current()?.read()?, &mut creds)?;
+            if !u.members().iter().any(|m| m.is(&member)) {
+                return Err(err!(NotFound, "no member {member:?}"));
+            }
+            let password = if u.has_password() { Some(format::Credentials::password(&mut creds)?) } else { None };
+            let mut quorum = Vec::new();
+            let quorum_members: Vec<String> = u.quorum().map(|(_, m)| m.iter().map(|m| m.to_string()).collect()).unwrap_or_default();
+            for m in quorum_members {
+                let pw = Zeroizing::new(prompt::password(format!("Password for {m}: "))?);
+                quorum.push((m, pw));
+            }
+            u.remove_member(&member, password.as_ref().map(|p| p.as_str()), &quorum)?;
+            save(&v, &u)?;
+            // 覚えている古い鍵はもう使えない
+            if keychain::remembered()? { keychain::remember(u.data_key())?; }
+            if agent::stop()? { eprintln!("note: the agent was stopped because the data key changed; run `rustpass unlock` again"); }
+            println!("Removed {member}; the data key was rotated and rewrapped for the remaining key slots.");
+        }
+        Cmd::Members { cmd: MembersCmd::List } => {
+            let members = format::members(&VaultFile::current()?.read()?)?;
+            if json {
+                let out: Vec<serde_json::Value> = members.iter().map(|m| serde_json::json!({ "name": m.name, "recipient": m.recipient })).collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            } else if members.is_empty() {
+                println!("This vault has no age recipients (use `rustpass members add age1...`).");
+            } else {
+                let width = members.iter().filter_map(|m| m.name.as_ref()).map(|n| n.chars().count()).max().unwrap_or(0);
+                for m in &members {
+                    if width == 0 { println!("{}", m.recipient); } else { println!("{:width$}  {}", m.name.as_deref().unwrap_or("-"), m.recipient); }
+                }
+            }
+        }
+        Cmd::Tag { cmd: TagCmd::Add { name, tags } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == name).ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            let mut added = Vec::new();
+            for t in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                if !e.tags.iter().any(|x| x == t) && !added.contains(&t) { added.push(t); }
+            }
+            if added.is_empty() {
+                println!("{name} already has {}.", tags.join(", "));
+            } else {
+                e.tags.extend(added.iter().map(|t| t.to_string()));
+                e.updated_at = now_iso();
+                save(&v, &u)?;
+                println!("Tagged {name} with {}.", added.join(", "));
+            }
+        }
+        Cmd::Tag { cmd: TagCmd::Remove { name, tags } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == name).ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            if let Some(t) = tags.iter().find(|t| !e.tags.contains(t)) {
+                return Err(err!(NotFound, "{name} has no tag {t:?}"));
+            }
+            e.tags.retain(|t| !tags.contains(t));
+            e.updated_at = now_iso();
+            save(&v, &u)?;
+            println!("Removed {} from {name}.", tags.join(", "));
+        }
+        Cmd::Tag { cmd: TagCmd::List } => {
+            let (v, _) = open_vault(&mut creds)?;
+            let mut counts = std::collections::BTreeMap::<&str, usize>::new();
+            for t in v.entries.iter().flat_map(|e| &e.tags) { *counts.entry(t).or_default() += 1; }
+            if json {
+                let out: Vec<serde_json::Value> = counts.iter().map(|(t, n)| serde_json::json!({ "tag": t, "entries": n })).collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+                return Ok(());
+            }
+            if cfg.accessible { println!("{} tags.", counts.len()); }
+            for (t, n) in &counts {
+                if cfg.accessible {
+                    println!("{t}, {n} {}.", if *n == 1 { "entry" } else { "entries" });
+                } else {
+                    println!("{t}  ({n})");
+                }
+            }
+        }
+        Cmd::Policy { cmd: PolicyCmd::Set { min_length, require, ban, no_reuse } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let p = policy::Policy { min_length, require, banned_words: ban, no_reuse };
+            let rules = p.describe();
+            if rules.is_empty() {
+                return Err(err!(InvalidInput, "no rules given; use `policy clear` to remove the policy"));
+            }
+            v.policy = Some(p);
+            save(&v, &u)?;
+            println!("Policy saved:");
+            for r in rules { println!("  {r}"); }
+            println!("Run `rustpass audit --policy` to check existing entries.");
+        }
+        Cmd::Canary { cmd: CanaryCmd::Mark { name } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == name)
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            e.canary = true;
+            save(&v, &u)?;
+            println!("{name} is now a canary; accessing it will be logged and trigger the hook.");
+        }
+        Cmd::Canary { cmd: CanaryCmd::Unmark { name } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let e = v.entries.iter_mut().find(|e| e.name == name)
+                .ok_or_else(|| err!(NotFound, "entry {name:?} not found"))?;
+            e.canary = false;
+            save(&v, &u)?;
+            println!("{name} is no longer a canary.");
+        }
+        Cmd::Canary { cmd: CanaryCmd::List } => {
+            let (v, _) = open_vault(&mut creds)?;
+            for e in v.entries.iter().filter(|e| e.canary) {
+                println!("{}", e.name);
+            }
+        }
+        Cmd::Canary { cmd: CanaryCmd::Log { clear } } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            if clear {
+                v.canary_events.clear();
+                save(&v, &u)?;
+                println!("Canary log cleared.");
+                return Ok(());
+            }
+            if json {
+                println!("{}", serde_json::to_string_pretty(&v.canary_events)?);
+                return Ok(());
+            }
+            if v.canary_events.is_empty() { println!("No canary access recorded."); }
+            for ev in &v.canary_events {
+                println!("{}  {}  {}  pid {}  parent {}", ev.at, ev.entry, ev.action, ev.pid, ev.parent.as_deref().unwrap_or("-"));
+            }
+        }
+        Cmd::Policy { cmd: PolicyCmd::Show } => {
+            let (v, _) = open_vault(&mut creds)?;
+            match &v.policy {
+                Some(p) => for r in p.describe() { println!("{r}"); },
+                None => println!("No policy set."),
+            }
+        }
+        Cmd::Policy { cmd: PolicyCmd::Clear } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            v.policy = None;
+            save(&v, &u)?;
+            println!("Policy removed.");
+        }
+        Cmd::Dedupe { dry_run } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let removed = dedupe::run(&mut v, dry_run)?;
+            if removed > 0 {
+                save(&v, &u)?;
+                println!("Merged {removed} duplicate entries.");
+            }
+        }
+        Cmd::Merge { path, prefer } => {
+            let (mut v, u) = open_vault(&mut creds)?;
+            let data = fs::read(&path).map_err(|e| anyhow!("cannot read {}: {e}", path.display()))?;
+            let remote = open_other(&data, &mut creds, &path.display().to_string())?;
+            let s = merge::run(&mut v, remote, prefer, cfg.accessible)?;
+            if s.changed() { save(&v, &u)?; }
+            println!(
+                "Merged: {} added, {} updated, {} kept as copies, {} kept local.",
+                s.added, s.updated, s.kept_both, s.kept_local
+            );
+        }
+        Cmd::Salvage { path, out, force } => salvage::run(&path, &out, force, &mut creds)?,
+        Cmd::Export { format, out, passphrase: other_passphrase, gpg_ids, force } => {
+            let format = format.or_else(|| export::Format::from_path(&out))
+                .ok_or_else(|| err!(InvalidInput, "--format is required unless the output file ends in .rpss"))?;
+            if !gpg_ids.is_empty() && !matches!(format, export::Format::Pass) {
+                return Err(err!(InvalidInput, "--gpg-id is only for --format pass"));
+            }
+            if matches!(format, export::Format::Pass) {
+                // 出力先はディレクトリ（既存のストアにも書き足せる）
+                let (mut v, u) = open_vault(&mut creds)?;
+                let n = passstore::write(&v, &out, &gpg_ids, force)?;
+                println!("Exported {n} entries to the password store at {}", out.display());
+                let canaries: Vec<String> = v.entries.iter().filter(|e| e.canary).map(|e| e.name.clone()).collect();
+                for name in &canaries { canary::trip(&mut v, cfg.canary.hook.as_deref(), name, "export"); }
+                if !canaries.is_empty() { save(&v, &u)?; }
+                return Ok(());
+            }
+            let snapshot = matches!(format, export::Format::Rpss);
+            let vault_kdf = format.vault_kdf();
+            if other_passphrase && !vault_kdf {
+                return Err(err!(InvalidInput, "--passphrase is only for --format rpss or html-vault (other encrypted formats always ask for one)"));
+            }
+            if out.exists() && !force {
+                return Err(err!(AlreadyExists, "{} already exists (use --force to overwrite)", out.display()));
+            }
+            let (mut v, u) = open_vault(&mut creds)?;
+            let passphrase = match &creds.password {
+                // スナップショットと HTML は金庫と同じKDFなので、既定ではマスターパスワードで暗号化する
+                Some(master) if vault_kdf && !other_passphrase => Some(master.clone()),
+                _ if format.encrypted() => {
+                    let pw = Zeroizing::new(prompt::password("Export passphrase: ")?);
+                    let again = Zeroizing::new(prompt::password("Repeat export passphrase: ")?);
+                    if pw != again { return Err(err!(InvalidInput, "passphrases do not match")); }
+                    if pw.is_empty() { return Err(err!(InvalidInput, "export passphrase is empty")); }
+                    // 書き出し先はKDFが弱いので、マスターパスワードの使い回しは拒否する
+                    if !vault_kdf && creds.password.as_ref().is_some_and(|m| *m == pw) {
+                        return Err(err!(InvalidInput, "use a different passphrase than the master password for exports"));
+                    }
+                    Some(pw)
+                }
+                _ => {
+                    eprintln!("warning: this export is NOT encrypted; delete it once it has been imported");
+                    None
+                }
+            };
+            let data = export::render(format, &v, passphrase.as_deref().map(|s| s.as_str()))?;
+            export::write(&out, &data, force)?;
+            let n = v.entries.iter().filter(|e| format.includes(e)).count();
+            if snapshot {
+                // 読み戻して開けることを確かめる
+                rustpass_core::decrypt_vault(&fs::read(&out)?, passphrase.as_deref().map(|s| s.as_str()).unwrap_or_default())
+                    .map_err(|e| err!(CorruptVault, "snapshot verification after write failed: {e}"))?;
+                println!("Wrote an encrypted snapshot of {n} entries to {}", out.display());
+                println!("Restore it with `rustpass import --format rpss {}`.", out.display());
+            } else {
+                println!("Exported {n} entries to {}", out.display());
+                // 書き出しに含まれたカナリアも参照扱い（暗号化されたままのスナップショットは除く）
+                let canaries: Vec<String> = v.entries.iter().filter(|e| e.canary && format.includes(e)).map(|e| e.name.clone()).collect();
+                for name in &canaries { canary::trip(&mut v, cfg.canary.hook.as_deref(), name, "export"); }
+                if !canaries.is_empty() { save(&v, &u)?; }
+            }
+        }
+        Cmd::Import { format, path, kdbx_keyfile, duplicates, map, dry_run } => {
+            if kdbx_keyfile.is_some() && !matches!(format, import::Format::Kdbx) {
+                return Err(err!(InvalidInput, "--kdbx-keyfile is only for --format kdbx"));
+            }
+            if map.is_some() && !matches!(format, import::Format::Csv) {
+                return Err(err!(InvalidInput, "--map is only for --format csv"));
+            }
+            let (mut v, u) = open_vault(&mut creds)?;
+            let entries = match format {
+                import::Format::Rpss => {
+                    let mut snap = import::read_snapshot(&path)?;
+                    // ポリシーは金庫にまだ無い場合だけ引き継ぐ
+                    if v.policy.is_none() && snap.policy.is_some() {
+                        v.policy = snap.policy.take();
+                        eprintln!("note: restored the password policy from the snapshot");
+                    }
+                    std::mem::take(&mut snap.entries)
+                }
+                _ => import::read(format, &path, kdbx_keyfile.as_deref(), map.as_deref())?,
+            };
+            let m = import::merge(&mut v, entries, duplicates);
+            if let Some(p) = &v.policy {
+                let bad = v.entries.iter()
+                    .filter(|e| m.names.contains(&e.name))
+                    .filter(|e| !e.password.is_empty() && !p.violations(&e.password, &v, &e.name).is_empty())
+                    .count();
+                if bad > 0 {
+                    eprintln!("warning: {bad} imported entries violate the vault policy (see `rustpass audit --policy`)");
+                }
+            }
+            let mut notes = Vec::new();
+            if m.renamed > 0 { notes.push(format!("{} renamed to avoid name clashes", m.renamed)); }
+            if m.replaced > 0 { notes.push(format!("{} replaced existing entries", m.replaced)); }
+            if m.skipped > 0 { notes.push(format!("{} skipped as duplicates", m.skipped)); }
+            let notes = if notes.is_empty() { String::new() } else { format!(" ({})", notes.join(", ")) };
+            if dry_run {
+                // 取り込んだ後の名前で表示する（パスワードは出さない）
+                println!("Would import {} entries{notes}:", m.names.len());
+                for e in v.entries.iter().filter(|e| m.names.contains(&e.name)) {
+                    let mut extra = Vec::new();
+                    if e.password.is_empty() { extra.push("no password".to_string()); }
+                    if e.otp.is_some() { extra.push("TOTP".to_string()); }
+                    if !e.fields.is_empty() { extra.push(format!("{} fields", e.fields.len())); }
+                    if !e.tags.is_empty() { extra.push(format!("tags: {}", e.tags.join(", "))); }
+                    let extra = if extra.is_empty() { String::new() } else { format!("  [{}]", extra.join("; ")) };
+                    println!("  {}  ({})  {}{extra}", e.name, e.username, e.url.as_deref().unwrap_or("-"));
+                }
+                println!("Dry run: nothing was saved.");
+                return Ok(());
+            }
+            save(&v, &u)?;
+            println!("Imported {} entries{notes}.", m.names.len());
+        }
+        Cmd::Serve { web, port, timeout } => {
+            let (v, u) = open_vault(&mut creds)?;
+            // 要求ごとにデータ鍵で開き直すので、最新形式に移行しておく
+            if u.version < format::VERSION { save(&v, &u)?; }
+            lock::release();
+            serve::run(u.data_key().clone(), &cfg, port, web, timeout)?;
+        }
+        Cmd::Migrate => {
+            let path = vault_path()?;
+            if !path.exists() {
+                return Err(err!(NoVault, "no vault at {}", path.display()));
+            }
+            let data = VaultFile::current()?.read()?;
+            // 形式はヘッダだけで分かるので、最新ならパスワードを尋ねない
+            let old = format::inspect(&data)?.version;
+            if old >= format::VERSION {
+                println!("Vault already uses format v{old}; nothing to do.");
+                return Ok(());
+            }
+            let (v, u) = format::unlock(&data, &mut creds)?;
+            save(&v, &u)?;
+            println!("Migrated vault from format v{old} to v{}.", format::VERSION);
+            if cfg.vault_backups() > 0 {
+                println!("The previous file was kept as {}.", backup::rotated(&path, 1).display());
+            }
+        }
+        Cmd::BenchKdf { target_ms, max_memory, parallelism } => {
+            eprintln!("Measuring argon2id on this machine (target {target_ms} ms)...");
+            let (kdf, took) = bench::suggest(std::time::Duration::from_millis(target_ms), max_memory.saturating_mul(1024), parallelism)?;
+            println!("Suggested:  argon2id m={}KiB ({} MiB) t={} p={}  ({} ms here)", kdf.m, kdf.m / 1024, kdf.t, kdf.p, took.as_millis());
+            // 今の金庫のパラメータ（ヘッダから読むのでロック解除は不要）
+            let current = fs::read(vault_path()?).ok()
+                .and_then(|data| format::inspect(&data).ok())
+                .and_then(|info| info.slots.into_iter().find(|s| s.kind == "password"))
+                .and_then(|s| s.kdf);
+            if let Some(k) = current {
+                println!("This vault: argon2id m={}KiB t={} p={}  ({} ms here)", k.m, k.t, k.p, k.measure()?.as_millis());
+            }
+            if creds.min_kdf.is_some_and(|min| kdf.weaker_than(&min)) {
+                println!("note: this is below the configured min_kdf; new and rekey raise it to the minimum");
+            }
+            let flags = format!("--kdf-memory {} --kdf-time {} --kdf-parallelism {}", kdf.m / 1024, kdf.t, kdf.p);
+            println!();
+            println!("  new vault:       rustpass new {flags}");
+            println!("  existing vault:  rustpass rekey {flags}");
+            println!("  config.toml:     kdf = {{ m = {}, t = {}, p = {} }}", kdf.m, kdf.t, kdf.p);
+        }
+        Cmd::Tui => {
+            if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+                return Err(err!(InvalidInput, "tui needs a terminal"));
+            }
+            if cfg.accessible {
+                return Err(err!(InvalidInput, "tui redraws the whole screen; with --accessible use `rustpass get` without a name or `rustpass find`"));
+            }
+            let (v, u) = open_vault(&mut creds)?;
+            // 開いている間ほかのコマンドを待たせないよう、ロックは保存のたびに取り直す
+            lock::release();
+            tui::run(v, u, &cfg)?;
+        }
+        Cmd::Status { unlock } => {
+            status::run(&mut creds, unlock)?;
+        }
+        Cmd::Unlock { remember: true, .. } => {
+            let (v, u) = open_vault(&mut creds)?;
+            keychain::check(&u)?;
+            // 鍵だけで開けるのは最新形式の金庫だけなので先に移行しておく
+            if u.version < format::VERSION { save(&v, &u)?; }
+            keychain::remember(u.data_key())?;
+            println!("Key stored in the OS keychain; this vault opens without the password until `rustpass forget`.");
+        }
+        Cmd::Forget => {
+            if keychain::forget()? { println!("Removed the key from the OS keychain."); }
+            else { println!("No key stored in the OS keychain for this vault."); }
+        }
+        Cmd::Agent { cmd: AgentCmd::Start { timeout } } | Cmd::Unlock { remember: false, timeout } => {
+            if agent::status().is_some() {
+                return Err(err!(AlreadyExists, "agent already running (see `rustpass agent status`)"));
+            }
+            let timeout = timeout.or(cfg.agent.timeout).unwrap_or(agent::DEFAULT_TIMEOUT);
+            let (v, u) = open_vault(&mut creds)?;
+            // エージェントは最新形式の金庫しか開けないので先に移行しておく
+            if u.version < format::VERSION { save(&v, &u)?; }
+            let pid = agent::start(u.data_key(), timeout)?;
+            println!("Agent started (pid {pid}); locks after {timeout}s without use.");
+        }
+        Cmd::Agent { cmd: AgentCmd::Stop } | Cmd::Lock => {
+            if agent::stop()? { println!("Agent stopped; vault locked."); }
+            else { println!("No agent running."); }
+        }
+        Cmd::Agent { cmd: AgentCmd::Status } => match agent::status() {
+            Some(s) => {
+                println!("Agent running (pid {}), locks in {}s without use.", s.pid, s.locks_in);
+                println!("Client log: {}", agent::log_path()?.display());
+            }
+            None => println!("No agent running."),
+        },
+        Cmd::BrowserHost { caller } => browser::run(&cfg, creds.min_kdf, &caller)?,
+        Cmd::Completions { shell } => {
+            completions::generate(shell, &mut Cli::command())?;
+        }
+        Cmd::CompleteNames => {
+            // 補完の途中なのでパスワードは尋ねず、エージェントが動いていなければ何も出さない。
+            // 補完のたびに待たされないようロックも取らない（保存は rename なので読みかけで壊れない）
+            let path = vault_path()?;
+            let Some(key) = agent::key().filter(|_| path.exists()) else { return Ok(()) };
+            let Ok((v, _)) = format::unlock_with_key(&fs::read(&path)?, key) else { return Ok(()) };
+            for e in v.entries.iter().filter(|e| !e.name.contains('\n')) {
+                println!("{}", e.name);
+            }
+        }
+        Cmd::AgentServe { timeout } => {
+            agent::serve(timeout, &cfg.agent.allowed_executables)?;
+        }
+        Cmd::ClipboardHold { timeout } => {
+            clipboard::hold(timeout)?;
         }
     }
     Ok(())
@@ -0,0 +1,71 @@
+// 外部の選択メニュー（rofi / dmenu / fzf）でエントリを選ぶ
+//
+// 候補のエントリ名を1行ずつ標準入力で渡し、選ばれた行を標準出力から受け取る。
+// メニューを閉じた（Esc）ときはどれも終了コードが0以外で何も出力しないので、取り消しとして扱う。
+use crate::error::err;
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Picker {
+    Rofi,
+    Dmenu,
+    Fzf,
+    /// 端末内の選択画面（get で名前を省略したときと同じ）
+    Builtin,
+}
+
+fn in_path(prog: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|d| Path::new(&d).join(prog).is_file()))
+}
+
+impl Picker {
+    /// 端末から起動されたなら fzf、そうでなければ rofi → dmenu の順に PATH から探す
+    pub fn detect() -> Picker {
+        let terminal = io::stdin().is_terminal() && io::stderr().is_terminal();
+        let candidates: &[Picker] = if terminal { &[Picker::Fzf] } else { &[Picker::Rofi, Picker::Dmenu] };
+        candidates.iter().copied()
+            .find(|p| p.command().is_some_and(|(prog, _)| in_path(prog)))
+            .unwrap_or(Picker::Builtin)
+    }
+
+    fn command(self) -> Option<(&'static str, &'static [&'static str])> {
+        match self {
+            Picker::Rofi => Some(("rofi", &["-dmenu", "-i", "-p", "rustpass"])),
+            Picker::Dmenu => Some(("dmenu", &["-i", "-p", "rustpass"])),
+            Picker::Fzf => Some(("fzf", &["--prompt", "rustpass> "])),
+            Picker::Builtin => None,
+        }
+    }
+}
+
+/// 選ばれた候補のインデックスを返す（取り消したら None）
+pub fn choose(picker: Picker, names: &[String], accessible: bool) -> Result<Option<usize>> {
+    let Some((prog, args)) = picker.command() else { return crate::picker::pick(names, accessible) };
+    let mut child = Command::new(prog)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => err!(NotFound, "{prog} not found in PATH (choose another with --picker)"),
+            _ => e.into(),
+        })?;
+    {
+        let mut stdin = child.stdin.take().ok_or(anyhow!("no stdin for {prog}"))?;
+        // 選ぶ前にメニューを閉じられると書き込みが失敗するが、それは取り消しとして扱う
+        let _ = stdin.write_all(names.join("\n").as_bytes());
+    }
+    let out = child.wait_with_output()?;
+    let chosen = String::from_utf8_lossy(&out.stdout);
+    let chosen = chosen.trim_end_matches(['\r', '\n']);
+    if !out.status.success() || chosen.is_empty() { return Ok(None); }
+    // rofi・dmenu では一覧に無い名前も入力できる
+    names.iter().position(|n| n == chosen)
+        .map(Some)
+        .ok_or_else(|| err!(NotFound, "entry {chosen:?} not found"))
+}
@@ -0,0 +1,416 @@
+// 別の金庫ファイル（同期の競合コピーやバックアップ）との統合
+//
+// merge コマンドでは共通の祖先が分からないため、同じIDで内容が異なるエントリは両側で変更された
+// ものとして扱い、新しい方を黙って採用せずに差分を見せて選ばせる。
+// sync では git の履歴から共通の祖先（最後に同期した金庫）が分かるので、項目ごとに
+// どちらで変更されたかを比べ、同じ項目が両側で別の値に変えられたときだけ選ばせる（3方向マージ）。
+use crate::error::err;
+use crate::import::unique_name;
+use crate::{attachment, now_iso, peek_mask, trash, Entry, Vault};
+use anyhow::Result;
+use clap::ValueEnum;
+use std::io::{self, IsTerminal, Write};
+use uuid::Uuid;
+
+/// 競合時の既定の解決方法（対話できない場合に必要）
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Prefer {
+    Local,
+    Remote,
+    /// updated_at が新しい方
+    Newest,
+}
+
+#[derive(Default)]
+pub struct Summary {
+    pub added: usize,
+    pub updated: usize,
+    pub kept_local: usize,
+    pub kept_both: usize,
+    /// 相手側で削除されたので削除した
+    pub deleted: usize,
+    /// 片側で削除されたが、もう片側で変更されていたので残した
+    pub restored: usize,
+    /// 件数に出ない変更（参照日時・検知の記録・パスワードポリシー・ゴミ箱）があった
+    pub touched: bool,
+}
+
+impl Summary {
+    /// 統合で local が変わったか（保存が要るか）
+    pub fn changed(&self) -> bool {
+        self.added + self.updated + self.kept_both + self.deleted + self.restored > 0 || self.touched
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Part {
+    Name,
+    Username,
+    Password,
+    Url,
+    Notes,
+    Tags,
+    Field(String),
+    Otp,
+    SshKey,
+    Cert,
+    Derive,
+    Passkey,
+    Autotype,
+    Card,
+    Identity,
+    SecureNote,
+    Attachments,
+    Expires,
+    MaxAge,
+    Canary,
+}
+
+fn json<T: serde::Serialize>(v: &T) -> serde_json::Value {
+    serde_json::to_value(v).unwrap_or_default()
+}
+
+fn label(p: &Part) -> String {
+    match p {
+        Part::Name => "name".into(),
+        Part::Username => "username".into(),
+        Part::Password => "password".into(),
+        Part::Url => "url".into(),
+        Part::Notes => "notes".into(),
+        Part::Tags => "tags".into(),
+        Part::Field(n) => format!("field {n}"),
+        Part::Otp => "totp".into(),
+        Part::SshKey => "ssh key".into(),
+        Part::Cert => "certificate".into(),
+        Part::Derive => "derive".into(),
+        Part::Passkey => "passkey".into(),
+        Part::Autotype => "autotype".into(),
+        Part::Card => "card".into(),
+        Part::Identity => "identity".into(),
+        Part::SecureNote => "secure note".into(),
+        Part::Attachments => "attachments".into(),
+        Part::Expires => "expires".into(),
+        Part::MaxAge => "max age".into(),
+        Part::Canary => "canary".into(),
+    }
+}
+
+// 差分表示用の1行表現（秘密は伏せる）
+fn show(p: &Part, e: &Entry, reveal: bool) -> String {
+    let opt = |s: &Option<String>| s.as_deref().unwrap_or("-").replace('\n', " / ");
+    let secret = |s: &str| if s.is_empty() { "-".into() } else if reveal { s.to_string() } else { peek_mask(s) };
+    match p {
+        Part::Name => e.name.clone(),
+        Part::Username => e.username.clone(),
+        Part::Password => secret(&e.password),
+        Part::Url => opt(&e.url),
+        Part::Notes => opt(&e.notes),
+        Part::Tags => e.tags.join(", "),
+        Part::Field(n) => match e.fields.iter().find(|f| &f.name == n) {
+            Some(f) if f.json => "<JSON>".into(),
+            Some(f) => secret(&f.value),
+            None => "-".into(),
+        },
+        Part::Otp => e.otp.as_ref().map_or("-".into(), |o| format!("{} digits / {}s", o.digits, o.period)),
+        Part::SshKey => e.ssh_key.as_ref().map_or("-".into(), |k| k.algorithm.clone()),
+        Part::Cert => e.cert.as_ref().map_or("-".into(), |c| format!("expires {}", c.not_after)),
+        Part::Derive => e.derive.as_ref().map_or("-".into(), |d| format!("login {} counter {}", d.login, d.counter)),
+        Part::Passkey => e.passkey.as_ref().map_or("-".into(), |k| format!("{} on {}", k.rp_id, k.authenticator.as_deref().unwrap_or("?"))),
+        Part::Autotype => opt(&e.autotype),
+        Part::Card => e.card.as_ref().map_or("-".into(), |c| format!("{} expires {}", c.masked(), c.expires.as_deref().unwrap_or("-"))),
+        Part::Identity => e.identity.as_ref().map_or("-".into(), |i| i.items().iter().map(|(_, v)| v.replace('\n', " / ")).collect::<Vec<_>>().join(", ")),
+        Part::SecureNote => if e.secure_note { "yes".into() } else { "no".into() },
+        Part::Attachments if e.attachments.is_empty() => "-".into(),
+        Part::Attachments => e.attachments.iter().map(|a| format!("{} ({})", a.name, attachment::human(a.data.len()))).collect::<Vec<_>>().join(", "),
+        Part::Expires => opt(&e.expires_at),
+        Part::MaxAge => e.max_age_days.map_or("-".into(), |d| format!("{d} days")),
+        Part::Canary => if e.canary { "yes".into() } else { "no".into() },
+    }
+}
+
+fn differing(l: &Entry, r: &Entry) -> Vec<Part> {
+    let mut out = Vec::new();
+    if l.name != r.name { out.push(Part::Name); }
+    if l.username != r.username { out.push(Part::Username); }
+    if l.password != r.password { out.push(Part::Password); }
+    if l.url != r.url { out.push(Part::Url); }
+    if l.notes != r.notes { out.push(Part::Notes); }
+    if l.tags != r.tags { out.push(Part::Tags); }
+    let names: Vec<&String> = l.fields.iter().chain(&r.fields).map(|f| &f.name).collect();
+    for n in names {
+        let (a, b) = (l.fields.iter().find(|f| &f.name == n), r.fields.iter().find(|f| &f.name == n));
+        let p = Part::Field(n.clone());
+        if json(&a) != json(&b) && !out.contains(&p) { out.push(p); }
+    }
+    if json(&l.otp) != json(&r.otp) { out.push(Part::Otp); }
+    if json(&l.ssh_key) != json(&r.ssh_key) { out.push(Part::SshKey); }
+    if json(&l.cert) != json(&r.cert) { out.push(Part::Cert); }
+    if json(&l.derive) != json(&r.derive) { out.push(Part::Derive); }
+    if json(&l.passkey) != json(&r.passkey) { out.push(Part::Passkey); }
+    if l.autotype != r.autotype { out.push(Part::Autotype); }
+    if json(&l.card) != json(&r.card) { out.push(Part::Card); }
+    if json(&l.identity) != json(&r.identity) { out.push(Part::Identity); }
+    if l.secure_note != r.secure_note { out.push(Part::SecureNote); }
+    if json(&l.attachments) != json(&r.attachments) { out.push(Part::Attachments); }
+    if l.expires_at != r.expires_at { out.push(Part::Expires); }
+    if l.max_age_days != r.max_age_days { out.push(Part::MaxAge); }
+    if l.canary != r.canary { out.push(Part::Canary); }
+    out
+}
+
+// dst の該当部分を src の値で置き換える
+fn take(p: &Part, dst: &mut Entry, src: &Entry) {
+    match p {
+        Part::Name => dst.name = src.name.clone(),
+        Part::Username => dst.username = src.username.clone(),
+        Part::Password => {
+            dst.password = src.password.clone();
+            dst.password_changed_at = src.password_changed_at.clone();
+        }
+        Part::Url => dst.url = src.url.clone(),
+        Part::Notes => dst.notes = src.notes.clone(),
+        Part::Tags => dst.tags = src.tags.clone(),
+        Part::Field(n) => {
+            let pos = dst.fields.iter().position(|f| &f.name == n);
+            match (pos, src.fields.iter().find(|f| &f.name == n)) {
+                (Some(i), Some(f)) => dst.fields[i] = f.clone(),
+                (Some(i), None) => { dst.fields.remove(i); }
+                (None, Some(f)) => dst.fields.push(f.clone()),
+                (None, None) => {}
+            }
+        }
+        Part::Otp => dst.otp = src.otp.clone(),
+        Part::SshKey => dst.ssh_key = src.ssh_key.clone(),
+        Part::Cert => dst.cert = src.cert.clone(),
+        Part::Derive => dst.derive = src.derive.clone(),
+        Part::Passkey => dst.passkey = src.passkey.clone(),
+        Part::Autotype => dst.autotype = src.autotype.clone(),
+        Part::Card => dst.card = src.card.clone(),
+        Part::Identity => dst.identity = src.identity.clone(),
+        Part::SecureNote => dst.secure_note = src.secure_note,
+        Part::Attachments => dst.attachments = src.attachments.clone(),
+        Part::Expires => dst.expires_at = src.expires_at.clone(),
+        Part::MaxAge => dst.max_age_days = src.max_age_days,
+        Part::Canary => dst.canary = src.canary,
+    }
+}
+
+fn print_diff(l: &Entry, r: &Entry, parts: &[Part], reveal: bool, accessible: bool) {
+    if accessible {
+        for p in parts {
+            println!("{}: local {}; remote {}.", label(p), show(p, l, reveal), show(p, r, reveal));
+        }
+        println!("updated: local {}; remote {}.", l.updated_at, r.updated_at);
+        return;
+    }
+    let rows: Vec<(String, String, String)> = parts.iter()
+        .map(|p| (label(p), show(p, l, reveal), show(p, r, reveal)))
+        .chain([("updated".into(), l.updated_at.clone(), r.updated_at.clone())])
+        .collect();
+    let w0 = rows.iter().map(|r| r.0.chars().count()).max().unwrap_or(0).max(5);
+    let w1 = rows.iter().map(|r| r.1.chars().count().min(40)).max().unwrap_or(0).max(5);
+    println!("  {:w0$}  {:w1$}  remote", "", "local");
+    for (k, a, b) in rows {
+        println!("  {k:w0$}  {a:w1$}  {b}");
+    }
+}
+
+fn ask(prompt: &str) -> Result<String> {
+    crate::prompt::require(prompt)?;
+    print!("{prompt}");
+    io::stdout().flush()?;
+    let mut s = String::new();
+    if io::stdin().read_line(&mut s)? == 0 { return Err(err!(InvalidInput, "unexpected end of input")); }
+    Ok(s.trim().to_lowercase())
+}
+
+// 項目ごとに local / remote を選ぶ
+fn edit(l: &Entry, r: &Entry, parts: &[Part], reveal: bool) -> Result<Entry> {
+    let mut out = l.clone();
+    for p in parts {
+        loop {
+            let a = ask(&format!("  {}: [l]ocal {} / [r]emote {}? ", label(p), show(p, l, reveal), show(p, r, reveal)))?;
+            match a.as_str() {
+                "l" | "local" => break,
+                "r" | "remote" => { take(p, &mut out, r); break; }
+                _ => continue,
+            }
+        }
+    }
+    out.updated_at = now_iso();
+    Ok(out)
+}
+
+enum Choice {
+    Local,
+    Remote,
+    Both,
+    Merged(Box<Entry>),
+}
+
+fn resolve(l: &Entry, r: &Entry, parts: &[Part], accessible: bool) -> Result<Choice> {
+    let mut reveal = false;
+    loop {
+        let a = ask("Keep [l]ocal, [r]emote, [b]oth as copies, [e]dit field by field, [s]how secrets? ")?;
+        match a.as_str() {
+            "l" | "local" => return Ok(Choice::Local),
+            "r" | "remote" => return Ok(Choice::Remote),
+            "b" | "both" => return Ok(Choice::Both),
+            "e" | "edit" => return Ok(Choice::Merged(Box::new(edit(l, r, parts, reveal)?))),
+            "s" | "show" => { reveal = true; print_diff(l, r, parts, true, accessible); }
+            _ => {}
+        }
+    }
+}
+
+// 両側で変更された項目の解決方法を決める（--prefer が無ければ対話で選ばせる）
+fn decide(l: &Entry, r: &Entry, parts: &[Part], prefer: Option<Prefer>, accessible: bool) -> Result<Choice> {
+    Ok(match prefer {
+        Some(Prefer::Local) => Choice::Local,
+        Some(Prefer::Remote) => Choice::Remote,
+        Some(Prefer::Newest) if r.updated_at > l.updated_at => Choice::Remote,
+        Some(Prefer::Newest) => Choice::Local,
+        None if !io::stdin().is_terminal() => {
+            return Err(err!(InvalidInput, "{:?} was changed on both sides; run on a terminal to resolve, or pass --prefer", l.name));
+        }
+        None => {
+            println!("\nConflict in {}:", l.name);
+            print_diff(l, r, parts, false, accessible);
+            resolve(l, r, parts, accessible)?
+        }
+    })
+}
+
+// 「両方残す」ときの相手側のコピー
+// 相手側の検知の記録のうち、こちらに無いものを足す（足したら true）
+fn merge_events(local: &mut Vault, remote: &mut Vault) -> bool {
+    let before = local.canary_events.len();
+    for ev in std::mem::take(&mut remote.canary_events) {
+        if !local.canary_events.iter().any(|x| json(x) == json(&ev)) { local.canary_events.push(ev); }
+    }
+    local.canary_events.sort_by(|a, b| a.at.cmp(&b.at));
+    local.canary_events.len() != before
+}
+
+fn add_copy(local: &mut Vault, r: Entry) {
+    let mut copy = r;
+    copy.id = Uuid::new_v4().to_string();
+    copy.name = format!("{} (remote)", copy.name);
+    copy.name = unique_name(local, &copy);
+    local.entries.push(copy);
+}
+
+/// remote の内容を local に統合する
+pub fn run(local: &mut Vault, mut remote: Vault, prefer: Option<Prefer>, accessible: bool) -> Result<Summary> {
+    let mut sum = Summary::default();
+    if local.policy.is_none() && remote.policy.is_some() {
+        local.policy = remote.policy.take();
+        sum.touched = true;
+    }
+    sum.touched |= merge_events(local, &mut remote);
+    for r in std::mem::take(&mut remote.entries) {
+        let Some(i) = local.entries.iter().position(|e| e.id == r.id) else {
+            let mut r = r;
+            r.name = unique_name(local, &r);
+            local.entries.push(r);
+            sum.added += 1;
+            continue;
+        };
+        let l = &local.entries[i];
+        let parts = differing(l, &r);
+        if parts.is_empty() {
+            // 参照日時だけの違いは新しい方を採用
+            if r.last_accessed > l.last_accessed {
+                local.entries[i].last_accessed = r.last_accessed;
+                sum.touched = true;
+            }
+            continue;
+        }
+        match decide(l, &r, &parts, prefer, accessible)? {
+            Choice::Local => sum.kept_local += 1,
+            Choice::Remote => { local.entries[i] = r; sum.updated += 1; }
+            Choice::Merged(e) => { local.entries[i] = *e; sum.updated += 1; }
+            Choice::Both => { add_copy(local, r); sum.kept_both += 1; }
+        }
+    }
+    sum.touched |= trash::merge(local, None, std::mem::take(&mut remote.trash));
+    Ok(sum)
+}
+
+/// 共通の祖先 base からの変更を比べて remote を local に統合する（sync 用）
+pub fn three_way(local: &mut Vault, base: &Vault, mut remote: Vault, prefer: Option<Prefer>, accessible: bool) -> Result<Summary> {
+    let mut sum = Summary::default();
+    let in_base = |id: &str| base.entries.iter().find(|e| e.id == id);
+    // 相手側で削除されたエントリ（こちらで変更していなければ削除する）
+    let remote_ids: Vec<&str> = remote.entries.iter().map(|e| e.id.as_str()).collect();
+    local.entries.retain(|l| {
+        if remote_ids.contains(&l.id.as_str()) { return true; }
+        match in_base(&l.id) {
+            None => true,
+            Some(b) if differing(b, l).is_empty() => { sum.deleted += 1; false }
+            Some(_) => { sum.restored += 1; true }
+        }
+    });
+    if json(&local.policy) == json(&base.policy) { local.policy = remote.policy.take(); }
+    sum.touched |= merge_events(local, &mut remote);
+    for r in std::mem::take(&mut remote.entries) {
+        let b = in_base(&r.id);
+        let Some(i) = local.entries.iter().position(|e| e.id == r.id) else {
+            match b {
+                // こちらで削除したエントリは、相手側で変更されていなければ削除のまま
+                Some(b) if differing(b, &r).is_empty() => {}
+                _ => {
+                    if b.is_some() { sum.restored += 1; } else { sum.added += 1; }
+                    let mut r = r;
+                    r.name = unique_name(local, &r);
+                    local.entries.push(r);
+                }
+            }
+            continue;
+        };
+        let l = &local.entries[i];
+        let parts = differing(l, &r);
+        if parts.is_empty() {
+            if r.last_accessed > l.last_accessed {
+                local.entries[i].last_accessed = r.last_accessed;
+                sum.touched = true;
+            }
+            continue;
+        }
+        // 祖先に無ければ（両側で同じIDを追加した）すべての違いを両側の変更とみなす
+        let (ours, theirs) = match b {
+            Some(b) => (differing(b, l), differing(b, &r)),
+            None => (parts.clone(), parts.clone()),
+        };
+        // 相手側だけで変更された項目は取り込み、両側で変更された項目だけを競合にする
+        let mut merged = l.clone();
+        let mut conflicts = Vec::new();
+        for p in parts {
+            match (ours.contains(&p), theirs.contains(&p)) {
+                (true, true) => conflicts.push(p),
+                (false, _) => take(&p, &mut merged, &r),
+                (true, false) => {}
+            }
+        }
+        merged.updated_at = l.updated_at.clone().max(r.updated_at.clone());
+        merged.last_accessed = l.last_accessed.clone().max(r.last_accessed.clone());
+        let took = !differing(l, &merged).is_empty();
+        if conflicts.is_empty() {
+            if took { sum.updated += 1; }
+            local.entries[i] = merged;
+            continue;
+        }
+        match decide(&merged, &r, &conflicts, prefer, accessible)? {
+            Choice::Local => { sum.kept_local += 1; local.entries[i] = merged; }
+            Choice::Remote => {
+                for p in &conflicts { take(p, &mut merged, &r); }
+                local.entries[i] = merged;
+                sum.updated += 1;
+            }
+            Choice::Merged(e) => { local.entries[i] = *e; sum.updated += 1; }
+            Choice::Both => { local.entries[i] = merged; add_copy(local, r); sum.kept_both += 1; }
+        }
+    }
+    sum.touched |= trash::merge(local, Some(base), std::mem::take(&mut remote.trash));
+    Ok(sum)
+}
@@ -0,0 +1,41 @@
+// ロック解除用TOTPの登録
+use crate::error::err;
+use crate::format::TotpSecret;
+use crate::totp::{self, Algorithm};
+use anyhow::{anyhow, Result};
+use qrcode::{render::unicode, QrCode};
+use rand::{rngs::OsRng, RngCore};
+use zeroize::Zeroizing;
+
+const DIGITS: u32 = 6;
+const PERIOD: u64 = 30;
+
+pub fn read_code(prompt: &str) -> Result<String> {
+    crate::prompt::line(prompt)
+}
+
+// シークレットを生成して表示し、認証アプリのコードで確認できたら返す
+pub fn enroll() -> Result<TotpSecret> {
+    let mut secret = Zeroizing::new(vec![0u8; 20]);
+    OsRng.fill_bytes(&mut secret);
+    let b32 = Zeroizing::new(totp::base32_encode(&secret));
+    let uri = Zeroizing::new(format!(
+        "otpauth://totp/rustpass:vault?secret={}&issuer=rustpass&algorithm=SHA1&digits={DIGITS}&period={PERIOD}",
+        b32.as_str()
+    ));
+
+    let qr = QrCode::new(uri.as_bytes()).map_err(|e| anyhow!("qr encode failed: {e}"))?;
+    println!("{}", qr.render::<unicode::Dense1x2>().quiet_zone(true).build());
+    println!("Scan the QR code with your authenticator app, or enter the secret manually:");
+    println!("  {}", b32.as_str());
+    println!("Keep a copy of this secret somewhere safe: without it the vault cannot be opened.");
+
+    for _ in 0..3 {
+        let code = read_code("Code from authenticator: ")?;
+        if totp::verify(&secret, Algorithm::Sha1, DIGITS, PERIOD, &code) {
+            return Ok(TotpSecret { secret, algorithm: Algorithm::Sha1, digits: DIGITS, period: PERIOD });
+        }
+        println!("Code does not match; try again.");
+    }
+    Err(err!(InvalidTotp, "TOTP enrollment failed"))
+}
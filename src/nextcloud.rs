@@ -0,0 +1,278 @@
+// Nextcloud Passwords の書き出し（JSON / CSV）の取り込みと、JSON での書き出し
+//
+// Nextcloud Passwords はフォルダ（入れ子）とタグを別々に持つ。取り込みではフォルダのパス
+// （"親/子"）を先頭のタグにし、タグを後ろに続ける。書き出しでは先頭のタグをフォルダにし、
+// 全てのタグを Nextcloud のタグとしても残す（取り込み直しても同じタグになる）。
+// TOTP は Nextcloud 側に対応する項目が無いため、otpauth:// URI の秘密フィールドにする。
+// CSV はタグを1列にまとめ、カスタムフィールドを「ラベル: 値」の行にする（取り込みと同じ形）。
+use crate::error::err;
+use crate::{now_iso, totp, Entry, Field, Vault};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::{fs, path::Path};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use uuid::Uuid;
+
+const ROOT: &str = "00000000-0000-0000-0000-000000000000";
+const OTP_FIELD: &str = "otpauth";
+
+#[derive(Deserialize)]
+struct Backup {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    passwords: Vec<Password>,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Password {
+    label: String,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    notes: String,
+    /// 新しい版は配列、古い版はラベルをキーにしたオブジェクト
+    #[serde(default)]
+    custom_fields: Value,
+    #[serde(default)]
+    folder: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    trashed: bool,
+    #[serde(default)]
+    created: Option<i64>,
+    #[serde(default)]
+    edited: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    label: String,
+    #[serde(default)]
+    parent: String,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    id: String,
+    label: String,
+}
+
+fn iso(ts: Option<i64>) -> Option<String> {
+    OffsetDateTime::from_unix_timestamp(ts?).ok()?.format(&Rfc3339).ok()
+}
+
+fn unix(ts: &str) -> i64 {
+    OffsetDateTime::parse(ts, &Rfc3339).map(|t| t.unix_timestamp()).unwrap_or(0)
+}
+
+// (ラベル, 値) の一覧。file 型は Nextcloud 上のファイルへの参照なので値をそのまま残す
+fn custom_fields(v: &Value) -> Vec<(String, String)> {
+    let pair = |label: &str, f: &Value| {
+        let value = f.get("value").and_then(Value::as_str).or(f.as_str())?;
+        Some((label.to_string(), value.to_string()))
+    };
+    match v {
+        Value::Array(a) => a.iter().filter_map(|f| pair(f.get("label")?.as_str()?, f)).collect(),
+        Value::Object(m) => m.iter().filter_map(|(k, f)| pair(k, f)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// フォルダIDから "親/子" のパスを作る（ルートは空）
+fn folder_path(id: &str, folders: &BTreeMap<&str, &Folder>) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut cur = id;
+    // 壊れたデータで循環していても止まるように深さを制限
+    for _ in 0..64 {
+        let Some(f) = folders.get(cur) else { break };
+        parts.push(f.label.trim());
+        if f.parent.is_empty() || f.parent == ROOT { break; }
+        cur = &f.parent;
+    }
+    parts.reverse();
+    Some(parts.join("/")).filter(|p| !p.is_empty())
+}
+
+// 1件分を組み立てる（JSON・CSV 共通）
+fn entry(label: &str, username: &str, password: &str, url: &str, notes: &str, fields: Vec<(String, String)>, tags: Vec<String>) -> Result<Entry> {
+    let opt = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    let mut e = Entry::new(label.trim().to_string());
+    e.username = username.trim().to_string();
+    e.password = password.to_string();
+    e.url = opt(url);
+    e.notes = opt(notes);
+    for t in tags {
+        if !t.is_empty() && !e.tags.contains(&t) { e.tags.push(t); }
+    }
+    for (name, value) in fields {
+        if e.otp.is_none() && value.starts_with("otpauth://") {
+            e.otp = Some(totp::parse_uri(&value).map_err(|err| err!(InvalidInput, "{:?}: {err}", e.name))?);
+            continue;
+        }
+        e.fields.push(Field { name, value, json: false, hidden: false });
+    }
+    Ok(e)
+}
+
+fn read_json(data: &[u8]) -> Result<Vec<Entry>> {
+    let b: Backup = serde_json::from_slice(data).map_err(|e| err!(InvalidInput, "not a Nextcloud Passwords export: {e}"))?;
+    if b.encrypted {
+        return Err(err!(InvalidInput, "encrypted Nextcloud Passwords backups are not supported; export without encryption"));
+    }
+    let folders: BTreeMap<&str, &Folder> = b.folders.iter().map(|f| (f.id.as_str(), f)).collect();
+    let tags: BTreeMap<&str, &str> = b.tags.iter().map(|t| (t.id.as_str(), t.label.trim())).collect();
+    let mut out = Vec::new();
+    for p in b.passwords.iter().filter(|p| !p.trashed) {
+        let tag_names = folder_path(&p.folder, &folders).into_iter()
+            .chain(p.tags.iter().filter_map(|t| tags.get(t.as_str()).map(|s| s.to_string())))
+            .collect();
+        let mut e = entry(&p.label, &p.username, &p.password, &p.url, &p.notes, custom_fields(&p.custom_fields), tag_names)?;
+        e.created_at = iso(p.created).or(e.created_at);
+        e.updated_at = iso(p.edited).unwrap_or_else(now_iso);
+        out.push(e);
+    }
+    Ok(out)
+}
+
+// CSV は列を選んで書き出せるため、見出しの名前で列を探す
+fn read_csv(data: &[u8]) -> Result<Vec<Entry>> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_lowercase().replace(['_', ' '], "")).collect();
+    let col = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let Some(label) = col(&["label", "name", "title"]) else {
+        return Err(err!(InvalidInput, "not a Nextcloud Passwords CSV (no Name/Label column; export with a header row)"));
+    };
+    let (user, pass, url, notes) = (col(&["username"]), col(&["password"]), col(&["url"]), col(&["notes"]));
+    let (fields, folder, tags) = (col(&["customfields"]), col(&["folder", "folderlabel"]), col(&["tags"]));
+    let mut out = Vec::new();
+    for rec in rdr.records() {
+        let rec = rec?;
+        let get = |c: Option<usize>| c.and_then(|c| rec.get(c)).unwrap_or_default();
+        // カスタムフィールドは1行に「ラベル: 値」
+        let custom = get(fields).lines()
+            .filter_map(|l| l.split_once(':').map(|(k, v)| (k.trim().to_string(), v.trim().to_string())))
+            .filter(|(k, _)| !k.is_empty())
+            .collect();
+        let tag_names = Some(get(folder).trim().to_string()).into_iter()
+            .chain(get(tags).split(',').map(|t| t.trim().to_string()))
+            .collect();
+        out.push(entry(get(Some(label)), get(user), get(pass), get(url), get(notes), custom, tag_names)?);
+    }
+    Ok(out)
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let data = fs::read(path)?;
+    if data.trim_ascii_start().starts_with(b"{") { read_json(&data) } else { read_csv(&data) }
+}
+
+// SSH鍵・証明書・TOTP・導出設定もカスタムフィールド/メモに載せた (フィールド, メモ) の一覧
+fn custom_and_notes(e: &Entry) -> (Vec<Value>, String) {
+    let mut fields: Vec<Value> = e.fields.iter()
+        .map(|f| json!({ "label": f.name, "type": if f.json { "data" } else { "secret" }, "value": f.value }))
+        .collect();
+    if let Some(o) = &e.otp {
+        let account = if e.username.is_empty() { &e.name } else { &e.username };
+        fields.push(json!({ "label": OTP_FIELD, "type": "secret", "value": o.uri(account) }));
+    }
+    // SSH鍵・証明書は鍵素材を秘密フィールドに入れる
+    if let Some(k) = &e.ssh_key {
+        fields.push(json!({ "label": "ssh public key", "type": "text", "value": k.public_key }));
+        fields.push(json!({ "label": "ssh private key", "type": "secret", "value": k.private_key }));
+    }
+    if let Some(c) = &e.cert {
+        fields.push(json!({ "label": "certificate", "type": "data", "value": c.cert_pem }));
+        fields.push(json!({ "label": "private key", "type": "secret", "value": c.key_pem }));
+        if let Some(chain) = &c.chain_pem { fields.push(json!({ "label": "chain", "type": "data", "value": chain })); }
+    }
+    // 導出設定のエントリはパスワードを持たないので、設定だけメモに残す
+    let mut notes = e.notes.clone().unwrap_or_default();
+    if let Some(d) = &e.derive {
+        if !notes.is_empty() { notes.push_str("\n\n"); }
+        notes.push_str(&format!(
+            "rustpass derived password: login {:?}, counter {}, length {} (not exported)",
+            d.login, d.counter, d.length
+        ));
+    }
+    (fields, notes)
+}
+
+pub fn render(vault: &Vault) -> Result<String> {
+    let mut folders: BTreeMap<&str, String> = BTreeMap::new();
+    let mut tags: BTreeMap<&str, String> = BTreeMap::new();
+    for e in &vault.entries {
+        if let Some(t) = e.tags.first() { folders.entry(t.as_str()).or_insert_with(|| Uuid::new_v4().to_string()); }
+        for t in &e.tags { tags.entry(t.as_str()).or_insert_with(|| Uuid::new_v4().to_string()); }
+    }
+    let passwords: Vec<Value> = vault.entries.iter().map(|e| {
+        let (fields, notes) = custom_and_notes(e);
+        json!({
+            "id": e.id,
+            "label": e.name,
+            "username": e.username,
+            "password": e.password,
+            "url": e.url.as_deref().unwrap_or_default(),
+            "notes": notes,
+            "customFields": fields,
+            "folder": e.tags.first().map_or(ROOT, |t| folders[t.as_str()].as_str()),
+            "tags": e.tags.iter().map(|t| &tags[t.as_str()]).collect::<Vec<_>>(),
+            "favorite": false,
+            "hidden": false,
+            "created": unix(e.created_at.as_deref().unwrap_or(&e.updated_at)),
+            "edited": unix(&e.updated_at),
+        })
+    }).collect();
+    let folders: Vec<Value> = folders.iter()
+        .map(|(label, id)| json!({ "id": id, "label": label, "parent": ROOT, "favorite": false, "hidden": false }))
+        .collect();
+    let tags: Vec<Value> = tags.iter()
+        .map(|(label, id)| json!({ "id": id, "label": label, "color": "#0082c9", "favorite": false, "hidden": false }))
+        .collect();
+    let out = json!({ "version": 3, "encrypted": false, "passwords": passwords, "folders": folders, "tags": tags });
+    Ok(serde_json::to_string_pretty(&out)?)
+}
+
+pub fn render_csv(vault: &Vault) -> Result<String> {
+    let mut w = csv::Writer::from_writer(Vec::new());
+    w.write_record(["Name", "Username", "Password", "Notes", "Url", "Folder", "Tags", "Custom Fields"])?;
+    for e in &vault.entries {
+        let (fields, notes) = custom_and_notes(e);
+        // 複数行の値（鍵・証明書など）は「ラベル: 値」の1行に収まらないのでメモに回す
+        let mut custom = Vec::new();
+        let mut notes = notes;
+        for f in &fields {
+            let (label, value) = (f["label"].as_str().unwrap_or_default(), f["value"].as_str().unwrap_or_default());
+            if value.contains('\n') {
+                if !notes.is_empty() { notes.push_str("\n\n"); }
+                notes.push_str(&format!("{label}:\n{value}"));
+            } else {
+                custom.push(format!("{label}: {value}"));
+            }
+        }
+        w.write_record([
+            e.name.as_str(),
+            &e.username,
+            &e.password,
+            &notes,
+            e.url.as_deref().unwrap_or_default(),
+            e.tags.first().map_or("", String::as_str),
+            &e.tags.join(","),
+            &custom.join("\n"),
+        ])?;
+    }
+    Ok(String::from_utf8(w.into_inner().map_err(|e| e.into_error())?)?)
+}
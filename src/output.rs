@@ -0,0 +1,168 @@
+// list / show / export の構造化出力（JSON・YAML・TOML）
+//
+// 構成管理（Ansible の変数ファイル、Hugo のデータファイルなど）がそのまま読めるように、
+// 同じ値を3つの形式で書き出す。YAML はブロック形式で、必要な文字列だけ引用符で囲む。
+// TOML は null を表せないため値の無い項目を省き、最上位が配列なら名前付きの表にする。
+use crate::{audit, config::Config, Entry};
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Output {
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// root は TOML で最上位が表でない場合に使う名前
+pub fn render(out: Output, root: &str, v: &Value) -> Result<String> {
+    match out {
+        Output::Json => Ok(serde_json::to_string_pretty(v)?),
+        Output::Yaml => {
+            let mut s = String::new();
+            yaml_block(v, 0, &mut s);
+            Ok(s)
+        }
+        Output::Toml => {
+            let v = strip_nulls(v.clone());
+            let v = if v.is_object() { v } else { json!({ root: v }) };
+            Ok(toml::to_string_pretty(&v)?)
+        }
+    }
+}
+
+/// list の1行分
+pub fn list_item(e: &Entry, cfg: &Config) -> Result<Value> {
+    let age = audit::age_violation(e, cfg)?;
+    Ok(json!({
+        "name": e.name,
+        "type": e.kind(),
+        "username": e.username,
+        "url": e.url,
+        "tags": e.tags,
+        "created": e.created_at,
+        "updated": e.updated_at,
+        "last_accessed": e.last_accessed,
+        "password_older_than_days": age.map(|a| a.max_days),
+    }))
+}
+
+/// show の内容（パスワードは with_password のときだけ含める）
+pub fn show_item(e: &Entry, with_password: bool) -> Result<Value> {
+    let fields: Map<String, Value> = e.fields.iter().map(|f| {
+        let v = if f.hidden && !with_password { Value::Null } else if f.json { serde_json::from_str(&f.value).unwrap_or_else(|_| f.value.clone().into()) } else { f.value.clone().into() };
+        (f.name.clone(), v)
+    }).collect();
+    let totp = e.otp.as_ref().map(|o| o.now()).transpose()?;
+    // カード番号・セキュリティコード・暗証番号もパスワードと同じ扱い
+    let card = e.card.as_ref().map(|c| match with_password {
+        true => json!(c),
+        false => json!({ "holder": c.holder, "number": c.masked(), "expires": c.expires }),
+    });
+    Ok(json!({
+        "name": e.name,
+        "type": e.kind(),
+        "username": e.username,
+        "password": with_password.then_some(&e.password),
+        "url": e.url,
+        "tags": e.tags,
+        "notes": e.notes,
+        "fields": fields,
+        "totp": totp.map(|(code, left)| json!({ "code": code, "expires_in": left })),
+        "autotype": e.autotype,
+        "expires_at": e.expires_at,
+        "max_age_days": e.max_age_days,
+        "passkey": e.passkey,
+        "card": card,
+        "identity": e.identity,
+        "attachments": e.attachments.iter().map(|a| json!({ "name": a.name, "size": a.data.len(), "added": a.added })).collect::<Vec<_>>(),
+        "created": e.created_at,
+        "updated": e.updated_at,
+        "last_accessed": e.last_accessed,
+    }))
+}
+
+fn strip_nulls(v: Value) -> Value {
+    match v {
+        Value::Object(m) => Value::Object(m.into_iter().filter(|(_, x)| !x.is_null()).map(|(k, x)| (k, strip_nulls(x))).collect()),
+        Value::Array(a) => Value::Array(a.into_iter().filter(|x| !x.is_null()).map(strip_nulls).collect()),
+        v => v,
+    }
+}
+
+// 引用符なしで書いても文字列のまま読まれるか（数値・真偽値・日付・記号始まりは引用する）
+fn plain(s: &str) -> bool {
+    const RESERVED: [&str; 11] = ["true", "false", "null", "yes", "no", "on", "off", "y", "n", "~", "<<"];
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '/')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || " _./@+-:".contains(c))
+        && !s.ends_with([' ', ':'])
+        && !s.contains(": ")
+        && !RESERVED.contains(&s.to_ascii_lowercase().as_str())
+}
+
+// JSON の文字列表記は YAML の二重引用符の文字列としてそのまま読める
+fn quoted(s: &str) -> String {
+    if plain(s) { s.to_string() } else { Value::from(s).to_string() }
+}
+
+fn scalar(v: &Value, indent: usize, out: &mut String) {
+    match v {
+        Value::String(s) if s.contains('\n') && !s.contains(['\r', '\t']) && !s.starts_with(' ') && !s.ends_with("\n\n") => {
+            // 複数行（鍵・メモなど）はリテラルブロックにする
+            out.push_str(if s.ends_with('\n') { " |\n" } else { " |-\n" });
+            for line in s.trim_end_matches('\n').split('\n') {
+                if !line.is_empty() { out.push_str(&" ".repeat(indent + 2)); }
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        Value::String(s) => { out.push(' '); out.push_str(&quoted(s)); out.push('\n'); }
+        Value::Object(_) => out.push_str(" {}\n"),
+        Value::Array(_) => out.push_str(" []\n"),
+        v => { out.push(' '); out.push_str(&v.to_string()); out.push('\n'); }
+    }
+}
+
+fn nested(v: &Value) -> bool {
+    match v {
+        Value::Object(m) => !m.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        _ => false,
+    }
+}
+
+fn yaml_block(v: &Value, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match v {
+        Value::Object(m) if !m.is_empty() => for (k, x) in m {
+            out.push_str(&pad);
+            out.push_str(&quoted(k));
+            out.push(':');
+            if nested(x) {
+                out.push('\n');
+                yaml_block(x, indent + 2, out);
+            } else {
+                scalar(x, indent, out);
+            }
+        },
+        Value::Array(a) if !a.is_empty() => for x in a {
+            out.push_str(&pad);
+            out.push('-');
+            if nested(x) {
+                // 表・配列の最初の行を "- " の後ろに続ける
+                let mut inner = String::new();
+                yaml_block(x, indent + 2, &mut inner);
+                out.push(' ');
+                out.push_str(&inner[indent + 2..]);
+            } else {
+                scalar(x, indent, out);
+            }
+        },
+        v => {
+            let mut s = String::new();
+            scalar(v, indent, &mut s);
+            out.push_str(s.strip_prefix(' ').unwrap_or(&s));
+        }
+    }
+}
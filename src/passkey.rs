@@ -0,0 +1,97 @@
+// パスキー（FIDO2 discoverable credential）の台帳
+//
+// 秘密鍵は認証器（スマートフォン・セキュリティキーなど）から取り出せないので、
+// どのサイトのどのアカウントにどの認証器で登録したかという情報だけを保存する。
+use crate::error::err;
+use crate::{Entry, Vault};
+use anyhow::Result;
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use serde::{Deserialize, Serialize};
+use time::{macros::format_description, Date};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Passkey {
+    /// Relying Party ID（通常はサイトのドメイン）
+    pub rp_id: String,
+    /// クレデンシャルID（base64url）
+    pub credential_id: String,
+    /// ユーザーハンドル（base64url）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_handle: Option<String>,
+    /// 保存先の認証器（例: "iCloud Keychain", "YubiKey 5C"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authenticator: Option<String>,
+    /// バックアップ（同期）可能なパスキーか（BEフラグ）
+    #[serde(default)]
+    pub backup_eligible: bool,
+    /// 登録日（YYYY-MM-DD）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+}
+
+// base64url（パディング有無・標準base64も受け付ける）を正規化
+fn normalize_id(s: &str, what: &str) -> Result<String> {
+    let s = s.trim().trim_end_matches('=');
+    let bytes = URL_SAFE_NO_PAD.decode(s)
+        .or_else(|_| STANDARD.decode(format!("{s}{}", "=".repeat((4 - s.len() % 4) % 4))))
+        .map_err(|_| err!(InvalidInput, "{what} must be base64url"))?;
+    if bytes.is_empty() { return Err(err!(InvalidInput, "{what} is empty")); }
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+impl Passkey {
+    pub fn new(
+        rp_id: &str,
+        credential_id: &str,
+        user_handle: Option<&str>,
+        authenticator: Option<String>,
+        backup_eligible: bool,
+        created: Option<&str>,
+    ) -> Result<Self> {
+        let rp_id = rp_id.trim().trim_end_matches('.').to_lowercase();
+        if rp_id.is_empty() || rp_id.contains(['/', ':', ' ']) {
+            return Err(err!(InvalidInput, "--rp must be a domain such as example.com"));
+        }
+        if let Some(d) = created {
+            Date::parse(d, format_description!("[year]-[month]-[day]"))
+                .map_err(|_| err!(InvalidInput, "--created must be YYYY-MM-DD"))?;
+        }
+        Ok(Passkey {
+            rp_id,
+            credential_id: normalize_id(credential_id, "--credential-id")?,
+            user_handle: user_handle.map(|h| normalize_id(h, "--user-handle")).transpose()?,
+            authenticator,
+            backup_eligible,
+            created: created.map(str::to_string),
+        })
+    }
+
+    /// URLのホストがこのRP IDの対象か（RP IDそのもの、またはそのサブドメイン）
+    pub fn covers(&self, url: &str) -> bool {
+        let h = host(url);
+        h == self.rp_id || h.ends_with(&format!(".{}", self.rp_id))
+    }
+}
+
+fn host(url: &str) -> String {
+    let s = url.split("://").last().unwrap_or(url);
+    let s = s.split(['/', '?', '#']).next().unwrap_or(s);
+    let s = s.rsplit('@').next().unwrap_or(s);
+    s.split(':').next().unwrap_or(s).to_lowercase()
+}
+
+// 同じサイトで、ユーザー名が一致する（どちらかが空なら一致とみなす）パスキーがあるか
+fn covered(login: &Entry, url: &str, keys: &[(&Entry, &Passkey)]) -> bool {
+    keys.iter().any(|(e, k)| {
+        k.covers(url) && (e.username.is_empty() || login.username.is_empty() || e.username.eq_ignore_ascii_case(&login.username))
+    })
+}
+
+/// パスキーがまだ無いログインエントリ（URLとパスワードを持つもの）
+pub fn missing(vault: &Vault) -> Vec<&Entry> {
+    let keys: Vec<(&Entry, &Passkey)> = vault.entries.iter().filter_map(|e| Some((e, e.passkey.as_ref()?))).collect();
+    vault.entries.iter()
+        .filter(|e| e.passkey.is_none() && !e.password.is_empty())
+        .filter(|e| e.url.as_deref().is_some_and(|u| !host(u).is_empty() && !covered(e, u, &keys)))
+        .collect()
+}
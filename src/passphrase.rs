@@ -0,0 +1,89 @@
+// 単語ベースのパスフレーズ生成（diceware方式）
+use anyhow::{anyhow, Context, Result};
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use std::{collections::BTreeSet, fs};
+
+// 同梱ワードリスト: (名前, 内容)
+const BUILTIN: &[(&str, &str)] = &[
+    ("eff-large", include_str!("../wordlists/eff_large.txt")),
+    ("eff-short", include_str!("../wordlists/eff_short.txt")),
+    ("jp-romaji", include_str!("../wordlists/jp_romaji.txt")),
+];
+
+pub const DEFAULT_WORDLIST: &str = "eff-large";
+
+// 1行1語。diceware形式（"11111<TAB>word"）の場合は末尾の語を使う
+fn parse(text: &str) -> Vec<String> {
+    let set: BTreeSet<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_whitespace().last())
+        .map(str::to_string)
+        .collect();
+    set.into_iter().collect()
+}
+
+// 同梱リスト名、またはファイルパス
+pub fn load_wordlist(spec: &str) -> Result<Vec<String>> {
+    let words = match BUILTIN.iter().find(|(name, _)| *name == spec) {
+        Some((_, text)) => parse(text),
+        None => {
+            let text = fs::read_to_string(spec)
+                .with_context(|| format!("unknown wordlist {spec:?} (not a built-in name or readable file)"))?;
+            parse(&text)
+        }
+    };
+    if words.len() < 2 {
+        return Err(anyhow!("wordlist {spec:?} has fewer than 2 distinct words"));
+    }
+    Ok(words)
+}
+
+pub fn bits_per_word(words: &[String]) -> f64 {
+    (words.len() as f64).log2()
+}
+
+/// 大文字・数字を要求するルール向けの整形
+#[derive(Clone, Copy, Default)]
+pub struct Style {
+    /// 各単語の先頭を大文字に
+    pub capitalize: bool,
+    /// ランダムな単語の末尾にランダムな数字を1桁付ける
+    pub include_number: bool,
+    /// 区切りなしで2語目以降の先頭を大文字に（camelCase）
+    pub camel_case: bool,
+}
+
+impl Style {
+    /// 単語の選択以外で増えるエントロピー（数字の値と位置）
+    pub fn extra_bits(&self, count: usize) -> f64 {
+        if self.include_number { (10.0 * count as f64).log2() } else { 0.0 }
+    }
+}
+
+/// "none" は区切りなし
+pub fn separator(spec: &str) -> &str {
+    if spec == "none" { "" } else { spec }
+}
+
+fn capitalized(word: &str) -> String {
+    let mut c = word.chars();
+    c.next().map(|f| f.to_uppercase().chain(c).collect()).unwrap_or_default()
+}
+
+pub fn generate_passphrase(words: &[String], count: usize, separator: &str, style: Style) -> Result<String> {
+    if count == 0 { return Err(anyhow!("--words must be >= 1")); }
+    let mut rng = OsRng;
+    let mut picked: Vec<String> = (0..count)
+        .map(|_| words.choose(&mut rng).unwrap().clone())
+        .collect();
+    for (i, w) in picked.iter_mut().enumerate() {
+        if style.capitalize || (style.camel_case && i > 0) { *w = capitalized(w); }
+    }
+    if style.include_number {
+        let i = rng.gen_range(0..count);
+        picked[i].push(char::from(b'0' + rng.gen_range(0..10)));
+    }
+    Ok(picked.join(if style.camel_case { "" } else { separator }))
+}
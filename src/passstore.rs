@@ -0,0 +1,249 @@
+// pass（password-store）のディレクトリの取り込みと書き出し。暗号化・復号は gpg コマンドに任せる
+//
+// 1ファイル1エントリで、1行目がパスワード、2行目以降は「login: ...」「url: ...」などの
+// 「キー: 値」の行と otpauth:// の行（pass-otp）、それ以外の自由なメモ。
+// ディレクトリのパス（"work/aws"）はエントリの先頭のタグにし、ファイル名をエントリ名にする。
+// 書き出しでは複数行の値を「キー: |」の後に2文字字下げした行で書き、取り込みで元に戻す。
+use crate::error::err;
+use crate::{totp, Entry, Field, Vault};
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::fs;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use zeroize::Zeroizing;
+
+const GPG_ID: &str = ".gpg-id";
+
+fn gpg<S: AsRef<OsStr>>(args: &[S], input: Option<&[u8]>) -> Result<Zeroizing<Vec<u8>>> {
+    let mut child = Command::new("gpg")
+        .args(["--quiet", "--batch", "--yes"])
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => err!(NotFound, "gpg not found in PATH (install GnuPG)"),
+            _ => e.into(),
+        })?;
+    if let (Some(data), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(data)?;
+    }
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        return Err(anyhow!("gpg failed: {}", String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(Zeroizing::new(out.stdout))
+}
+
+// 「キー: 値」の行か（"https://..." のような行は違う）
+fn key_value(line: &str) -> Option<(&str, &str)> {
+    let (k, v) = line.split_once(':')?;
+    if k.is_empty() || k.starts_with(char::is_whitespace) || !(v.is_empty() || v.starts_with(' ')) {
+        return None;
+    }
+    Some((k.trim_end(), v.trim()))
+}
+
+fn parse(name: &str, tag: Option<String>, text: &str) -> Result<Entry> {
+    let mut e = Entry::new(name.to_string());
+    e.tags.extend(tag);
+    let mut lines = text.lines().peekable();
+    e.password = lines.next().unwrap_or_default().to_string();
+    let mut notes = Vec::new();
+    while let Some(line) = lines.next() {
+        if line.trim_start().starts_with("otpauth://") {
+            e.otp = Some(totp::parse_uri(line)?);
+            continue;
+        }
+        let Some((key, value)) = key_value(line) else { notes.push(line.to_string()); continue };
+        let mut value = value.to_string();
+        // 「キー: |」に続く字下げした行が複数行の値
+        if value == "|" {
+            let mut body = Vec::new();
+            while let Some(l) = lines.next_if(|l| l.starts_with("  ") || l.is_empty()) {
+                body.push(l.strip_prefix("  ").unwrap_or(l));
+            }
+            value = body.join("\n").trim_end().to_string();
+        }
+        match key.to_lowercase().as_str() {
+            "login" | "username" | "user" if e.username.is_empty() => e.username = value,
+            "url" | "website" if e.url.is_none() => e.url = Some(value),
+            "otp" | "totp" if e.otp.is_none() => e.otp = Some(totp::parse_secret(&value)?),
+            "tags" => e.tags.extend(value.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from)),
+            "notes" | "comments" => notes.push(value),
+            _ => e.fields.push(Field { name: key.to_string(), value, json: false, hidden: false }),
+        }
+    }
+    let notes = notes.join("\n");
+    e.notes = Some(notes.trim().to_string()).filter(|n| !n.is_empty());
+    Ok(e)
+}
+
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let mut items: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    items.sort_by_key(|d| d.file_name());
+    for d in items {
+        // .git・.gpg-id・.extensions などは飛ばす
+        if d.file_name().to_string_lossy().starts_with('.') { continue; }
+        let path = d.path();
+        if d.file_type()?.is_dir() {
+            walk(&path, out)?;
+        } else if path.extension() == Some(OsStr::new("gpg")) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// ストアのディレクトリ（~/.password-store など）の全エントリを gpg で復号して読む
+pub fn read(dir: &Path) -> Result<Vec<Entry>> {
+    if !dir.is_dir() {
+        return Err(err!(InvalidInput, "{} is not a directory (pass the password store, e.g. ~/.password-store)", dir.display()));
+    }
+    let mut files = Vec::new();
+    walk(dir, &mut files)?;
+    let mut out = Vec::new();
+    for path in files {
+        let rel = path.strip_prefix(dir).unwrap_or(&path).with_extension("");
+        let name = rel.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let tag = rel.parent()
+            .map(|p| p.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/"))
+            .filter(|t| !t.is_empty());
+        let plain = gpg(&[OsStr::new("--decrypt"), path.as_os_str()], None)
+            .map_err(|e| anyhow!("{}: {e}", rel.display()))?;
+        let text = Zeroizing::new(String::from_utf8_lossy(&plain).into_owned());
+        let mut e = parse(&name, tag, &text).map_err(|e| err!(InvalidInput, "{}: {e}", rel.display()))?;
+        // pass は日時を持たないので、ファイルの更新日時を使う
+        if let Some(t) = fs::metadata(&path).and_then(|m| m.modified()).ok().and_then(|t| OffsetDateTime::from(t).format(&Rfc3339).ok()) {
+            e.updated_at = t;
+        }
+        out.push(e);
+    }
+    if out.is_empty() {
+        eprintln!("note: no .gpg files found under {}", dir.display());
+    }
+    Ok(out)
+}
+
+fn push_line(out: &mut String, key: &str, value: &str) {
+    if value.contains('\n') {
+        out.push_str(&format!("{key}: |\n"));
+        for l in value.lines() { out.push_str(&format!("  {l}\n")); }
+    } else {
+        out.push_str(&format!("{key}: {value}\n"));
+    }
+}
+
+fn render(e: &Entry) -> Zeroizing<String> {
+    let mut out = Zeroizing::new(format!("{}\n", e.password));
+    if !e.username.is_empty() { push_line(&mut out, "login", &e.username); }
+    if let Some(u) = &e.url { push_line(&mut out, "url", u); }
+    if let Some(o) = &e.otp {
+        out.push_str(&o.uri(if e.username.is_empty() { &e.name } else { &e.username }));
+        out.push('\n');
+    }
+    // 先頭のタグはディレクトリになるので、2つ目以降だけ残す
+    if e.tags.len() > 1 { push_line(&mut out, "tags", &e.tags[1..].join(", ")); }
+    for f in &e.fields { push_line(&mut out, &f.name, &f.value); }
+    if let Some(k) = &e.ssh_key {
+        push_line(&mut out, "ssh public key", &k.public_key);
+        push_line(&mut out, "ssh private key", &k.private_key);
+    }
+    if let Some(c) = &e.cert {
+        push_line(&mut out, "certificate", &c.cert_pem);
+        push_line(&mut out, "private key", &c.key_pem);
+        if let Some(chain) = &c.chain_pem { push_line(&mut out, "chain", chain); }
+        push_line(&mut out, "expires", &c.not_after);
+    }
+    let mut notes = e.notes.clone().unwrap_or_default();
+    // 導出設定のエントリはパスワードを持たないので、設定だけメモに残す
+    if let Some(d) = &e.derive {
+        let line = format!("rustpass derived password: login {:?}, counter {}, length {} (not exported)", d.login, d.counter, d.length);
+        notes = if notes.is_empty() { line } else { format!("{notes}\n\n{line}") };
+    }
+    if !notes.is_empty() { push_line(&mut out, "notes", &notes); }
+    out
+}
+
+// ファイル名に使えない部分を置き換える（"/" はディレクトリの区切りとしてそのまま使う）
+fn components(s: &str) -> Vec<String> {
+    s.split('/')
+        .map(|c| match c.trim() {
+            "" | "." | ".." => "_".to_string(),
+            c if c.starts_with('.') => format!("_{}", &c[1..]),
+            c => c.replace('\0', "_"),
+        })
+        .collect()
+}
+
+// ファイルのあるディレクトリから上へ、最も近い .gpg-id の鍵（pass と同じ）
+fn recipients(root: &Path, dir: &Path) -> Result<Vec<String>> {
+    for d in dir.ancestors().take_while(|d| d.starts_with(root)) {
+        if let Ok(text) = fs::read_to_string(d.join(GPG_ID)) {
+            return Ok(text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).map(String::from).collect());
+        }
+    }
+    Err(err!(InvalidInput, "{} has no {GPG_ID}; pass --gpg-id KEY (or run `pass init KEY` first)", root.display()))
+}
+
+fn create_dir(dir: &Path) -> Result<()> {
+    let mut b = fs::DirBuilder::new();
+    b.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        b.mode(0o700);
+    }
+    b.create(dir).map_err(|e| anyhow!("cannot create {}: {e}", dir.display()))
+}
+
+/// 金庫の全エントリを pass のストアとして書き出し、書いたファイル数を返す。
+/// gpg_ids が空ならストアの .gpg-id の鍵で暗号化する
+pub fn write(vault: &Vault, root: &Path, gpg_ids: &[String], force: bool) -> Result<usize> {
+    let id_file = root.join(GPG_ID);
+    if !gpg_ids.is_empty() {
+        let want = format!("{}\n", gpg_ids.join("\n"));
+        match fs::read_to_string(&id_file) {
+            Ok(have) if have.trim() != want.trim() && !force => {
+                return Err(err!(AlreadyExists, "{} is initialised for {} (use --force to replace {GPG_ID})", root.display(), have.trim().replace('\n', ", ")));
+            }
+            Ok(have) if have.trim() == want.trim() => {}
+            _ => {
+                create_dir(root)?;
+                fs::write(&id_file, want)?;
+            }
+        }
+    }
+    // 先に全ての書き出し先を決め、既存ファイルがあれば何も書かずに止める
+    let mut taken = HashSet::new();
+    let mut plan = Vec::new();
+    for e in &vault.entries {
+        let mut parts = e.tags.first().map(|t| components(t)).unwrap_or_default();
+        parts.extend(components(&e.name));
+        let base = parts.join("/");
+        let rel = std::iter::once(base.clone())
+            .chain((2..).map(|i| format!("{base} {i}")))
+            .find(|p| taken.insert(p.clone()))
+            .unwrap();
+        let path = root.join(format!("{rel}.gpg"));
+        if path.exists() && !force {
+            return Err(err!(AlreadyExists, "{} already exists (use --force to overwrite)", path.display()));
+        }
+        plan.push((e, path));
+    }
+    for (e, path) in &plan {
+        let dir = path.parent().unwrap_or(root);
+        let ids = recipients(root, dir)?;
+        create_dir(dir)?;
+        let mut args: Vec<&OsStr> = vec!["--compress-algo=none".as_ref(), "--no-encrypt-to".as_ref(), "--encrypt".as_ref()];
+        for id in &ids { args.extend(["-r".as_ref(), OsStr::new(id)]); }
+        args.extend(["--output".as_ref(), path.as_os_str()]);
+        gpg(&args, Some(render(e).as_bytes())).map_err(|err| anyhow!("{}: {err}", path.display()))?;
+    }
+    Ok(plan.len())
+}
@@ -0,0 +1,56 @@
+// 金庫ファイルと置き場所のディレクトリの権限（自分だけが読み書きできるようにする）
+//
+// Unix ではファイルを 0600、ディレクトリを 0700 にする。Windows では icacls で継承を切り、
+// 自分のアカウントだけにフルコントロールを与える。
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// ディレクトリを（無ければ作って）自分だけが使えるようにする
+pub fn private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    restrict(dir, true)
+}
+
+/// ファイルを自分だけが読み書きできるようにする
+pub fn private_file(path: &Path) -> Result<()> {
+    restrict(path, false)
+}
+
+#[cfg(unix)]
+fn restrict(path: &Path, dir: bool) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = if dir { 0o700 } else { 0o600 };
+    if fs::metadata(path)?.permissions().mode() & 0o777 != mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn restrict(path: &Path, dir: bool) -> Result<()> {
+    use anyhow::anyhow;
+    use std::process::{Command, Stdio};
+    let user = std::env::var("USERNAME").map_err(|_| anyhow!("USERNAME is not set; cannot restrict {}", path.display()))?;
+    let user = match std::env::var("USERDOMAIN") {
+        Ok(domain) => format!("{domain}\\{user}"),
+        Err(_) => user,
+    };
+    // ディレクトリは中に作るファイルにも引き継がせる
+    let grant = if dir { format!("{user}:(OI)(CI)F") } else { format!("{user}:F") };
+    let status = Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r", &grant])
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| anyhow!("cannot run icacls: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("icacls could not restrict access to {}", path.display()));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn restrict(_path: &Path, _dir: bool) -> Result<()> {
+    Ok(())
+}
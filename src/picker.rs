@@ -0,0 +1,120 @@
+// 入力に合わせて候補を絞り込む対話選択（dmenu/rofi風、端末内で完結）
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+use std::io::{self, Write};
+
+const MAX_ROWS: usize = 10;
+
+// 空白区切りの語が全て（大文字小文字を区別せず）含まれる候補のみ
+pub(crate) fn matches(query: &str, candidate: &str) -> bool {
+    let c = candidate.to_lowercase();
+    query.to_lowercase().split_whitespace().all(|w| c.contains(w))
+}
+
+pub(crate) struct RawMode;
+
+impl RawMode {
+    pub(crate) fn enable() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn draw(out: &mut impl Write, query: &str, items: &[String], hits: &[usize], sel: usize, drawn: u16) -> Result<u16> {
+    if drawn > 0 { queue!(out, cursor::MoveUp(drawn))?; }
+    queue!(out, cursor::MoveToColumn(0), terminal::Clear(ClearType::FromCursorDown))?;
+    let rows = hits.len().min(MAX_ROWS);
+    // 選択行が見えるようにスクロール
+    let top = sel.saturating_sub(rows.saturating_sub(1));
+    for (i, &idx) in hits.iter().enumerate().skip(top).take(rows) {
+        if i == sel {
+            queue!(out, SetAttribute(Attribute::Reverse), Print(format!("> {}", items[idx])), SetAttribute(Attribute::Reset))?;
+        } else {
+            queue!(out, Print(format!("  {}", items[idx])))?;
+        }
+        queue!(out, Print("\r\n"))?;
+    }
+    queue!(out, Print(format!("{}/{}  ", hits.len(), items.len())))?;
+    queue!(out, SetAttribute(Attribute::Bold), Print(format!("filter: {query}")), SetAttribute(Attribute::Reset))?;
+    out.flush()?;
+    Ok(rows as u16)
+}
+
+// --accessible 用：画面を書き換えず、番号付きの一覧と行入力で選ぶ
+fn pick_lines(items: &[String]) -> Result<Option<usize>> {
+    let mut hits: Vec<usize> = (0..items.len()).collect();
+    loop {
+        eprintln!("{} matching entries.", hits.len());
+        for (n, &i) in hits.iter().enumerate().take(MAX_ROWS) {
+            eprintln!("{}. {}", n + 1, items[i]);
+        }
+        if hits.len() > MAX_ROWS { eprintln!("{} more not shown; type words to narrow down.", hits.len() - MAX_ROWS); }
+        eprint!("Type a number to choose, words to filter, or press Enter to cancel: ");
+        io::stderr().flush()?;
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        let line = line.trim();
+        if line.is_empty() { return Ok(None); }
+        if let Ok(n) = line.parse::<usize>() {
+            match hits.get(n.wrapping_sub(1)).filter(|_| n <= MAX_ROWS) {
+                Some(&i) => return Ok(Some(i)),
+                None => { eprintln!("No entry number {n}."); continue; }
+            }
+        }
+        hits = (0..items.len()).filter(|&i| matches(line, &items[i])).collect();
+        if hits.len() == 1 { return Ok(Some(hits[0])); }
+        if hits.is_empty() {
+            eprintln!("Nothing matches {line:?}; showing all entries.");
+            hits = (0..items.len()).collect();
+        }
+    }
+}
+
+/// 選ばれた候補のインデックスを返す（Esc / Ctrl-C で None）
+pub fn pick(items: &[String], accessible: bool) -> Result<Option<usize>> {
+    crate::prompt::require("an entry name")?;
+    if accessible { return pick_lines(items); }
+    let mut out = io::stderr();
+    let _raw = RawMode::enable()?;
+    let mut query = String::new();
+    let mut sel = 0;
+    let mut drawn = 0;
+    let result = loop {
+        let hits: Vec<usize> = (0..items.len()).filter(|&i| matches(&query, &items[i])).collect();
+        sel = sel.min(hits.len().saturating_sub(1));
+        drawn = draw(&mut out, &query, items, &hits, sel, drawn)?;
+
+        let Event::Key(KeyEvent { code, modifiers, kind, .. }) = event::read()? else { continue };
+        if kind == KeyEventKind::Release { continue; }
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        match code {
+            KeyCode::Enter => break hits.get(sel).copied(),
+            KeyCode::Esc => break None,
+            KeyCode::Char('c') | KeyCode::Char('g') if ctrl => break None,
+            KeyCode::Up => sel = sel.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => sel = sel.saturating_sub(1),
+            KeyCode::Down => sel += 1,
+            KeyCode::Char('n') if ctrl => sel += 1,
+            KeyCode::Char('u') if ctrl => { query.clear(); sel = 0; }
+            KeyCode::Backspace => { query.pop(); sel = 0; }
+            KeyCode::Char(ch) if !ctrl => { query.push(ch); sel = 0; }
+            _ => {}
+        }
+    };
+    if drawn > 0 { queue!(out, cursor::MoveUp(drawn))?; }
+    queue!(out, cursor::MoveToColumn(0), terminal::Clear(ClearType::FromCursorDown))?;
+    out.flush()?;
+    Ok(result)
+}
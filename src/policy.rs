@@ -0,0 +1,77 @@
+// 金庫ごとのパスワードポリシー（保存時に強制、audit --policy で遡って確認）
+use crate::derive::CharClass;
+use crate::error::err;
+use crate::Vault;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Policy {
+    #[serde(default)]
+    pub min_length: Option<usize>,
+    /// 必須の文字種
+    #[serde(default)]
+    pub require: Vec<CharClass>,
+    /// 含んではいけない語（大文字小文字を区別しない）
+    #[serde(default)]
+    pub banned_words: Vec<String>,
+    /// 他のエントリと同じパスワードを禁止
+    #[serde(default)]
+    pub no_reuse: bool,
+}
+
+fn has_class(password: &str, class: CharClass) -> bool {
+    password.chars().any(|c| match class {
+        CharClass::Lowercase => c.is_lowercase(),
+        CharClass::Uppercase => c.is_uppercase(),
+        CharClass::Digits => c.is_ascii_digit(),
+        CharClass::Symbols => !c.is_alphanumeric() && !c.is_whitespace(),
+    })
+}
+
+impl Policy {
+    /// 違反内容の一覧（entry は更新中のエントリ名で、再利用チェックから除外する）
+    pub fn violations(&self, password: &str, vault: &Vault, entry: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        if let Some(min) = self.min_length {
+            let len = password.chars().count();
+            if len < min { out.push(format!("shorter than {min} characters ({len})")); }
+        }
+        for &class in &self.require {
+            if !has_class(password, class) { out.push(format!("missing {class:?}").to_lowercase()); }
+        }
+        let lower = password.to_lowercase();
+        for w in &self.banned_words {
+            if !w.is_empty() && lower.contains(&w.to_lowercase()) { out.push(format!("contains banned word {w:?}")); }
+        }
+        if self.no_reuse {
+            let reused: Vec<&str> = vault.entries.iter()
+                .filter(|e| e.name != entry && !e.password.is_empty() && e.password == password)
+                .map(|e| e.name.as_str())
+                .collect();
+            if !reused.is_empty() { out.push(format!("reused by {}", reused.join(", "))); }
+        }
+        out
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        if let Some(n) = self.min_length { out.push(format!("min length: {n}")); }
+        if !self.require.is_empty() {
+            let names: Vec<String> = self.require.iter().map(|c| format!("{c:?}").to_lowercase()).collect();
+            out.push(format!("require: {}", names.join(", ")));
+        }
+        if !self.banned_words.is_empty() { out.push(format!("banned words: {}", self.banned_words.join(", "))); }
+        if self.no_reuse { out.push("no reuse across entries".into()); }
+        out
+    }
+}
+
+/// 保存しようとしているパスワードがポリシーを満たすか確認
+pub fn enforce(vault: &Vault, entry: &str, password: &str) -> Result<()> {
+    let Some(p) = &vault.policy else { return Ok(()) };
+    if password.is_empty() { return Ok(()); }
+    let v = p.violations(password, vault, entry);
+    if v.is_empty() { return Ok(()); }
+    Err(err!(InvalidInput, "password for {entry:?} violates the vault policy: {}", v.join("; ")))
+}
@@ -0,0 +1,46 @@
+// 端末からの入力（--non-interactive のときは尋ねずにエラーにする）
+//
+// スクリプトや CI では入力待ちのまま止まるより、何が足りないかを示してすぐ失敗するほうが扱いやすい。
+// マスターパスワードは --password-file / --password-fd / RUSTPASS_PASSWORD で事前に渡せる。
+use crate::error::err;
+use anyhow::Result;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// 以降の入力要求をすべてエラーにする
+pub fn disable() {
+    NON_INTERACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// 入力を求めてよいか
+pub fn interactive() -> bool {
+    !NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// 入力を求めてよいか確かめる（what は足りないものの説明。プロンプトの文言でもよい）
+pub fn require(what: &str) -> Result<()> {
+    if !interactive() {
+        let what = what.trim().trim_end_matches(':').trim_end();
+        return Err(err!(InvalidInput, "{what} is required but --non-interactive is set"));
+    }
+    Ok(())
+}
+
+/// 伏せ字で1行読む
+pub fn password(label: impl AsRef<str>) -> Result<String> {
+    let label = label.as_ref();
+    require(label)?;
+    Ok(rpassword::prompt_password(label)?)
+}
+
+/// 1行読む（前後の空白は除く。入力が終わっていれば空文字列）
+pub fn line(label: &str) -> Result<String> {
+    require(label)?;
+    print!("{label}");
+    io::stdout().flush()?;
+    let mut s = String::new();
+    io::stdin().read_line(&mut s)?;
+    Ok(s.trim().to_string())
+}
@@ -0,0 +1,90 @@
+// jq風のパス式でJSONを参照（.a.b[0]."key" / .["key"] のみ対応）
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+enum Step {
+    Key(String),
+    Index(i64),
+}
+
+fn parse(q: &str) -> Result<Vec<Step>> {
+    let bad = |msg: &str| anyhow!("invalid query {q:?}: {msg}");
+    let chars: Vec<char> = q.trim().chars().collect();
+    if chars.first() != Some(&'.') { return Err(bad("must start with '.'")); }
+    let mut steps = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                i += 1;
+                if i >= chars.len() || chars[i] == '[' { continue; }
+                if chars[i] == '"' {
+                    let (s, next) = quoted(&chars, i).ok_or_else(|| bad("unterminated string"))?;
+                    steps.push(Step::Key(s));
+                    i = next;
+                } else {
+                    let start = i;
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '-') { i += 1; }
+                    if start == i { return Err(bad("expected key after '.'")); }
+                    steps.push(Step::Key(chars[start..i].iter().collect()));
+                }
+            }
+            '[' => {
+                i += 1;
+                if chars.get(i) == Some(&'"') {
+                    let (s, next) = quoted(&chars, i).ok_or_else(|| bad("unterminated string"))?;
+                    steps.push(Step::Key(s));
+                    i = next;
+                } else {
+                    let start = i;
+                    while i < chars.len() && chars[i] != ']' { i += 1; }
+                    let n: String = chars[start..i].iter().collect();
+                    steps.push(Step::Index(n.trim().parse().map_err(|_| bad("invalid index"))?));
+                }
+                if chars.get(i) != Some(&']') { return Err(bad("expected ']'")); }
+                i += 1;
+            }
+            _ => return Err(bad("unexpected character")),
+        }
+    }
+    Ok(steps)
+}
+
+// "..." を読み、(文字列, 閉じ引用符の次の位置) を返す
+fn quoted(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut out = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Some((out, i + 1)),
+            '\\' if i + 1 < chars.len() => { out.push(chars[i + 1]); i += 2; }
+            c => { out.push(c); i += 1; }
+        }
+    }
+    None
+}
+
+pub fn query<'a>(doc: &'a Value, q: &str) -> Result<&'a Value> {
+    let mut cur = doc;
+    for step in parse(q)? {
+        cur = match (&step, cur) {
+            (Step::Key(k), Value::Object(m)) => m.get(k).ok_or_else(|| anyhow!("key {k:?} not found"))?,
+            (Step::Index(n), Value::Array(a)) => {
+                // 負のインデックスは末尾から
+                let idx = if *n < 0 { a.len() as i64 + n } else { *n };
+                usize::try_from(idx).ok().and_then(|i| a.get(i)).ok_or_else(|| anyhow!("index {n} out of range"))?
+            }
+            (Step::Key(k), _) => return Err(anyhow!("cannot index non-object with {k:?}")),
+            (Step::Index(n), _) => return Err(anyhow!("cannot index non-array with {n}")),
+        };
+    }
+    Ok(cur)
+}
+
+// 文字列はそのまま、それ以外はJSONとして整形
+pub fn render(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,26 @@
+// 読み取り専用モード（--read-only・設定の read_only）
+//
+// 金庫ファイルを書き換える処理は check を通す。参照の記録（track_access・カナリア）のように
+// 読むついでに保存するだけのものは、エラーにせず保存を省く。
+use crate::error::err;
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// 以降の金庫への書き込みをすべてエラーにする
+pub fn enable() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// 金庫に書き込んでよいか確かめる
+pub fn check() -> Result<()> {
+    if enabled() {
+        return Err(err!(ReadOnly, "the vault is read-only (--read-only or read_only in the config); nothing was saved"));
+    }
+    Ok(())
+}
@@ -0,0 +1,332 @@
+// 金庫のリモートの保存先（WebDAV・S3 互換のオブジェクトストレージ）
+//
+// リモートの保存先を設定した金庫は、開くたびにリモートの版を取得して手元の金庫ファイルを
+// 置き換え、保存のたびに先にアップロードしてから手元を置き換える（手元のファイルはキャッシュ）。
+// アップロードには取得時の ETag を If-Match（まだ無ければ If-None-Match: *）で付けるので、
+// その間に別の端末が書き換えていれば 412 になり、何も保存せずに止まる。
+// HTTP は curl に任せる。認証情報はコマンドラインに出さず、標準入力から curl の設定として渡す。
+use crate::config::{self, S3Config, StorageConfig, WebDavConfig};
+use crate::error::err;
+use crate::{prompt, vaults};
+use anyhow::{anyhow, Result};
+use rustpass_core::storage::VaultStorage;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use time::{macros::format_description, OffsetDateTime};
+use zeroize::Zeroizing;
+
+/// 最後に取得・アップロードしたリモートの版
+static ETAG: Mutex<Version> = Mutex::new(Version::Absent);
+
+#[derive(Clone)]
+enum Version {
+    /// リモートにはまだ無い
+    Absent,
+    Known(String),
+    /// アップロードの応答に ETag が無かった（取得し直すまで保存しない）
+    Unknown,
+}
+
+pub enum Remote {
+    WebDav(WebDavConfig),
+    S3(S3Config),
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    fn error(&self, what: &str) -> anyhow::Error {
+        let body = String::from_utf8_lossy(&self.body);
+        let detail: String = body.trim().chars().take(200).collect();
+        match self.status {
+            401 | 403 => err!(Config, "{what}: access denied (HTTP {}); check the storage credentials", self.status),
+            _ => anyhow!("{what}: HTTP {} {detail}", self.status),
+        }
+    }
+}
+
+/// 選んでいる金庫のリモートの保存先（手元のファイルだけなら None）
+pub fn configured() -> Result<Option<Remote>> {
+    let Some(name) = vaults::name()? else { return Ok(None) };
+    Ok(match config::load()?.storage.remove(&name) {
+        Some(StorageConfig::Webdav(w)) => Some(Remote::WebDav(w)),
+        Some(StorageConfig::S3(s)) => Some(Remote::S3(s)),
+        Some(StorageConfig::Local) | None => None,
+    })
+}
+
+/// リモートの版とこの端末が最後に取得・アップロードした版の比較（status 用）
+pub enum Freshness {
+    Same,
+    /// ほかの端末が保存した
+    Changed,
+    /// リモートにまだ金庫が無い
+    Missing,
+    /// この端末ではまだ取得していない（または版が分からない）
+    Unknown,
+}
+
+impl Remote {
+    /// 表示用の場所
+    pub fn location(&self) -> String {
+        match self {
+            Remote::WebDav(w) => w.url.clone(),
+            Remote::S3(s) => format!("s3://{}/{}", s.bucket, s.key),
+        }
+    }
+
+    /// リモートの今の版を HEAD で尋ね、最後に取得・アップロードした版と比べる
+    pub fn freshness(&self) -> Result<Freshness> {
+        let res = send(self, "HEAD", &[], None)?;
+        let current = match res.status {
+            200 => res.header("ETag").map(str::to_string),
+            404 => return Ok(Freshness::Missing),
+            _ => return Err(res.error("cannot check the vault")),
+        };
+        let last = fs::read_to_string(etag_file()?).ok();
+        Ok(match (current, last) {
+            (Some(c), Some(l)) if c == l => Freshness::Same,
+            (Some(_), Some(_)) => Freshness::Changed,
+            _ => Freshness::Unknown,
+        })
+    }
+}
+
+// 設定のコマンドの出力の1行目（秘密を設定ファイルに書かないため）
+fn secret_from(env: &str, command: Option<&str>) -> Result<Option<Zeroizing<String>>> {
+    if let Ok(v) = std::env::var(env) { return Ok(Some(Zeroizing::new(v))); }
+    let Some(cmd) = command else { return Ok(None) };
+    let out = Command::new("sh").args(["-c", cmd]).stderr(Stdio::inherit()).output()?;
+    if !out.status.success() {
+        return Err(err!(Config, "storage secret command failed ({})", out.status));
+    }
+    let text = Zeroizing::new(String::from_utf8_lossy(&out.stdout).into_owned());
+    Ok(Some(Zeroizing::new(text.lines().next().unwrap_or_default().to_string())))
+}
+
+// curl の設定ファイルの文字列
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn curl(method: &str, url: &str, headers: &[(String, String)], user: Option<&str>, body: Option<&Path>) -> Result<Response> {
+    let mut config = Zeroizing::new(format!("url = {}\nrequest = {}\n", quote(url), quote(method)));
+    // 100-continue を待たない
+    config.push_str("header = \"Expect:\"\n");
+    for (k, v) in headers { config.push_str(&format!("header = {}\n", quote(&format!("{k}: {v}")))); }
+    if let Some(u) = user { config.push_str(&format!("user = {}\n", quote(u))); }
+    let mut cmd = Command::new("curl");
+    cmd.args(["-sS", "--include", "--max-time", "60", "-A", "rustpass", "--config", "-"]);
+    match body {
+        Some(path) => { cmd.arg("--data-binary").arg(format!("@{}", path.display())); }
+        None if method == "HEAD" => { cmd.arg("--head"); }
+        None => {}
+    }
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => err!(NotFound, "curl not found in PATH (needed for remote vault storage)"),
+        _ => e.into(),
+    })?;
+    if let Some(mut stdin) = child.stdin.take() { stdin.write_all(config.as_bytes())?; }
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        return Err(anyhow!("{method} {url} failed: {}", String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    parse_response(out.stdout)
+}
+
+// --include の出力（ヘッダ + 本文）を分ける。100 Continue やプロキシの応答は読み飛ばす
+fn parse_response(mut raw: Vec<u8>) -> Result<Response> {
+    loop {
+        let end = raw.windows(4).position(|w| w == b"\r\n\r\n").ok_or_else(|| anyhow!("malformed HTTP response"))?;
+        let head = String::from_utf8_lossy(&raw[..end]).into_owned();
+        let body = raw.split_off(end + 4);
+        let mut lines = head.lines();
+        let status_line = lines.next().unwrap_or_default();
+        let status: u16 = status_line.split_whitespace().nth(1).and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow!("malformed HTTP status line: {status_line:?}"))?;
+        let interim = status / 100 == 1 || status_line.to_ascii_lowercase().contains("connection established");
+        if interim && body.windows(5).any(|w| w == b"HTTP/") {
+            raw = body;
+            continue;
+        }
+        let headers = lines.filter_map(|l| l.split_once(':')).map(|(k, v)| (k.trim().to_string(), v.trim().to_string())).collect();
+        return Ok(Response { status, headers, body });
+    }
+}
+
+// ---- WebDAV ----
+
+fn webdav_user(w: &WebDavConfig) -> Result<Option<Zeroizing<String>>> {
+    let Some(user) = &w.username else { return Ok(None) };
+    let password = match secret_from("RUSTPASS_WEBDAV_PASSWORD", w.password_command.as_deref())? {
+        Some(p) => p,
+        None => Zeroizing::new(prompt::password(format!("WebDAV password for {user}: "))?),
+    };
+    Ok(Some(Zeroizing::new(format!("{user}:{}", password.as_str()))))
+}
+
+// ---- S3（署名バージョン4）----
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut m = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    m.update(data.as_bytes());
+    m.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn uri_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+// 署名付きのURLとヘッダ
+fn s3_request(s: &S3Config, method: &str, body: &[u8]) -> Result<(String, Vec<(String, String)>)> {
+    let access_key = std::env::var("AWS_ACCESS_KEY_ID").ok().or_else(|| s.access_key_id.clone())
+        .ok_or_else(|| err!(Config, "S3 access key is not set (AWS_ACCESS_KEY_ID or storage access_key_id)"))?;
+    let secret = secret_from("AWS_SECRET_ACCESS_KEY", s.secret_access_key_command.as_deref())?
+        .ok_or_else(|| err!(Config, "S3 secret key is not set (AWS_SECRET_ACCESS_KEY or storage secret_access_key_command)"))?;
+    let token = std::env::var("AWS_SESSION_TOKEN").ok();
+    let region = s.region.as_deref().unwrap_or("us-east-1");
+    let (scheme, host) = s.endpoint.trim_end_matches('/').split_once("://").unwrap_or(("https", &s.endpoint));
+    let key: Vec<String> = s.key.trim_start_matches('/').split('/').map(uri_encode).collect();
+    let (host, path) = if s.virtual_host {
+        (format!("{}.{host}", s.bucket), format!("/{}", key.join("/")))
+    } else {
+        (host.to_string(), format!("/{}/{}", uri_encode(&s.bucket), key.join("/")))
+    };
+    let now = OffsetDateTime::now_utc();
+    let amz_date = now.format(format_description!("[year][month][day]T[hour][minute][second]Z"))?;
+    let date = &amz_date[..8];
+    let payload = hex(&Sha256::digest(body));
+    let mut signed = vec![
+        ("host".to_string(), host.clone()),
+        ("x-amz-content-sha256".to_string(), payload.clone()),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(t) = &token { signed.push(("x-amz-security-token".to_string(), t.clone())); }
+    let names = signed.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+    let canonical_headers: String = signed.iter().map(|(k, v)| format!("{k}:{v}\n")).collect();
+    let canonical = format!("{method}\n{path}\n\n{canonical_headers}\n{names}\n{payload}");
+    let scope = format!("{date}/{region}/s3/aws4_request");
+    let to_sign = format!("AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}", hex(&Sha256::digest(canonical.as_bytes())));
+    let mut k = hmac(format!("AWS4{}", secret.as_str()).as_bytes(), date);
+    for part in [region, "s3", "aws4_request"] { k = hmac(&k, part); }
+    let signature = hex(&hmac(&k, &to_sign));
+    let mut headers: Vec<(String, String)> = signed.into_iter().filter(|(k, _)| k != "host").collect();
+    headers.push(("Authorization".into(), format!("AWS4-HMAC-SHA256 Credential={access_key}/{scope}, SignedHeaders={names}, Signature={signature}")));
+    Ok((format!("{scheme}://{host}{path}"), headers))
+}
+
+// ---- 共通 ----
+
+fn send(r: &Remote, method: &str, extra: &[(String, String)], body: Option<(&Path, &[u8])>) -> Result<Response> {
+    match r {
+        Remote::WebDav(w) => {
+            let user = webdav_user(w)?;
+            curl(method, &w.url, extra, user.as_deref().map(|u| u.as_str()), body.map(|b| b.0))
+        }
+        Remote::S3(s) => {
+            let (url, mut headers) = s3_request(s, method, body.map_or(&[][..], |b| b.1))?;
+            headers.extend_from_slice(extra);
+            curl(method, &url, &headers, None, body.map(|b| b.0))
+        }
+    }
+}
+
+// status のために、この端末が最後に取得・アップロードした版を金庫の隣にも残す
+fn etag_file() -> Result<std::path::PathBuf> {
+    Ok(vaults::path()?.with_extension("bin.etag"))
+}
+
+fn set_version(v: Version) {
+    if let Ok(path) = etag_file() {
+        let _ = match &v {
+            Version::Known(etag) => fs::write(path, etag),
+            Version::Absent | Version::Unknown => fs::remove_file(path),
+        };
+    }
+    *ETAG.lock().unwrap_or_else(|e| e.into_inner()) = v;
+}
+
+fn set_etag(etag: Option<&str>) {
+    set_version(etag.map_or(Version::Absent, |e| Version::Known(e.to_string())));
+}
+
+// curl に渡すための、アップロードする金庫の一時ファイル（暗号化されたまま）
+fn upload_file(data: &[u8]) -> Result<std::path::PathBuf> {
+    let path = vaults::path()?.with_extension("bin.upload");
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    opts.open(&path)?.write_all(data)?;
+    Ok(path)
+}
+
+impl VaultStorage for Remote {
+    // リモートにまだ金庫が無ければ None
+    fn load(&self) -> Result<Option<Vec<u8>>> {
+        let res = send(self, "GET", &[], None)?;
+        match res.status {
+            200 => {
+                set_etag(res.header("ETag"));
+                Ok(Some(res.body))
+            }
+            404 => {
+                eprintln!("note: no vault at {} yet; it will be uploaded on the next save", self.location());
+                set_etag(None);
+                Ok(None)
+            }
+            _ => Err(res.error("cannot download the vault")),
+        }
+    }
+
+    fn store(&self, data: &[u8]) -> Result<()> {
+        let condition = match ETAG.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+            Version::Known(etag) => ("If-Match".to_string(), etag),
+            Version::Absent => ("If-None-Match".to_string(), "*".to_string()),
+            Version::Unknown => {
+                return Err(err!(Conflict, "the server did not return the version of the last upload to {}; run the command again to load the vault before saving", self.location()));
+            }
+        };
+        let file = upload_file(data)?;
+        let res = send(self, "PUT", &[condition], Some((&file, data)));
+        let _ = fs::remove_file(&file);
+        let res = res?;
+        match res.status {
+            200 | 201 | 204 => {}
+            412 => {
+                return Err(err!(Conflict, "the vault at {} was changed by another device since it was loaded; nothing was saved", self.location()));
+            }
+            404 | 409 => return Err(err!(NotFound, "cannot upload the vault: the parent folder does not exist (HTTP {})", res.status)),
+            _ => return Err(res.error("cannot upload the vault")),
+        }
+        // 次の保存の If-Match 用。PUT の応答に ETag が無ければ、後から HEAD で尋ねた版は
+        // 別の端末が保存したものかもしれないので使わず、次の保存の前に取得し直させる
+        set_version(res.header("ETag").map_or(Version::Unknown, |e| Version::Known(e.to_string())));
+        Ok(())
+    }
+}
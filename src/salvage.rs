@@ -0,0 +1,135 @@
+// 壊れた金庫ファイルからの救出（salvage）
+//
+// format::recover でヘッダの修復・復号まで行い、ここでは平文のJSONから読めるエントリを
+// 1件ずつ取り出す。読めなかった部分は、分かる範囲でエントリ名などを添えて報告する。
+// 結果は元のファイルや現在の金庫を変更せず、同じ鍵スロット（同じパスワード）の新しい金庫に書く。
+use crate::error::err;
+use crate::format::{self, Credentials};
+use crate::{canary, export, policy, vault_path, Entry, Vault};
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::ops::Range;
+use std::{fs, path::Path};
+
+// 各エントリは "id" から書き出される（Entry のフィールド順）
+const ENTRY_START: &[u8] = b",{\"id\":\"";
+
+struct Salvaged {
+    vault: Vault,
+    lost: Vec<String>,
+    /// 認証できなかった範囲から取り出したエントリ
+    unverified: Vec<String>,
+}
+
+fn find(hay: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    hay.get(from..)?.windows(needle.len()).position(|w| w == needle).map(|i| i + from)
+}
+
+// 壊れた範囲からエントリ名を拾う（読めなければ位置だけ）
+fn describe(region: &[u8], at: usize) -> String {
+    let name = find(region, b"\"name\":\"", 0).and_then(|i| {
+        let rest = &region[i + 8..];
+        let end = rest.iter().position(|&b| b == b'"')?;
+        Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+    });
+    match name {
+        Some(n) => format!("entry {n:?} (damaged)"),
+        None => format!("an entry at byte {at} (damaged; name unreadable)"),
+    }
+}
+
+// data[pos..] の先頭にある値を1つ読む（読めたら値と次の位置）
+fn next_value(data: &[u8], pos: usize) -> Option<(Value, usize)> {
+    let mut stream = serde_json::Deserializer::from_slice(&data[pos..]).into_iter::<Value>();
+    let v = stream.next()?.ok()?;
+    Some((v, pos + stream.byte_offset()))
+}
+
+fn section<T: DeserializeOwned>(data: &[u8], from: usize, key: &str, lost: &mut Vec<String>) -> Option<T> {
+    let needle = format!(",\"{key}\":");
+    let at = find(data, needle.as_bytes(), from)?;
+    let parsed = next_value(data, at + needle.len()).and_then(|(v, _)| serde_json::from_value(v).ok());
+    if parsed.is_none() { lost.push(format!("{key} (damaged)")); }
+    parsed
+}
+
+fn salvage_json(data: &[u8], bad: &[Range<usize>]) -> Salvaged {
+    if bad.is_empty() {
+        if let Ok(vault) = serde_json::from_slice::<Vault>(data) {
+            return Salvaged { vault, lost: Vec::new(), unverified: Vec::new() };
+        }
+    }
+    let mut vault = Vault::default();
+    let mut lost = Vec::new();
+    let mut unverified = Vec::new();
+    let Some(start) = find(data, b"\"entries\":[", 0) else {
+        lost.push("all entries (the entry list could not be found)".into());
+        return Salvaged { vault, lost, unverified };
+    };
+    let mut pos = start + 11;
+    loop {
+        while data.get(pos).is_some_and(|b| b.is_ascii_whitespace() || *b == b',') { pos += 1; }
+        if data.get(pos).is_none_or(|&b| b == b']') { break; }
+        match next_value(data, pos) {
+            Some((v, next)) => {
+                match serde_json::from_value::<Entry>(v) {
+                    Ok(e) => {
+                        if bad.iter().any(|r| r.start < next && pos < r.end) { unverified.push(e.name.clone()); }
+                        vault.entries.push(e);
+                    }
+                    Err(_) => lost.push(describe(&data[pos..next], pos)),
+                }
+                pos = next;
+            }
+            // 次のエントリの先頭まで読み飛ばす
+            None => match find(data, ENTRY_START, pos + 1) {
+                Some(next) => {
+                    lost.push(describe(&data[pos..next], pos));
+                    pos = next + 1;
+                }
+                None => {
+                    lost.push(describe(&data[pos..], pos));
+                    lost.push(format!("everything after byte {pos} (truncated or damaged; later entries may be missing)"));
+                    return Salvaged { vault, lost, unverified };
+                }
+            },
+        }
+    }
+    vault.policy = section::<policy::Policy>(data, pos, "policy", &mut lost);
+    vault.canary_events = section::<Vec<canary::Event>>(data, pos, "canary_events", &mut lost).unwrap_or_default();
+    Salvaged { vault, lost, unverified }
+}
+
+/// 破損したファイルから読めるエントリを取り出し、out に新しい金庫として書く
+pub fn run(path: &Path, out: &Path, force: bool, creds: &mut dyn Credentials) -> Result<()> {
+    if out.exists() && !force {
+        return Err(err!(AlreadyExists, "{} already exists (use --force to overwrite)", out.display()));
+    }
+    let data = fs::read(path)?;
+    let r = format::recover(&data, creds)?;
+    let s = salvage_json(&r.plaintext, &r.unverified);
+    for n in &r.notes { println!("note: {n}"); }
+    println!("Recovered {} entries.", s.vault.entries.len());
+    if s.lost.is_empty() {
+        println!("Nothing was lost.");
+    } else {
+        println!("Lost:");
+        for l in &s.lost { println!("  {l}"); }
+    }
+    if !s.unverified.is_empty() {
+        println!("Recovered from data that failed authentication (check these):");
+        for n in &s.unverified { println!("  {n}"); }
+    }
+
+    let bytes = format::seal(&s.vault, &r.unlocked)?;
+    export::write(out, &bytes, force)?;
+    format::verify(&fs::read(out)?, &r.unlocked)?;
+    println!("Wrote the salvaged vault to {} (same password as the damaged one).", out.display());
+    if !s.unverified.is_empty() {
+        eprintln!("warning: damaged bytes may have silently changed values in the entries listed above");
+        eprintln!("         check their passwords (and compare with a backup) before relying on them");
+    }
+    println!("Review it, then replace {} with it.", vault_path()?.display());
+    Ok(())
+}
@@ -0,0 +1,125 @@
+// scrypt (RFC 7914)。Aegis のバックアップが鍵導出に使う
+use crate::error::err;
+use anyhow::Result;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+// 細工したファイルで大量のメモリを確保させないための上限（1 GiB）
+const MAX_MEMORY: u64 = 1 << 30;
+
+fn salsa20_8(b: &mut [u32; 16]) {
+    let mut x = *b;
+    let mut quarter = |a: usize, b: usize, c: usize, d: usize| {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    };
+    for _ in 0..4 {
+        quarter(0, 4, 8, 12);
+        quarter(5, 9, 13, 1);
+        quarter(10, 14, 2, 6);
+        quarter(15, 3, 7, 11);
+        quarter(0, 1, 2, 3);
+        quarter(5, 6, 7, 4);
+        quarter(10, 11, 8, 9);
+        quarter(15, 12, 13, 14);
+    }
+    for (b, x) in b.iter_mut().zip(x) {
+        *b = b.wrapping_add(x);
+    }
+}
+
+// 64バイト（16ワード）単位のブロックを 2r 個並べたもの
+fn block_mix(b: &[u32], out: &mut [u32], r: usize) {
+    let mut x = [0u32; 16];
+    x.copy_from_slice(&b[(2 * r - 1) * 16..]);
+    for i in 0..2 * r {
+        for (x, b) in x.iter_mut().zip(&b[i * 16..(i + 1) * 16]) {
+            *x ^= b;
+        }
+        salsa20_8(&mut x);
+        // 偶数番目を前半、奇数番目を後半に並べ替える
+        let dst = (i / 2 + (i % 2) * r) * 16;
+        out[dst..dst + 16].copy_from_slice(&x);
+    }
+}
+
+fn ro_mix(block: &mut [u32], n: usize, r: usize) {
+    let len = 32 * r;
+    let mut v = Zeroizing::new(vec![0u32; len * n]);
+    let mut x = Zeroizing::new(block.to_vec());
+    let mut y = Zeroizing::new(vec![0u32; len]);
+    for i in 0..n {
+        v[i * len..(i + 1) * len].copy_from_slice(&x);
+        block_mix(&x, &mut y, r);
+        std::mem::swap(&mut x, &mut y);
+    }
+    for _ in 0..n {
+        let j = x[(2 * r - 1) * 16] as usize & (n - 1);
+        for (x, v) in x.iter_mut().zip(&v[j * len..(j + 1) * len]) {
+            *x ^= v;
+        }
+        block_mix(&x, &mut y, r);
+        std::mem::swap(&mut x, &mut y);
+    }
+    block.copy_from_slice(&x);
+}
+
+pub fn derive(password: &[u8], salt: &[u8], n: u64, r: u32, p: u32, len: usize) -> Result<Zeroizing<Vec<u8>>> {
+    if n < 2 || !n.is_power_of_two() || r == 0 || p == 0 {
+        return Err(err!(InvalidInput, "invalid scrypt parameters (N={n}, r={r}, p={p})"));
+    }
+    if n.saturating_mul(r as u64 * 128) > MAX_MEMORY || p > 16 {
+        return Err(err!(InvalidInput, "scrypt parameters too large (N={n}, r={r}, p={p})"));
+    }
+    let (n, r, p) = (n as usize, r as usize, p as usize);
+    let mut b = Zeroizing::new(vec![0u8; p * 128 * r]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, 1, &mut b);
+    let mut words = Zeroizing::new(vec![0u32; 32 * r]);
+    for chunk in b.chunks_mut(128 * r) {
+        for (w, c) in words.iter_mut().zip(chunk.chunks(4)) {
+            *w = u32::from_le_bytes(c.try_into().unwrap());
+        }
+        ro_mix(&mut words, n, r);
+        for (c, w) in chunk.chunks_mut(4).zip(words.iter()) {
+            c.copy_from_slice(&w.to_le_bytes());
+        }
+    }
+    let mut out = Zeroizing::new(vec![0u8; len]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, &b, 1, &mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::derive;
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|x| format!("{x:02x}")).collect()
+    }
+
+    // RFC 7914 §12 のテストベクタ（N=1048576 のものは 1 GiB 使うので除く）
+    #[test]
+    fn rfc7914_vectors() {
+        let cases: [(&str, &str, u64, u32, u32, &str); 3] = [
+            ("", "", 16, 1, 1,
+             "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"),
+            ("password", "NaCl", 1024, 8, 16,
+             "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"),
+            ("pleaseletmein", "SodiumChloride", 16384, 8, 1,
+             "7023bdcb3afd7348461c06cd81fd38ebfda8fbba904f8e3ea9b543f6545da1f2d5432955613f0fcf62d49705242a9af9e61e85dc0d651e40dfcf017b45575887"),
+        ];
+        for (password, salt, n, r, p, want) in cases {
+            let got = derive(password.as_bytes(), salt.as_bytes(), n, r, p, 64).unwrap();
+            assert_eq!(hex(&got), want, "P={password:?} S={salt:?} N={n} r={r} p={p}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        assert!(derive(b"", b"", 15, 1, 1, 32).is_err());
+        assert!(derive(b"", b"", 16, 0, 1, 32).is_err());
+        assert!(derive(b"", b"", 1 << 30, 8, 1, 32).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+// 名前・ユーザー名・URL・メモの検索（find と get の名前解決）
+//
+// 空白区切りの語がすべてどこかに一致するエントリを、一致の強さ（完全一致 > 前方一致 >
+// 語頭の部分一致 > 部分一致 > 文字の順番だけが合うあいまい一致）と項目（名前 > ユーザー名 >
+// URL > メモ）の順に並べる。大文字小文字は区別しない。メモは長いのであいまい一致の対象にしない。
+use crate::error::err;
+use crate::{domain, Entry, Vault};
+use anyhow::Result;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Matched {
+    Notes,
+    Url,
+    Username,
+    Name,
+}
+
+impl Matched {
+    pub fn label(self) -> &'static str {
+        match self {
+            Matched::Name => "name",
+            Matched::Username => "username",
+            Matched::Url => "url",
+            Matched::Notes => "notes",
+        }
+    }
+}
+
+pub struct Hit {
+    /// vault.entries での位置
+    pub index: usize,
+    pub score: u32,
+    /// 最もよく一致した項目
+    pub matched: Matched,
+}
+
+// クエリの文字が順番どおりに現れるか（続けて並ぶ文字・語頭の文字ほど高い）
+fn subsequence(word: &[char], text: &[char]) -> Option<u32> {
+    let mut score = 0;
+    let mut from = 0;
+    let mut prev = None;
+    for &c in word {
+        let j = (from..text.len()).find(|&j| text[j] == c)?;
+        score += 1;
+        if prev.is_some_and(|p| p + 1 == j) { score += 2; }
+        if j == 0 || !text[j - 1].is_alphanumeric() { score += 2; }
+        prev = Some(j);
+        from = j + 1;
+    }
+    Some(score)
+}
+
+// 1語の1項目に対する一致の強さ（0 は不一致）
+fn quality(word: &str, text: &str, fuzzy: bool) -> u32 {
+    let text = text.to_lowercase();
+    if text.is_empty() { return 0; }
+    if text == word { return 1000; }
+    if text.starts_with(word) { return 800; }
+    if let Some(i) = text.find(word) {
+        let at_word_start = text[..i].chars().next_back().is_none_or(|c| !c.is_alphanumeric());
+        return if at_word_start { 700 } else { 600 };
+    }
+    if !fuzzy { return 0; }
+    let w: Vec<char> = word.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    // 長い文字列ほど偶然一致しやすいので、候補の長さに対して短すぎる語は除く
+    if w.len() < 2 || w.len() * 4 < t.len() { return 0; }
+    subsequence(&w, &t).map_or(0, |s| 100 + s * 10)
+}
+
+fn score(words: &[String], e: &Entry) -> Option<(u32, Matched)> {
+    let fields = [
+        (Matched::Name, e.name.as_str(), true),
+        (Matched::Username, e.username.as_str(), true),
+        (Matched::Url, e.url.as_deref().unwrap_or(""), true),
+        (Matched::Notes, e.notes.as_deref().unwrap_or(""), false),
+    ];
+    let mut total = 0;
+    let mut best = (0, Matched::Notes);
+    for w in words {
+        // 一致の強さが同じなら名前に近い項目を優先
+        let (q, field) = fields.iter()
+            .map(|&(f, text, fuzzy)| (quality(w, text, fuzzy), f))
+            .filter(|&(q, _)| q > 0)
+            .max()?;
+        total += q + 10 * field as u32;
+        best = best.max((q, field));
+    }
+    Some((total, best.1))
+}
+
+/// 一致したエントリを良い順に返す
+pub fn find(vault: &Vault, query: &str) -> Vec<Hit> {
+    let words: Vec<String> = query.to_lowercase().split_whitespace().map(str::to_string).collect();
+    if words.is_empty() { return Vec::new(); }
+    let mut hits: Vec<Hit> = vault.entries.iter().enumerate()
+        .filter_map(|(index, e)| score(&words, e).map(|(score, matched)| Hit { index, score, matched }))
+        .collect();
+    hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| vault.entries[a.index].name.cmp(&vault.entries[b.index].name)));
+    hits
+}
+
+/// get などの名前を解決する（完全一致が無ければ、一致するエントリが1つだけのときにそれを使う）
+pub fn resolve(vault: &Vault, name: &str) -> Result<String> {
+    if vault.entries.iter().any(|e| e.name == name) { return Ok(name.to_string()); }
+    let lower = name.to_lowercase();
+    let same: Vec<&Entry> = vault.entries.iter().filter(|e| e.name.to_lowercase() == lower).collect();
+    if let [e] = same.as_slice() { return Ok(e.name.clone()); }
+    let hits = find(vault, name);
+    match hits.as_slice() {
+        [] => Err(err!(NotFound, "entry {name:?} not found")),
+        [h] => {
+            let found = vault.entries[h.index].name.clone();
+            eprintln!("note: using {found:?} (matched {name:?})");
+            Ok(found)
+        }
+        _ => {
+            let names: Vec<&str> = hits.iter().take(5).map(|h| vault.entries[h.index].name.as_str()).collect();
+            let more = if hits.len() > names.len() { ", ..." } else { "" };
+            Err(err!(NotFound, "entry {name:?} not found; it matches {} entries ({}{more}); see `rustpass find {name}`", hits.len(), names.join(", ")))
+        }
+    }
+}
+
+/// get --url のエントリを探す（URL の登録可能ドメインが同じエントリのうち、ホストが一致するものを優先）
+pub fn resolve_url(vault: &Vault, url: &str) -> Result<String> {
+    let host = domain::host(url).ok_or_else(|| err!(InvalidInput, "no host in URL {url:?}"))?;
+    let scored: Vec<(u8, &Entry)> = vault.entries.iter()
+        .filter_map(|e| Some((domain::closeness(&host, &domain::host(e.url.as_deref()?)?), e)))
+        .filter(|(c, _)| *c > 0)
+        .collect();
+    let best = scored.iter().map(|(c, _)| *c).max()
+        .ok_or_else(|| err!(NotFound, "no entry has a URL on {}", domain::registrable(&host).unwrap_or(&host)))?;
+    let mut names: Vec<&str> = scored.iter().filter(|(c, _)| *c == best).map(|(_, e)| e.name.as_str()).collect();
+    names.sort();
+    match names.as_slice() {
+        [name] => {
+            if best < 2 { eprintln!("note: using {name:?} (same site as {host})"); }
+            Ok(name.to_string())
+        }
+        _ => Err(err!(NotFound, "{} entries match {host} ({}); pass the entry name instead", names.len(), names.join(", "))),
+    }
+}
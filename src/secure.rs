@@ -0,0 +1,98 @@
+//! 秘密を置くメモリの扱い
+//!
+//! 鍵は [`Locked`] に置く。可能なら mlock でスワップへの書き出しを防ぎ、破棄するときに消去する。
+//! 文字列の秘密（エントリのパスワードなど）は [`Entry`](crate::Entry) が破棄されるときに消去される。
+use std::io;
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+/// 固定長の秘密（鍵など）。ヒープ上の動かない場所に置いて可能なら mlock し、破棄するときに消去する
+pub struct Locked<const N: usize> {
+    bytes: Box<[u8; N]>,
+    locked: bool,
+}
+
+impl<const N: usize> Locked<N> {
+    /// 0 で埋めた領域（ここに直接書き込めば、スタックにコピーが残らない）
+    pub fn zeroed() -> Self {
+        let bytes = Box::new([0u8; N]);
+        let locked = lock(&bytes[..]);
+        Locked { bytes, locked }
+    }
+
+    /// bytes をコピーする（渡された配列は消去する）
+    pub fn new(mut bytes: [u8; N]) -> Self {
+        let mut k = Self::zeroed();
+        k.copy_from_slice(&bytes);
+        bytes.zeroize();
+        k
+    }
+
+    /// 長さが N でなければ None
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == N).then(|| {
+            let mut k = Self::zeroed();
+            k.copy_from_slice(bytes);
+            k
+        })
+    }
+
+    /// mlock できたか（RLIMIT_MEMLOCK を超えた場合や未対応の環境では false）
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+impl<const N: usize> Deref for Locked<N> {
+    type Target = [u8; N];
+
+    fn deref(&self) -> &[u8; N] {
+        &self.bytes
+    }
+}
+
+impl<const N: usize> DerefMut for Locked<N> {
+    fn deref_mut(&mut self) -> &mut [u8; N] {
+        &mut self.bytes
+    }
+}
+
+impl<const N: usize> Clone for Locked<N> {
+    fn clone(&self) -> Self {
+        Self::from_slice(&self.bytes[..]).expect("same length")
+    }
+}
+
+impl<const N: usize> Drop for Locked<N> {
+    fn drop(&mut self) {
+        // ロックはページ単位で、同じページにある他の鍵も巻き込むので munlock はしない
+        self.bytes.zeroize();
+    }
+}
+
+#[cfg(unix)]
+fn lock(buf: &[u8]) -> bool {
+    // SAFETY: buf は有効な領域（解放後もページがロックされたままになるだけ）
+    unsafe { libc::mlock(buf.as_ptr().cast(), buf.len()) == 0 }
+}
+
+#[cfg(not(unix))]
+fn lock(_buf: &[u8]) -> bool {
+    false
+}
+
+/// コアダンプを無効にする（落ちたときに金庫の中身がファイルに残らないように）
+#[cfg(unix)]
+pub fn no_core_dumps() -> io::Result<()> {
+    let no_core = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: no_core は有効な rlimit
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &no_core) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn no_core_dumps() -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,198 @@
+// ローカルHTTP API（serve）と読み取り専用のWeb UI（serve --web）
+//
+// 127.0.0.1 だけで待ち受け、起動時に作るセッショントークンを Authorization: Bearer で要求する。
+// Host ヘッダも確認して DNS リバインディングを防ぐ。パスワードなどの秘密は API から返さず、
+// このマシンのクリップボードへのコピー（一定時間後に消去）だけを提供する。
+// 金庫は要求のたびにファイルから読み直す（起動中に他のコマンドで変更されても上書きしない）。
+use crate::error::{err, kind_of, Kind};
+use crate::format::{self, DataKey};
+use crate::{clipboard, config::Config, http, record_access, Entry, Vault, VaultFile};
+use anyhow::Result;
+use rand::{rngs::OsRng, RngCore};
+use serde_json::{json, Value};
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+use std::thread;
+use subtle::ConstantTimeEq;
+
+/// 最後の要求からこの秒数で終了（ロック）
+pub const DEFAULT_TIMEOUT: u64 = 900;
+
+const PAGE: &str = include_str!("web.html");
+
+fn random_token() -> String {
+    let mut b = [0u8; 32];
+    OsRng.fill_bytes(&mut b);
+    b.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'%' {
+            out.push(u8::from_str_radix(s.get(i + 1..i + 3)?, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(b[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn send(stream: &TcpStream, status: &str, body: Value) {
+    let _ = http::respond(stream, status, "application/json", body.to_string().as_bytes());
+}
+
+fn summary(e: &Entry) -> Value {
+    json!({ "name": e.name, "username": e.username, "url": e.url, "tags": e.tags })
+}
+
+// 秘密以外の項目と、コピーできるものの一覧
+fn detail(e: &Entry) -> Value {
+    let mut copy = Vec::new();
+    if !e.username.is_empty() { copy.push("username".to_string()); }
+    if !e.password.is_empty() { copy.push("password".to_string()); }
+    if e.otp.is_some() { copy.push("totp".to_string()); }
+    json!({
+        "name": e.name,
+        "username": e.username,
+        "url": e.url,
+        "notes": e.notes,
+        "tags": e.tags,
+        "fields": e.fields.iter().map(|f| &f.name).collect::<Vec<_>>(),
+        "copy": copy,
+        "updated_at": e.updated_at,
+    })
+}
+
+fn copy_value(e: &Entry, what: &[String]) -> Result<String> {
+    let value = match what {
+        [w] if w == "username" => e.username.clone(),
+        [w] if w == "password" => e.password.clone(),
+        [w] if w == "totp" => e.otp.as_ref().ok_or_else(|| err!(NotFound, "entry {:?} has no TOTP", e.name))?.now()?.0,
+        [w, name] if w == "field" => e.fields.iter().find(|f| &f.name == name)
+            .map(|f| f.value.clone())
+            .ok_or_else(|| err!(NotFound, "field {name:?} not found in {:?}", e.name))?,
+        _ => return Err(err!(NotFound, "unknown item to copy")),
+    };
+    if value.is_empty() { return Err(err!(NotFound, "nothing to copy")); }
+    Ok(value)
+}
+
+struct Server {
+    key: DataKey,
+    token: String,
+    hosts: [String; 2],
+    web: bool,
+}
+
+impl Server {
+    fn open(&self) -> Result<(Vault, format::Unlocked)> {
+        format::unlock_with_key(&VaultFile::current()?.read()?, self.key.clone())
+    }
+
+    fn authorized(&self, req: &http::Request) -> bool {
+        let token = req.header("authorization").and_then(|v| v.strip_prefix("Bearer ")).unwrap_or("");
+        bool::from(token.as_bytes().ct_eq(self.token.as_bytes()))
+    }
+
+    fn handle(&self, stream: &TcpStream, req: &http::Request, cfg: &Config) -> Result<()> {
+        // 別のホスト名で解決させたページからの要求（DNSリバインディング）を拒否
+        if !req.header("host").is_some_and(|h| self.hosts.iter().any(|x| x == h)) {
+            send(stream, "421 Misdirected Request", json!({ "error": "unexpected Host header" }));
+            return Ok(());
+        }
+        let path = req.path.split('?').next().unwrap_or("");
+        if path == "/" && self.web {
+            let _ = http::respond(stream, "200 OK", "text/html; charset=utf-8", PAGE.as_bytes());
+            return Ok(());
+        }
+        let Some(rest) = path.strip_prefix("/api/") else {
+            send(stream, "404 Not Found", json!({ "error": "not found" }));
+            return Ok(());
+        };
+        if !self.authorized(req) {
+            send(stream, "401 Unauthorized", json!({ "error": "missing or invalid session token" }));
+            return Ok(());
+        }
+        let Some(parts) = rest.split('/').map(percent_decode).collect::<Option<Vec<String>>>() else {
+            send(stream, "400 Bad Request", json!({ "error": "bad path" }));
+            return Ok(());
+        };
+        let (mut v, u) = self.open()?;
+        match (req.method.as_str(), parts.as_slice()) {
+            ("GET", [p]) if p == "entries" => {
+                let list: Vec<Value> = v.entries.iter().map(summary).collect();
+                send(stream, "200 OK", json!(list));
+            }
+            ("GET", [p, name]) if p == "entries" => match v.entries.iter().find(|e| &e.name == name) {
+                Some(e) => {
+                    send(stream, "200 OK", detail(e));
+                    record_access(&mut v, &u, cfg, name, "serve")?;
+                }
+                None => send(stream, "404 Not Found", json!({ "error": format!("entry {name:?} not found") })),
+            },
+            ("POST", [p, name, c, what @ ..]) if p == "entries" && c == "copy" => {
+                let Some(e) = v.entries.iter().find(|e| &e.name == name) else {
+                    send(stream, "404 Not Found", json!({ "error": format!("entry {name:?} not found") }));
+                    return Ok(());
+                };
+                match copy_value(e, what).and_then(|s| clipboard::copy(&s, cfg.clipboard_timeout())) {
+                    Ok(()) => {
+                        send(stream, "200 OK", json!({ "copied": what.join("/"), "clears_in": cfg.clipboard_timeout() }));
+                        record_access(&mut v, &u, cfg, name, "serve")?;
+                    }
+                    Err(e) if kind_of(&e) == Kind::NotFound => send(stream, "404 Not Found", json!({ "error": e.to_string() })),
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => send(stream, "404 Not Found", json!({ "error": "not found" })),
+        }
+        Ok(())
+    }
+}
+
+/// Ctrl-C か、timeout 秒要求が無ければ終了する
+pub fn run(key: DataKey, cfg: &Config, port: u16, web: bool, timeout: u64) -> Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    listener.set_nonblocking(true)?;
+    let port = listener.local_addr()?.port();
+    let server = Server {
+        key,
+        token: random_token(),
+        hosts: [format!("127.0.0.1:{port}"), format!("localhost:{port}")],
+        web,
+    };
+    if web {
+        // トークンはフラグメントに入れる（サーバーへのリクエストやログに載らない）
+        println!("http://127.0.0.1:{port}/#{}", server.token);
+    } else {
+        println!("http://127.0.0.1:{port}/api/");
+        println!("token: {}", server.token);
+    }
+    println!("Locks after {timeout}s without requests; press Ctrl-C to stop.");
+
+    let mut deadline = Instant::now() + Duration::from_secs(timeout);
+    while Instant::now() < deadline {
+        let stream = match listener.accept() {
+            Ok((s, _)) => s,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        stream.set_nonblocking(false)?;
+        let Ok(req) = http::read_request(&stream) else { continue };
+        if server.authorized(&req) { deadline = Instant::now() + Duration::from_secs(timeout); }
+        if let Err(e) = server.handle(&stream, &req, cfg) {
+            send(&stream, "500 Internal Server Error", json!({ "error": e.to_string() }));
+        }
+    }
+    println!("Idle timeout; locked.");
+    Ok(())
+}
@@ -0,0 +1,72 @@
+// GF(256) 上の Shamir の秘密分散（K-of-N）
+use anyhow::{anyhow, Result};
+use rand::{rngs::OsRng, RngCore};
+use zeroize::Zeroizing;
+
+// AES と同じ既約多項式 x^8 + x^4 + x^3 + x + 1。分岐なしで計算する
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    for _ in 0..8 {
+        p ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    p
+}
+
+// a^254 = a^-1
+fn inv(a: u8) -> u8 {
+    let mut r = 1u8;
+    let mut base = a;
+    let mut e = 254u8;
+    while e > 0 {
+        if e & 1 == 1 { r = mul(r, base); }
+        base = mul(base, base);
+        e >>= 1;
+    }
+    r
+}
+
+pub struct Share {
+    pub x: u8,
+    pub y: Zeroizing<Vec<u8>>,
+}
+
+/// secret を n 個のシェアに分割し、任意の k 個で復元できるようにする
+pub fn split(secret: &[u8], k: u8, n: u8) -> Result<Vec<Share>> {
+    if k < 2 || k > n { return Err(anyhow!("threshold must be between 2 and the number of shares")); }
+    let mut shares: Vec<Share> = (1..=n).map(|x| Share { x, y: Zeroizing::new(Vec::with_capacity(secret.len())) }).collect();
+    let mut coeffs = Zeroizing::new(vec![0u8; k as usize]);
+    for &byte in secret {
+        coeffs[0] = byte;
+        OsRng.fill_bytes(&mut coeffs[1..]);
+        for s in shares.iter_mut() {
+            // ホーナー法で f(x) を評価
+            let y = coeffs.iter().rev().fold(0u8, |acc, &c| mul(acc, s.x) ^ c);
+            s.y.push(y);
+        }
+    }
+    Ok(shares)
+}
+
+/// ラグランジュ補間で f(0) を求める
+pub fn combine(shares: &[Share]) -> Result<Zeroizing<Vec<u8>>> {
+    let len = shares.first().map(|s| s.y.len()).ok_or(anyhow!("no shares"))?;
+    if shares.iter().any(|s| s.y.len() != len || s.x == 0) { return Err(anyhow!("inconsistent shares")); }
+    for (i, a) in shares.iter().enumerate() {
+        if shares[..i].iter().any(|b| b.x == a.x) { return Err(anyhow!("duplicate share")); }
+    }
+    let mut out = Zeroizing::new(vec![0u8; len]);
+    for (i, si) in shares.iter().enumerate() {
+        // l_i(0) = Π x_j / (x_j - x_i)、GF(2^8) では減算は XOR
+        let mut l = 1u8;
+        for (j, sj) in shares.iter().enumerate() {
+            if i != j { l = mul(l, mul(sj.x, inv(sj.x ^ si.x))); }
+        }
+        for (o, &y) in out.iter_mut().zip(si.y.iter()) {
+            *o ^= mul(l, y);
+        }
+    }
+    Ok(out)
+}
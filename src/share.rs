@@ -0,0 +1,79 @@
+// 一度だけ閲覧できる共有URL（share-once）
+use crate::http;
+use anyhow::{anyhow, Result};
+use rand::{rngs::OsRng, RngCore};
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, TcpListener, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+const PAGE: &str = "<!doctype html><meta charset=utf-8><title>rustpass</title>\
+<p>This secret can be viewed only once.</p>\
+<form method=post><button>Reveal</button></form>";
+
+// LAN側のアドレスを推定（UDPのconnectはパケットを送らない）
+fn lan_addr() -> Result<IpAddr> {
+    let sock = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    sock.connect((Ipv4Addr::new(192, 0, 2, 1), 9))?;
+    Ok(sock.local_addr()?.ip())
+}
+
+fn random_token() -> String {
+    let mut b = [0u8; 16];
+    OsRng.fill_bytes(&mut b);
+    b.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+// GETでは確認ページのみ返し、POSTで初めて秘密を渡す
+// （チャットのリンクプレビュー等で閲覧権を消費されないように）。秘密はどの経路で戻っても消去される
+pub fn serve_once(secret: Zeroizing<String>, lan: bool, port: u16, ttl: Duration) -> Result<()> {
+    let (bind, host) = if lan {
+        (IpAddr::V4(Ipv4Addr::UNSPECIFIED), lan_addr()?)
+    } else {
+        let lo = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        (lo, lo)
+    };
+    let listener = TcpListener::bind((bind, port))?;
+    listener.set_nonblocking(true)?;
+    let port = listener.local_addr()?.port();
+    let path = format!("/s/{}", random_token());
+
+    println!("http://{host}:{port}{path}");
+    println!("Link is valid for {}s and can be opened once.", ttl.as_secs());
+
+    let deadline = Instant::now() + ttl;
+    let result = loop {
+        if Instant::now() >= deadline {
+            break Err(anyhow!("share link expired without being opened"));
+        }
+        let stream = match listener.accept() {
+            Ok((s, _)) => s,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            Err(e) => break Err(e.into()),
+        };
+        stream.set_nonblocking(false)?;
+        let req = match http::read_request(&stream) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        if req.path != path {
+            let _ = http::respond(&stream, "404 Not Found", "text/plain", b"not found");
+            continue;
+        }
+        match req.method.as_str() {
+            "GET" => { let _ = http::respond(&stream, "200 OK", "text/html; charset=utf-8", PAGE.as_bytes()); }
+            "POST" => {
+                http::respond(&stream, "200 OK", "text/plain; charset=utf-8", secret.as_bytes())?;
+                break Ok(());
+            }
+            _ => { let _ = http::respond(&stream, "405 Method Not Allowed", "text/plain", b""); }
+        }
+    };
+    result?;
+    println!("Secret delivered; link closed.");
+    Ok(())
+}
@@ -0,0 +1,87 @@
+// パスワードの読み上げ用表記（NATOフォネティック）
+const NATO: [&str; 26] = [
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India",
+    "Juliett", "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo",
+    "Sierra", "Tango", "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+const DIGITS: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+fn symbol_name(c: char) -> Option<&'static str> {
+    Some(match c {
+        ' ' => "space",
+        '!' => "exclamation mark",
+        '"' => "double quote",
+        '#' => "hash",
+        '$' => "dollar",
+        '%' => "percent",
+        '&' => "ampersand",
+        '\'' => "apostrophe",
+        '(' => "left parenthesis",
+        ')' => "right parenthesis",
+        '*' => "asterisk",
+        '+' => "plus",
+        ',' => "comma",
+        '-' => "hyphen",
+        '.' => "period",
+        '/' => "slash",
+        ':' => "colon",
+        ';' => "semicolon",
+        '<' => "less-than",
+        '=' => "equals",
+        '>' => "greater-than",
+        '?' => "question mark",
+        '@' => "at sign",
+        '[' => "left bracket",
+        '\\' => "backslash",
+        ']' => "right bracket",
+        '^' => "caret",
+        '_' => "underscore",
+        '`' => "backtick",
+        '{' => "left brace",
+        '|' => "vertical bar",
+        '}' => "right brace",
+        '~' => "tilde",
+        _ => return None,
+    })
+}
+
+pub fn describe(c: char) -> String {
+    if c.is_ascii_lowercase() {
+        format!("lowercase {}", NATO[(c as u8 - b'a') as usize])
+    } else if c.is_ascii_uppercase() {
+        format!("CAPITAL {}", NATO[(c as u8 - b'A') as usize])
+    } else if c.is_ascii_digit() {
+        format!("digit {}", DIGITS[(c as u8 - b'0') as usize])
+    } else if let Some(name) = symbol_name(c) {
+        format!("symbol {name}")
+    } else {
+        format!("character U+{:04X}", c as u32)
+    }
+}
+
+// 1文字1行で出力する文字列を返す
+pub fn spell(secret: &str) -> Vec<String> {
+    secret
+        .chars()
+        .enumerate()
+        .map(|(i, c)| format!("{:>3}  {}  {}", i + 1, c, describe(c)))
+        .collect()
+}
+
+// --accessible 用：桁揃えの表にせず、位置と文字の説明だけを読み上げる
+pub fn spell_plain(secret: &str) -> Vec<String> {
+    let n = secret.chars().count();
+    secret
+        .chars()
+        .enumerate()
+        .map(|(i, c)| format!("character {} of {n}: {}", i + 1, describe(c)))
+        .collect()
+}
+
+// 数字を1桁ずつ読ませる（"123456" が「十二万…」と読まれないように）
+pub fn digits(code: &str) -> String {
+    code.chars().map(String::from).collect::<Vec<_>>().join(" ")
+}
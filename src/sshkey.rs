@@ -0,0 +1,55 @@
+// SSH鍵ペアの生成と書き出し
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use ssh_key::{Algorithm, LineEnding, PrivateKey};
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum KeyType {
+    Ed25519,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SshKey {
+    pub algorithm: String,
+    /// OpenSSH形式の秘密鍵（パスフレーズなし。金庫ごと暗号化される）
+    pub private_key: String,
+    /// authorized_keys 形式の公開鍵
+    pub public_key: String,
+}
+
+pub fn generate(key_type: KeyType, comment: &str) -> Result<SshKey> {
+    let alg = match key_type {
+        KeyType::Ed25519 => Algorithm::Ed25519,
+    };
+    let mut key = PrivateKey::random(&mut OsRng, alg)
+        .map_err(|e| anyhow!("ssh key generation failed: {e}"))?;
+    key.set_comment(comment);
+    let private_key = key
+        .to_openssh(LineEnding::LF)
+        .map_err(|e| anyhow!("ssh key encoding failed: {e}"))?
+        .to_string();
+    let public_key = key
+        .public_key()
+        .to_openssh()
+        .map_err(|e| anyhow!("ssh key encoding failed: {e}"))?;
+    Ok(SshKey { algorithm: key.algorithm().to_string(), private_key, public_key })
+}
+
+// 既存ファイルは上書きしない。Unixでは 0600 で作成
+pub fn export_private(key: &SshKey, path: &Path) -> Result<()> {
+    let mut opts = OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut f = opts
+        .open(path)
+        .map_err(|e| anyhow!("cannot create {}: {e}", path.display()))?;
+    f.write_all(key.private_key.as_bytes())?;
+    Ok(())
+}
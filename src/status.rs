@@ -0,0 +1,125 @@
+// 金庫の状態表示（--unlock なしではパスワード不要）
+use crate::format::{self, Credentials};
+use crate::remote::{self, Freshness};
+use crate::{agent, backup, keychain, sync, vault_path, VaultFile};
+use anyhow::Result;
+use std::{fs, path::Path, time::SystemTime};
+use time::OffsetDateTime;
+
+fn timestamp(t: SystemTime) -> String {
+    let rfc = OffsetDateTime::from(t)
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_default();
+    let secs = SystemTime::now().duration_since(t).map(|d| d.as_secs()).unwrap_or(0);
+    let ago = match secs {
+        0..=59 => format!("{secs}s"),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    };
+    format!("{rfc} ({ago} ago)")
+}
+
+// git の同期（sync init）とリモートの保存先（storage）。どちらも無ければ not configured
+fn sync_status(path: &Path, data: &[u8]) {
+    let mut configured = false;
+    if sync::dir(path).join(".git").exists() {
+        configured = true;
+        match git_status(path, data) {
+            Ok(s) => println!("sync:          {s}"),
+            Err(e) => println!("sync:          git, UNREADABLE ({e})"),
+        }
+    }
+    match remote::configured() {
+        Ok(Some(r)) => {
+            configured = true;
+            let state = match r.freshness() {
+                Ok(Freshness::Same) => "same version as this device last loaded or saved".to_string(),
+                Ok(Freshness::Changed) => "CHANGED by another device since this device last loaded or saved it".to_string(),
+                Ok(Freshness::Missing) => "no vault there yet (uploaded on the next save)".to_string(),
+                Ok(Freshness::Unknown) => "not loaded on this device yet".to_string(),
+                Err(e) => format!("cannot reach ({e})"),
+            };
+            println!("sync:          storage {}: {state}", r.location());
+        }
+        Ok(None) => {}
+        Err(e) => {
+            configured = true;
+            println!("sync:          storage, INVALID ({e})");
+        }
+    }
+    if !configured {
+        println!("sync:          not configured");
+    }
+}
+
+fn git_status(path: &Path, data: &[u8]) -> Result<String> {
+    let repo = sync::Repo::open(path)?;
+    // fetch はしない（ネットワークに出ない。最新は `rustpass sync status` で）
+    let state = match repo.ahead_behind()? {
+        None => "not pushed yet".to_string(),
+        Some((0, 0)) => "up to date as of the last fetch".to_string(),
+        Some((ahead, behind)) => format!("{ahead} ahead, {behind} behind as of the last fetch"),
+    };
+    let dirty = if repo.committed().as_deref() == Some(data) { "" } else { "; this vault has changes not synced yet" };
+    Ok(format!("git {} ({}): {state}{dirty}", repo.remote()?, repo.branch))
+}
+
+pub fn run(creds: &mut dyn Credentials, unlock: bool) -> Result<()> {
+    let path = vault_path()?;
+    println!("vault:         {}", path.display());
+    if !path.exists() {
+        println!("               (not created yet; run `rustpass new`)");
+        return Ok(());
+    }
+    match (agent::status(), keychain::remembered()?) {
+        (Some(s), _) => println!("session:       agent (pid {}, locks in {}s without use)", s.pid, s.locks_in),
+        (None, true) => println!("session:       key remembered in the OS keychain (`rustpass forget` to remove it)"),
+        (None, false) => println!("session:       none (each command prompts for the master password)"),
+    }
+
+    let data = VaultFile::current()?.read()?;
+    let meta = fs::metadata(&path)?;
+    println!("size:          {} bytes", meta.len());
+    if let Ok(t) = meta.modified() {
+        println!("last modified: {}", timestamp(t));
+    }
+    match format::inspect(&data) {
+        Ok(info) => {
+            let upgrade = if info.version < format::VERSION { " (upgraded on next save)" } else { "" };
+            println!("format:        v{}{}", info.version, upgrade);
+            let min = creds.min_kdf();
+            for s in &info.slots {
+                let weak = min.is_some_and(|m| s.kdf.is_some_and(|k| k.weaker_than(&m)));
+                println!(
+                    "key slot:      {}{}{}{}{}{}",
+                    s.kind,
+                    s.kdf.map(|k| format!("  argon2id m={}KiB t={} p={}", k.m, k.t, k.p)).unwrap_or_default(),
+                    if s.totp { "  + TOTP" } else { "" },
+                    if s.keyfile { "  + key file" } else { "" },
+                    s.token.as_ref().map(|t| format!("  + {}", t.describe())).unwrap_or_default(),
+                    if weak { "  BELOW PINNED MINIMUM" } else { "" },
+                );
+            }
+            if let Some(m) = min {
+                println!("kdf minimum:   m={}KiB t={} p={} (config)", m.m, m.t, m.p);
+            }
+        }
+        Err(e) => println!("format:        INVALID ({e})"),
+    }
+
+    let points = backup::restore_points()?;
+    match points.first().and_then(|p| fs::metadata(p).and_then(|m| m.modified()).ok()) {
+        Some(t) => println!("backups:       {}, newest {}", points.len(), timestamp(t)),
+        None => println!("backups:       none"),
+    }
+    sync_status(&path, &data);
+
+    if unlock {
+        let (vault, _) = format::unlock(&data, creds)?;
+        println!("entries:       {}", vault.entries.len());
+    } else {
+        println!("entries:       (locked; use --unlock to count)");
+    }
+    Ok(())
+}
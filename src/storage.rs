@@ -0,0 +1,153 @@
+//! 暗号化された金庫の保存先
+//!
+//! 金庫は [`VaultStorage::load`] で読み、[`VaultStorage::store`] で書く。rustpass の CLI は
+//! 金庫ファイル（とリモートの保存先）を使うが、アプリのデータベースなどに金庫を置く場合は
+//! このトレイトを実装して [`load_vault`](crate::load_vault) / [`store_vault`](crate::store_vault)
+//! に渡す。渡すのは暗号化されたままのバイト列なので、保存先が中身を読むことはない。
+//!
+//! ```no_run
+//! use anyhow::Result;
+//! use rustpass_core::storage::VaultStorage;
+//! use rustpass_core::{load_vault, store_vault, Entry};
+//!
+//! struct Db { /* 接続など */ }
+//!
+//! impl VaultStorage for Db {
+//!     fn load(&self) -> Result<Option<Vec<u8>>> {
+//!         // SELECT data FROM vaults WHERE id = ?
+//!         # Ok(None)
+//!     }
+//!     fn store(&self, data: &[u8]) -> Result<()> {
+//!         // UPDATE vaults SET data = ? WHERE id = ?
+//!         # let _ = data;
+//!         # Ok(())
+//!     }
+//! }
+//!
+//! let db = Db {};
+//! let mut vault = load_vault(&db, "master password")?.unwrap_or_default();
+//! vault.entries.push(Entry::new("example".into()));
+//! store_vault(&db, &vault, "master password")?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use anyhow::Result;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 暗号化された金庫を読み書きする場所
+pub trait VaultStorage {
+    /// 保存されている金庫。まだ一度も保存されていなければ None
+    fn load(&self) -> Result<Option<Vec<u8>>>;
+
+    /// 金庫を保存する。失敗したときは前の版をそのまま残すこと（途中まで書いた版で置き換えない）
+    fn store(&self, data: &[u8]) -> Result<()>;
+}
+
+impl<T: VaultStorage + ?Sized> VaultStorage for &T {
+    fn load(&self) -> Result<Option<Vec<u8>>> {
+        (**self).load()
+    }
+
+    fn store(&self, data: &[u8]) -> Result<()> {
+        (**self).store(data)
+    }
+}
+
+impl<T: VaultStorage + ?Sized> VaultStorage for Box<T> {
+    fn load(&self) -> Result<Option<Vec<u8>>> {
+        (**self).load()
+    }
+
+    fn store(&self, data: &[u8]) -> Result<()> {
+        (**self).store(data)
+    }
+}
+
+/// 1つのファイル。同じディレクトリの一時ファイルに書いて fsync してから rename で置き換える
+///
+/// 複数のプロセスからの同時書き込みは防がない（CLI はロックファイルを併用している）。
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: impl Into<PathBuf>) -> FileStorage {
+        FileStorage { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn tmp(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        self.path.with_file_name(name)
+    }
+}
+
+impl VaultStorage for FileStorage {
+    fn load(&self) -> Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store(&self, data: &[u8]) -> Result<()> {
+        let tmp = self.tmp();
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        let written = opts.open(&tmp).and_then(|mut f| {
+            f.write_all(data)?;
+            f.sync_all()
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        fs::rename(&tmp, &self.path)?;
+        // 置き換え（ディレクトリエントリの更新）も永続化する
+        #[cfg(unix)]
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// メモリ上だけの保存先（テストや、金庫を別の仕組みで運ぶ場合に）
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: Mutex<Option<Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new(data: Option<Vec<u8>>) -> MemoryStorage {
+        MemoryStorage { data: Mutex::new(data) }
+    }
+
+    /// 最後に保存された金庫
+    pub fn into_inner(self) -> Option<Vec<u8>> {
+        self.data.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl VaultStorage for MemoryStorage {
+    fn load(&self) -> Result<Option<Vec<u8>>> {
+        Ok(self.data.lock().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    fn store(&self, data: &[u8]) -> Result<()> {
+        *self.data.lock().unwrap_or_else(|e| e.into_inner()) = Some(data.to_vec());
+        Ok(())
+    }
+}
@@ -0,0 +1,327 @@
+// パスワード強度の推定（zxcvbn と同じ考え方の簡易版）
+//
+// パスワードを「よく使われるパスワード・辞書の単語・エントリ名などの入力・キーボードの並び・
+// 連番・繰り返し・年や日付」のパターンに分け、攻撃者が順に試した場合の推測回数が最小になる
+// 分け方を動的計画法で探す。どのパターンにも当たらない文字は文字種の数だけ推測が要るとみなす。
+// 推測回数は桁が大きいので常に log10 で扱う。
+use crate::passphrase;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const COMMON: &str = include_str!("../wordlists/common_passwords.txt");
+const KEY_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+const REFERENCE_YEAR: i32 = 2026;
+// 低速なハッシュ（bcrypt・Argon2 など）へのオフライン攻撃を想定した1秒あたりの推測回数
+const GUESSES_PER_SECOND: f64 = 1e4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    Common,
+    Word,
+    UserInput,
+    Spatial,
+    Sequence,
+    Repeat,
+    Date,
+}
+
+struct Match {
+    start: usize,
+    end: usize,
+    log10: f64,
+    pattern: Pattern,
+}
+
+pub struct Estimate {
+    /// 推測回数（log10）
+    pub log10_guesses: f64,
+    /// 0（非常に弱い）〜 4（強い）
+    pub score: u8,
+    pub warnings: Vec<&'static str>,
+}
+
+impl Estimate {
+    /// 生成したパスワードのように、エントロピー（ビット）が分かっている場合
+    pub fn from_bits(bits: f64) -> Self {
+        let log10_guesses = bits * 2f64.log10();
+        Estimate { log10_guesses, score: score(log10_guesses), warnings: Vec::new() }
+    }
+
+    pub fn label(&self) -> &'static str {
+        ["very weak", "weak", "fair", "good", "strong"][self.score as usize]
+    }
+
+    pub fn bits(&self) -> f64 {
+        self.log10_guesses / 2f64.log10()
+    }
+
+    /// 1行の要約
+    pub fn summary(&self) -> String {
+        format!("{} ({}/4), ~{:.0} bits, crack time {} (offline, slow hash)", self.label(), self.score, self.bits(), self.crack_time())
+    }
+
+    /// オフライン攻撃（低速ハッシュ）で総当たりされるまでの目安
+    pub fn crack_time(&self) -> String {
+        let secs = 10f64.powf(self.log10_guesses - GUESSES_PER_SECOND.log10());
+        let units = [(60.0, "second"), (60.0, "minute"), (24.0, "hour"), (30.4, "day"), (12.0, "month"), (100.0, "year")];
+        if secs < 1.0 { return "less than a second".into(); }
+        let mut t = secs;
+        for (size, name) in units {
+            if t < size {
+                let n = t.round() as u64;
+                return format!("{n} {name}{}", if n == 1 { "" } else { "s" });
+            }
+            t /= size;
+        }
+        "centuries".into()
+    }
+}
+
+fn score(log10: f64) -> u8 {
+    match log10 {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    }
+}
+
+// 単語 → 頻度順位（よく使われるパスワードは順位そのもの、ワードリストの単語は平均的な順位）
+fn dictionary() -> &'static HashMap<String, (f64, Pattern)> {
+    static DICT: OnceLock<HashMap<String, (f64, Pattern)>> = OnceLock::new();
+    DICT.get_or_init(|| {
+        let mut d = HashMap::new();
+        if let Ok(words) = passphrase::load_wordlist("eff-large") {
+            let rank = words.len() as f64 / 2.0;
+            for w in words.into_iter().filter(|w| w.chars().count() >= 3) { d.insert(w, (rank, Pattern::Word)); }
+        }
+        let common = COMMON.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+        for (i, w) in common.enumerate() { d.insert(w.to_lowercase(), ((i + 1) as f64, Pattern::Common)); }
+        d
+    })
+}
+
+// よくある置き換え（p@ssw0rd → password）を戻す
+fn unleet(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' => 't',
+        c => c,
+    }
+}
+
+fn cardinality(c: char) -> f64 {
+    if c.is_ascii_digit() { 10.0 } else if c.is_ascii_lowercase() || c.is_ascii_uppercase() { 26.0 } else { 33.0 }
+}
+
+// 大文字の使い方による推測回数の増加（先頭だけ・全部大文字は2倍、それ以外は位置の組み合わせ）
+fn case_variations(word: &[char]) -> f64 {
+    let upper = word.iter().filter(|c| c.is_uppercase()).count();
+    let lower = word.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 { return 1.0; }
+    if lower == 0 || (upper == 1 && word[0].is_uppercase()) { return 2.0; }
+    let n = word.len() as f64;
+    (1..=upper.min(lower)).map(|k| binomial(n, k as f64)).sum::<f64>().max(2.0)
+}
+
+fn binomial(n: f64, k: f64) -> f64 {
+    (0..k as usize).fold(1.0, |acc, i| acc * (n - i as f64) / (i as f64 + 1.0))
+}
+
+fn dictionary_matches(chars: &[char], inputs: &HashMap<String, f64>, out: &mut Vec<Match>) {
+    let dict = dictionary();
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    for i in 0..chars.len() {
+        for j in i + 3..=chars.len().min(i + 24) {
+            let plain: String = lower[i..j].iter().collect();
+            let leet: String = lower[i..j].iter().map(|&c| unleet(c)).collect();
+            let reversed: String = lower[i..j].iter().rev().collect();
+            let variants = [(plain.as_str(), 1.0), (leet.as_str(), 2.0), (reversed.as_str(), 2.0)];
+            for (word, extra) in variants {
+                let found = inputs.get(word).map(|&r| (r, Pattern::UserInput)).or_else(|| dict.get(word).copied());
+                if let Some((rank, pattern)) = found {
+                    let guesses = rank * extra * case_variations(&chars[i..j]);
+                    out.push(Match { start: i, end: j, log10: guesses.log10(), pattern });
+                }
+            }
+        }
+    }
+}
+
+fn spatial_matches(lower: &[char], out: &mut Vec<Match>) {
+    let on_row = |w: &[char]| {
+        let s: String = w.iter().collect();
+        let r: String = w.iter().rev().collect();
+        KEY_ROWS.iter().any(|row| row.contains(&s) || row.contains(&r))
+    };
+    for i in 0..lower.len() {
+        let mut j = i + 3;
+        while j <= lower.len() && on_row(&lower[i..j]) {
+            out.push(Match { start: i, end: j, log10: (40.0 * (j - i) as f64).log10(), pattern: Pattern::Spatial });
+            j += 1;
+        }
+    }
+}
+
+fn sequence_matches(chars: &[char], out: &mut Vec<Match>) {
+    for i in 0..chars.len() {
+        let mut j = i + 1;
+        let delta = |a: char, b: char| b as i32 - a as i32;
+        while j < chars.len() && delta(chars[j - 1], chars[j]).abs() == 1 && delta(chars[j - 1], chars[j]) == delta(chars[i], chars[i + 1]) {
+            j += 1;
+        }
+        if j - i < 3 { continue; }
+        let first = chars[i];
+        let base = if "aAzZ019".contains(first) { 4.0 } else if first.is_ascii_digit() { 10.0 } else { 26.0 };
+        let descending = if delta(chars[i], chars[i + 1]) < 0 { 2.0 } else { 1.0 };
+        out.push(Match { start: i, end: j, log10: (base * (j - i) as f64 * descending).log10(), pattern: Pattern::Sequence });
+    }
+}
+
+// 同じ文字・同じ並びの繰り返し（aaa、abcabc）。推測回数は1回分の推測回数×回数
+fn repeat_matches(chars: &[char], out: &mut Vec<Match>) {
+    for i in 0..chars.len() {
+        for period in 1..=(chars.len() - i) / 2 {
+            let mut j = i + period;
+            while j < chars.len() && chars[j] == chars[j - period] { j += 1; }
+            let count = (j - i) / period;
+            if count < 2 || j - i < 3 { continue; }
+            let end = i + count * period;
+            let block: String = chars[i..i + period].iter().collect();
+            let base = if period == 1 { cardinality(chars[i]).log10() } else { estimate(&block, &[]).log10_guesses };
+            out.push(Match { start: i, end, log10: base + (count as f64).log10(), pattern: Pattern::Repeat });
+        }
+    }
+}
+
+fn year_space(year: i32) -> f64 {
+    ((year - REFERENCE_YEAR).abs().max(20)) as f64
+}
+
+fn digits(s: &[char]) -> bool {
+    !s.is_empty() && s.iter().all(char::is_ascii_digit)
+}
+
+fn number(s: &[char]) -> i32 {
+    s.iter().fold(0, |n, c| n * 10 + c.to_digit(10).unwrap_or(0) as i32)
+}
+
+// 3つの数の並びが日付として読めれば、その年（2桁の年は 1950〜2049 とみなす）
+fn date_year(groups: &[&[char]]) -> Option<i32> {
+    let [a, b, c] = groups else { return None };
+    let day_month = |x: i32, y: i32| ((1..=31).contains(&x) && (1..=12).contains(&y)) || ((1..=12).contains(&x) && (1..=31).contains(&y));
+    let year = |s: &[char]| match (s.len(), number(s)) {
+        (4, y) if (1900..=2049).contains(&y) => Some(y),
+        (2, y) => Some(if y < 50 { 2000 + y } else { 1900 + y }),
+        _ => None,
+    };
+    if a.len() == 4 && day_month(number(b), number(c)) { return year(a); }
+    if day_month(number(a), number(b)) { return year(c); }
+    None
+}
+
+// 19xx・20xx の年と、区切りあり・なしの日付（年月日・日月年・月日年）
+fn date_matches(chars: &[char], out: &mut Vec<Match>) {
+    for i in 0..chars.len() {
+        if let Some(s) = chars.get(i..i + 4).filter(|s| digits(s)) {
+            let y = number(s);
+            if (1900..=2049).contains(&y) {
+                out.push(Match { start: i, end: i + 4, log10: year_space(y).log10(), pattern: Pattern::Date });
+            }
+        }
+        for len in 6..=10 {
+            let Some(s) = chars.get(i..i + len) else { break };
+            let parts: Vec<&[char]> = s.split(|c| "-/. ".contains(*c)).collect();
+            let (year, separated) = if parts.len() == 3 && parts.iter().all(|p| digits(p) && p.len() <= 4) {
+                (date_year(&parts), true)
+            } else if digits(s) && len == 6 {
+                (date_year(&[&s[..2], &s[2..4], &s[4..]]), false)
+            } else if digits(s) && len == 8 {
+                (date_year(&[&s[..4], &s[4..6], &s[6..]]).or_else(|| date_year(&[&s[..2], &s[2..4], &s[4..]])), false)
+            } else {
+                continue;
+            };
+            let Some(year) = year else { continue };
+            let guesses = 365.0 * year_space(year) * if separated { 4.0 } else { 1.0 };
+            out.push(Match { start: i, end: i + len, log10: guesses.log10(), pattern: Pattern::Date });
+        }
+    }
+}
+
+// エントリ名・ユーザー名・URL などの語（ユーザーが入力しそうな語は最初に試される）
+fn input_words(user_inputs: &[&str]) -> HashMap<String, f64> {
+    let mut words = HashMap::new();
+    for (i, w) in user_inputs.iter()
+        .flat_map(|s| s.split(|c: char| !c.is_alphanumeric()))
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() >= 3)
+        .enumerate()
+    {
+        words.entry(w).or_insert((i + 1) as f64);
+    }
+    words
+}
+
+fn warning(pattern: Pattern, whole: bool) -> &'static str {
+    match pattern {
+        Pattern::Common if whole => "this is one of the most common passwords",
+        Pattern::Common => "contains a commonly used password",
+        Pattern::Word => "a few dictionary words are easy to guess; add more words or characters",
+        Pattern::UserInput => "contains the entry name, username, or site name",
+        Pattern::Spatial => "keyboard patterns like qwerty are easy to guess",
+        Pattern::Sequence => "sequences like abc or 6543 are easy to guess",
+        Pattern::Repeat => "repeats like aaa or abcabc are easy to guess",
+        Pattern::Date => "dates and years are easy to guess",
+    }
+}
+
+/// user_inputs はエントリ名・ユーザー名・URL など、推測に使われやすい語
+pub fn estimate(password: &str, user_inputs: &[&str]) -> Estimate {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.is_empty() { return Estimate { log10_guesses: 0.0, score: 0, warnings: vec!["empty password"] }; }
+    let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let mut matches = Vec::new();
+    dictionary_matches(&chars, &input_words(user_inputs), &mut matches);
+    spatial_matches(&lower, &mut matches);
+    sequence_matches(&chars, &mut matches);
+    repeat_matches(&chars, &mut matches);
+    date_matches(&chars, &mut matches);
+
+    // best[i] = 先頭 i 文字の最小推測回数と、そこで使ったパターン
+    let n = chars.len();
+    let mut best: Vec<(f64, Option<usize>)> = vec![(f64::INFINITY, None); n + 1];
+    best[0].0 = 0.0;
+    for end in 1..=n {
+        best[end] = (best[end - 1].0 + cardinality(chars[end - 1]).log10(), None);
+        for (k, m) in matches.iter().enumerate().filter(|(_, m)| m.end == end) {
+            // 1文字ずつの総当たりより安くならないよう、1つのパターンにも最低限の推測回数を課す
+            let g = best[m.start].0 + m.log10.max(1.0);
+            if g < best[end].0 { best[end] = (g, Some(k)); }
+        }
+    }
+    let log10_guesses = best[n].0;
+
+    let mut warnings = Vec::new();
+    let mut i = n;
+    while i > 0 {
+        match best[i].1 {
+            Some(k) => {
+                let m = &matches[k];
+                let w = warning(m.pattern, m.start == 0 && m.end == n);
+                if !warnings.contains(&w) { warnings.push(w); }
+                i = m.start;
+            }
+            None => i -= 1,
+        }
+    }
+    warnings.reverse();
+    if n < 8 { warnings.push("too short; use at least 12 characters"); }
+    let score = score(log10_guesses);
+    if score >= 3 { warnings.clear(); }
+    Estimate { log10_guesses, score, warnings }
+}
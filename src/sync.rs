@@ -0,0 +1,227 @@
+// git リポジトリを介した金庫の同期（git コマンドに任せる）
+//
+// 金庫ごとに、金庫ファイルの隣の sync ディレクトリを作業ツリーにし、暗号化されたままの
+// vault.bin だけをコミットする。同期のたびに fetch してからローカルの変更をコミットし、
+// 相手だけが進んでいれば早送りして金庫を置き換え、こちらだけが進んでいれば push する。
+// 両方が進んでいれば、共通の祖先の金庫と合わせて3方向マージした結果をマージコミットにする。
+use crate::error::err;
+use anyhow::{anyhow, Result};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::fs;
+
+const FILE: &str = "vault.bin";
+const REMOTE: &str = "origin";
+
+/// ローカルとリモートの関係
+#[derive(PartialEq)]
+pub enum State {
+    UpToDate,
+    /// こちらだけに新しいコミットがある（リモートにまだブランチが無い場合も）
+    Ahead,
+    /// リモートだけに新しいコミットがある
+    Behind,
+    /// 両方で変更された
+    Diverged,
+}
+
+/// init でリモートを確認した結果
+pub enum Joined {
+    /// リモートはまだ空
+    Empty,
+    /// リモートの金庫を取り出した（この端末にはまだ金庫が無い）
+    Fetched(Vec<u8>),
+    /// この端末の金庫と同じ
+    Same,
+    /// この端末の金庫と違う（最初の sync で両方で変更されたものとして扱う）
+    Differs,
+}
+
+pub struct Repo {
+    pub dir: PathBuf,
+    pub branch: String,
+}
+
+/// 金庫の同期用リポジトリの場所
+pub fn dir(vault: &Path) -> PathBuf {
+    vault.parent().unwrap_or(Path::new(".")).join("sync")
+}
+
+fn git_in(dir: &Path, args: &[&str]) -> Result<std::process::Output> {
+    Command::new("git").arg("-C").arg(dir).args(args).output().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => err!(NotFound, "git not found in PATH (https://git-scm.com/downloads)"),
+        _ => e.into(),
+    })
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let out = git_in(dir, args)?;
+    if !out.status.success() {
+        return Err(anyhow!("git {} failed: {}", args[0], String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+// コミットメッセージに入れる端末名
+fn host() -> String {
+    fs::read_to_string("/etc/hostname").ok()
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown host".into())
+}
+
+/// 同期用リポジトリを作り、remote を設定する。local はこの端末の金庫ファイルの中身
+pub fn init(vault: &Path, remote: &str, branch: &str, local: Option<&[u8]>) -> Result<(Repo, Joined)> {
+    let dir = dir(vault);
+    if dir.join(".git").exists() {
+        let url = git(&dir, &["remote", "get-url", REMOTE]).unwrap_or_default();
+        return Err(err!(AlreadyExists, "sync is already set up for this vault (remote {url}, repository {})", dir.display()));
+    }
+    fs::create_dir_all(&dir)?;
+    git(&dir, &["init", "-q", "-b", branch])?;
+    git(&dir, &["remote", "add", REMOTE, remote])?;
+    // 名前とメールが未設定でもコミットできるように
+    if git(&dir, &["config", "user.email"]).is_err() {
+        git(&dir, &["config", "user.name", "rustpass"])?;
+        git(&dir, &["config", "user.email", "rustpass@localhost"])?;
+    }
+    let repo = Repo { dir, branch: branch.to_string() };
+    repo.fetch()?;
+    let joined = if repo.has_remote_branch()? {
+        let theirs = repo.theirs()?;
+        match local {
+            Some(l) if l != theirs => Joined::Differs,
+            _ => {
+                git(&repo.dir, &["checkout", "-q", "-B", branch, "--track", &repo.tracking()])?;
+                if local.is_some() { Joined::Same } else { Joined::Fetched(theirs) }
+            }
+        }
+    } else {
+        Joined::Empty
+    };
+    // リモートの履歴を引き継がない場合は、こちらの最初のコミットを作っておく
+    if matches!(joined, Joined::Empty | Joined::Differs) {
+        // 改行の変換や差分表示の対象にしない
+        fs::write(repo.dir.join(".gitattributes"), format!("{FILE} binary\n"))?;
+        git(&repo.dir, &["add", ".gitattributes"])?;
+        git(&repo.dir, &["commit", "-q", "-m", "Set up rustpass sync"])?;
+    }
+    Ok((repo, joined))
+}
+
+impl Repo {
+    /// 金庫の同期用リポジトリを開く
+    pub fn open(vault: &Path) -> Result<Repo> {
+        let dir = dir(vault);
+        if !dir.join(".git").exists() {
+            return Err(err!(NotFound, "sync is not set up for this vault; run `rustpass sync init <REMOTE>` first"));
+        }
+        let branch = git(&dir, &["symbolic-ref", "--short", "HEAD"])?;
+        Ok(Repo { dir, branch })
+    }
+
+    pub fn remote(&self) -> Result<String> {
+        git(&self.dir, &["remote", "get-url", REMOTE])
+    }
+
+    fn tracking(&self) -> String {
+        format!("{REMOTE}/{}", self.branch)
+    }
+
+    fn has_remote_branch(&self) -> Result<bool> {
+        Ok(git_in(&self.dir, &["rev-parse", "-q", "--verify", &self.tracking()])?.status.success())
+    }
+
+    pub fn fetch(&self) -> Result<()> {
+        git(&self.dir, &["fetch", "-q", REMOTE])?;
+        Ok(())
+    }
+
+    /// 最後に同期した（コミット済みの）金庫
+    pub fn committed(&self) -> Option<Vec<u8>> {
+        git_in(&self.dir, &["show", &format!("HEAD:{FILE}")]).ok()
+            .filter(|o| o.status.success())
+            .map(|o| o.stdout)
+    }
+
+    /// リモートの金庫
+    pub fn theirs(&self) -> Result<Vec<u8>> {
+        let out = git_in(&self.dir, &["show", &format!("{}:{FILE}", self.tracking())])?;
+        if !out.status.success() {
+            return Err(anyhow!("{} has no {FILE}", self.tracking()));
+        }
+        Ok(out.stdout)
+    }
+
+    /// 金庫が最後の同期から変わっていればコミットする（コミットしたら true）
+    pub fn commit(&self, data: &[u8]) -> Result<bool> {
+        if self.committed().as_deref() == Some(data) { return Ok(false); }
+        fs::write(self.dir.join(FILE), data)?;
+        git(&self.dir, &["add", FILE])?;
+        git(&self.dir, &["commit", "-q", "-m", &format!("Update vault from {}", host())])?;
+        Ok(true)
+    }
+
+    pub fn state(&self) -> Result<State> {
+        if !self.has_remote_branch()? { return Ok(State::Ahead); }
+        let tracking = self.tracking();
+        let head = git(&self.dir, &["rev-parse", "HEAD"])?;
+        let theirs = git(&self.dir, &["rev-parse", &tracking])?;
+        if head == theirs { return Ok(State::UpToDate); }
+        let ancestor = |a: &str, b: &str| -> Result<bool> {
+            Ok(git_in(&self.dir, &["merge-base", "--is-ancestor", a, b])?.status.success())
+        };
+        Ok(if ancestor(&theirs, &head)? {
+            State::Ahead
+        } else if ancestor(&head, &theirs)? {
+            State::Behind
+        } else {
+            State::Diverged
+        })
+    }
+
+    /// こちらだけ・リモートだけにあるコミットの数（最後に fetch した時点。リモートにブランチが無ければ None）
+    pub fn ahead_behind(&self) -> Result<Option<(usize, usize)>> {
+        if !self.has_remote_branch()? { return Ok(None); }
+        let out = git(&self.dir, &["rev-list", "--left-right", "--count", &format!("HEAD...{}", self.tracking())])?;
+        let mut n = out.split_whitespace().map(|s| s.parse().unwrap_or(0));
+        Ok(Some((n.next().unwrap_or(0), n.next().unwrap_or(0))))
+    }
+
+    /// 共通の祖先（最後に同期した版）の金庫。祖先が無ければ None、祖先に金庫が無ければ空
+    pub fn base(&self) -> Result<Option<Vec<u8>>> {
+        let out = git_in(&self.dir, &["merge-base", "HEAD", &self.tracking()])?;
+        if !out.status.success() { return Ok(None); }
+        let commit = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        let out = git_in(&self.dir, &["show", &format!("{commit}:{FILE}")])?;
+        Ok(Some(if out.status.success() { out.stdout } else { Vec::new() }))
+    }
+
+    /// リモートの変更を早送りで取り込み、新しい金庫を返す
+    pub fn fast_forward(&self) -> Result<Vec<u8>> {
+        git(&self.dir, &["merge", "-q", "--ff-only", &self.tracking()])?;
+        Ok(fs::read(self.dir.join(FILE))?)
+    }
+
+    /// 両方の履歴をつなぐマージコミットを作り、金庫を data にする
+    pub fn merge(&self, data: &[u8], message: &str) -> Result<()> {
+        git(&self.dir, &["merge", "-q", "--no-commit", "--allow-unrelated-histories", "-s", "ours", &self.tracking()])?;
+        fs::write(self.dir.join(FILE), data)?;
+        git(&self.dir, &["add", FILE])?;
+        git(&self.dir, &["commit", "-q", "-m", message])?;
+        Ok(())
+    }
+
+    pub fn push(&self) -> Result<()> {
+        git(&self.dir, &["push", "-q", "-u", REMOTE, &self.branch])?;
+        Ok(())
+    }
+
+    /// 最後の同期のコミット（日時と件名）
+    pub fn last(&self) -> Result<String> {
+        git(&self.dir, &["log", "-1", "--format=%ci  %s"])
+    }
+}
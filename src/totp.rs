@@ -0,0 +1,229 @@
+// TOTP (RFC 6238) とBase32 (RFC 4648)
+use crate::error::err;
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_uppercase().as_str() {
+            "SHA1" => Ok(Algorithm::Sha1),
+            "SHA256" => Ok(Algorithm::Sha256),
+            "SHA512" => Ok(Algorithm::Sha512),
+            _ => Err(format!("unknown algorithm {s:?} (use sha1, sha256 or sha512)")),
+        }
+    }
+}
+
+const B32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::new();
+    let (mut buf, mut bits) = (0u32, 0);
+    for &b in data {
+        buf = (buf << 8) | b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(B32[((buf >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(B32[((buf << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+// 空白・パディング・小文字を許容
+pub fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut buf, mut bits) = (0u32, 0);
+    for c in s.chars().filter(|c| !c.is_whitespace() && *c != '=' && *c != '-') {
+        let v = B32.iter().position(|&b| b as char == c.to_ascii_uppercase())? as u32;
+        buf = (buf << 5) | v;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn hmac(alg: Algorithm, key: &[u8], msg: &[u8]) -> Vec<u8> {
+    match alg {
+        Algorithm::Sha1 => {
+            let mut m = Hmac::<Sha1>::new_from_slice(key).expect("hmac key");
+            m.update(msg);
+            m.finalize().into_bytes().to_vec()
+        }
+        Algorithm::Sha256 => {
+            let mut m = Hmac::<Sha256>::new_from_slice(key).expect("hmac key");
+            m.update(msg);
+            m.finalize().into_bytes().to_vec()
+        }
+        Algorithm::Sha512 => {
+            let mut m = Hmac::<Sha512>::new_from_slice(key).expect("hmac key");
+            m.update(msg);
+            m.finalize().into_bytes().to_vec()
+        }
+    }
+}
+
+// RFC 4226 の動的切り詰め
+pub fn code_at(secret: &[u8], alg: Algorithm, digits: u32, period: u64, unix: u64) -> String {
+    let counter = unix / period;
+    let mac = hmac(alg, secret, &counter.to_be_bytes());
+    let off = (mac[mac.len() - 1] & 0x0f) as usize;
+    let bin = u32::from_be_bytes([mac[off] & 0x7f, mac[off + 1], mac[off + 2], mac[off + 3]]);
+    let code = bin as u64 % 10u64.pow(digits);
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// 時計のずれを考慮して前後1ステップまで許容
+pub fn verify(secret: &[u8], alg: Algorithm, digits: u32, period: u64, code: &str) -> bool {
+    let code = code.trim();
+    let t = now();
+    [t.saturating_sub(period), t, t + period]
+        .iter()
+        .any(|&at| code_at(secret, alg, digits, period, at).as_bytes().ct_eq(code.as_bytes()).into())
+}
+
+/// エントリに保存するTOTP設定（シークレットはBase32）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Otp {
+    pub secret: String,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    pub period: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+}
+
+impl Otp {
+    /// 現在のコードと残り秒数
+    pub fn now(&self) -> Result<(String, u64)> {
+        let key = base32_decode(&self.secret).ok_or_else(|| err!(InvalidInput, "TOTP secret is not valid base32"))?;
+        let t = now();
+        Ok((code_at(&key, self.algorithm, self.digits, self.period, t), self.period - t % self.period))
+    }
+
+    /// otpauth:// URI（parse_uri の逆。account が無ければ fallback をラベルに使う）
+    pub fn uri(&self, fallback: &str) -> String {
+        let account = self.account.as_deref().unwrap_or(fallback);
+        let label = match &self.issuer {
+            Some(i) => format!("{}:{}", percent_encode(i), percent_encode(account)),
+            None => percent_encode(account),
+        };
+        let alg = match self.algorithm {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        };
+        let mut uri = format!(
+            "otpauth://totp/{label}?secret={}&algorithm={alg}&digits={}&period={}",
+            self.secret, self.digits, self.period
+        );
+        if let Some(i) = &self.issuer { uri.push_str(&format!("&issuer={}", percent_encode(i))); }
+        uri
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        match b[i] {
+            b'%' if i + 2 < b.len() => {
+                let hex = std::str::from_utf8(&b[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(v) => { out.push(v); i += 3; }
+                    None => { out.push(b'%'); i += 1; }
+                }
+            }
+            b'+' => { out.push(b' '); i += 1; }
+            c => { out.push(c); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// otpauth:// URI、または Base32 のシークレットだけ（SHA1・6桁・30秒）
+pub fn parse_secret(s: &str) -> Result<Otp> {
+    if s.trim_start().starts_with("otpauth://") { return parse_uri(s); }
+    let secret = s.to_ascii_uppercase().replace([' ', '-', '='], "");
+    if base32_decode(&secret).is_none_or(|k| k.is_empty()) {
+        return Err(err!(InvalidInput, "TOTP secret must be an otpauth:// URI or base32"));
+    }
+    Ok(Otp { secret, algorithm: Algorithm::Sha1, digits: 6, period: 30, issuer: None, account: None })
+}
+
+/// otpauth://totp/Issuer:account?secret=...&issuer=...&algorithm=...&digits=...&period=...
+pub fn parse_uri(uri: &str) -> Result<Otp> {
+    let bad = |msg: &str| err!(InvalidInput, "invalid otpauth URI: {msg}");
+    let rest = uri.trim().strip_prefix("otpauth://").ok_or_else(|| bad("must start with otpauth://"))?;
+    let (kind, rest) = rest.split_once('/').ok_or_else(|| bad("missing type"))?;
+    if !kind.eq_ignore_ascii_case("totp") { return Err(bad("only totp is supported")); }
+    let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let label = percent_decode(label);
+    let (label_issuer, account) = match label.split_once(':') {
+        Some((i, a)) => (Some(i.trim().to_string()), a.trim().to_string()),
+        None => (None, label.trim().to_string()),
+    };
+    let mut otp = Otp {
+        secret: String::new(),
+        algorithm: Algorithm::Sha1,
+        digits: 6,
+        period: 30,
+        issuer: label_issuer,
+        account: (!account.is_empty()).then_some(account),
+    };
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        let v = percent_decode(v);
+        match k.to_ascii_lowercase().as_str() {
+            "secret" => otp.secret = v.to_ascii_uppercase().replace([' ', '='], ""),
+            "issuer" => otp.issuer = Some(v),
+            "algorithm" => otp.algorithm = v.parse().map_err(|_| bad("unknown algorithm"))?,
+            "digits" => otp.digits = v.parse().ok().filter(|d| (6..=8).contains(d)).ok_or_else(|| bad("digits must be 6-8"))?,
+            "period" => otp.period = v.parse().ok().filter(|&p| p > 0).ok_or_else(|| bad("invalid period"))?,
+            _ => {}
+        }
+    }
+    if otp.secret.is_empty() || base32_decode(&otp.secret).is_none_or(|k| k.is_empty()) {
+        return Err(bad("missing or invalid secret"));
+    }
+    Ok(otp)
+}
@@ -0,0 +1,95 @@
+// ゴミ箱（削除・上書きしたエントリを保持期間のあいだ残す）
+//
+// ゴミ箱は金庫の中にあり、エントリと同じく暗号化される。保持期間を過ぎたものは、
+// 次にゴミ箱へ移したときかゴミ箱を操作したときに消去する。
+use crate::error::err;
+use crate::{now_iso, Entry, Vault};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+#[derive(Serialize, Deserialize)]
+pub struct Trashed {
+    /// ゴミ箱へ移した日時
+    pub deleted: String,
+    pub entry: Entry,
+}
+
+impl Trashed {
+    /// 消去されるまでの残り日数（日時が読めなければ None）
+    pub fn days_left(&self, retention: i64) -> Option<i64> {
+        let t = OffsetDateTime::parse(&self.deleted, &Rfc3339).ok()?;
+        Some(retention - (OffsetDateTime::now_utc() - t).whole_days())
+    }
+
+    // 同じ削除を見分ける（同期で両側のゴミ箱を統合するとき用）
+    fn key(&self) -> (&str, &str) {
+        (&self.entry.id, &self.deleted)
+    }
+}
+
+/// エントリをゴミ箱へ移す（保持期間が0ならその場で消去し、false を返す）
+pub fn discard(v: &mut Vault, mut entry: Entry, retention: i64) -> bool {
+    if retention <= 0 {
+        entry.wipe();
+        return false;
+    }
+    v.trash.push(Trashed { deleted: now_iso(), entry });
+    purge(v, retention);
+    true
+}
+
+/// 保持期間を過ぎたものを消去し、消した数を返す
+pub fn purge(v: &mut Vault, retention: i64) -> usize {
+    let before = v.trash.len();
+    v.trash.retain_mut(|t| {
+        let keep = t.days_left(retention).is_none_or(|d| d > 0);
+        if !keep { t.entry.wipe(); }
+        keep
+    });
+    before - v.trash.len()
+}
+
+/// 名前が name のうち最後に捨てたものを金庫へ戻す
+pub fn restore(v: &mut Vault, name: &str) -> Result<()> {
+    let i = v.trash.iter().rposition(|t| t.entry.name == name)
+        .ok_or_else(|| err!(NotFound, "{name:?} is not in the trash"))?;
+    if let Some(e) = v.entries.iter().find(|e| e.name == name) {
+        return Err(err!(AlreadyExists, "entry {name:?} already exists ({}); rename or remove it first", e.summary()));
+    }
+    let t = v.trash.remove(i);
+    v.entries.push(t.entry);
+    Ok(())
+}
+
+/// ゴミ箱を空にし、消した数を返す
+pub fn empty(v: &mut Vault) -> usize {
+    let n = v.trash.len();
+    for mut t in std::mem::take(&mut v.trash) { t.entry.wipe(); }
+    n
+}
+
+/// 同期・統合で相手側のゴミ箱を取り込む。base（共通の祖先）があれば、
+/// そこにあって相手側で無くなったもの（戻した・空にした）はこちらでも消す。こちらのゴミ箱が変わったら true
+pub fn merge(local: &mut Vault, base: Option<&Vault>, remote: Vec<Trashed>) -> bool {
+    let before: Vec<(String, String)> = local.trash.iter().map(|t| (t.entry.id.clone(), t.deleted.clone())).collect();
+    let in_base = |t: &Trashed| base.is_some_and(|b| b.trash.iter().any(|x| x.key() == t.key()));
+    local.trash.retain_mut(|t| {
+        let keep = !in_base(t) || remote.iter().any(|r| r.key() == t.key());
+        if !keep { t.entry.wipe(); }
+        keep
+    });
+    for mut r in remote {
+        if in_base(&r) || local.trash.iter().any(|t| t.key() == r.key()) { r.entry.wipe(); continue; }
+        local.trash.push(r);
+    }
+    // 相手側で戻されたエントリはゴミ箱に残さない
+    let Vault { entries, trash, .. } = local;
+    trash.retain_mut(|t| {
+        let keep = !entries.iter().any(|e| e.id == t.entry.id);
+        if !keep { t.entry.wipe(); }
+        keep
+    });
+    trash.sort_by(|a, b| a.deleted.cmp(&b.deleted));
+    trash.iter().map(Trashed::key).ne(before.iter().map(|(id, deleted)| (id.as_str(), deleted.as_str())))
+}
@@ -0,0 +1,394 @@
+// 全画面のエントリブラウザ（rustpass tui）
+//
+// 左に絞り込みできる一覧、右に選んだエントリの詳細を出す。パスワードは v を押すまで伏せ、
+// コピーは他のコマンドと同じくクリップボードの自動消去つき。編集とパスワード生成は右側の欄で行い、
+// 確定したらその場で保存する。代替画面を使うので、終了すると元の端末の表示に戻る。
+// 描画は ratatui（crossterm のバックエンド）に任せ、毎回画面全体を組み立て直す。
+use crate::config::Config;
+use crate::picker::{self, RawMode};
+use crate::{clipboard, policy, record_access, save, strength, VaultFile};
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{self, ClearType},
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListState, Padding, Paragraph};
+use ratatui::{Frame, Terminal};
+use rustpass_core::{format, generate_password, now_iso, Avoid, Vault};
+use std::io::{self, Write};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+const HELP_BROWSE: &str = "/ filter  ↑↓ move  v show  c copy password  u copy user  t copy TOTP  e edit  g generate  r reload  q quit";
+const HELP_FILTER: &str = "type to filter  ↑↓ move  enter done  esc clear";
+const HELP_EDIT: &str = "tab/↑↓ field  type to edit  ctrl-u clear field  enter save  esc cancel";
+const HELP_GEN: &str = "space reroll  s symbols  a ambiguous  +/- length  c copy  enter use as password  esc cancel";
+
+// 編集できる項目（タグはカンマ区切り）
+const FIELDS: [&str; 4] = ["username", "url", "notes", "tags"];
+
+// 代替画面とカーソルを、終了時（エラーで抜けたときも）に元へ戻す。raw モードはその後に RawMode が戻す
+struct Screen(#[allow(dead_code)] RawMode);
+
+impl Screen {
+    fn enter(out: &mut impl Write) -> Result<Self> {
+        let raw = RawMode::enable()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(ClearType::All))?;
+        Ok(Screen(raw))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stderr(), cursor::Show, terminal::LeaveAlternateScreen);
+    }
+}
+
+enum Mode {
+    Browse,
+    Filter,
+    Edit { field: usize, values: [String; 4] },
+    Gen { len: usize, symbols: bool, ambiguous: bool, current: Zeroizing<String> },
+}
+
+struct App<'a> {
+    vault: Vault,
+    unlocked: format::Unlocked,
+    cfg: &'a Config,
+    query: String,
+    hits: Vec<usize>,
+    sel: usize,
+    reveal: bool,
+    mode: Mode,
+    status: String,
+}
+
+// 改行などの制御文字を1行に収まる記号に置き換える
+fn clean(s: &str) -> String {
+    s.chars().map(|c| match c { '\n' => '↵', c if c.is_control() => ' ', c => c }).collect()
+}
+
+impl App<'_> {
+    fn selected(&self) -> Option<usize> {
+        self.hits.get(self.sel).copied()
+    }
+
+    // 絞り込み直す（選んでいたエントリが残っていれば選んだままにする）
+    fn refilter(&mut self) {
+        let current = self.selected().map(|i| self.vault.entries[i].id.clone());
+        self.hits = (0..self.vault.entries.len()).filter(|&i| {
+            let e = &self.vault.entries[i];
+            let text = format!("{} {} {} {}", e.name, e.username, e.url.as_deref().unwrap_or_default(), e.tags.join(" "));
+            picker::matches(&self.query, &text)
+        }).collect();
+        self.hits.sort_by(|&a, &b| self.vault.entries[a].name.to_lowercase().cmp(&self.vault.entries[b].name.to_lowercase()));
+        self.sel = current
+            .and_then(|id| self.hits.iter().position(|&i| self.vault.entries[i].id == id))
+            .unwrap_or(0);
+    }
+
+    fn select(&mut self, sel: usize) {
+        let sel = sel.min(self.hits.len().saturating_sub(1));
+        if sel != self.sel { self.reveal = false; }
+        self.sel = sel;
+    }
+
+    fn copy(&mut self, what: &str) -> Result<()> {
+        let Some(i) = self.selected() else { return Ok(()) };
+        let e = &self.vault.entries[i];
+        let value = match what {
+            "password" => Zeroizing::new(e.password.clone()),
+            "username" => Zeroizing::new(e.username.clone()),
+            _ => Zeroizing::new(e.otp.as_ref().map(|o| o.now()).transpose()?.map(|(code, _)| code).unwrap_or_default()),
+        };
+        let name = e.name.clone();
+        if value.is_empty() {
+            self.status = format!("{name} has no {what}.");
+            return Ok(());
+        }
+        let timeout = self.cfg.clipboard_timeout();
+        clipboard::copy(&value, timeout)?;
+        self.status = format!("Copied {what} of {name} (clears in {timeout}s).");
+        record_access(&mut self.vault, &self.unlocked, self.cfg, &name, "tui")
+    }
+
+    // エントリを変更して保存する（保存できなければ変更を取り消す）
+    fn update(&mut self, i: usize, change: impl FnOnce(&mut rustpass_core::Entry) -> Vec<&'static str>) -> Result<()> {
+        let before = self.vault.entries[i].clone();
+        let changed = change(&mut self.vault.entries[i]);
+        let name = before.name.clone();
+        if changed.is_empty() {
+            self.status = "No changes.".into();
+            return Ok(());
+        }
+        self.vault.entries[i].updated_at = now_iso();
+        if let Err(e) = save(&self.vault, &self.unlocked) {
+            self.vault.entries[i] = before;
+            return Err(e);
+        }
+        self.status = format!("Updated {} of {name}.", changed.join(", "));
+        self.refilter();
+        Ok(())
+    }
+
+    // 他のプロセスの変更を読み込む（データ鍵で開き直すのでパスワードは尋ねない）
+    fn reload(&mut self) -> Result<()> {
+        let data = VaultFile::current()?.read()?;
+        let (vault, unlocked) = format::unlock_with_key(&data, self.unlocked.data_key().clone())?;
+        (self.vault, self.unlocked) = (vault, unlocked);
+        self.refilter();
+        self.status = format!("Reloaded {} entries.", self.vault.entries.len());
+        Ok(())
+    }
+
+    fn reroll(&mut self) {
+        let Mode::Gen { len, symbols, ambiguous, current } = &mut self.mode else { return };
+        // 条件を満たせない組み合わせは直前の候補を残してメッセージを出す
+        match generate_password(*len, *symbols, *ambiguous, &[], Avoid::default()) {
+            Ok(p) => { *current = Zeroizing::new(p); self.status.clear(); }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn start_edit(&mut self) {
+        let Some(i) = self.selected() else { return };
+        let e = &self.vault.entries[i];
+        let values = [
+            e.username.clone(),
+            e.url.clone().unwrap_or_default(),
+            e.notes.clone().unwrap_or_default(),
+            e.tags.join(", "),
+        ];
+        self.mode = Mode::Edit { field: 0, values };
+    }
+
+    fn finish_edit(&mut self, values: [String; 4]) -> Result<()> {
+        let Some(i) = self.selected() else { return Ok(()) };
+        let [user, url, notes, tags] = values;
+        let opt = |s: String| (!s.is_empty()).then_some(s);
+        let tags: Vec<String> = tags.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+        self.update(i, |e| {
+            let mut changed = Vec::new();
+            if user != e.username { e.username = user; changed.push("username"); }
+            if let Some(x) = Some(opt(url)).filter(|x| *x != e.url) { e.url = x; changed.push("url"); }
+            if let Some(x) = Some(opt(notes)).filter(|x| *x != e.notes) { e.notes = x; changed.push("notes"); }
+            if tags != e.tags { e.tags = tags; changed.push("tags"); }
+            changed
+        })
+    }
+
+    fn start_gen(&mut self) {
+        if self.selected().is_none() { return; }
+        let g = &self.cfg.generator;
+        self.mode = Mode::Gen { len: g.length(None), symbols: g.symbols(false, false), ambiguous: false, current: Zeroizing::new(String::new()) };
+        self.reroll();
+    }
+
+    fn finish_gen(&mut self, password: Zeroizing<String>) -> Result<()> {
+        let Some(i) = self.selected() else { return Ok(()) };
+        policy::enforce(&self.vault, &self.vault.entries[i].name, &password)?;
+        self.update(i, |e| {
+            e.set_password(password.to_string());
+            vec!["password"]
+        })
+    }
+
+    /// キー1つを処理する。終了するなら true
+    fn key(&mut self, code: KeyCode, ctrl: bool) -> Result<bool> {
+        if ctrl && code == KeyCode::Char('c') { return Ok(true); }
+        let page = terminal::size().map_or(10, |(_, h)| (h as usize).saturating_sub(4).max(1));
+        match std::mem::replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => match code {
+                KeyCode::Char('q') => return Ok(true),
+                KeyCode::Esc if self.query.is_empty() => return Ok(true),
+                KeyCode::Esc => { self.query.clear(); self.refilter(); }
+                KeyCode::Char('/') => self.mode = Mode::Filter,
+                KeyCode::Up | KeyCode::Char('k') => self.select(self.sel.saturating_sub(1)),
+                KeyCode::Down | KeyCode::Char('j') => self.select(self.sel + 1),
+                KeyCode::PageUp => self.select(self.sel.saturating_sub(page)),
+                KeyCode::PageDown => self.select(self.sel + page),
+                KeyCode::Home => self.select(0),
+                KeyCode::End => self.select(usize::MAX),
+                KeyCode::Char('v') => self.reveal = !self.reveal,
+                KeyCode::Enter | KeyCode::Char('c') => self.copy("password")?,
+                KeyCode::Char('u') => self.copy("username")?,
+                KeyCode::Char('t') => self.copy("TOTP code")?,
+                KeyCode::Char('e') => self.start_edit(),
+                KeyCode::Char('g') => self.start_gen(),
+                KeyCode::Char('r') => self.reload()?,
+                _ => {}
+            },
+            Mode::Filter => {
+                self.mode = Mode::Filter;
+                match code {
+                    KeyCode::Enter => self.mode = Mode::Browse,
+                    KeyCode::Esc => { self.mode = Mode::Browse; self.query.clear(); self.refilter(); }
+                    KeyCode::Up => self.select(self.sel.saturating_sub(1)),
+                    KeyCode::Down => self.select(self.sel + 1),
+                    KeyCode::Char('u') if ctrl => { self.query.clear(); self.refilter(); }
+                    KeyCode::Backspace => { self.query.pop(); self.refilter(); }
+                    KeyCode::Char(c) if !ctrl => { self.query.push(c); self.refilter(); }
+                    _ => {}
+                }
+            }
+            Mode::Edit { mut field, mut values } => match code {
+                KeyCode::Esc => self.status = "Edit cancelled.".into(),
+                KeyCode::Enter => self.finish_edit(values)?,
+                code => {
+                    match code {
+                        KeyCode::Tab | KeyCode::Down => field = (field + 1) % FIELDS.len(),
+                        KeyCode::BackTab | KeyCode::Up => field = (field + FIELDS.len() - 1) % FIELDS.len(),
+                        KeyCode::Char('u') if ctrl => values[field].clear(),
+                        KeyCode::Backspace => { values[field].pop(); }
+                        KeyCode::Char(c) if !ctrl => values[field].push(c),
+                        _ => {}
+                    }
+                    self.mode = Mode::Edit { field, values };
+                }
+            },
+            Mode::Gen { mut len, mut symbols, mut ambiguous, current } => match code {
+                KeyCode::Esc | KeyCode::Char('q') => self.status = "Password unchanged.".into(),
+                KeyCode::Enter => self.finish_gen(current)?,
+                KeyCode::Char('c') => {
+                    clipboard::copy(&current, self.cfg.clipboard_timeout())?;
+                    self.status = format!("Copied the candidate (clears in {}s).", self.cfg.clipboard_timeout());
+                    self.mode = Mode::Gen { len, symbols, ambiguous, current };
+                }
+                code => {
+                    match code {
+                        KeyCode::Char('s') => symbols = !symbols,
+                        KeyCode::Char('a') => ambiguous = !ambiguous,
+                        KeyCode::Char('+' | '=') | KeyCode::Right | KeyCode::Up => len += 1,
+                        KeyCode::Char('-') | KeyCode::Left | KeyCode::Down => len = len.saturating_sub(1).max(4),
+                        _ => {}
+                    }
+                    self.mode = Mode::Gen { len, symbols, ambiguous, current };
+                    self.reroll();
+                }
+            },
+        }
+        Ok(false)
+    }
+
+    // 右側に出す行
+    fn detail(&self) -> Vec<String> {
+        let Some(i) = self.selected() else {
+            return vec![if self.vault.entries.is_empty() { "The vault is empty." } else { "Nothing matches the filter." }.into()];
+        };
+        let e = &self.vault.entries[i];
+        match &self.mode {
+            Mode::Edit { field, values } => {
+                let mut lines = vec![format!("Editing {}", e.name), String::new()];
+                for (k, label) in FIELDS.iter().enumerate() {
+                    let (mark, cursor) = if k == *field { ('>', "_") } else { (' ', "") };
+                    lines.push(format!("{mark} {label:<9} {}{cursor}", values[k]));
+                }
+                lines
+            }
+            Mode::Gen { len, symbols, ambiguous, current } => {
+                let on = |b: bool| if b { "on" } else { "off" };
+                vec![
+                    format!("New password for {}", e.name),
+                    String::new(),
+                    current.to_string(),
+                    String::new(),
+                    format!("length {len}, symbols {}, ambiguous characters {}", on(*symbols), on(*ambiguous)),
+                    format!("strength: {}", strength::estimate(current, &[]).summary()),
+                ]
+            }
+            Mode::Browse | Mode::Filter => {
+                let password = if e.password.is_empty() {
+                    "(none)".to_string()
+                } else if self.reveal {
+                    e.password.clone()
+                } else {
+                    "••••••••  (v to show)".to_string()
+                };
+                let mut lines = vec![e.name.clone(), String::new()];
+                lines.push(format!("username  {}", e.username));
+                lines.push(format!("password  {password}"));
+                if let Some(url) = &e.url { lines.push(format!("url       {url}")); }
+                if !e.tags.is_empty() { lines.push(format!("tags      {}", e.tags.join(", "))); }
+                if let Some(Ok((code, left))) = e.otp.as_ref().map(|o| o.now()) {
+                    lines.push(format!("totp      {code}  ({left}s left)"));
+                }
+                if !e.fields.is_empty() {
+                    let names: Vec<&str> = e.fields.iter().map(|f| f.name.as_str()).collect();
+                    lines.push(format!("fields    {}", names.join(", ")));
+                }
+                lines.push(format!("updated   {}", e.updated_at));
+                if let Some(notes) = &e.notes {
+                    lines.push(String::new());
+                    lines.extend(notes.lines().map(str::to_string));
+                }
+                lines
+            }
+        }
+    }
+
+    fn draw(&self, f: &mut Frame) {
+        let reverse = Style::new().add_modifier(Modifier::REVERSED);
+        let [title, body, status, help] = Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1), Constraint::Length(1)]).areas(f.area());
+        let [list, detail] = Layout::horizontal([Constraint::Length((body.width / 3).clamp(16, 40)), Constraint::Min(0)]).areas(body);
+        let filter = match (&self.mode, self.query.is_empty()) {
+            (Mode::Filter, _) => format!("  filter: {}_", self.query),
+            (_, false) => format!("  filter: {}", self.query),
+            (_, true) => String::new(),
+        };
+        let header = format!(" rustpass  {}/{} entries{filter}", self.hits.len(), self.vault.entries.len());
+        f.render_widget(Paragraph::new(clean(&header)).style(reverse), title);
+        // 選択行が見えるようにするスクロールは List に任せる
+        let labels = self.hits.iter().map(|&i| {
+            let e = &self.vault.entries[i];
+            let label = if e.username.is_empty() { e.name.clone() } else { format!("{}  ({})", e.name, e.username) };
+            format!(" {}", clean(&label))
+        });
+        let mut state = ListState::default().with_selected(self.selected().map(|_| self.sel));
+        f.render_stateful_widget(List::new(labels).block(Block::new().borders(Borders::RIGHT)).highlight_style(reverse), list, &mut state);
+        let lines: Vec<String> = self.detail().iter().map(|l| clean(l)).collect();
+        f.render_widget(Paragraph::new(lines.join("\n")).block(Block::new().padding(Padding::left(1))), detail);
+        f.render_widget(Paragraph::new(clean(&self.status)), status);
+        let keys = match self.mode {
+            Mode::Browse => HELP_BROWSE,
+            Mode::Filter => HELP_FILTER,
+            Mode::Edit { .. } => HELP_EDIT,
+            Mode::Gen { .. } => HELP_GEN,
+        };
+        f.render_widget(Paragraph::new(keys).style(reverse), help);
+    }
+}
+
+pub fn run(vault: Vault, unlocked: format::Unlocked, cfg: &Config) -> Result<()> {
+    crate::prompt::require("a terminal for the TUI")?;
+    let mut app = App {
+        vault, unlocked, cfg,
+        query: String::new(), hits: Vec::new(), sel: 0, reveal: false,
+        mode: Mode::Browse, status: String::new(),
+    };
+    app.refilter();
+    let _screen = Screen::enter(&mut io::stderr())?;
+    let mut term = Terminal::new(CrosstermBackend::new(io::stderr()))?;
+    loop {
+        // 端末の大きさが変わっていれば draw が合わせて全体を描き直す
+        term.draw(|f| app.draw(f))?;
+        // TOTP の残り秒数を更新するため、入力が無くても1秒ごとに描き直す
+        if !event::poll(Duration::from_secs(1))? { continue; }
+        match event::read()? {
+            Event::Key(KeyEvent { code, modifiers, kind, .. }) if kind != KeyEventKind::Release => {
+                match app.key(code, modifiers.contains(KeyModifiers::CONTROL)) {
+                    Ok(true) => break,
+                    Ok(false) => {}
+                    // 操作の失敗は画面を閉じずに下の行へ出す
+                    Err(e) => app.status = format!("error: {e}"),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
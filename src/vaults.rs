@@ -0,0 +1,121 @@
+// 名前付きの金庫（--vault / RUSTPASS_VAULT で選ぶ）
+//
+// 既定の金庫はこれまでどおりデータディレクトリの vault.bin。名前付きの金庫は
+// vaults/<名前>/vault.bin に置き、直前の版やバックアップも金庫ごとのディレクトリに残す。
+// --vault にはパス（"/" を含むか .bin で終わるもの）も指定できる。
+use crate::error::err;
+use crate::{perms, readonly};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const DEFAULT: &str = "default";
+
+static SELECTED: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn data_dir() -> Result<PathBuf> {
+    let base = dirs::data_local_dir().ok_or(anyhow!("data dir not found"))?;
+    Ok(base.join("rustpass"))
+}
+
+fn vaults_dir() -> Result<PathBuf> {
+    Ok(data_dir()?.join("vaults"))
+}
+
+fn check_name(name: &str) -> Result<()> {
+    let ok = name.len() <= 64
+        && name.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !ok {
+        return Err(err!(InvalidInput, "invalid vault name {name:?} (use letters, digits, '-', '_' and '.')"));
+    }
+    Ok(())
+}
+
+/// 名前の金庫ファイル
+pub fn named(name: &str) -> Result<PathBuf> {
+    if name == DEFAULT { return Ok(data_dir()?.join("vault.bin")); }
+    check_name(name)?;
+    Ok(vaults_dir()?.join(name).join("vault.bin"))
+}
+
+/// 名前またはパスを金庫ファイルのパスにする
+pub fn resolve(spec: &str) -> Result<PathBuf> {
+    if spec.contains(std::path::is_separator) || spec.ends_with(".bin") {
+        return Ok(std::path::absolute(spec)?);
+    }
+    named(spec)
+}
+
+/// このコマンドで使う金庫を選ぶ（None なら既定の金庫）
+pub fn select(spec: Option<&str>) -> Result<()> {
+    let path = match spec {
+        Some(s) => resolve(s)?,
+        None => named(DEFAULT)?,
+    };
+    let _ = SELECTED.set(path);
+    Ok(())
+}
+
+/// 選んだ金庫のパス（置き場所のディレクトリは作成しておく）
+pub fn path() -> Result<PathBuf> {
+    let path = match SELECTED.get() {
+        Some(p) => p.clone(),
+        None => named(DEFAULT)?,
+    };
+    // データディレクトリの中と、ここで作るディレクトリだけ権限を絞る（--vault で指定した共有フォルダなどはそのまま）
+    if let Some(dir) = path.parent() {
+        if !dir.exists() || dir.starts_with(data_dir()?) { perms::private_dir(dir)?; }
+    }
+    Ok(path)
+}
+
+/// 選んでいる金庫の名前（--vault にパスを指定した場合は None）
+pub fn name() -> Result<Option<String>> {
+    let path = path()?;
+    if path == named(DEFAULT)? { return Ok(Some(DEFAULT.to_string())); }
+    let dir = vaults_dir()?;
+    Ok(path.parent()
+        .filter(|p| p.parent() == Some(dir.as_path()) && path.file_name() == Some("vault.bin".as_ref()))
+        .and_then(|p| p.file_name()?.to_str().map(String::from))
+        .filter(|n| check_name(n).is_ok()))
+}
+
+/// 既定の金庫を選んでいるか
+pub fn is_default() -> Result<bool> {
+    Ok(path()? == named(DEFAULT)?)
+}
+
+/// 作成済みの金庫（既定の金庫が先頭、あとは名前順）
+pub fn list() -> Result<Vec<(String, PathBuf)>> {
+    let mut found = Vec::new();
+    let default = named(DEFAULT)?;
+    if default.exists() { found.push((DEFAULT.to_string(), default)); }
+    let dir = vaults_dir()?;
+    if dir.is_dir() {
+        let mut names: Vec<String> = fs::read_dir(&dir)?
+            .filter_map(|e| e.ok()?.file_name().into_string().ok())
+            .filter(|n| check_name(n).is_ok() && dir.join(n).join("vault.bin").exists())
+            .collect();
+        names.sort();
+        found.extend(names.into_iter().map(|n| {
+            let path = dir.join(&n).join("vault.bin");
+            (n, path)
+        }));
+    }
+    Ok(found)
+}
+
+/// 名前付きの金庫をディレクトリごと削除する（直前の版・バックアップも消える）
+pub fn delete(name: &str) -> Result<PathBuf> {
+    if name == DEFAULT {
+        return Err(err!(InvalidInput, "the default vault cannot be deleted this way; remove {} yourself", named(DEFAULT)?.display()));
+    }
+    let path = named(name)?;
+    if !path.exists() { return Err(err!(NotFound, "no vault named {name:?}")); }
+    let dir = path.parent().map(Path::to_path_buf).ok_or(anyhow!("vault path has no parent"))?;
+    readonly::check()?;
+    fs::remove_dir_all(&dir)?;
+    Ok(dir)
+}
@@ -0,0 +1,10 @@
+#!/bin/sh
+# export --format html-vault に埋め込む wasm と JS を作り直す（src/export/web/ に書き、コミットする）
+#
+# 必要なもの: rustup target add wasm32-unknown-unknown
+#             cargo install wasm-bindgen-cli --version 0.2.100   # web/Cargo.toml の wasm-bindgen と同じ版
+set -eu
+cd "$(dirname "$0")/.."
+cargo build -p rustpass-web --target wasm32-unknown-unknown --release
+wasm-bindgen --target no-modules --no-typescript --out-dir src/export/web \
+    target/wasm32-unknown-unknown/release/rustpass_web.wasm
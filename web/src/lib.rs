@@ -0,0 +1,24 @@
+//! ブラウザ用の金庫ビューア（`export --format html-vault`）が使う wasm
+//!
+//! 金庫の形式と暗号は rustpass_core をそのまま使う。ページは埋め込んだ金庫ファイルとパスワードを渡して
+//! エントリの JSON を受け取り、TOTP のコードもここで計算する。
+use rustpass_core::totp::{self, Otp};
+use wasm_bindgen::prelude::*;
+
+/// 金庫ファイルをパスワードで開き、エントリの JSON を返す
+#[wasm_bindgen]
+pub fn open(data: &[u8], password: &str) -> Result<String, JsError> {
+    let vault = rustpass_core::decrypt_vault(data, password).map_err(|e| JsError::new(&e.to_string()))?;
+    serde_json::to_string(&vault.entries).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// エントリの otp（JSON）の unix 秒の時点のコード
+#[wasm_bindgen]
+pub fn totp_code(otp: &str, unix: f64) -> Result<String, JsError> {
+    let otp: Otp = serde_json::from_str(otp).map_err(|e| JsError::new(&e.to_string()))?;
+    let secret = totp::base32_decode(&otp.secret).ok_or_else(|| JsError::new("TOTP secret is not valid base32"))?;
+    if !(6..=8).contains(&otp.digits) || otp.period == 0 {
+        return Err(JsError::new("unsupported TOTP parameters"));
+    }
+    Ok(totp::code_at(&secret, otp.algorithm, otp.digits, otp.period, unix as u64))
+}